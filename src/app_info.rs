@@ -0,0 +1,92 @@
+//! Desktop entries lookup.
+//!
+//! Windows only give us their application ID, which usually, but not always, matches the name of
+//! their desktop entry. This tries the common mismatches to find the entry, to get the icon and
+//! name of the application.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use gtk::prelude::*;
+use gtk::{gdk, gio};
+
+thread_local! {
+    /// The lookups we already made, by application ID.
+    ///
+    /// Lookups go through all the installed desktop entries, so we cache them until they change.
+    static CACHE: RefCell<Option<Cache>> = const { RefCell::new(None) };
+}
+
+struct Cache {
+    // GIO only sends changes while we hold a reference to the monitor.
+    _monitor: gio::AppInfoMonitor,
+    lookups: HashMap<String, Option<gio::DesktopAppInfo>>,
+}
+
+/// Find the desktop entry of this application ID.
+pub fn lookup(app_id: &str) -> Option<gio::DesktopAppInfo> {
+    if app_id.is_empty() {
+        return None;
+    }
+
+    CACHE.with_borrow_mut(|cache| {
+        let cache = cache.get_or_insert_with(|| {
+            // Installing or removing applications changes the results.
+            let monitor = gio::AppInfoMonitor::get();
+            monitor.connect_changed(|_| {
+                CACHE.with_borrow_mut(|cache| {
+                    if let Some(cache) = cache {
+                        cache.lookups.clear();
+                    }
+                })
+            });
+            Cache {
+                _monitor: monitor,
+                lookups: HashMap::new(),
+            }
+        });
+        cache
+            .lookups
+            .entry(app_id.to_string())
+            .or_insert_with(|| find(app_id))
+            .clone()
+    })
+}
+
+/// Get the icon of this application ID, if it has a desktop entry.
+pub fn icon(app_id: &str) -> Option<gio::Icon> {
+    lookup(app_id).and_then(|app_info| app_info.icon())
+}
+
+fn find(app_id: &str) -> Option<gio::DesktopAppInfo> {
+    let lowercase = app_id.to_lowercase();
+    let candidates = [app_id, lowercase.as_str()];
+    if let Some(app_info) = candidates
+        .iter()
+        .find_map(|id| gio::DesktopAppInfo::new(&format!("{id}.desktop")))
+    {
+        return Some(app_info);
+    }
+
+    // X11 and some Wayland applications use their window class, that desktop entries can match.
+    let by_wm_class = gio::AppInfo::all().into_iter().find_map(|app_info| {
+        let app_info = app_info.downcast::<gio::DesktopAppInfo>().ok()?;
+        let wm_class = app_info.startup_wm_class()?;
+        wm_class.eq_ignore_ascii_case(app_id).then_some(app_info)
+    });
+    if by_wm_class.is_some() {
+        return by_wm_class;
+    }
+
+    // Reverse-DNS IDs whose desktop entry only uses the last component, like `org.gnome.Nautilus`.
+    let (_, last) = lowercase.rsplit_once('.')?;
+    gio::DesktopAppInfo::new(&format!("{last}.desktop"))
+}
+
+/// Launch an application, as if the user started it from this display.
+pub fn launch(app_info: &impl IsA<gio::AppInfo>, display: &gdk::Display) {
+    let context = display.app_launch_context();
+    if let Err(err) = app_info.launch(&[], Some(&context)) {
+        warn!(?err, app_id = ?app_info.id(), "Failed to launch application");
+    }
+}
@@ -1,3 +1,4 @@
+use glib::prelude::ObjectExt;
 use gtk::subclass::prelude::*;
 use gtk::{gio, glib};
 
@@ -5,20 +6,29 @@ use crate::panel::PanelWindow;
 
 mod imp {
     use std::cell::OnceCell;
+    use std::sync::LazyLock;
 
     use adw::subclass::prelude::AdwApplicationImpl;
+    use futures_util::StreamExt;
     use glib::object::{Cast, ObjectExt};
+    use glib::subclass::Signal;
+    use glib::types::StaticType;
     use glib::WeakRef;
     use gtk::gdk;
-    use gtk::prelude::{DisplayExt, GtkWindowExt, WidgetExt};
+    use gtk::prelude::{ActionMapExtManual, DisplayExt, GtkWindowExt, WidgetExt};
     use gtk4_layer_shell::{Edge, LayerShell};
 
     use super::*;
+    use crate::daemons::{audio, backlight, power_profiles, upower};
+    use crate::launcher::LauncherWindow;
+    use crate::osd::Osd;
     use crate::sass::load_css_from_path;
 
     #[derive(Debug, Default)]
     pub struct Application {
         shells: OnceCell<Vec<OutputShell>>,
+        osd: OnceCell<Osd>,
+        launcher: OnceCell<LauncherWindow>,
     }
 
     #[glib::object_subclass]
@@ -28,7 +38,20 @@ mod imp {
         type ParentType = adw::Application;
     }
 
-    impl ObjectImpl for Application {}
+    impl ObjectImpl for Application {
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: LazyLock<Vec<Signal>> = LazyLock::new(|| {
+                vec![
+                    // Emitted when the system switches between AC and battery power, with whether
+                    // we are now running on battery.
+                    Signal::builder("power-source-changed")
+                        .param_types([bool::static_type()])
+                        .build(),
+                ]
+            });
+            &SIGNALS
+        }
+    }
 
     impl ApplicationImpl for Application {
         fn activate(&self) {
@@ -79,6 +102,12 @@ mod imp {
             }
 
             self.shells.set(shells).expect("Panels already set.");
+            self.osd.set(crate::osd::start(&app)).unwrap();
+            self.launcher.set(LauncherWindow::new(&app)).unwrap();
+
+            crate::battery_warnings::start(&app);
+            crate::bluetooth_agent::start(&app);
+            crate::notifications::start(&app);
         }
 
         fn startup(&self) {
@@ -116,6 +145,168 @@ mod imp {
                     gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
                 );
             }
+
+            self.watch_power_source();
+            self.setup_actions();
+        }
+    }
+
+    impl Application {
+        /// Setup the application actions.
+        ///
+        /// These are mostly meant for keybindings, and can be activated from outside the shell
+        /// with `gapplication action fht.desktop.Shell <action> [parameter]`.
+        fn setup_actions(&self) {
+            let brightness_up = gio::ActionEntry::builder("brightness-up")
+                .activate(|_: &super::Application, _, _| {
+                    let step = crate::config::get().backlight.step;
+                    glib::spawn_future_local(step_brightness(step));
+                })
+                .build();
+            let brightness_down = gio::ActionEntry::builder("brightness-down")
+                .activate(|_: &super::Application, _, _| {
+                    let step = crate::config::get().backlight.step;
+                    glib::spawn_future_local(step_brightness(-step));
+                })
+                .build();
+            // Takes the brightness in percents.
+            let set_brightness = gio::ActionEntry::builder("set-brightness")
+                .parameter_type(Some(glib::VariantTy::DOUBLE))
+                .activate(|_: &super::Application, _, parameter| {
+                    let Some(percentage) = parameter.and_then(|p| p.get::<f64>()) else {
+                        return;
+                    };
+                    glib::spawn_future_local(async move {
+                        let fraction = percentage / 100.0;
+                        if let Err(err) = backlight::get().set_fraction(fraction).await {
+                            error!(?err, "Failed to set brightness");
+                        }
+                    });
+                })
+                .build();
+
+            let volume_up = gio::ActionEntry::builder("volume-up")
+                .activate(|_: &super::Application, _, _| {
+                    let step = crate::config::get().audio.step;
+                    if let Err(err) = audio::get().step_volume(step) {
+                        error!(?err, "Failed to change volume");
+                    }
+                })
+                .build();
+            let volume_down = gio::ActionEntry::builder("volume-down")
+                .activate(|_: &super::Application, _, _| {
+                    let step = crate::config::get().audio.step;
+                    if let Err(err) = audio::get().step_volume(-step) {
+                        error!(?err, "Failed to change volume");
+                    }
+                })
+                .build();
+            let toggle_mute = gio::ActionEntry::builder("toggle-mute")
+                .activate(|_: &super::Application, _, _| {
+                    if let Err(err) = audio::get().toggle_muted() {
+                        error!(?err, "Failed to toggle mute");
+                    }
+                })
+                .build();
+
+            // Takes a `a{sv}` dictionary with the `icon`, and optionally the `level` and `label`.
+            let show_osd = gio::ActionEntry::builder("show-osd")
+                .parameter_type(Some(glib::VariantTy::VARDICT))
+                .activate(|app: &super::Application, _, parameter| {
+                    let Some(content) = parameter.and_then(crate::osd::Content::from_variant)
+                    else {
+                        warn!("Missing OSD icon");
+                        return;
+                    };
+                    if let Some(osd) = app.imp().osd.get() {
+                        osd.show(content);
+                    }
+                })
+                .build();
+
+            let toggle_launcher = gio::ActionEntry::builder("toggle-launcher")
+                .activate(|app: &super::Application, _, _| {
+                    if let Some(launcher) = app.imp().launcher.get() {
+                        launcher.toggle();
+                    }
+                })
+                .build();
+
+            self.obj().add_action_entries([
+                brightness_up,
+                brightness_down,
+                set_brightness,
+                volume_up,
+                volume_down,
+                toggle_mute,
+                show_osd,
+                toggle_launcher,
+            ]);
+        }
+
+        fn watch_power_source(&self) {
+            let app = self.obj();
+            app.connect_power_source_changed(|_, on_battery| apply_power_source(on_battery));
+            // Only switch profiles on actual changes, to not override the user's choice on startup.
+            app.connect_power_source_changed(|_, on_battery| {
+                glib::spawn_future_local(power_profiles::apply_power_source(on_battery));
+            });
+
+            let weak_app = ObjectExt::downgrade(&*app);
+            glib::spawn_future_local(async move {
+                let proxy = upower::get().proxy();
+                let mut on_battery_changes = proxy.receive_on_battery_changed().await;
+                let mut on_battery = proxy.on_battery().await.unwrap_or(false);
+                apply_power_source(on_battery);
+
+                while let Some(changed) = on_battery_changes.next().await {
+                    let Ok(new_on_battery) = changed.get().await else {
+                        continue;
+                    };
+                    if new_on_battery == on_battery {
+                        continue;
+                    }
+                    on_battery = new_on_battery;
+
+                    let Some(app) = weak_app.upgrade() else {
+                        break;
+                    };
+                    info!(on_battery, "Power source changed");
+                    app.emit_by_name::<()>("power-source-changed", &[&on_battery]);
+                }
+            });
+        }
+    }
+
+    async fn step_brightness(step: f64) {
+        if let Err(err) = backlight::get().step(step).await {
+            error!(?err, "Failed to change brightness");
+        }
+    }
+
+    /// Apply the settings from the config that depend on the power source.
+    fn apply_power_source(on_battery: bool) {
+        thread_local! {
+            /// Whether animations were enabled before we disabled them, to restore it on AC.
+            static ANIMATIONS_ENABLED: std::cell::Cell<Option<bool>> = const {
+                std::cell::Cell::new(None)
+            };
+        }
+
+        let config = &crate::config::get().power;
+        if !config.disable_animations_on_battery {
+            return;
+        }
+        let Some(settings) = gtk::Settings::default() else {
+            return;
+        };
+        if on_battery {
+            if ANIMATIONS_ENABLED.get().is_none() {
+                ANIMATIONS_ENABLED.set(Some(settings.is_gtk_enable_animations()));
+            }
+            settings.set_gtk_enable_animations(false);
+        } else if let Some(enabled) = ANIMATIONS_ENABLED.take() {
+            settings.set_gtk_enable_animations(enabled);
         }
     }
 
@@ -142,4 +333,17 @@ impl Application {
             .property("resource-base-path", "/fht/desktop/Shell/")
             .build()
     }
+
+    /// Connect to the `power-source-changed` signal, emitted when the system switches between AC
+    /// and battery power.
+    pub fn connect_power_source_changed<F: Fn(&Self, bool) + 'static>(
+        &self,
+        f: F,
+    ) -> glib::SignalHandlerId {
+        self.connect_closure(
+            "power-source-changed",
+            false,
+            glib::closure_local!(move |app: &Self, on_battery: bool| f(app, on_battery)),
+        )
+    }
 }
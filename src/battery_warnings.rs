@@ -0,0 +1,373 @@
+//! Low and critical battery warnings.
+//!
+//! We follow the warning level UPower computes for the display device, optionally escalated with
+//! the thresholds from the config. Each time the level gets worse, we send a notification and play
+//! the configured sound. For as long as the battery stays low, all the panels show a banner.
+//!
+//! Peripherals (mice, headsets, etc.) only get a notification when they go below the configured
+//! threshold, since they don't affect the system itself.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures_util::StreamExt;
+use gtk::glib;
+use gtk::prelude::*;
+use tokio::sync::broadcast;
+
+use crate::application::Application;
+use crate::config::BatteryWarningsConfig;
+use crate::daemons::notifications::{self, Urgency};
+use crate::daemons::upower::{self, device};
+use crate::panel::PanelWindow;
+
+/// How long UPower waits before running the critical action once the battery reaches the action
+/// warning level. This is hardcoded in UPower itself.
+const CRITICAL_ACTION_DELAY: Duration = Duration::from_secs(20);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Level {
+    None,
+    Low,
+    Critical,
+    /// UPower is about to run the critical action.
+    Action,
+}
+
+/// Start watching the battery levels to warn the user.
+pub fn start(app: &Application) {
+    let config = &crate::config::get().battery;
+    if config.warnings.enable {
+        start_system_warnings(app, &config.warnings);
+    }
+
+    if config.peripherals.notify {
+        start_peripheral_warnings(config.peripherals.low_threshold);
+    }
+}
+
+fn start_system_warnings(app: &Application, config: &'static BatteryWarningsConfig) {
+    let app = app.downgrade();
+    glib::spawn_future_local(async move {
+        let proxy = upower::get().display_device().proxy();
+        let mut changes = futures_util::stream::select_all([
+            proxy
+                .receive_warning_level_changed()
+                .await
+                .map(|_| ())
+                .boxed_local(),
+            proxy
+                .receive_percentage_changed()
+                .await
+                .map(|_| ())
+                .boxed_local(),
+            proxy
+                .receive_state_changed()
+                .await
+                .map(|_| ())
+                .boxed_local(),
+        ]);
+
+        let mut warnings = Warnings {
+            app,
+            level: Level::None,
+            notification_id: 0,
+            countdown: None,
+            sound: None,
+        };
+
+        // Initial update, for when we start with an already low battery.
+        warnings.update(config).await;
+        while changes.next().await.is_some() {
+            warnings.update(config).await;
+        }
+    });
+}
+
+struct Warnings {
+    app: glib::WeakRef<Application>,
+    level: Level,
+    // The ID of the last notification we sent, so that we replace it instead of stacking them.
+    notification_id: u32,
+    // The timer updating the banner until UPower runs the critical action.
+    countdown: Option<glib::SourceId>,
+    // We must keep the media around for as long as it plays.
+    sound: Option<gtk::MediaFile>,
+}
+
+impl Warnings {
+    async fn update(&mut self, config: &BatteryWarningsConfig) {
+        let proxy = upower::get().display_device().proxy();
+        let (warning_level, percentage, state) =
+            match futures_util::try_join!(proxy.warning_level(), proxy.percentage(), proxy.state())
+            {
+                Ok(values) => values,
+                Err(err) => {
+                    error!(?err, "Failed to get battery warning level");
+                    return;
+                }
+            };
+
+        let level = compute_level(config, warning_level, percentage, state);
+        if level == self.level {
+            return;
+        }
+
+        let previous_level = std::mem::replace(&mut self.level, level);
+        if let Some(countdown) = self.countdown.take() {
+            countdown.remove();
+        }
+
+        if level == Level::None {
+            self.set_banner(None);
+            if self.notification_id != 0 {
+                let _ = notifications::close(self.notification_id).await;
+                self.notification_id = 0;
+            }
+            return;
+        }
+
+        let (summary, body, icon) = match level {
+            Level::None => unreachable!(),
+            Level::Low => (
+                "Battery low",
+                format!("{percentage:.0}% of battery remaining"),
+                "battery-caution-symbolic",
+            ),
+            Level::Critical => (
+                "Battery critically low",
+                format!("{percentage:.0}% of battery remaining, plug in your computer now"),
+                "battery-empty-symbolic",
+            ),
+            Level::Action => {
+                let action = critical_action().await;
+                self.start_countdown(action);
+                (
+                    "Battery critically low",
+                    format!(
+                        "{action} in {} seconds, plug in your computer now",
+                        CRITICAL_ACTION_DELAY.as_secs()
+                    ),
+                    "battery-empty-symbolic",
+                )
+            }
+        };
+
+        if level != Level::Action {
+            let title = format!("{summary}: {body}");
+            self.set_banner(Some((title.as_str(), level)));
+        }
+
+        // Only get the user's attention when things get worse, not when they start charging
+        // back from a critical level.
+        if level > previous_level {
+            let urgency = match level {
+                Level::Low => Urgency::Normal,
+                _ => Urgency::Critical,
+            };
+            let id = self.notification_id;
+            match notifications::send(summary, &body, icon, urgency, id, &[]).await {
+                Ok(id) => self.notification_id = id,
+                Err(err) => warn!(?err, "Failed to send battery warning notification"),
+            }
+
+            if let Some(path) = &config.sound {
+                let sound = gtk::MediaFile::for_filename(path);
+                sound.play();
+                self.sound = Some(sound);
+            }
+        }
+    }
+
+    /// Show the time left before UPower runs the critical action in the banner.
+    fn start_countdown(&mut self, action: &'static str) {
+        let deadline = Instant::now() + CRITICAL_ACTION_DELAY;
+        let app = self.app.clone();
+
+        let update_banner = move || {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let title = format!(
+                "Battery critically low: {action} in {} seconds",
+                remaining.as_secs()
+            );
+            set_banner(&app, Some((title.as_str(), Level::Action)));
+        };
+
+        update_banner();
+        // NOTE: We keep ticking even after reaching zero, since we remove the source ourselves
+        // once the level changes.
+        let countdown = glib::timeout_add_seconds_local(1, move || {
+            update_banner();
+            glib::ControlFlow::Continue
+        });
+        self.countdown = Some(countdown);
+    }
+
+    fn set_banner(&self, banner: Option<(&str, Level)>) {
+        set_banner(&self.app, banner);
+    }
+}
+
+fn set_banner(app: &glib::WeakRef<Application>, banner: Option<(&str, Level)>) {
+    let Some(app) = app.upgrade() else {
+        return;
+    };
+
+    for panel in app
+        .windows()
+        .into_iter()
+        .filter_map(|window| window.downcast::<PanelWindow>().ok())
+    {
+        match banner {
+            Some((title, Level::Low)) => panel.show_banner(title, &["battery-low"]),
+            Some((title, _)) => panel.show_banner(title, &["battery-critical"]),
+            None => panel.hide_banner(),
+        }
+    }
+}
+
+fn compute_level(
+    config: &BatteryWarningsConfig,
+    warning_level: device::WarningLevel,
+    percentage: f64,
+    state: device::State,
+) -> Level {
+    let upower_level = match warning_level {
+        device::WarningLevel::Unknown
+        | device::WarningLevel::None
+        | device::WarningLevel::Discharging => Level::None,
+        device::WarningLevel::Low => Level::Low,
+        device::WarningLevel::Critical => Level::Critical,
+        device::WarningLevel::Action => Level::Action,
+    };
+
+    // Our own thresholds can only make UPower's level worse, never better.
+    let config_level = if state != device::State::Discharging {
+        Level::None
+    } else if config
+        .critical_threshold
+        .is_some_and(|threshold| percentage <= threshold)
+    {
+        Level::Critical
+    } else if config
+        .low_threshold
+        .is_some_and(|threshold| percentage <= threshold)
+    {
+        Level::Low
+    } else {
+        Level::None
+    };
+
+    upower_level.max(config_level)
+}
+
+fn start_peripheral_warnings(low_threshold: f64) {
+    let upower_daemon = upower::get();
+    let mut watchers = HashMap::<Arc<str>, glib::JoinHandle<()>>::new();
+    for device in upower_daemon.devices() {
+        let id = device.id();
+        watchers.insert(
+            id,
+            glib::spawn_future_local(watch_peripheral(device, low_threshold)),
+        );
+    }
+
+    let mut events = upower_daemon.subscribe();
+    glib::spawn_future_local(async move {
+        loop {
+            let event = match events.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+
+            match event {
+                upower::Event::DeviceAdded(device) => {
+                    let id = device.id();
+                    let watcher = glib::spawn_future_local(watch_peripheral(device, low_threshold));
+                    if let Some(previous) = watchers.insert(id, watcher) {
+                        previous.abort();
+                    }
+                }
+                upower::Event::DeviceRemoved(id) => {
+                    if let Some(watcher) = watchers.remove(&id) {
+                        watcher.abort();
+                    }
+                }
+            }
+        }
+    });
+}
+
+async fn watch_peripheral(device: upower::Device, low_threshold: f64) {
+    if !device.is_peripheral().await.unwrap_or(false) {
+        return;
+    }
+
+    let proxy = device.proxy();
+    let mut changes = futures_util::stream::select(
+        proxy.receive_percentage_changed().await.map(|_| ()),
+        proxy.receive_battery_level_changed().await.map(|_| ()),
+    );
+    let mut notification_id = 0;
+    let mut warned = false;
+
+    loop {
+        let percentage = proxy.percentage().await.unwrap_or(0.0);
+        let battery_level = proxy
+            .battery_level()
+            .await
+            .unwrap_or(device::BatteryLevel::None);
+        let is_low = battery_level.is_low(percentage, low_threshold);
+
+        if is_low && !warned {
+            let type_ = proxy.type_().await.unwrap_or(device::Type::Unknown);
+            let model = proxy.model().await.unwrap_or_default();
+
+            let summary = format!("{} battery low", type_.name());
+            let body = if model.is_empty() {
+                format!("{} remaining", battery_level.describe(percentage))
+            } else {
+                format!("{model}: {} remaining", battery_level.describe(percentage))
+            };
+            match notifications::send(
+                &summary,
+                &body,
+                type_.icon_name(),
+                Urgency::Normal,
+                notification_id,
+                &[],
+            )
+            .await
+            {
+                Ok(id) => notification_id = id,
+                Err(err) => warn!(?err, "Failed to send peripheral battery notification"),
+            }
+        } else if !is_low && warned && notification_id != 0 {
+            // The device got charged back up.
+            let _ = notifications::close(notification_id).await;
+            notification_id = 0;
+        }
+
+        warned = is_low;
+        if changes.next().await.is_none() {
+            break;
+        }
+    }
+}
+
+/// Get a description of what UPower does when the battery runs out.
+async fn critical_action() -> &'static str {
+    match upower::get().proxy().get_critical_action().await.as_deref() {
+        Ok("PowerOff") => "Powering off",
+        Ok("Hibernate") => "Hibernating",
+        Ok("HybridSleep") => "Entering hybrid sleep",
+        Ok("Suspend") => "Suspending",
+        Ok(_) => "Running the critical action",
+        Err(err) => {
+            warn!(?err, "Failed to get UPower critical action");
+            "Running the critical action"
+        }
+    }
+}
@@ -0,0 +1,223 @@
+//! Bluetooth pairing dialogs.
+//!
+//! The BlueZ daemon registers a pairing agent, and forwards what BlueZ asks it. We answer these
+//! requests here by asking the user with a dialog.
+
+use std::cell::RefCell;
+
+use adw::prelude::*;
+use gtk::glib;
+use tokio::sync::oneshot;
+
+use crate::application::Application;
+use crate::daemons::bluez::{self, agent::AgentRequest};
+
+/// Start answering the requests of our Bluetooth pairing agent.
+pub fn start(app: &Application) {
+    let Some(mut requests) = bluez::get().take_agent_requests() else {
+        warn!("Bluetooth agent requests are already handled");
+        return;
+    };
+
+    // BlueZ only pairs with one device at a time, so we only need to track a single dialog.
+    let mut current_dialog = None::<adw::MessageDialog>;
+    let mut displayed_code = None::<String>;
+    let app = app.downgrade();
+    glib::spawn_future_local(async move {
+        while let Some(request) = requests.recv().await {
+            let Some(app) = app.upgrade() else {
+                break;
+            };
+
+            // BlueZ displays the passkey again each time the user types a digit on the device.
+            if let AgentRequest::Display { code, .. } = &request {
+                let still_open = current_dialog
+                    .as_ref()
+                    .is_some_and(|dialog| dialog.is_visible());
+                if still_open && displayed_code.as_ref() == Some(code) {
+                    continue;
+                }
+            }
+            displayed_code = match &request {
+                AgentRequest::Display { code, .. } => Some(code.clone()),
+                _ => None,
+            };
+
+            if let Some(dialog) = current_dialog.take() {
+                dialog.force_close();
+            }
+
+            let dialog = match request {
+                AgentRequest::PinCode { device, responder } => {
+                    let name = device_name(&device).await;
+                    let dialog = new_dialog(
+                        &app,
+                        "Pair Device",
+                        &format!(
+                            "Enter the PIN code of {name}. It is usually written in its manual."
+                        ),
+                    );
+                    dialog.add_responses(&[("cancel", "Cancel"), ("pair", "Pair")]);
+                    dialog.set_response_appearance("pair", adw::ResponseAppearance::Suggested);
+                    let entry = gtk::Entry::builder().activates_default(true).build();
+                    dialog.set_extra_child(Some(&entry));
+                    respond_with(&dialog, "pair", responder, move |accepted| {
+                        let code = entry.text().to_string();
+                        (accepted && !code.is_empty()).then_some(code)
+                    });
+                    dialog
+                }
+                AgentRequest::Passkey { device, responder } => {
+                    let name = device_name(&device).await;
+                    let dialog = new_dialog(
+                        &app,
+                        "Pair Device",
+                        &format!("Enter the passkey shown on {name}."),
+                    );
+                    dialog.add_responses(&[("cancel", "Cancel"), ("pair", "Pair")]);
+                    dialog.set_response_appearance("pair", adw::ResponseAppearance::Suggested);
+                    let entry = gtk::Entry::builder()
+                        .activates_default(true)
+                        .input_purpose(gtk::InputPurpose::Digits)
+                        .max_length(6)
+                        .build();
+                    dialog.set_extra_child(Some(&entry));
+                    respond_with(&dialog, "pair", responder, move |accepted| {
+                        accepted.then(|| entry.text().trim().parse::<u32>().ok())?
+                    });
+                    dialog
+                }
+                AgentRequest::Confirmation {
+                    device,
+                    passkey,
+                    responder,
+                } => {
+                    let name = device_name(&device).await;
+                    let dialog = new_dialog(
+                        &app,
+                        "Confirm Passkey",
+                        &format!("Make sure that {name} shows the following passkey."),
+                    );
+                    dialog.add_responses(&[("cancel", "Cancel"), ("confirm", "Confirm")]);
+                    dialog.set_response_appearance("confirm", adw::ResponseAppearance::Suggested);
+                    dialog.set_extra_child(Some(&passkey_label(&format!("{passkey:06}"))));
+                    respond_with(&dialog, "confirm", responder, |accepted| accepted);
+                    dialog
+                }
+                AgentRequest::Authorization { device, responder } => {
+                    let name = device_name(&device).await;
+                    let dialog = new_dialog(
+                        &app,
+                        "Pair Device",
+                        &format!("{name} wants to pair with this computer."),
+                    );
+                    dialog.add_responses(&[("cancel", "Deny"), ("allow", "Allow")]);
+                    dialog.set_response_appearance("allow", adw::ResponseAppearance::Suggested);
+                    respond_with(&dialog, "allow", responder, |accepted| accepted);
+                    dialog
+                }
+                AgentRequest::ServiceAuthorization {
+                    device,
+                    uuid,
+                    responder,
+                } => {
+                    let name = device_name(&device).await;
+                    let dialog = new_dialog(
+                        &app,
+                        "Authorize Service",
+                        &format!("{name} wants to use {}.", service_name(&uuid)),
+                    );
+                    dialog.add_responses(&[("cancel", "Deny"), ("allow", "Allow")]);
+                    dialog.set_response_appearance("allow", adw::ResponseAppearance::Suggested);
+                    respond_with(&dialog, "allow", responder, |accepted| accepted);
+                    dialog
+                }
+                AgentRequest::Display { device, code } => {
+                    let name = device_name(&device).await;
+                    let dialog = new_dialog(
+                        &app,
+                        "Pair Device",
+                        &format!("Type the following code on {name}, then press Enter."),
+                    );
+                    dialog.add_responses(&[("cancel", "Close")]);
+                    dialog.set_extra_child(Some(&passkey_label(&code)));
+                    dialog
+                }
+                AgentRequest::Cancel => continue,
+            };
+
+            dialog.present();
+            current_dialog = Some(dialog);
+        }
+    });
+}
+
+fn new_dialog(app: &Application, heading: &str, body: &str) -> adw::MessageDialog {
+    let dialog = adw::MessageDialog::builder()
+        .application(app)
+        .heading(heading)
+        .body(body)
+        .default_response("cancel")
+        .close_response("cancel")
+        .css_classes(["bluetooth-agent-dialog"])
+        .build();
+    dialog.set_default_response(None::<&str>);
+    dialog
+}
+
+fn passkey_label(code: &str) -> gtk::Label {
+    gtk::Label::builder()
+        .label(code)
+        .selectable(true)
+        .css_classes(["title-1", "numeric"])
+        .build()
+}
+
+/// Answer the agent request once the user picks a response, `get_value` being told whether it was
+/// `accept_response`.
+///
+/// Closing the dialog without a response drops the responder, which cancels the request.
+fn respond_with<T: 'static>(
+    dialog: &adw::MessageDialog,
+    accept_response: &str,
+    responder: oneshot::Sender<T>,
+    get_value: impl Fn(bool) -> T + 'static,
+) {
+    dialog.set_default_response(Some(accept_response));
+    let accept_response = accept_response.to_string();
+    let responder = RefCell::new(Some(responder));
+    dialog.connect_response(None, move |_, response| {
+        let Some(responder) = responder.take() else {
+            return;
+        };
+        let _ = responder.send(get_value(response == accept_response));
+    });
+}
+
+/// Get a human readable name of a Bluetooth service from its UUID.
+fn service_name(uuid: &str) -> String {
+    // Standard services are `0000XXXX-0000-1000-8000-00805f9b34fb`.
+    let short = uuid
+        .strip_prefix("0000")
+        .and_then(|uuid| uuid.strip_suffix("-0000-1000-8000-00805f9b34fb"))
+        .map(str::to_ascii_lowercase);
+    let name = match short.as_deref() {
+        Some("1101") => "the serial port",
+        Some("1105") => "file transfer",
+        Some("1106") => "file transfer",
+        Some("110a") | Some("110b") | Some("110d") => "audio",
+        Some("110c") | Some("110e") | Some("110f") => "media controls",
+        Some("1108") | Some("1112") | Some("111e") | Some("111f") => "calls",
+        Some("1115") | Some("1116") => "the network",
+        Some("1124") => "input",
+        _ => return format!("the service {uuid}"),
+    };
+    name.to_string()
+}
+
+async fn device_name(path: &str) -> String {
+    match bluez::get().device_at(path) {
+        Some(device) => device.display_name().await,
+        None => String::from("The device"),
+    }
+}
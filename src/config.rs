@@ -0,0 +1,350 @@
+//! Shell configuration.
+//!
+//! The configuration is read once, the first time it's needed, from
+//! `$XDG_CONFIG_HOME/fht/shell/config.toml`. Every field has a default value, so a missing or empty
+//! file is a valid configuration.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use anyhow::Context;
+use serde::Deserialize;
+
+use crate::daemons::power_profiles::Profile;
+
+/// Get the shell configuration.
+pub fn get() -> &'static Config {
+    static CONFIG: OnceLock<Config> = OnceLock::new();
+    CONFIG.get_or_init(|| {
+        let path = crate::BASE_DIRECTORIES.get_config_file("fht/shell/config.toml");
+        match load_from_path(&path) {
+            Ok(config) => config,
+            Err(err) => {
+                error!(?err, ?path, "Failed to load config, using defaults");
+                Config::default()
+            }
+        }
+    })
+}
+
+fn load_from_path(path: &Path) -> anyhow::Result<Config> {
+    if !path.exists() {
+        info!(?path, "No config file, using defaults");
+        return Ok(Config::default());
+    }
+
+    info!(?path, "Loading config");
+    let contents = fs::read_to_string(path)?;
+    toml::from_str(&contents).context("Failed to parse config")
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub audio: AudioConfig,
+    pub backlight: BacklightConfig,
+    pub battery: BatteryConfig,
+    pub calendar: CalendarConfig,
+    pub clock: ClockConfig,
+    pub focused_window: FocusedWindowConfig,
+    pub launcher: LauncherConfig,
+    pub osd: OsdConfig,
+    pub power: PowerConfig,
+    pub taskbar: TaskbarConfig,
+    pub timers: TimersConfig,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PowerConfig {
+    /// Disable animations while running on battery, to save some power.
+    pub disable_animations_on_battery: bool,
+    /// How many seconds the power menu waits before running an action, giving you some time to
+    /// cancel it.
+    pub confirmation_countdown: u32,
+    pub profiles: PowerProfilesConfig,
+}
+
+impl Default for PowerConfig {
+    fn default() -> Self {
+        Self {
+            disable_animations_on_battery: false,
+            confirmation_countdown: 60,
+            profiles: PowerProfilesConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PowerProfilesConfig {
+    /// The power profile to switch to when unplugging the AC adapter, for example `power-saver`.
+    pub on_battery: Option<Profile>,
+    /// The power profile to switch to when plugging the AC adapter back, for example `balanced`.
+    pub on_ac: Option<Profile>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AudioConfig {
+    /// How much the volume actions raise or lower the volume, in percents.
+    pub step: f64,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self { step: 5.0 }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BacklightConfig {
+    /// The backlight device to control, for example `intel_backlight`.
+    ///
+    /// By default, we pick the first one from `/sys/class/backlight`, preferring firmware
+    /// interfaces.
+    pub device: Option<String>,
+    /// How much the brightness actions raise or lower the brightness, in percents.
+    pub step: f64,
+}
+
+impl Default for BacklightConfig {
+    fn default() -> Self {
+        Self {
+            device: None,
+            step: 5.0,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BatteryConfig {
+    /// The UPower device ID of the battery to show in the panel, for example `battery_BAT1`.
+    ///
+    /// By default, we use UPower's display device, which combines all the system batteries.
+    pub device: Option<String>,
+    pub warnings: BatteryWarningsConfig,
+    pub peripherals: PeripheralsConfig,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BatteryWarningsConfig {
+    /// Whether to warn when the battery is running low.
+    pub enable: bool,
+    /// The percentage at which the battery is considered low.
+    ///
+    /// UPower already computes a warning level with its own thresholds, this is only useful if
+    /// you want to get warned earlier than that.
+    pub low_threshold: Option<f64>,
+    /// The percentage at which the battery is considered critically low.
+    pub critical_threshold: Option<f64>,
+    /// A sound file to play alongside the warning.
+    pub sound: Option<PathBuf>,
+}
+
+impl Default for BatteryWarningsConfig {
+    fn default() -> Self {
+        Self {
+            enable: true,
+            low_threshold: None,
+            critical_threshold: None,
+            sound: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PeripheralsConfig {
+    /// Whether to send a notification when a peripheral battery is running low.
+    pub notify: bool,
+    /// The percentage at which a peripheral battery is considered low.
+    pub low_threshold: f64,
+}
+
+impl Default for PeripheralsConfig {
+    fn default() -> Self {
+        Self {
+            notify: true,
+            low_threshold: 20.0,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FocusedWindowConfig {
+    /// The maximum width of the window title, in characters, before it gets ellipsized.
+    pub max_width_chars: i32,
+    /// Where to cut the window title when it's too long.
+    pub ellipsize: Ellipsize,
+}
+
+impl Default for FocusedWindowConfig {
+    fn default() -> Self {
+        Self {
+            max_width_chars: 60,
+            ellipsize: Ellipsize::End,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Ellipsize {
+    /// Never cut the title, ignoring the maximum width.
+    None,
+    Start,
+    Middle,
+    #[default]
+    End,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TaskbarConfig {
+    /// The desktop entries of the applications to always show in the taskbar, even when they are
+    /// not running, for example `org.gnome.Nautilus.desktop`.
+    pub pinned: Vec<String>,
+    /// Whether to show the windows of the same application as a single button.
+    pub group_by_app: bool,
+}
+
+impl Default for TaskbarConfig {
+    fn default() -> Self {
+        Self {
+            pinned: vec![],
+            group_by_app: true,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LauncherConfig {
+    /// The URL opened to search the web, with `{}` replaced by the search query.
+    pub web_search_url: String,
+    /// The priority of each search provider, by name, to change the order of the results.
+    ///
+    /// Results of providers with a higher priority come first. The providers are `calculator`
+    /// (100), `command` (90), `apps` (50), `emoji` (30), `files` (20) and `web` (0).
+    pub priorities: HashMap<String, i32>,
+}
+
+impl Default for LauncherConfig {
+    fn default() -> Self {
+        Self {
+            web_search_url: "https://duckduckgo.com/?q={}".to_string(),
+            priorities: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CalendarConfig {
+    /// The calendars to show events from, as `.ics` files or directories of them, like the ones
+    /// vdirsyncer syncs to. Directories are read recursively.
+    pub paths: Vec<PathBuf>,
+    /// How many days of upcoming events the agenda shows.
+    pub agenda_days: u32,
+    /// Whether to show the week numbers in the calendar.
+    pub show_week_numbers: bool,
+}
+
+impl Default for CalendarConfig {
+    fn default() -> Self {
+        Self {
+            paths: vec![],
+            agenda_days: 7,
+            show_week_numbers: true,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClockConfig {
+    /// The format of the clock, see <https://docs.rs/chrono/latest/chrono/format/strftime>.
+    ///
+    /// By default, the weekday, the day of the month and the time.
+    pub format: Option<String>,
+    /// Whether to show the seconds with the default format.
+    pub show_seconds: bool,
+    /// Whether to show times with 12 or 24 hours.
+    pub hour_format: HourFormat,
+    /// Other timezones to show the time of, in the clock tooltip.
+    pub world_clocks: Vec<WorldClockConfig>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum HourFormat {
+    /// Use the format of the current locale.
+    #[default]
+    #[serde(rename = "locale")]
+    Locale,
+    #[serde(rename = "12h")]
+    TwelveHour,
+    #[serde(rename = "24h")]
+    TwentyFourHour,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorldClockConfig {
+    /// The timezone identifier, for example `America/New_York`.
+    pub timezone: String,
+    /// The name to show, by default the city of the timezone.
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimersConfig {
+    /// How long to snooze timers and alarms for, in minutes.
+    pub snooze_minutes: u32,
+    /// The durations of the timers to start in one click, in minutes.
+    pub presets: Vec<u32>,
+}
+
+impl Default for TimersConfig {
+    fn default() -> Self {
+        Self {
+            snooze_minutes: 5,
+            presets: vec![5, 10, 25],
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OsdConfig {
+    /// The outputs to show the on-screen display on.
+    pub output: OsdOutput,
+    /// How many milliseconds the on-screen display stays after the last change.
+    pub timeout: u32,
+}
+
+impl Default for OsdConfig {
+    fn default() -> Self {
+        Self {
+            output: OsdOutput::All,
+            timeout: 1500,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OsdOutput {
+    /// Show it on every output.
+    #[default]
+    All,
+    /// Show it only on the output with the focused window.
+    Focused,
+}
@@ -0,0 +1,418 @@
+//! Audio daemon.
+//!
+//! The daemon keeps a [`State`] of the audio graph: devices (sinks and sources), application
+//! streams, and the default devices. The state is kept up to date by a [`Backend`], which is also
+//! used to change it. Other parts of the shell subscribe to the state and react on changes.
+//!
+//! The only real backend is [`pipewire::PipeWireBackend`], but anything implementing [`Backend`]
+//! can be used with [`Daemon::new`], for example to test the shell without a sound server.
+#![allow(unused)]
+use std::collections::BTreeMap;
+use std::sync::{Arc, OnceLock};
+
+use tokio::sync::watch;
+
+pub mod pipewire;
+
+/// An audio daemon, used to control volume and audio devices.
+pub struct Daemon {
+    backend: Arc<dyn Backend>,
+    state: watch::Sender<State>,
+}
+
+impl Daemon {
+    /// Create a new [`Daemon`] on top of this [`Backend`].
+    ///
+    /// The backend gets ran on its own thread, sending state updates to the daemon.
+    pub fn new(backend: Arc<dyn Backend>) -> Self {
+        let state = watch::Sender::new(State::default());
+        let backend_state = state.clone();
+        let runner = Arc::clone(&backend);
+        super::spawn("audio-backend", async move { runner.run(backend_state) });
+        Self { backend, state }
+    }
+
+    /// Get a snapshot of the current audio state.
+    pub fn state(&self) -> State {
+        self.state.borrow().clone()
+    }
+
+    /// Subscribe to the changes of the audio state.
+    pub fn subscribe(&self) -> watch::Receiver<State> {
+        self.state.subscribe()
+    }
+
+    /// Set the volume of a node, `1.0` being 100%.
+    pub fn set_volume(&self, id: u32, volume: f64) -> anyhow::Result<()> {
+        self.backend.set_volume(id, volume.clamp(0.0, MAX_VOLUME))
+    }
+
+    /// Mute or unmute a node.
+    pub fn set_muted(&self, id: u32, muted: bool) -> anyhow::Result<()> {
+        self.backend.set_muted(id, muted)
+    }
+
+    /// Make a device the default one for its direction.
+    pub fn set_default(&self, id: u32) -> anyhow::Result<()> {
+        self.backend.set_default(id)
+    }
+
+    /// Move a stream to another device.
+    pub fn move_stream(&self, stream: u32, device: u32) -> anyhow::Result<()> {
+        let state = self.state.borrow();
+        let Some(device) = state.nodes.get(&device) else {
+            anyhow::bail!("No device with ID {device}");
+        };
+        self.backend.move_stream(stream, &device.name)
+    }
+
+    /// Raise or lower the volume of the default sink by `step` percents.
+    pub fn step_volume(&self, step: f64) -> anyhow::Result<()> {
+        let Some(sink) = self.state().default_sink().cloned() else {
+            anyhow::bail!("No default sink");
+        };
+        // Going up from silence should always unmute.
+        if sink.muted && step > 0.0 {
+            self.set_muted(sink.id, false)?;
+        }
+        // Don't go above 100% with steps, this is almost never what you want.
+        let max = sink.volume.max(1.0);
+        let volume = (sink.volume + step / 100.0).clamp(0.0, max);
+        self.set_volume(sink.id, volume)
+    }
+
+    /// Toggle the mute of the default sink.
+    pub fn toggle_muted(&self) -> anyhow::Result<()> {
+        let Some(sink) = self.state().default_sink().cloned() else {
+            anyhow::bail!("No default sink");
+        };
+        self.set_muted(sink.id, !sink.muted)
+    }
+}
+
+/// The maximum volume we allow, going over 100% amplifies the signal.
+pub const MAX_VOLUME: f64 = 1.5;
+
+/// A backend for the audio [`Daemon`].
+///
+/// Volumes are in the same scale as the one users see in volume controls, IE. cubic, with `1.0`
+/// being 100%.
+pub trait Backend: Send + Sync + 'static {
+    /// Run the backend, sending every change of the audio graph to `state`.
+    ///
+    /// This runs on its own thread, and should only return when the backend can't go on.
+    fn run(&self, state: watch::Sender<State>) -> anyhow::Result<()>;
+
+    /// Set the volume of a node.
+    fn set_volume(&self, id: u32, volume: f64) -> anyhow::Result<()>;
+
+    /// Mute or unmute a node.
+    fn set_muted(&self, id: u32, muted: bool) -> anyhow::Result<()>;
+
+    /// Make a device the default one for its direction.
+    fn set_default(&self, id: u32) -> anyhow::Result<()>;
+
+    /// Move a stream to another device, by its [`Node::name`].
+    fn move_stream(&self, stream: u32, device_name: &str) -> anyhow::Result<()>;
+}
+
+/// The state of the audio graph.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct State {
+    /// All the nodes we care about, by ID.
+    pub nodes: BTreeMap<u32, Node>,
+    /// The name of the default sink, see [`Node::name`].
+    pub default_sink: Option<String>,
+    /// The name of the default source, see [`Node::name`].
+    pub default_source: Option<String>,
+}
+
+impl State {
+    /// Get the nodes of this kind.
+    pub fn nodes(&self, kind: NodeKind) -> impl Iterator<Item = &Node> {
+        self.nodes.values().filter(move |node| node.kind == kind)
+    }
+
+    /// Get the default device of this kind.
+    ///
+    /// `kind` must be [`NodeKind::Sink`] or [`NodeKind::Source`].
+    pub fn default_device(&self, kind: NodeKind) -> Option<&Node> {
+        let name = match kind {
+            NodeKind::Sink => self.default_sink.as_deref()?,
+            NodeKind::Source => self.default_source.as_deref()?,
+            _ => return None,
+        };
+        self.nodes(kind).find(|node| node.name == name)
+    }
+
+    /// Get the default output device.
+    pub fn default_sink(&self) -> Option<&Node> {
+        self.default_device(NodeKind::Sink)
+    }
+
+    /// Get the default input device.
+    pub fn default_source(&self) -> Option<&Node> {
+        self.default_device(NodeKind::Source)
+    }
+}
+
+/// What a [`Node`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    /// An output device, like speakers or headphones.
+    Sink,
+    /// An input device, like a microphone.
+    Source,
+    /// An application playing audio.
+    PlaybackStream,
+    /// An application recording audio.
+    RecordingStream,
+}
+
+/// A node of the audio graph, either a device or an application stream.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub id: u32,
+    pub kind: NodeKind,
+    /// The unique name of the node, IE. `alsa_output.pci-0000_00_1f.3.analog-stereo`
+    pub name: String,
+    /// A human readable description of the node.
+    ///
+    /// For devices, this is the device name. For streams, this is what's playing, if known.
+    pub description: String,
+    /// The volume of the node, `1.0` being 100%.
+    pub volume: f64,
+    pub muted: bool,
+    /// The name of the application owning this stream.
+    pub app_name: Option<String>,
+    /// The icon of the application owning this stream.
+    pub app_icon_name: Option<String>,
+    /// The process owning this stream.
+    pub app_pid: Option<u32>,
+    /// The device this stream is linked to.
+    pub target: Option<u32>,
+}
+
+impl Node {
+    /// Get the volume icon matching the volume and mute of this node.
+    pub fn volume_icon_name(&self) -> &'static str {
+        match self.kind {
+            NodeKind::Source | NodeKind::RecordingStream => {
+                if self.muted || self.volume <= 0.0 {
+                    "microphone-sensitivity-muted-symbolic"
+                } else if self.volume < 0.33 {
+                    "microphone-sensitivity-low-symbolic"
+                } else if self.volume < 0.66 {
+                    "microphone-sensitivity-medium-symbolic"
+                } else {
+                    "microphone-sensitivity-high-symbolic"
+                }
+            }
+            NodeKind::Sink | NodeKind::PlaybackStream => {
+                if self.muted || self.volume <= 0.0 {
+                    "audio-volume-muted-symbolic"
+                } else if self.volume < 0.33 {
+                    "audio-volume-low-symbolic"
+                } else if self.volume < 0.66 {
+                    "audio-volume-medium-symbolic"
+                } else if self.volume <= 1.0 {
+                    "audio-volume-high-symbolic"
+                } else {
+                    "audio-volume-overamplified-symbolic"
+                }
+            }
+        }
+    }
+}
+
+static INSTANCE: OnceLock<Daemon> = OnceLock::new();
+
+pub fn get() -> &'static Daemon {
+    INSTANCE.get().expect("daemons::start() must be called")
+}
+
+pub async fn start() -> anyhow::Result<()> {
+    if INSTANCE.get().is_some() {
+        return Ok(());
+    }
+
+    let daemon = Daemon::new(Arc::new(pipewire::PipeWireBackend::new()));
+    // NOTE: If we already started he handled it above.
+    let _ = INSTANCE.set(daemon);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Call {
+        SetVolume(u32, f64),
+        SetMuted(u32, bool),
+        SetDefault(u32),
+        MoveStream(u32, String),
+    }
+
+    /// A [`Backend`] recording the calls made to it.
+    #[derive(Default)]
+    struct MockBackend {
+        calls: Mutex<Vec<Call>>,
+    }
+
+    impl MockBackend {
+        fn take_calls(&self) -> Vec<Call> {
+            std::mem::take(&mut self.calls.lock().unwrap())
+        }
+    }
+
+    impl Backend for MockBackend {
+        fn run(&self, _state: watch::Sender<State>) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn set_volume(&self, id: u32, volume: f64) -> anyhow::Result<()> {
+            self.calls.lock().unwrap().push(Call::SetVolume(id, volume));
+            Ok(())
+        }
+
+        fn set_muted(&self, id: u32, muted: bool) -> anyhow::Result<()> {
+            self.calls.lock().unwrap().push(Call::SetMuted(id, muted));
+            Ok(())
+        }
+
+        fn set_default(&self, id: u32) -> anyhow::Result<()> {
+            self.calls.lock().unwrap().push(Call::SetDefault(id));
+            Ok(())
+        }
+
+        fn move_stream(&self, stream: u32, device_name: &str) -> anyhow::Result<()> {
+            let call = Call::MoveStream(stream, device_name.to_string());
+            self.calls.lock().unwrap().push(call);
+            Ok(())
+        }
+    }
+
+    fn node(id: u32, kind: NodeKind, name: &str) -> Node {
+        Node {
+            id,
+            kind,
+            name: name.to_string(),
+            description: String::new(),
+            volume: 0.5,
+            muted: false,
+            app_name: None,
+            app_icon_name: None,
+            app_pid: None,
+            target: None,
+        }
+    }
+
+    /// Create a daemon with a speakers sink and a playback stream.
+    fn mock_daemon(volume: f64, muted: bool) -> (Daemon, Arc<MockBackend>) {
+        let backend = Arc::new(MockBackend::default());
+        let daemon = Daemon::new(Arc::clone(&backend) as Arc<dyn Backend>);
+
+        let speakers = Node {
+            volume,
+            muted,
+            ..node(1, NodeKind::Sink, "speakers")
+        };
+        let headphones = node(2, NodeKind::Sink, "headphones");
+        let stream = node(3, NodeKind::PlaybackStream, "stream");
+        daemon.state.send_replace(State {
+            nodes: [(1, speakers), (2, headphones), (3, stream)].into(),
+            default_sink: Some("speakers".to_string()),
+            default_source: None,
+        });
+
+        (daemon, backend)
+    }
+
+    #[test]
+    fn step_volume() {
+        let (daemon, backend) = mock_daemon(0.5, false);
+        daemon.step_volume(5.0).unwrap();
+        daemon.step_volume(-10.0).unwrap();
+        assert_eq!(
+            backend.take_calls(),
+            [Call::SetVolume(1, 0.55), Call::SetVolume(1, 0.4)]
+        );
+    }
+
+    #[test]
+    fn step_volume_is_clamped() {
+        let (daemon, backend) = mock_daemon(0.97, false);
+        daemon.step_volume(5.0).unwrap();
+        assert_eq!(backend.take_calls(), [Call::SetVolume(1, 1.0)]);
+
+        let (daemon, backend) = mock_daemon(0.02, false);
+        daemon.step_volume(-5.0).unwrap();
+        assert_eq!(backend.take_calls(), [Call::SetVolume(1, 0.0)]);
+
+        // Steps don't amplify more, but don't bring the volume back to 100% either.
+        let (daemon, backend) = mock_daemon(1.2, false);
+        daemon.step_volume(5.0).unwrap();
+        assert_eq!(backend.take_calls(), [Call::SetVolume(1, 1.2)]);
+    }
+
+    #[test]
+    fn step_volume_up_unmutes() {
+        let (daemon, backend) = mock_daemon(0.5, true);
+        daemon.step_volume(-5.0).unwrap();
+        assert_eq!(backend.take_calls(), [Call::SetVolume(1, 0.45)]);
+
+        daemon.step_volume(5.0).unwrap();
+        assert_eq!(
+            backend.take_calls(),
+            [Call::SetMuted(1, false), Call::SetVolume(1, 0.55)]
+        );
+    }
+
+    #[test]
+    fn set_volume_is_clamped() {
+        let (daemon, backend) = mock_daemon(0.5, false);
+        daemon.set_volume(3, 2.0).unwrap();
+        daemon.set_volume(3, -1.0).unwrap();
+        assert_eq!(
+            backend.take_calls(),
+            [Call::SetVolume(3, MAX_VOLUME), Call::SetVolume(3, 0.0)]
+        );
+    }
+
+    #[test]
+    fn toggle_muted() {
+        let (daemon, backend) = mock_daemon(0.5, false);
+        daemon.toggle_muted().unwrap();
+        assert_eq!(backend.take_calls(), [Call::SetMuted(1, true)]);
+
+        let (daemon, backend) = mock_daemon(0.5, true);
+        daemon.toggle_muted().unwrap();
+        assert_eq!(backend.take_calls(), [Call::SetMuted(1, false)]);
+    }
+
+    #[test]
+    fn no_default_sink() {
+        let (daemon, backend) = mock_daemon(0.5, false);
+        daemon.state.send_modify(|state| state.default_sink = None);
+        assert!(daemon.step_volume(5.0).is_err());
+        assert!(daemon.toggle_muted().is_err());
+        assert_eq!(backend.take_calls(), []);
+    }
+
+    #[test]
+    fn move_stream() {
+        let (daemon, backend) = mock_daemon(0.5, false);
+        daemon.move_stream(3, 2).unwrap();
+        assert_eq!(
+            backend.take_calls(),
+            [Call::MoveStream(3, "headphones".to_string())]
+        );
+
+        assert!(daemon.move_stream(3, 42).is_err());
+        assert_eq!(backend.take_calls(), []);
+    }
+}
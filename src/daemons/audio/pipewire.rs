@@ -0,0 +1,547 @@
+//! PipeWire audio backend.
+//!
+//! The pipewire and wireplumber crates are stuck on an older glib than the one we use, so instead
+//! we go through the command line tools that ship with PipeWire and WirePlumber: `pw-dump` to
+//! follow the audio graph, and `wpctl` and `pw-metadata` to change it.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::BufReader;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use serde_json::Value;
+use tokio::sync::watch;
+
+use super::{Backend, Node, NodeKind, State};
+
+/// How long we wait before restarting `pw-dump` when it exits, for example when PipeWire gets
+/// restarted.
+const RESTART_DELAY: Duration = Duration::from_secs(2);
+
+/// A [`Backend`] using the PipeWire command line tools.
+pub struct PipeWireBackend {
+    volumes: Arc<PendingVolumes>,
+}
+
+impl PipeWireBackend {
+    pub fn new() -> Self {
+        let volumes = Arc::new(PendingVolumes::default());
+        let writer_volumes = Arc::clone(&volumes);
+        std::thread::Builder::new()
+            .name("audio-volumes".to_string())
+            .spawn(move || loop {
+                let (id, volume) = writer_volumes.next();
+                // wpctl uses the same cubic scale as us.
+                run_command(
+                    "wpctl",
+                    &["set-volume", &id.to_string(), &format!("{volume:.3}")],
+                );
+            })
+            .expect("Failed to spawn audio volumes thread");
+
+        Self { volumes }
+    }
+}
+
+impl Backend for PipeWireBackend {
+    fn run(&self, state: watch::Sender<State>) -> anyhow::Result<()> {
+        loop {
+            let mut child = Command::new("pw-dump")
+                .args(["--monitor", "--no-colors"])
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()?;
+            let stdout = child.stdout.take().expect("stdout is piped");
+
+            let mut graph = Graph::default();
+            let updates = serde_json::Deserializer::from_reader(BufReader::new(stdout))
+                .into_iter::<Vec<Value>>();
+            for update in updates {
+                let update = match update {
+                    Ok(update) => update,
+                    Err(err) => {
+                        warn!(?err, "Failed to parse pw-dump output");
+                        break;
+                    }
+                };
+
+                for object in update {
+                    graph.apply(object);
+                }
+                let new_state = graph.to_state();
+                state.send_if_modified(|current| {
+                    if *current == new_state {
+                        return false;
+                    }
+                    *current = new_state;
+                    true
+                });
+            }
+
+            let status = child.wait()?;
+            warn!(?status, "pw-dump exited, restarting it");
+            state.send_replace(State::default());
+            std::thread::sleep(RESTART_DELAY);
+        }
+    }
+
+    fn set_volume(&self, id: u32, volume: f64) -> anyhow::Result<()> {
+        self.volumes.set(id, volume);
+        Ok(())
+    }
+
+    fn set_muted(&self, id: u32, muted: bool) -> anyhow::Result<()> {
+        let muted = if muted { "1" } else { "0" };
+        spawn_command("wpctl", &["set-mute", &id.to_string(), muted])
+    }
+
+    fn set_default(&self, id: u32) -> anyhow::Result<()> {
+        spawn_command("wpctl", &["set-default", &id.to_string()])
+    }
+
+    fn move_stream(&self, stream: u32, device_name: &str) -> anyhow::Result<()> {
+        // WirePlumber follows the target.object metadata of the stream, and relinks it.
+        spawn_command(
+            "pw-metadata",
+            &[&stream.to_string(), "target.object", device_name],
+        )
+    }
+}
+
+/// Spawn a command, logging its failure without waiting for it.
+fn spawn_command(program: &str, args: &[&str]) -> anyhow::Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    let command = format!("{program} {}", args.join(" "));
+    std::thread::spawn(move || match child.wait() {
+        Ok(status) if status.success() => (),
+        Ok(status) => error!(?status, command, "Audio command failed"),
+        Err(err) => error!(?err, command, "Failed to wait for audio command"),
+    });
+
+    Ok(())
+}
+
+/// Run a command until it exits, logging its failure.
+fn run_command(program: &str, args: &[&str]) {
+    let status = Command::new(program)
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+
+    let command = format!("{program} {}", args.join(" "));
+    match status {
+        Ok(status) if status.success() => (),
+        Ok(status) => error!(?status, command, "Audio command failed"),
+        Err(err) => error!(?err, command, "Failed to run audio command"),
+    }
+}
+
+/// The volumes waiting to be written, by node ID.
+///
+/// Volume sliders change the volume a lot while being dragged, and running a command for each
+/// change piles up processes. Instead, volumes get written one at a time, and only the latest
+/// volume of a node gets written.
+#[derive(Default)]
+struct PendingVolumes {
+    volumes: Mutex<BTreeMap<u32, f64>>,
+    changed: Condvar,
+}
+
+impl PendingVolumes {
+    fn set(&self, id: u32, volume: f64) {
+        self.volumes.lock().unwrap().insert(id, volume);
+        self.changed.notify_one();
+    }
+
+    /// Wait for the next volume to write.
+    fn next(&self) -> (u32, f64) {
+        let mut volumes = self.volumes.lock().unwrap();
+        loop {
+            if let Some(volume) = volumes.pop_first() {
+                return volume;
+            }
+            volumes = self.changed.wait(volumes).unwrap();
+        }
+    }
+}
+
+/// The PipeWire objects we care about, as reported by `pw-dump`.
+#[derive(Default)]
+struct Graph {
+    nodes: HashMap<u32, Node>,
+    // Links between nodes, as (output node, input node).
+    links: HashMap<u32, (u32, u32)>,
+    // The default metadata, by key.
+    metadata: HashMap<String, String>,
+    // The ID of the default metadata object.
+    metadata_id: Option<u32>,
+}
+
+impl Graph {
+    /// Apply an object from `pw-dump` to the graph.
+    fn apply(&mut self, object: Value) {
+        let Some(id) = object["id"].as_u64().map(|id| id as u32) else {
+            return;
+        };
+
+        // Removed objects are reported with a null info.
+        if object.get("info").is_some_and(Value::is_null) {
+            self.nodes.remove(&id);
+            self.links.remove(&id);
+            if self.metadata_id == Some(id) {
+                self.metadata_id = None;
+                self.metadata.clear();
+            }
+            return;
+        }
+
+        match object["type"].as_str() {
+            Some("PipeWire:Interface:Node") => {
+                let previous = self.nodes.remove(&id);
+                if let Some(node) = parse_node(id, &object["info"], previous) {
+                    self.nodes.insert(id, node);
+                }
+            }
+            Some("PipeWire:Interface:Link") => {
+                let info = &object["info"];
+                let output = info["output-node-id"].as_u64();
+                let input = info["input-node-id"].as_u64();
+                if let (Some(output), Some(input)) = (output, input) {
+                    self.links.insert(id, (output as u32, input as u32));
+                }
+            }
+            Some("PipeWire:Interface:Metadata")
+                if object["props"]["metadata.name"].as_str() == Some("default") =>
+            {
+                self.metadata_id = Some(id);
+                let Some(entries) = object["metadata"].as_array() else {
+                    return;
+                };
+                for entry in entries {
+                    let Some(key) = entry["key"].as_str() else {
+                        continue;
+                    };
+                    // The default devices are JSON objects with the node name.
+                    let value = match &entry["value"] {
+                        Value::Null => {
+                            self.metadata.remove(key);
+                            continue;
+                        }
+                        Value::Object(value) => value.get("name").and_then(Value::as_str),
+                        Value::String(value) => Some(value.as_str()),
+                        _ => None,
+                    };
+                    if let Some(value) = value {
+                        self.metadata.insert(key.to_string(), value.to_string());
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn to_state(&self) -> State {
+        let mut nodes = self.nodes.clone();
+        // Streams are linked to a single device in practice.
+        for (output, input) in self.links.values() {
+            if let Some(node) = nodes.get_mut(output) {
+                if node.kind == NodeKind::PlaybackStream {
+                    node.target = Some(*input);
+                }
+            }
+            if let Some(node) = nodes.get_mut(input) {
+                if node.kind == NodeKind::RecordingStream {
+                    node.target = Some(*output);
+                }
+            }
+        }
+
+        State {
+            nodes: nodes.into_iter().collect(),
+            default_sink: self.metadata.get("default.audio.sink").cloned(),
+            default_source: self.metadata.get("default.audio.source").cloned(),
+        }
+    }
+}
+
+fn parse_node(id: u32, info: &Value, previous: Option<Node>) -> Option<Node> {
+    // Props and params are only sent when they change, keep the previous values otherwise.
+    let mut node = match &info["props"] {
+        Value::Null => previous?,
+        props => {
+            let mut node = parse_props(id, props)?;
+            if let Some(previous) = previous {
+                node.volume = previous.volume;
+                node.muted = previous.muted;
+            }
+            node
+        }
+    };
+
+    if let Some(params) = info["params"]["Props"].as_array() {
+        for param in params {
+            if let Some(channel_volumes) = param["channelVolumes"].as_array() {
+                // PipeWire volumes are linear, convert them to the cubic scale users know.
+                let linear = channel_volumes
+                    .iter()
+                    .filter_map(Value::as_f64)
+                    .fold(0.0, f64::max);
+                node.volume = linear.cbrt();
+            }
+            if let Some(mute) = param["mute"].as_bool() {
+                node.muted = mute;
+            }
+        }
+    }
+
+    Some(node)
+}
+
+/// Parse a node from its props, with the default volume.
+fn parse_props(id: u32, props: &Value) -> Option<Node> {
+    let kind = match props["media.class"].as_str()? {
+        "Audio/Sink" => NodeKind::Sink,
+        "Audio/Source" => NodeKind::Source,
+        "Stream/Output/Audio" => NodeKind::PlaybackStream,
+        "Stream/Input/Audio" => NodeKind::RecordingStream,
+        _ => return None,
+    };
+    let string = |key: &str| props[key].as_str().map(str::to_string);
+
+    let name = string("node.name").unwrap_or_default();
+    let description = match kind {
+        NodeKind::Sink | NodeKind::Source => string("node.description")
+            .or_else(|| string("node.nick"))
+            .unwrap_or_else(|| name.clone()),
+        NodeKind::PlaybackStream | NodeKind::RecordingStream => {
+            string("media.name").unwrap_or_default()
+        }
+    };
+
+    Some(Node {
+        id,
+        kind,
+        name,
+        description,
+        volume: 1.0,
+        muted: false,
+        app_name: string("application.name"),
+        app_icon_name: string("application.icon-name"),
+        app_pid: props["application.process.id"]
+            .as_u64()
+            .or_else(|| props["application.process.id"].as_str()?.parse().ok())
+            .map(|pid| pid as u32),
+        target: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// Apply a `pw-dump --monitor` output to a new graph.
+    fn graph(dump: &str) -> Graph {
+        let mut graph = Graph::default();
+        for update in serde_json::Deserializer::from_str(dump).into_iter::<Vec<Value>>() {
+            for object in update.unwrap() {
+                graph.apply(object);
+            }
+        }
+        graph
+    }
+
+    #[test]
+    fn pending_volumes_keep_the_latest_one() {
+        let volumes = PendingVolumes::default();
+        volumes.set(50, 0.1);
+        volumes.set(80, 0.7);
+        volumes.set(50, 0.2);
+        volumes.set(50, 0.3);
+        assert_eq!(volumes.next(), (50, 0.3));
+        assert_eq!(volumes.next(), (80, 0.7));
+        assert!(volumes.volumes.lock().unwrap().is_empty());
+
+        // Waiting for the next one.
+        let volumes = Arc::new(volumes);
+        let writer_volumes = Arc::clone(&volumes);
+        let writer = std::thread::spawn(move || writer_volumes.next());
+        volumes.set(51, 1.0);
+        assert_eq!(writer.join().unwrap(), (51, 1.0));
+    }
+
+    #[test]
+    fn initial_dump() {
+        let state = graph(include_str!("fixtures/pw-dump.json")).to_state();
+
+        // The video node and the MIDI bridge are not audio nodes.
+        assert_eq!(
+            state.nodes.keys().copied().collect::<Vec<_>>(),
+            [50, 51, 80, 90]
+        );
+        assert_eq!(
+            state.default_sink.as_deref(),
+            Some("alsa_output.pci-0000_00_1f.3.analog-stereo")
+        );
+        assert_eq!(
+            state.default_source.as_deref(),
+            Some("alsa_input.pci-0000_00_1f.3.analog-stereo")
+        );
+
+        let sink = state.default_sink().unwrap();
+        assert_eq!(sink.id, 50);
+        assert_eq!(sink.description, "Built-in Audio Analog Stereo");
+        assert!((sink.volume - 0.5).abs() < 1e-6);
+        assert!(!sink.muted);
+
+        let source = state.default_source().unwrap();
+        assert_eq!(source.id, 51);
+        assert!(source.muted);
+
+        let playback = &state.nodes[&80];
+        assert_eq!(playback.kind, NodeKind::PlaybackStream);
+        assert_eq!(playback.description, "Big Buck Bunny");
+        assert_eq!(playback.app_name.as_deref(), Some("Firefox"));
+        assert_eq!(playback.app_icon_name.as_deref(), Some("firefox"));
+        assert_eq!(playback.app_pid, Some(4242));
+        assert_eq!(playback.target, Some(50));
+
+        let recording = &state.nodes[&90];
+        assert_eq!(recording.kind, NodeKind::RecordingStream);
+        assert_eq!(recording.app_pid, Some(1337));
+        assert_eq!(recording.target, Some(51));
+    }
+
+    #[test]
+    fn updates() {
+        let mut graph = graph(include_str!("fixtures/pw-dump.json"));
+
+        // Only the changed params, without props.
+        graph.apply(json!({
+            "id": 50,
+            "type": "PipeWire:Interface:Node",
+            "info": {
+                "change-mask": ["params"],
+                "params": { "Props": [{ "mute": true, "channelVolumes": [0.001, 0.008] }] }
+            }
+        }));
+        // The stream moving to another sink.
+        graph.apply(json!({ "id": 100, "info": null }));
+        graph.apply(json!({
+            "id": 101,
+            "type": "PipeWire:Interface:Link",
+            "info": { "output-node-id": 80, "input-node-id": 60 }
+        }));
+        graph.apply(json!({
+            "id": 60,
+            "type": "PipeWire:Interface:Node",
+            "info": {
+                "props": {
+                    "media.class": "Audio/Sink",
+                    "node.name": "bluez_output.00_11_22_33_44_55.1",
+                    "node.nick": "Headphones"
+                }
+            }
+        }));
+        // The default sink changing too, and the recording going away.
+        graph.apply(json!({
+            "id": 40,
+            "type": "PipeWire:Interface:Metadata",
+            "props": { "metadata.name": "default" },
+            "metadata": [
+                {
+                    "subject": 0,
+                    "key": "default.audio.sink",
+                    "type": "Spa:String:JSON",
+                    "value": { "name": "bluez_output.00_11_22_33_44_55.1" }
+                },
+                { "subject": 0, "key": "default.audio.source", "value": null }
+            ]
+        }));
+        graph.apply(json!({ "id": 90, "info": null }));
+        let state = graph.to_state();
+
+        let sink = &state.nodes[&50];
+        assert_eq!(sink.description, "Built-in Audio Analog Stereo");
+        assert!((sink.volume - 0.2).abs() < 1e-6);
+        assert!(sink.muted);
+
+        assert_eq!(state.default_sink().unwrap().description, "Headphones");
+        assert_eq!(state.default_sink().unwrap().volume, 1.0);
+        assert_eq!(state.default_source, None);
+        assert_eq!(state.nodes[&80].target, Some(60));
+        assert!(!state.nodes.contains_key(&90));
+    }
+
+    #[test]
+    fn parse_node_kinds() {
+        let info = |class: &str| json!({ "props": { "media.class": class, "node.name": "node" } });
+        let kind = |class| parse_node(1, &info(class), None).map(|node| node.kind);
+        assert_eq!(kind("Audio/Sink"), Some(NodeKind::Sink));
+        assert_eq!(kind("Audio/Source"), Some(NodeKind::Source));
+        assert_eq!(kind("Stream/Output/Audio"), Some(NodeKind::PlaybackStream));
+        assert_eq!(kind("Stream/Input/Audio"), Some(NodeKind::RecordingStream));
+        assert_eq!(kind("Video/Source"), None);
+        assert_eq!(parse_node(1, &json!({}), None), None);
+    }
+
+    #[test]
+    fn parse_node_descriptions() {
+        let device = json!({
+            "props": { "media.class": "Audio/Sink", "node.name": "sink", "node.nick": "Nick" }
+        });
+        assert_eq!(parse_node(1, &device, None).unwrap().description, "Nick");
+        let device = json!({ "props": { "media.class": "Audio/Sink", "node.name": "sink" } });
+        assert_eq!(parse_node(1, &device, None).unwrap().description, "sink");
+
+        let stream = json!({
+            "props": {
+                "media.class": "Stream/Output/Audio",
+                "node.name": "stream",
+                "application.process.id": "12"
+            }
+        });
+        let stream = parse_node(1, &stream, None).unwrap();
+        assert_eq!(stream.description, "");
+        assert_eq!(stream.app_pid, Some(12));
+    }
+
+    #[test]
+    fn parse_node_keeps_previous_values() {
+        let info = json!({
+            "props": { "media.class": "Audio/Sink", "node.name": "sink" },
+            "params": { "Props": [{ "channelVolumes": [0.125, 0.125], "mute": true }] }
+        });
+        let node = parse_node(1, &info, None).unwrap();
+        assert!((node.volume - 0.5).abs() < 1e-6);
+        assert!(node.muted);
+
+        // New props without params.
+        let info = json!({
+            "props": { "media.class": "Audio/Sink", "node.name": "sink", "node.nick": "Renamed" }
+        });
+        let renamed = parse_node(1, &info, Some(node.clone())).unwrap();
+        assert_eq!(renamed.description, "Renamed");
+        assert_eq!((renamed.volume, renamed.muted), (node.volume, node.muted));
+
+        // New params without props.
+        let info = json!({ "params": { "Props": [{ "mute": false }] } });
+        let unmuted = parse_node(1, &info, Some(renamed.clone())).unwrap();
+        assert_eq!(
+            unmuted,
+            Node {
+                muted: false,
+                ..renamed
+            }
+        );
+        assert_eq!(parse_node(1, &info, None), None);
+    }
+}
@@ -0,0 +1,467 @@
+//! Screen backlight daemon.
+//!
+//! The backlight devices are read from sysfs, under `/sys/class/backlight`, and written through
+//! logind's `Session.SetBrightness`, so that we don't need root permissions or udev rules.
+//!
+//! sysfs attributes don't support inotify, but the kernel sends an uevent whenever the brightness
+//! of a backlight changes, be it from us, the firmware or another program.
+#![allow(unused)]
+use std::os::fd::OwnedFd;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::{fs, io};
+
+use async_io::Async;
+use rustix::io::Errno;
+use rustix::net::netlink::{self, SocketAddrNetlink};
+use rustix::net::{self, AddressFamily, RecvFlags, SocketFlags, SocketType};
+use tokio::sync::watch;
+
+/// Where the kernel exposes backlight devices.
+pub const SYSFS_ROOT: &str = "/sys/class/backlight";
+
+/// A backlight daemon, used to control the brightness of the screen.
+pub struct Daemon {
+    root: PathBuf,
+    /// The device to use when it exists, instead of the preferred one.
+    device: Option<String>,
+    backlight: watch::Sender<Option<Backlight>>,
+}
+
+impl Daemon {
+    /// Create a new [`Daemon`] reading backlight devices from `root`, using `device` if any.
+    ///
+    /// `root` is [`SYSFS_ROOT`] outside of testing.
+    pub fn new(root: impl Into<PathBuf>, device: Option<String>) -> Self {
+        let root = root.into();
+        let backlight = pick_backlight(&root, device.as_deref());
+        Self {
+            root,
+            device,
+            backlight: watch::Sender::new(backlight),
+        }
+    }
+
+    /// Get the backlight we are controlling, if any.
+    pub fn backlight(&self) -> Option<Backlight> {
+        self.backlight.borrow().clone()
+    }
+
+    /// Subscribe to the changes of the backlight, including the ones made outside of the shell.
+    pub fn subscribe(&self) -> watch::Receiver<Option<Backlight>> {
+        self.backlight.subscribe()
+    }
+
+    /// Read the backlight state from sysfs again, notifying subscribers if anything changed.
+    pub fn refresh(&self) {
+        let name = self.backlight.borrow().as_ref().map(|b| b.name.clone());
+        let backlight = match name {
+            Some(name) => match Backlight::read(&self.root, &name) {
+                Ok(backlight) => Some(backlight),
+                // The device went away, fallback to another one.
+                Err(_) => pick_backlight(&self.root, self.device.as_deref()),
+            },
+            None => pick_backlight(&self.root, self.device.as_deref()),
+        };
+
+        self.backlight.send_if_modified(|current| {
+            if *current == backlight {
+                return false;
+            }
+            *current = backlight;
+            true
+        });
+    }
+
+    /// Set the brightness of the backlight, in raw device units.
+    pub async fn set_brightness(&self, brightness: u32) -> zbus::Result<()> {
+        let Some(backlight) = self.backlight() else {
+            return Err(zbus::Error::Failure("No backlight device".to_string()));
+        };
+        let brightness = brightness.min(backlight.max_brightness);
+        super::logind::get()
+            .session()
+            .ok_or_else(|| zbus::Error::Failure("Not running in a logind session".to_string()))?
+            .set_brightness("backlight", &backlight.name, brightness)
+            .await?;
+
+        // Don't wait for the uevent to update everyone.
+        self.refresh();
+        Ok(())
+    }
+
+    /// Set the brightness of the backlight, from `0.0` to `1.0`.
+    pub async fn set_fraction(&self, fraction: f64) -> zbus::Result<()> {
+        let Some(backlight) = self.backlight() else {
+            return Err(zbus::Error::Failure("No backlight device".to_string()));
+        };
+        self.set_brightness(backlight.brightness_for(fraction))
+            .await
+    }
+
+    /// Raise or lower the brightness by `step` percents.
+    pub async fn step(&self, step: f64) -> zbus::Result<()> {
+        let Some(backlight) = self.backlight() else {
+            return Err(zbus::Error::Failure("No backlight device".to_string()));
+        };
+        self.set_brightness(backlight.stepped(step)).await
+    }
+}
+
+/// A backlight device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backlight {
+    /// The name of the device, IE. `intel_backlight`
+    pub name: String,
+    pub brightness: u32,
+    pub max_brightness: u32,
+}
+
+impl Backlight {
+    /// Read a backlight device from sysfs.
+    pub fn read(root: &Path, name: &str) -> io::Result<Self> {
+        let path = root.join(name);
+        Ok(Self {
+            name: name.to_string(),
+            brightness: read_u32(&path.join("brightness"))?,
+            max_brightness: read_u32(&path.join("max_brightness"))?,
+        })
+    }
+
+    /// Get the brightness, from `0.0` to `1.0`.
+    pub fn fraction(&self) -> f64 {
+        if self.max_brightness == 0 {
+            return 0.0;
+        }
+        f64::from(self.brightness) / f64::from(self.max_brightness)
+    }
+
+    /// Get the raw brightness matching this fraction.
+    pub fn brightness_for(&self, fraction: f64) -> u32 {
+        let fraction = fraction.clamp(0.0, 1.0);
+        (fraction * f64::from(self.max_brightness)).round() as u32
+    }
+
+    /// Get the raw brightness `step` percents away from the current one.
+    pub fn stepped(&self, step: f64) -> u32 {
+        let brightness = self.brightness_for(self.fraction() + step / 100.0);
+        if brightness != self.brightness {
+            return brightness;
+        }
+
+        // Make sure we move at least by one unit on devices with a few levels.
+        if step > 0.0 {
+            brightness.saturating_add(1).min(self.max_brightness)
+        } else {
+            brightness.saturating_sub(1)
+        }
+    }
+}
+
+fn read_u32(path: &Path) -> io::Result<u32> {
+    let contents = fs::read_to_string(path)?;
+    contents
+        .trim()
+        .parse()
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// List the backlight devices under `root`, the preferred ones first.
+///
+/// Firmware interfaces usually work best, then platform specific ones, and then raw access to the
+/// graphics card registers, this is the order the kernel documentation recommends.
+pub fn enumerate(root: &Path) -> io::Result<Vec<String>> {
+    let mut devices = vec![];
+    for entry in fs::read_dir(root)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let type_ = fs::read_to_string(entry.path().join("type")).unwrap_or_default();
+        let priority = match type_.trim() {
+            "firmware" => 0,
+            "platform" => 1,
+            "raw" => 2,
+            _ => 3,
+        };
+        devices.push((priority, name));
+    }
+
+    devices.sort();
+    Ok(devices.into_iter().map(|(_, name)| name).collect())
+}
+
+/// Pick the backlight device to control, either `device` or the preferred one.
+fn pick_backlight(root: &Path, device: Option<&str>) -> Option<Backlight> {
+    if let Some(name) = device {
+        return Backlight::read(root, name)
+            .inspect_err(|err| warn!(?err, name, "Failed to read configured backlight"))
+            .ok();
+    }
+
+    enumerate(root)
+        .ok()?
+        .into_iter()
+        .find_map(|name| Backlight::read(root, &name).ok())
+}
+
+/// Listens to the kernel uevents about backlight devices.
+struct UeventListener(Async<OwnedFd>);
+
+impl UeventListener {
+    fn new() -> io::Result<Self> {
+        let socket = net::socket_with(
+            AddressFamily::NETLINK,
+            SocketType::DGRAM,
+            SocketFlags::CLOEXEC | SocketFlags::NONBLOCK,
+            Some(netlink::KOBJECT_UEVENT),
+        )?;
+        // The kernel sends its uevents to the first multicast group, udev uses the second one.
+        net::bind(&socket, &SocketAddrNetlink::new(0, 1))?;
+        Ok(Self(Async::new(socket)?))
+    }
+
+    /// Wait until a backlight device changes.
+    async fn changed(&self) -> io::Result<()> {
+        let mut buffer = vec![0; 8192];
+        loop {
+            let received = self
+                .0
+                .read_with(
+                    |socket| match net::recv(socket, &mut buffer[..], RecvFlags::empty()) {
+                        Ok((len, _)) => Ok(Some(len)),
+                        // We missed some uevents, they might have been about backlights.
+                        Err(Errno::NOBUFS) => Ok(None),
+                        Err(err) => Err(err.into()),
+                    },
+                )
+                .await?;
+            match received {
+                Some(len) if !is_backlight_uevent(&buffer[..len]) => continue,
+                _ => return Ok(()),
+            }
+        }
+    }
+}
+
+/// Check whether a kernel uevent is about a backlight device.
+///
+/// They look like `change@/devices/...\0ACTION=change\0...\0SUBSYSTEM=backlight\0...`
+fn is_backlight_uevent(uevent: &[u8]) -> bool {
+    uevent
+        .split(|&byte| byte == 0)
+        .skip(1)
+        .any(|field| field == b"SUBSYSTEM=backlight")
+}
+
+static INSTANCE: OnceLock<Daemon> = OnceLock::new();
+
+pub fn get() -> &'static Daemon {
+    INSTANCE.get().expect("daemons::start() must be called")
+}
+
+pub async fn start() -> anyhow::Result<()> {
+    if INSTANCE.get().is_some() {
+        return Ok(());
+    }
+
+    let device = crate::config::get().backlight.device.clone();
+    let daemon = Daemon::new(SYSFS_ROOT, device);
+    let has_backlight = match daemon.backlight() {
+        Some(backlight) => {
+            info!(name = backlight.name, "Using backlight device");
+            true
+        }
+        None => {
+            info!("No backlight device found");
+            false
+        }
+    };
+
+    // NOTE: If we already started he handled it above.
+    let _ = INSTANCE.set(daemon);
+    if !has_backlight {
+        return Ok(());
+    }
+
+    let listener = match UeventListener::new() {
+        Ok(listener) => listener,
+        Err(err) => {
+            warn!(?err, "Failed to listen to backlight uevents");
+            return Ok(());
+        }
+    };
+
+    super::spawn("backlight-uevents", async move {
+        loop {
+            listener.changed().await?;
+            get().refresh();
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    /// A fake `/sys/class/backlight`, removed once dropped.
+    struct FakeSysfs(PathBuf);
+
+    impl FakeSysfs {
+        fn new() -> Self {
+            static COUNTER: AtomicUsize = AtomicUsize::new(0);
+            let name = format!(
+                "fht-shell-backlight-{}-{}",
+                std::process::id(),
+                COUNTER.fetch_add(1, Ordering::Relaxed)
+            );
+            let root = std::env::temp_dir().join(name);
+            fs::create_dir_all(&root).unwrap();
+            Self(root)
+        }
+
+        fn add(&self, name: &str, type_: &str, brightness: u32, max_brightness: u32) {
+            let path = self.0.join(name);
+            fs::create_dir_all(&path).unwrap();
+            fs::write(path.join("type"), format!("{type_}\n")).unwrap();
+            fs::write(path.join("brightness"), format!("{brightness}\n")).unwrap();
+            fs::write(path.join("max_brightness"), format!("{max_brightness}\n")).unwrap();
+        }
+    }
+
+    impl Drop for FakeSysfs {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn backlight(brightness: u32, max_brightness: u32) -> Backlight {
+        Backlight {
+            name: "test".to_string(),
+            brightness,
+            max_brightness,
+        }
+    }
+
+    #[test]
+    fn prefers_firmware_then_platform_then_raw() {
+        let sysfs = FakeSysfs::new();
+        sysfs.add("intel_backlight", "raw", 10, 100);
+        sysfs.add("thinkpad_screen", "platform", 20, 100);
+        assert_eq!(
+            enumerate(&sysfs.0).unwrap(),
+            ["thinkpad_screen", "intel_backlight"]
+        );
+        let daemon = Daemon::new(&sysfs.0, None);
+        assert_eq!(daemon.backlight().unwrap().name, "thinkpad_screen");
+
+        sysfs.add("acpi_video0", "firmware", 5, 15);
+        let daemon = Daemon::new(&sysfs.0, None);
+        assert_eq!(daemon.backlight().unwrap().name, "acpi_video0");
+    }
+
+    #[test]
+    fn uses_the_given_device() {
+        let sysfs = FakeSysfs::new();
+        sysfs.add("acpi_video0", "firmware", 5, 15);
+        sysfs.add("intel_backlight", "raw", 10, 100);
+        let daemon = Daemon::new(&sysfs.0, Some("intel_backlight".to_string()));
+        assert_eq!(daemon.backlight().unwrap().name, "intel_backlight");
+
+        let daemon = Daemon::new(&sysfs.0, Some("missing".to_string()));
+        assert_eq!(daemon.backlight(), None);
+    }
+
+    #[test]
+    fn no_devices() {
+        let sysfs = FakeSysfs::new();
+        assert_eq!(Daemon::new(&sysfs.0, None).backlight(), None);
+        assert_eq!(Daemon::new(sysfs.0.join("missing"), None).backlight(), None);
+    }
+
+    #[test]
+    fn reads_device() {
+        let sysfs = FakeSysfs::new();
+        sysfs.add("intel_backlight", "raw", 1200, 4800);
+        assert_eq!(
+            Backlight::read(&sysfs.0, "intel_backlight").unwrap(),
+            Backlight {
+                name: "intel_backlight".to_string(),
+                brightness: 1200,
+                max_brightness: 4800,
+            }
+        );
+
+        fs::write(sysfs.0.join("intel_backlight/brightness"), "garbage").unwrap();
+        let err = Backlight::read(&sysfs.0, "intel_backlight").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(Backlight::read(&sysfs.0, "missing").is_err());
+    }
+
+    #[test]
+    fn refresh_follows_external_changes() {
+        let sysfs = FakeSysfs::new();
+        sysfs.add("intel_backlight", "raw", 10, 100);
+        let daemon = Daemon::new(&sysfs.0, None);
+        let mut changes = daemon.subscribe();
+
+        daemon.refresh();
+        assert!(!changes.has_changed().unwrap());
+
+        fs::write(sysfs.0.join("intel_backlight/brightness"), "50").unwrap();
+        daemon.refresh();
+        assert!(changes.has_changed().unwrap());
+        assert_eq!(changes.borrow_and_update().as_ref().unwrap().brightness, 50);
+    }
+
+    #[test]
+    fn backlight_uevents() {
+        assert!(is_backlight_uevent(
+            b"change@/devices/pci0000:00/0000:00:02.0/drm/card1/card1-eDP-1/intel_backlight\0\
+              ACTION=change\0\
+              DEVPATH=/devices/pci0000:00/0000:00:02.0/drm/card1/card1-eDP-1/intel_backlight\0\
+              SUBSYSTEM=backlight\0\
+              SOURCE=sysfs\0\
+              SEQNUM=4242\0"
+        ));
+        assert!(!is_backlight_uevent(
+            b"change@/devices/LNXSYSTM:00/LNXSYBUS:00/PNP0C0A:00/power_supply/BAT0\0\
+              ACTION=change\0\
+              SUBSYSTEM=power_supply\0\
+              POWER_SUPPLY_NAME=BAT0\0"
+        ));
+        // The header is not a field.
+        assert!(!is_backlight_uevent(b"SUBSYSTEM=backlight\0ACTION=add\0"));
+        assert!(!is_backlight_uevent(b""));
+    }
+
+    #[test]
+    fn fraction_conversion() {
+        let backlight = backlight(1200, 4800);
+        assert_eq!(backlight.fraction(), 0.25);
+        assert_eq!(backlight.brightness_for(0.25), 1200);
+        assert_eq!(backlight.brightness_for(0.5), 2400);
+        assert_eq!(backlight.brightness_for(-1.0), 0);
+        assert_eq!(backlight.brightness_for(2.0), 4800);
+        assert_eq!(self::backlight(3, 0).fraction(), 0.0);
+    }
+
+    #[test]
+    fn step_is_clamped() {
+        assert_eq!(backlight(50, 100).stepped(10.0), 60);
+        assert_eq!(backlight(50, 100).stepped(-10.0), 40);
+        assert_eq!(backlight(95, 100).stepped(10.0), 100);
+        assert_eq!(backlight(5, 100).stepped(-10.0), 0);
+        assert_eq!(backlight(100, 100).stepped(10.0), 100);
+        assert_eq!(backlight(0, 100).stepped(-10.0), 0);
+    }
+
+    #[test]
+    fn step_moves_at_least_one_level() {
+        // 5% of 7 levels rounds back to the current one.
+        assert_eq!(backlight(3, 7).stepped(5.0), 4);
+        assert_eq!(backlight(3, 7).stepped(-5.0), 2);
+    }
+}
@@ -0,0 +1,61 @@
+//! # D-Bus interface proxy for: `org.bluez.Adapter1`
+//!
+//! This code was generated by `zbus-xmlgen` `5.1.0` from D-Bus introspection data.
+//! Source: `Interface '/org/bluez/hci0' from service 'org.bluez' on system bus`.
+//!
+//! You may prefer to adapt it, instead of using it verbatim.
+//!
+//! More information can be found in the [Writing a client proxy] section of the zbus
+//! documentation.
+//!
+//! This type implements the [D-Bus standard interfaces], (`org.freedesktop.DBus.*`) for which the
+//! following zbus API can be used:
+//!
+//! * [`zbus::fdo::PropertiesProxy`]
+//! * [`zbus::fdo::IntrospectableProxy`]
+//! * [`zbus::fdo::PeerProxy`]
+//!
+//! Consequently `zbus-xmlgen` did not generate code for the above interfaces.
+//!
+//! [Writing a client proxy]: https://dbus2.github.io/zbus/client.html
+//! [D-Bus standard interfaces]: https://dbus.freedesktop.org/doc/dbus-specification.html#standard-interfaces,
+use zbus::proxy;
+#[proxy(interface = "org.bluez.Adapter1", default_service = "org.bluez")]
+pub trait Adapter1 {
+    /// RemoveDevice method
+    fn remove_device(&self, device: &zbus::zvariant::ObjectPath<'_>) -> zbus::Result<()>;
+
+    /// StartDiscovery method
+    fn start_discovery(&self) -> zbus::Result<()>;
+
+    /// StopDiscovery method
+    fn stop_discovery(&self) -> zbus::Result<()>;
+
+    /// Address property
+    #[zbus(property)]
+    fn address(&self) -> zbus::Result<String>;
+
+    /// Alias property
+    #[zbus(property)]
+    fn alias(&self) -> zbus::Result<String>;
+
+    /// Discoverable property
+    #[zbus(property)]
+    fn discoverable(&self) -> zbus::Result<bool>;
+    #[zbus(property)]
+    fn set_discoverable(&self, value: bool) -> zbus::Result<()>;
+
+    /// Discovering property
+    #[zbus(property)]
+    fn discovering(&self) -> zbus::Result<bool>;
+
+    /// Name property
+    #[zbus(property)]
+    fn name(&self) -> zbus::Result<String>;
+
+    /// Powered property
+    #[zbus(property)]
+    fn powered(&self) -> zbus::Result<bool>;
+    #[zbus(property)]
+    fn set_powered(&self, value: bool) -> zbus::Result<()>;
+}
@@ -0,0 +1,181 @@
+//! Our BlueZ pairing agent.
+//!
+//! BlueZ calls the agent when pairing needs the user, to enter a PIN code or to confirm a passkey
+//! for example. We forward these calls as [`AgentRequest`]s, for the shell to show a dialog, and
+//! wait for the answer.
+
+use tokio::sync::{mpsc, oneshot};
+use zbus::zvariant::OwnedObjectPath;
+
+use super::device::Device1Proxy;
+
+/// Where we serve the agent on the system bus.
+pub const AGENT_PATH: &str = "/fht/desktop/Shell/BluetoothAgent";
+
+/// A request from BlueZ needing the user.
+#[derive(Debug)]
+pub enum AgentRequest {
+    /// Enter the PIN code of the device.
+    PinCode {
+        device: OwnedObjectPath,
+        responder: oneshot::Sender<Option<String>>,
+    },
+    /// Enter the passkey shown on the device.
+    Passkey {
+        device: OwnedObjectPath,
+        responder: oneshot::Sender<Option<u32>>,
+    },
+    /// Confirm that the device shows the same passkey.
+    Confirmation {
+        device: OwnedObjectPath,
+        passkey: u32,
+        responder: oneshot::Sender<bool>,
+    },
+    /// Allow a device to pair without any code.
+    Authorization {
+        device: OwnedObjectPath,
+        responder: oneshot::Sender<bool>,
+    },
+    /// Allow a device that is not trusted to use a service, by its UUID.
+    ServiceAuthorization {
+        device: OwnedObjectPath,
+        uuid: String,
+        responder: oneshot::Sender<bool>,
+    },
+    /// Display a PIN code or a passkey to type on the device.
+    Display {
+        device: OwnedObjectPath,
+        code: String,
+    },
+    /// BlueZ cancelled the current request, any dialog should be closed.
+    Cancel,
+}
+
+#[derive(Debug, zbus::DBusError)]
+#[zbus(prefix = "org.bluez.Error")]
+pub enum AgentError {
+    #[zbus(error)]
+    ZBus(zbus::Error),
+    Rejected(String),
+    Canceled(String),
+}
+
+pub(super) struct Agent {
+    pub(super) requests: mpsc::UnboundedSender<AgentRequest>,
+}
+
+impl Agent {
+    /// Send a request to the shell and wait for the answer.
+    async fn ask<T>(
+        &self,
+        make_request: impl FnOnce(oneshot::Sender<T>) -> AgentRequest,
+    ) -> Result<T, AgentError> {
+        let (responder, response) = oneshot::channel();
+        if self.requests.send(make_request(responder)).is_err() {
+            return Err(AgentError::Rejected("Nobody to ask".to_string()));
+        }
+        // The responder gets dropped when the dialog is dismissed.
+        response
+            .await
+            .map_err(|_| AgentError::Canceled("Request was dismissed".to_string()))
+    }
+}
+
+#[zbus::interface(name = "org.bluez.Agent1")]
+impl Agent {
+    fn release(&self) {
+        debug!("Bluetooth agent released");
+    }
+
+    async fn request_pin_code(&self, device: OwnedObjectPath) -> Result<String, AgentError> {
+        self.ask(|responder| AgentRequest::PinCode { device, responder })
+            .await?
+            .ok_or_else(|| AgentError::Rejected("PIN code was not entered".to_string()))
+    }
+
+    fn display_pin_code(&self, device: OwnedObjectPath, pincode: String) {
+        let _ = self.requests.send(AgentRequest::Display {
+            device,
+            code: pincode,
+        });
+    }
+
+    async fn request_passkey(&self, device: OwnedObjectPath) -> Result<u32, AgentError> {
+        self.ask(|responder| AgentRequest::Passkey { device, responder })
+            .await?
+            .ok_or_else(|| AgentError::Rejected("Passkey was not entered".to_string()))
+    }
+
+    fn display_passkey(&self, device: OwnedObjectPath, passkey: u32, _entered: u16) {
+        let _ = self.requests.send(AgentRequest::Display {
+            device,
+            code: format!("{passkey:06}"),
+        });
+    }
+
+    async fn request_confirmation(
+        &self,
+        device: OwnedObjectPath,
+        passkey: u32,
+    ) -> Result<(), AgentError> {
+        let confirmed = self
+            .ask(|responder| AgentRequest::Confirmation {
+                device,
+                passkey,
+                responder,
+            })
+            .await?;
+        if confirmed {
+            Ok(())
+        } else {
+            Err(AgentError::Rejected(
+                "Passkey was not confirmed".to_string(),
+            ))
+        }
+    }
+
+    async fn request_authorization(&self, device: OwnedObjectPath) -> Result<(), AgentError> {
+        let authorized = self
+            .ask(|responder| AgentRequest::Authorization { device, responder })
+            .await?;
+        if authorized {
+            Ok(())
+        } else {
+            Err(AgentError::Rejected(
+                "Pairing was not authorized".to_string(),
+            ))
+        }
+    }
+
+    async fn authorize_service(
+        &self,
+        #[zbus(connection)] conn: &zbus::Connection,
+        device: OwnedObjectPath,
+        uuid: String,
+    ) -> Result<(), AgentError> {
+        // Trusted devices can use any service, that's what trusting them means.
+        let proxy = Device1Proxy::new(conn, device.clone()).await?;
+        if proxy.trusted().await.unwrap_or(false) {
+            return Ok(());
+        }
+
+        let authorized = self
+            .ask(|responder| AgentRequest::ServiceAuthorization {
+                device,
+                uuid,
+                responder,
+            })
+            .await?;
+        if authorized {
+            Ok(())
+        } else {
+            Err(AgentError::Rejected(
+                "Service was not authorized".to_string(),
+            ))
+        }
+    }
+
+    fn cancel(&self) {
+        let _ = self.requests.send(AgentRequest::Cancel);
+    }
+}
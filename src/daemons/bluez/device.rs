@@ -0,0 +1,71 @@
+//! # D-Bus interface proxy for: `org.bluez.Device1`
+//!
+//! This code was generated by `zbus-xmlgen` `5.1.0` from D-Bus introspection data.
+//! Source: `Interface '/org/bluez/hci0/dev_XX_XX_XX_XX_XX_XX' from service 'org.bluez' on
+//! system bus`.
+//!
+//! You may prefer to adapt it, instead of using it verbatim.
+//!
+//! More information can be found in the [Writing a client proxy] section of the zbus
+//! documentation.
+//!
+//! This type implements the [D-Bus standard interfaces], (`org.freedesktop.DBus.*`) for which the
+//! following zbus API can be used:
+//!
+//! * [`zbus::fdo::PropertiesProxy`]
+//! * [`zbus::fdo::IntrospectableProxy`]
+//! * [`zbus::fdo::PeerProxy`]
+//!
+//! Consequently `zbus-xmlgen` did not generate code for the above interfaces.
+//!
+//! [Writing a client proxy]: https://dbus2.github.io/zbus/client.html
+//! [D-Bus standard interfaces]: https://dbus.freedesktop.org/doc/dbus-specification.html#standard-interfaces,
+use zbus::proxy;
+#[proxy(interface = "org.bluez.Device1", default_service = "org.bluez")]
+pub trait Device1 {
+    /// CancelPairing method
+    fn cancel_pairing(&self) -> zbus::Result<()>;
+
+    /// Connect method
+    fn connect(&self) -> zbus::Result<()>;
+
+    /// Disconnect method
+    fn disconnect(&self) -> zbus::Result<()>;
+
+    /// Pair method
+    fn pair(&self) -> zbus::Result<()>;
+
+    /// Adapter property
+    #[zbus(property)]
+    fn adapter(&self) -> zbus::Result<zbus::zvariant::OwnedObjectPath>;
+
+    /// Address property
+    #[zbus(property)]
+    fn address(&self) -> zbus::Result<String>;
+
+    /// Alias property
+    #[zbus(property)]
+    fn alias(&self) -> zbus::Result<String>;
+
+    /// Blocked property
+    #[zbus(property)]
+    fn blocked(&self) -> zbus::Result<bool>;
+
+    /// Connected property
+    #[zbus(property)]
+    fn connected(&self) -> zbus::Result<bool>;
+
+    /// Icon property
+    #[zbus(property)]
+    fn icon(&self) -> zbus::Result<String>;
+
+    /// Paired property
+    #[zbus(property)]
+    fn paired(&self) -> zbus::Result<bool>;
+
+    /// Trusted property
+    #[zbus(property)]
+    fn trusted(&self) -> zbus::Result<bool>;
+    #[zbus(property)]
+    fn set_trusted(&self, value: bool) -> zbus::Result<()>;
+}
@@ -0,0 +1,332 @@
+#![allow(unused)]
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+
+use futures_util::StreamExt;
+use tokio::sync::{broadcast, mpsc, watch};
+use zbus::zvariant::{ObjectPath, OwnedObjectPath};
+
+pub mod adapter;
+pub mod agent;
+pub mod agent_manager;
+pub mod battery;
+pub mod device;
+
+const ADAPTER_INTERFACE: &str = "org.bluez.Adapter1";
+const DEVICE_INTERFACE: &str = "org.bluez.Device1";
+const BATTERY_INTERFACE: &str = "org.bluez.Battery1";
+
+/// A `BlueZ` daemon, used to manage Bluetooth adapters and devices.
+/// See BlueZ's documentation: <https://github.com/bluez/bluez/tree/master/doc>
+pub struct Daemon {
+    adapter: watch::Sender<Option<adapter::Adapter1Proxy<'static>>>,
+    devices: RwLock<Vec<Device>>,
+    sender: broadcast::Sender<Event>,
+    agent_requests: Mutex<Option<mpsc::UnboundedReceiver<agent::AgentRequest>>>,
+}
+
+impl Daemon {
+    /// Get the adapter we use, if any.
+    pub fn adapter(&self) -> Option<adapter::Adapter1Proxy<'static>> {
+        self.adapter.borrow().clone()
+    }
+
+    /// Subscribe to the adapter we use, if any.
+    ///
+    /// When there are multiple adapters, we use the first one we found.
+    pub fn subscribe_adapter(&self) -> watch::Receiver<Option<adapter::Adapter1Proxy<'static>>> {
+        self.adapter.subscribe()
+    }
+
+    /// Get all the devices currently known by BlueZ, paired or just discovered.
+    pub fn devices(&self) -> Vec<Device> {
+        self.devices.read().unwrap().clone()
+    }
+
+    /// Get a device by its ID.
+    pub fn device(&self, id: &str) -> Option<Device> {
+        let devices = self.devices.read().unwrap();
+        devices.iter().find(|device| &*device.id == id).cloned()
+    }
+
+    /// Get the device at this D-Bus path.
+    pub fn device_at(&self, path: &str) -> Option<Device> {
+        self.device(&Device::id_from_path(path))
+    }
+
+    /// Subscribe to the events of this [`Daemon`].
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+
+    /// Take the requests of our pairing agent.
+    ///
+    /// There can only be one receiver, so this returns `None` after the first call.
+    pub fn take_agent_requests(&self) -> Option<mpsc::UnboundedReceiver<agent::AgentRequest>> {
+        self.agent_requests.lock().unwrap().take()
+    }
+
+    /// Forget about a device, unpairing it.
+    pub async fn remove_device(&self, device: &Device) -> zbus::Result<()> {
+        let Some(adapter) = self.adapter.borrow().clone() else {
+            return Err(zbus::Error::Failure("No Bluetooth adapter".to_string()));
+        };
+        adapter.remove_device(device.proxy.inner().path()).await
+    }
+
+    /// Follow the interfaces of a new object, logging the ones we fail to follow.
+    async fn add_object(&self, path: OwnedObjectPath, interfaces: impl IntoIterator<Item = &str>) {
+        let conn = super::system_connection().inner();
+        let interfaces = interfaces.into_iter().collect::<Vec<_>>();
+
+        if interfaces.contains(&ADAPTER_INTERFACE) && self.adapter.borrow().is_none() {
+            match adapter::Adapter1Proxy::new(conn, path.clone()).await {
+                Ok(proxy) => {
+                    debug!(?path, "Bluetooth adapter added");
+                    self.adapter.send_replace(Some(proxy));
+                }
+                Err(err) => warn!(?err, ?path, "Failed to add Bluetooth adapter"),
+            }
+        }
+
+        if interfaces.contains(&DEVICE_INTERFACE) {
+            let device = match Device::new(path.clone()).await {
+                Ok(device) => device,
+                Err(err) => {
+                    warn!(?err, ?path, "Failed to add Bluetooth device");
+                    return;
+                }
+            };
+            debug!(device_id = ?device.id, "Bluetooth device added");
+            self.devices.write().unwrap().push(device.clone());
+            let _ = self.sender.send(Event::DeviceAdded(device));
+        } else if interfaces.contains(&BATTERY_INTERFACE) {
+            // The battery interface can show up after the device connected.
+            let id = Device::id_from_path(&path);
+            let device = match Device::new(path.clone()).await {
+                Ok(device) => device,
+                Err(err) => {
+                    warn!(?err, ?path, "Failed to update Bluetooth device");
+                    return;
+                }
+            };
+            let mut devices = self.devices.write().unwrap();
+            if let Some(existing) = devices.iter_mut().find(|device| device.id == id) {
+                *existing = device.clone();
+                drop(devices);
+                let _ = self.sender.send(Event::DeviceChanged(device));
+            }
+        }
+    }
+
+    fn remove_object(&self, path: &ObjectPath<'_>, interfaces: &[&str]) {
+        let is_adapter = self
+            .adapter
+            .borrow()
+            .as_ref()
+            .is_some_and(|adapter| adapter.inner().path() == path);
+        if is_adapter && interfaces.contains(&ADAPTER_INTERFACE) {
+            debug!(?path, "Bluetooth adapter removed");
+            self.adapter.send_replace(None);
+        }
+
+        if interfaces.contains(&DEVICE_INTERFACE) {
+            let id = Device::id_from_path(path);
+            debug!(device_id = ?id, "Bluetooth device removed");
+            self.devices
+                .write()
+                .unwrap()
+                .retain(|device| device.id != id);
+            let _ = self.sender.send(Event::DeviceRemoved(id));
+        }
+    }
+}
+
+/// A single Bluetooth device.
+///
+/// The unique ID of the device is the end of it's D-Bus path, IE.
+/// `/org/bluez/hci0/dev_00_11_22_33_44_55` -> `dev_00_11_22_33_44_55`
+#[derive(Clone, Debug)]
+pub struct Device {
+    id: Arc<str>,
+    proxy: device::Device1Proxy<'static>,
+    battery: battery::Battery1Proxy<'static>,
+}
+
+impl Device {
+    async fn new(path: OwnedObjectPath) -> zbus::Result<Self> {
+        let conn = super::system_connection().inner();
+        let id = Self::id_from_path(&path);
+        let proxy = device::Device1Proxy::new(conn, path.clone()).await?;
+        let battery = battery::Battery1Proxy::new(conn, path).await?;
+        Ok(Self { id, proxy, battery })
+    }
+
+    fn id_from_path(path: &str) -> Arc<str> {
+        path.rsplit('/').next().expect("Invalid device path").into()
+    }
+
+    /// Get the unique ID of this [`Device`].
+    pub fn id(&self) -> Arc<str> {
+        Arc::clone(&self.id)
+    }
+
+    /// Get the underlying [`zbus::Proxy`] behind this interface.
+    pub fn proxy(&self) -> &device::Device1Proxy<'static> {
+        &self.proxy
+    }
+
+    /// Get the battery of this device.
+    ///
+    /// Calls fail for devices that don't report their battery.
+    pub fn battery(&self) -> &battery::Battery1Proxy<'static> {
+        &self.battery
+    }
+
+    /// Get a name to show to the user for this device.
+    pub async fn display_name(&self) -> String {
+        match self.proxy.alias().await {
+            Ok(alias) if !alias.is_empty() => alias,
+            _ => self.proxy.address().await.unwrap_or_default(),
+        }
+    }
+}
+
+/// An event sent by the `BlueZ` [`Daemon`].
+#[derive(Clone, Debug)]
+#[allow(clippy::enum_variant_names)]
+pub enum Event {
+    /// A new device was discovered or plugged in.
+    DeviceAdded(Device),
+    /// A device got new interfaces, IE. a battery.
+    DeviceChanged(Device),
+    /// A device was removed, with its ID.
+    DeviceRemoved(Arc<str>),
+}
+
+static INSTANCE: OnceLock<Daemon> = OnceLock::new();
+
+pub fn get() -> &'static Daemon {
+    INSTANCE.get().expect("daemons::start() must be called")
+}
+
+/// Register our pairing agent, and make it the default one.
+async fn register_agent(agent_manager: &agent_manager::AgentManager1Proxy<'_>) {
+    let agent_path = ObjectPath::from_static_str_unchecked(agent::AGENT_PATH);
+    match agent_manager
+        .register_agent(&agent_path, "KeyboardDisplay")
+        .await
+    {
+        Ok(()) => {
+            if let Err(err) = agent_manager.request_default_agent(&agent_path).await {
+                warn!(?err, "Failed to make our Bluetooth agent the default one");
+            }
+        }
+        Err(err) => warn!(?err, "Failed to register Bluetooth agent"),
+    }
+}
+
+pub async fn start() -> anyhow::Result<()> {
+    if INSTANCE.get().is_some() {
+        return Ok(());
+    }
+
+    let conn = super::system_connection().inner();
+    let object_manager = zbus::fdo::ObjectManagerProxy::builder(conn)
+        .destination("org.bluez")?
+        .path("/")?
+        .build()
+        .await?;
+
+    let (sender, _) = broadcast::channel(32);
+    let (agent_sender, agent_receiver) = mpsc::unbounded_channel();
+    let daemon = Daemon {
+        adapter: watch::Sender::new(None),
+        devices: RwLock::new(vec![]),
+        sender,
+        agent_requests: Mutex::new(Some(agent_receiver)),
+    };
+
+    // Subscribe before listing objects so that we don't miss anything in between.
+    let mut interfaces_added = object_manager.receive_interfaces_added().await?;
+    let mut interfaces_removed = object_manager.receive_interfaces_removed().await?;
+
+    // BlueZ is not installed everywhere, we still want to start without it.
+    match object_manager.get_managed_objects().await {
+        Ok(objects) => {
+            for (path, interfaces) in objects {
+                let interfaces = interfaces.keys().map(|name| name.as_str());
+                daemon.add_object(path, interfaces).await;
+            }
+        }
+        Err(err) => warn!(?err, "Failed to get Bluetooth objects, is BlueZ running?"),
+    }
+
+    let agent = agent::Agent {
+        requests: agent_sender,
+    };
+    conn.object_server().at(agent::AGENT_PATH, agent).await?;
+    let agent_manager = agent_manager::AgentManager1Proxy::new(conn).await?;
+    // BlueZ forgets about our agent when it restarts, we have to register it again.
+    let dbus_proxy = zbus::fdo::DBusProxy::new(conn).await?;
+    let mut bluez_owner_changes = dbus_proxy
+        .receive_name_owner_changed_with_args(&[(0, "org.bluez")])
+        .await?;
+    register_agent(&agent_manager).await;
+
+    // NOTE: If we already started he handled it above.
+    let _ = INSTANCE.set(daemon);
+
+    super::spawn("bluez-agent", async move {
+        while let Some(changed) = bluez_owner_changes.next().await {
+            let args = match changed.args() {
+                Ok(args) => args,
+                Err(err) => {
+                    warn!(?err, "Invalid NameOwnerChanged signal");
+                    continue;
+                }
+            };
+            if args.new_owner.is_some() {
+                info!("BlueZ started, registering our Bluetooth agent");
+                register_agent(&agent_manager).await;
+            }
+        }
+
+        Ok(())
+    });
+
+    super::spawn("bluez-objects", async move {
+        loop {
+            futures_util::select! {
+                added = interfaces_added.next() => {
+                    let Some(added) = added else { break };
+                    let args = match added.args() {
+                        Ok(args) => args,
+                        Err(err) => {
+                            warn!(?err, "Invalid InterfacesAdded signal");
+                            continue;
+                        }
+                    };
+                    let path = OwnedObjectPath::from(args.object_path.clone());
+                    let interfaces = args.interfaces_and_properties.keys().map(|name| name.as_str());
+                    get().add_object(path, interfaces).await;
+                }
+                removed = interfaces_removed.next() => {
+                    let Some(removed) = removed else { break };
+                    let args = match removed.args() {
+                        Ok(args) => args,
+                        Err(err) => {
+                            warn!(?err, "Invalid InterfacesRemoved signal");
+                            continue;
+                        }
+                    };
+                    let interfaces = args.interfaces.iter().map(|name| name.as_str()).collect::<Vec<_>>();
+                    get().remove_object(&args.object_path, &interfaces);
+                }
+            }
+        }
+
+        Ok(())
+    });
+
+    Ok(())
+}
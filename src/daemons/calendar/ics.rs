@@ -0,0 +1,805 @@
+//! A minimal iCalendar (RFC 5545) parser, only reading the events.
+//!
+//! Times with a `TZID` are converted to local time with the matching `VTIMEZONE` of the file, which
+//! calendar applications include for the timezones they use. We don't ship a timezone database,
+//! so times with an undefined `TZID` are taken as local times.
+
+use std::collections::HashMap;
+
+use chrono::{
+    DateTime, Duration, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime, Utc, Weekday,
+};
+
+use super::recurrence::{Frequency, Recurrence};
+
+/// A single event, or the master event of a recurring series.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub uid: String,
+    pub summary: String,
+    pub location: Option<String>,
+    /// The local start time, at midnight for all-day events.
+    pub start: NaiveDateTime,
+    pub duration: Duration,
+    pub all_day: bool,
+    pub recurrence: Option<Recurrence>,
+    /// The start times of the occurrences removed from the series.
+    pub exceptions: Vec<NaiveDateTime>,
+    /// The start time of the occurrence this event replaces, for modified occurrences.
+    pub recurrence_id: Option<NaiveDateTime>,
+    /// Whether this event got cancelled, only kept to remove it from its series.
+    pub cancelled: bool,
+}
+
+/// Parse all the events of an iCalendar file.
+pub fn parse(contents: &str) -> Vec<Event> {
+    let properties = unfold(contents)
+        .iter()
+        .filter_map(|line| Property::parse(line))
+        .collect::<Vec<_>>();
+    // Timezones can be defined after the events using them.
+    let timezones = parse_timezones(&properties);
+
+    let mut events = vec![];
+    // Components can be nested, like alarms in events, we only read the properties of events.
+    let mut components = vec![];
+    let mut current: Option<EventBuilder> = None;
+
+    for property in &properties {
+        match property.name.as_str() {
+            "BEGIN" => {
+                if property.value.eq_ignore_ascii_case("VEVENT") {
+                    current = Some(EventBuilder::default());
+                }
+                components.push(property.value.to_ascii_uppercase());
+                continue;
+            }
+            "END" => {
+                if components.pop().as_deref() == Some("VEVENT") {
+                    if let Some(event) = current.take().and_then(EventBuilder::build) {
+                        events.push(event);
+                    }
+                }
+                continue;
+            }
+            _ => (),
+        }
+
+        if components.last().map(String::as_str) != Some("VEVENT") {
+            continue;
+        }
+        if let Some(builder) = current.as_mut() {
+            builder.apply(property, &timezones);
+        }
+    }
+
+    events
+}
+
+/// The timezones defined in a file, by `TZID`.
+type TimeZones = HashMap<String, TimeZone>;
+
+/// A timezone defined by a `VTIMEZONE`, as the UTC offsets it goes through.
+#[derive(Debug, Default)]
+struct TimeZone {
+    observances: Vec<Observance>,
+}
+
+/// A `STANDARD` or `DAYLIGHT` part of a timezone, IE. when an UTC offset starts being used.
+#[derive(Debug, Default)]
+struct Observance {
+    /// The first time the offset gets used, in the local time of the previous offset.
+    start: Option<NaiveDateTime>,
+    offset_from: Option<FixedOffset>,
+    offset_to: Option<FixedOffset>,
+    recurrence: Option<Recurrence>,
+    /// Other times the offset starts being used, like `start`.
+    dates: Vec<NaiveDateTime>,
+}
+
+impl TimeZone {
+    /// Convert a time of this timezone to local time.
+    fn to_local(&self, time: NaiveDateTime) -> NaiveDateTime {
+        let Some(offset) = self.offset_at(time) else {
+            return time;
+        };
+        let utc = time - Duration::seconds(offset.local_minus_utc().into());
+        DateTime::<Utc>::from_naive_utc_and_offset(utc, Utc)
+            .with_timezone(&Local)
+            .naive_local()
+    }
+
+    /// Get the UTC offset used at a time of this timezone.
+    fn offset_at(&self, time: NaiveDateTime) -> Option<FixedOffset> {
+        let latest = self
+            .observances
+            .iter()
+            .filter_map(|observance| Some((observance.last_onset(time)?, observance.offset_to?)))
+            .max_by_key(|(onset, _)| *onset);
+        if let Some((_, offset)) = latest {
+            return Some(offset);
+        }
+
+        // Before the timezone got defined, the offset is the one the first observance changes.
+        self.observances
+            .iter()
+            .filter_map(|observance| Some((observance.start?, observance.offset_from?)))
+            .min_by_key(|(start, _)| *start)
+            .map(|(_, offset)| offset)
+    }
+}
+
+impl Observance {
+    /// Get the last time the offset started being used, up to `time`.
+    fn last_onset(&self, time: NaiveDateTime) -> Option<NaiveDateTime> {
+        let start = self.start?;
+        let onsets = match &self.recurrence {
+            Some(recurrence) => recurrence.occurrences(start, time + Duration::seconds(1)),
+            None => vec![start],
+        };
+        onsets
+            .into_iter()
+            .chain(self.dates.iter().copied())
+            .filter(|onset| *onset <= time)
+            .max()
+    }
+}
+
+/// Parse the `VTIMEZONE` components of a file.
+fn parse_timezones(properties: &[Property]) -> TimeZones {
+    let mut timezones = TimeZones::new();
+    let mut components = vec![];
+    let mut current: Option<(Option<String>, TimeZone)> = None;
+
+    for property in properties {
+        match property.name.as_str() {
+            "BEGIN" => {
+                let component = property.value.to_ascii_uppercase();
+                match (component.as_str(), current.as_mut()) {
+                    ("VTIMEZONE", _) => current = Some((None, TimeZone::default())),
+                    ("STANDARD" | "DAYLIGHT", Some((_, timezone))) => {
+                        timezone.observances.push(Observance::default());
+                    }
+                    _ => (),
+                }
+                components.push(component);
+                continue;
+            }
+            "END" => {
+                if components.pop().as_deref() == Some("VTIMEZONE") {
+                    if let Some((Some(tzid), timezone)) = current.take() {
+                        timezones.insert(tzid, timezone);
+                    }
+                }
+                continue;
+            }
+            _ => (),
+        }
+
+        let Some((tzid, timezone)) = current.as_mut() else {
+            continue;
+        };
+        match components.last().map(String::as_str) {
+            Some("VTIMEZONE") if property.name == "TZID" => *tzid = Some(property.value.clone()),
+            Some("STANDARD" | "DAYLIGHT") => {
+                let Some(observance) = timezone.observances.last_mut() else {
+                    continue;
+                };
+                match property.name.as_str() {
+                    "DTSTART" => observance.start = parse_date_time(&property.value),
+                    "TZOFFSETFROM" => observance.offset_from = parse_utc_offset(&property.value),
+                    "TZOFFSETTO" => observance.offset_to = parse_utc_offset(&property.value),
+                    "RRULE" => observance.recurrence = parse_recurrence(&property.value),
+                    "RDATE" => observance
+                        .dates
+                        .extend(property.value.split(',').filter_map(parse_date_time)),
+                    _ => (),
+                }
+            }
+            _ => (),
+        }
+    }
+
+    timezones
+}
+
+/// Parse an UTC offset, like `+0100` or `-053000`.
+fn parse_utc_offset(value: &str) -> Option<FixedOffset> {
+    let (sign, digits) = match value.split_at_checked(1)? {
+        ("+", digits) => (1, digits),
+        ("-", digits) => (-1, digits),
+        _ => return None,
+    };
+    if !matches!(digits.len(), 4 | 6) || !digits.bytes().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let number = |range: std::ops::Range<usize>| digits.get(range)?.parse::<i32>().ok();
+    let seconds = number(0..2)? * 3600 + number(2..4)? * 60 + number(4..6).unwrap_or(0);
+    FixedOffset::east_opt(sign * seconds)
+}
+
+/// Join the folded lines, continued lines start with a space or a tab.
+fn unfold(contents: &str) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    for line in contents.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continued), Some(last)) => last.push_str(continued),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+#[derive(Debug)]
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    /// Parse a `NAME;PARAM=VALUE:VALUE` line.
+    fn parse(line: &str) -> Option<Self> {
+        // Parameter values can be quoted and contain colons.
+        let mut in_quotes = false;
+        let colon = line.char_indices().find_map(|(idx, c)| {
+            match c {
+                '"' => in_quotes = !in_quotes,
+                ':' if !in_quotes => return Some(idx),
+                _ => (),
+            }
+            None
+        })?;
+        let (head, value) = (&line[..colon], &line[colon + 1..]);
+
+        let mut parts = head.split(';');
+        let name = parts.next()?.trim().to_ascii_uppercase();
+        let params = parts
+            .filter_map(|param| {
+                let (key, value) = param.split_once('=')?;
+                Some((
+                    key.to_ascii_uppercase(),
+                    value.trim_matches('"').to_string(),
+                ))
+            })
+            .collect();
+
+        Some(Self {
+            name,
+            params,
+            value: value.to_string(),
+        })
+    }
+
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Parse the value as a date or a local date-time, with whether it's a date.
+    fn date_time(&self, timezones: &TimeZones) -> Option<(NaiveDateTime, bool)> {
+        let is_date = self.param("VALUE") == Some("DATE") || self.value.len() == 8;
+        if is_date {
+            let date = NaiveDate::parse_from_str(&self.value, "%Y%m%d").ok()?;
+            return Some((date.and_time(NaiveTime::MIN), true));
+        }
+        Some((self.local_time(&self.value, timezones)?, false))
+    }
+
+    /// Parse a date-time of the value, converting it from its `TZID` to local time.
+    fn local_time(&self, value: &str, timezones: &TimeZones) -> Option<NaiveDateTime> {
+        let time = parse_date_time(value)?;
+        // UTC times are already converted, and the TZID must be ignored for them.
+        if value.ends_with('Z') {
+            return Some(time);
+        }
+        match self.param("TZID").and_then(|tzid| timezones.get(tzid)) {
+            Some(timezone) => Some(timezone.to_local(time)),
+            None => Some(time),
+        }
+    }
+}
+
+/// Parse a date-time, converting UTC ones to local time.
+fn parse_date_time(value: &str) -> Option<NaiveDateTime> {
+    if let Some(utc) = value.strip_suffix('Z') {
+        let utc = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        let local: DateTime<Local> = DateTime::<Utc>::from_naive_utc_and_offset(utc, Utc).into();
+        return Some(local.naive_local());
+    }
+
+    NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+        .ok()
+        .or_else(|| {
+            let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+            Some(date.and_time(NaiveTime::MIN))
+        })
+}
+
+/// Parse a duration, like `PT1H30M` or `P1D`.
+fn parse_duration(value: &str) -> Option<Duration> {
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(value) => (true, value),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let value = value.strip_prefix('P')?;
+
+    let mut duration = Duration::zero();
+    let mut number = String::new();
+    for c in value.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => (),
+            unit => {
+                let n = number.parse::<i64>().ok()?;
+                number.clear();
+                duration += match unit {
+                    'W' => Duration::weeks(n),
+                    'D' => Duration::days(n),
+                    'H' => Duration::hours(n),
+                    'M' => Duration::minutes(n),
+                    'S' => Duration::seconds(n),
+                    _ => return None,
+                };
+            }
+        }
+    }
+
+    Some(if negative { -duration } else { duration })
+}
+
+/// Unescape a text value.
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push('\n'),
+            Some(c) => unescaped.push(c),
+            None => (),
+        }
+    }
+    unescaped
+}
+
+fn parse_weekday(value: &str) -> Option<Weekday> {
+    Some(match value {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    })
+}
+
+/// Parse a `RRULE` value, like `FREQ=WEEKLY;BYDAY=MO,WE`.
+fn parse_recurrence(value: &str) -> Option<Recurrence> {
+    let mut frequency = None;
+    let mut recurrence = Recurrence::default();
+    for part in value.split(';') {
+        let (key, value) = part.split_once('=')?;
+        match key.to_ascii_uppercase().as_str() {
+            "FREQ" => {
+                frequency = Some(match value.to_ascii_uppercase().as_str() {
+                    "DAILY" => Frequency::Daily,
+                    "WEEKLY" => Frequency::Weekly,
+                    "MONTHLY" => Frequency::Monthly,
+                    "YEARLY" => Frequency::Yearly,
+                    // Sub-daily recurrences are not worth it for an agenda.
+                    _ => return None,
+                });
+            }
+            "INTERVAL" => recurrence.interval = value.parse().ok()?,
+            "COUNT" => recurrence.count = Some(value.parse().ok()?),
+            "UNTIL" => recurrence.until = Some(parse_date_time(value)?),
+            "BYDAY" => {
+                for day in value.split(',') {
+                    // The weekday is the last two letters, after an optional ordinal like `-1`.
+                    let split = day.len().checked_sub(2)?;
+                    let (ordinal, weekday) = day.split_at(split);
+                    let ordinal = match ordinal {
+                        "" => None,
+                        ordinal => Some(ordinal.trim_start_matches('+').parse().ok()?),
+                    };
+                    recurrence.by_day.push((ordinal, parse_weekday(weekday)?));
+                }
+            }
+            "BYMONTHDAY" => {
+                for day in value.split(',') {
+                    recurrence.by_month_day.push(day.parse().ok()?);
+                }
+            }
+            "BYMONTH" => {
+                for month in value.split(',') {
+                    recurrence.by_month.push(month.parse().ok()?);
+                }
+            }
+            // Ignore what we don't support rather than the whole event.
+            _ => (),
+        }
+    }
+
+    recurrence.frequency = frequency?;
+    Some(recurrence)
+}
+
+#[derive(Debug, Default)]
+struct EventBuilder {
+    uid: Option<String>,
+    summary: Option<String>,
+    location: Option<String>,
+    start: Option<(NaiveDateTime, bool)>,
+    end: Option<NaiveDateTime>,
+    duration: Option<Duration>,
+    recurrence: Option<Recurrence>,
+    exceptions: Vec<NaiveDateTime>,
+    recurrence_id: Option<NaiveDateTime>,
+    cancelled: bool,
+}
+
+impl EventBuilder {
+    fn apply(&mut self, property: &Property, timezones: &TimeZones) {
+        match property.name.as_str() {
+            "UID" => self.uid = Some(property.value.clone()),
+            "SUMMARY" => self.summary = Some(unescape(&property.value)),
+            "LOCATION" => self.location = Some(unescape(&property.value)),
+            "DTSTART" => self.start = property.date_time(timezones),
+            "DTEND" => self.end = property.date_time(timezones).map(|(end, _)| end),
+            "DURATION" => self.duration = parse_duration(&property.value),
+            "RRULE" => self.recurrence = parse_recurrence(&property.value),
+            "EXDATE" => {
+                let is_date = property.param("VALUE") == Some("DATE");
+                for value in property.value.split(',') {
+                    let exception = if is_date {
+                        NaiveDate::parse_from_str(value, "%Y%m%d")
+                            .ok()
+                            .map(|date| date.and_time(NaiveTime::MIN))
+                    } else {
+                        property.local_time(value, timezones)
+                    };
+                    self.exceptions.extend(exception);
+                }
+            }
+            "RECURRENCE-ID" => {
+                self.recurrence_id = property.date_time(timezones).map(|(id, _)| id);
+            }
+            "STATUS" => self.cancelled = property.value.eq_ignore_ascii_case("CANCELLED"),
+            _ => (),
+        }
+    }
+
+    fn build(self) -> Option<Event> {
+        let (start, all_day) = self.start?;
+        // Without an end, events last a day if all-day, or are instants otherwise.
+        let duration = match (self.end, self.duration) {
+            (Some(end), _) => end - start,
+            (None, Some(duration)) => duration,
+            (None, None) if all_day => Duration::days(1),
+            (None, None) => Duration::zero(),
+        };
+
+        // Cancelled occurrences are still needed to remove them from their series.
+        if self.cancelled && self.recurrence_id.is_none() {
+            return None;
+        }
+
+        Some(Event {
+            uid: self.uid.unwrap_or_default(),
+            summary: self.summary.unwrap_or_default(),
+            location: self.location.filter(|location| !location.is_empty()),
+            start,
+            duration,
+            all_day,
+            recurrence: self.recurrence,
+            exceptions: self.exceptions,
+            recurrence_id: self.recurrence_id,
+            cancelled: self.cancelled,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    /// Wrap events in a calendar, with the CRLF line endings of the RFC.
+    fn calendar(events: &str) -> String {
+        format!("BEGIN:VCALENDAR\nVERSION:2.0\nPRODID:-//Test//EN\n{events}END:VCALENDAR\n")
+            .replace('\n', "\r\n")
+    }
+
+    #[test]
+    fn parses_events() {
+        let events = parse(&calendar(
+            "BEGIN:VEVENT\n\
+             UID:meeting@example.com\n\
+             SUMMARY:Planning\\, with \\\"everyone\\\"\n\
+             LOCATION:Room 4\\nSecond floor\n\
+             DTSTART;TZID=Europe/Paris:20240115T100000\n\
+             DTEND;TZID=Europe/Paris:20240115T113000\n\
+             END:VEVENT\n",
+        ));
+        assert_eq!(
+            events,
+            [Event {
+                uid: "meeting@example.com".to_string(),
+                summary: "Planning, with \"everyone\"".to_string(),
+                location: Some("Room 4\nSecond floor".to_string()),
+                start: at(2024, 1, 15, 10, 0),
+                duration: Duration::minutes(90),
+                all_day: false,
+                recurrence: None,
+                exceptions: vec![],
+                recurrence_id: None,
+                cancelled: false,
+            }]
+        );
+    }
+
+    /// The timezone of Paris, like calendar applications define it.
+    const PARIS: &str = "BEGIN:VTIMEZONE\n\
+                         TZID:Europe/Paris\n\
+                         BEGIN:DAYLIGHT\n\
+                         TZOFFSETFROM:+0100\n\
+                         TZOFFSETTO:+0200\n\
+                         TZNAME:CEST\n\
+                         DTSTART:19700329T020000\n\
+                         RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU\n\
+                         END:DAYLIGHT\n\
+                         BEGIN:STANDARD\n\
+                         TZOFFSETFROM:+0200\n\
+                         TZOFFSETTO:+0100\n\
+                         TZNAME:CET\n\
+                         DTSTART:19701025T030000\n\
+                         RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU\n\
+                         END:STANDARD\n\
+                         END:VTIMEZONE\n";
+
+    /// Convert an UTC time to local time.
+    fn from_utc(utc: NaiveDateTime) -> NaiveDateTime {
+        DateTime::<Utc>::from_naive_utc_and_offset(utc, Utc)
+            .with_timezone(&Local)
+            .naive_local()
+    }
+
+    #[test]
+    fn converts_timezones() {
+        let events = parse(&calendar(&format!(
+            "BEGIN:VEVENT\nUID:winter\nDTSTART;TZID=Europe/Paris:20240115T100000\n\
+             DTEND;TZID=Europe/Paris:20240115T113000\nEND:VEVENT\n\
+             BEGIN:VEVENT\nUID:summer\nDTSTART;TZID=\"Europe/Paris\":20240715T100000\n\
+             RRULE:FREQ=WEEKLY\nEXDATE;TZID=Europe/Paris:20240722T100000\nEND:VEVENT\n\
+             BEGIN:VEVENT\nUID:kathmandu\nDTSTART;TZID=Asia/Kathmandu:20240115T100000\n\
+             END:VEVENT\n\
+             BEGIN:VEVENT\nUID:undefined\nDTSTART;TZID=America/New_York:20240115T100000\n\
+             END:VEVENT\n\
+             BEGIN:VEVENT\nUID:utc\nDTSTART;TZID=Europe/Paris:20240115T100000Z\nEND:VEVENT\n\
+             {PARIS}\
+             BEGIN:VTIMEZONE\nTZID:Asia/Kathmandu\nBEGIN:STANDARD\nTZOFFSETFROM:+0530\n\
+             TZOFFSETTO:+0545\nDTSTART:19860101T000000\nEND:STANDARD\nEND:VTIMEZONE\n",
+        )));
+        let starts = events
+            .iter()
+            .map(|event| (event.uid.as_str(), event.start))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            starts,
+            [
+                ("winter", from_utc(at(2024, 1, 15, 9, 0))),
+                ("summer", from_utc(at(2024, 7, 15, 8, 0))),
+                ("kathmandu", from_utc(at(2024, 1, 15, 4, 15))),
+                // Without a definition, we can only take it as a local time.
+                ("undefined", at(2024, 1, 15, 10, 0)),
+                ("utc", from_utc(at(2024, 1, 15, 10, 0))),
+            ]
+        );
+        assert_eq!(events[0].duration, Duration::minutes(90));
+        assert_eq!(events[1].exceptions, [from_utc(at(2024, 7, 22, 8, 0))]);
+    }
+
+    #[test]
+    fn timezone_offsets() {
+        let properties = unfold(PARIS)
+            .iter()
+            .filter_map(|line| Property::parse(line))
+            .collect::<Vec<_>>();
+        let timezones = parse_timezones(&properties);
+        let paris = &timezones["Europe/Paris"];
+        let offset = |time| paris.offset_at(time).unwrap().local_minus_utc() / 3600;
+
+        assert_eq!(offset(at(2024, 3, 31, 1, 59)), 1);
+        assert_eq!(offset(at(2024, 3, 31, 3, 0)), 2);
+        assert_eq!(offset(at(2024, 10, 27, 2, 59)), 2);
+        assert_eq!(offset(at(2024, 10, 27, 3, 0)), 1);
+        // Before the first change.
+        assert_eq!(offset(at(1960, 6, 1, 0, 0)), 1);
+    }
+
+    #[test]
+    fn parses_utc_offsets() {
+        let offset = |value| parse_utc_offset(value).map(|offset| offset.local_minus_utc());
+        assert_eq!(offset("+0100"), Some(3600));
+        assert_eq!(offset("-0500"), Some(-18_000));
+        assert_eq!(offset("+0545"), Some(20_700));
+        assert_eq!(offset("-003015"), Some(-1815));
+        assert_eq!(offset("0100"), None);
+        assert_eq!(offset("+01"), None);
+        assert_eq!(offset("+01:00"), None);
+        assert_eq!(offset(""), None);
+    }
+
+    #[test]
+    fn unfolds_lines() {
+        let events = parse(&calendar(
+            "BEGIN:VEVENT\n\
+             UID:1\n\
+             SUMMARY:A very long summary that got\n  folded \n\tacross lines\n\
+             DTSTART:2024\n 0115T100000\n\
+             END:VEVENT\n",
+        ));
+        assert_eq!(
+            events[0].summary,
+            "A very long summary that got folded across lines"
+        );
+        assert_eq!(events[0].start, at(2024, 1, 15, 10, 0));
+    }
+
+    #[test]
+    fn durations() {
+        let events = parse(&calendar(
+            "BEGIN:VEVENT\nUID:all-day\nDTSTART;VALUE=DATE:20240301\n\
+             DTEND;VALUE=DATE:20240303\nEND:VEVENT\n\
+             BEGIN:VEVENT\nUID:one-day\nDTSTART;VALUE=DATE:20240301\nEND:VEVENT\n\
+             BEGIN:VEVENT\nUID:duration\nDTSTART:20240301T090000\nDURATION:PT1H30M\nEND:VEVENT\n\
+             BEGIN:VEVENT\nUID:week\nDTSTART:20240301T090000\nDURATION:P1W\nEND:VEVENT\n\
+             BEGIN:VEVENT\nUID:instant\nDTSTART:20240301T090000\nEND:VEVENT\n",
+        ));
+        let durations = events
+            .iter()
+            .map(|event| (event.uid.as_str(), event.all_day, event.duration))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            durations,
+            [
+                // DTEND is exclusive, this is two days.
+                ("all-day", true, Duration::days(2)),
+                ("one-day", true, Duration::days(1)),
+                ("duration", false, Duration::minutes(90)),
+                ("week", false, Duration::weeks(1)),
+                ("instant", false, Duration::zero()),
+            ]
+        );
+        assert_eq!(events[0].start, at(2024, 3, 1, 0, 0));
+    }
+
+    #[test]
+    fn ignores_nested_components() {
+        let events = parse(&calendar(
+            "BEGIN:VTIMEZONE\nTZID:Europe/Paris\nBEGIN:STANDARD\n\
+             DTSTART:19701025T030000\nEND:STANDARD\nEND:VTIMEZONE\n\
+             BEGIN:VEVENT\nUID:1\nSUMMARY:Dentist\nDTSTART:20240115T100000\n\
+             BEGIN:VALARM\nACTION:DISPLAY\nSUMMARY:Reminder\nDURATION:PT15M\n\
+             TRIGGER:-PT15M\nEND:VALARM\n\
+             DTEND:20240115T103000\nEND:VEVENT\n\
+             BEGIN:VTODO\nUID:2\nSUMMARY:Task\nDTSTART:20240115T100000\nEND:VTODO\n",
+        ));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].summary, "Dentist");
+        assert_eq!(events[0].duration, Duration::minutes(30));
+    }
+
+    #[test]
+    fn exceptions_and_overrides() {
+        let events = parse(&calendar(
+            "BEGIN:VEVENT\nUID:standup\nDTSTART:20240115T090000\nRRULE:FREQ=DAILY\n\
+             EXDATE:20240116T090000,20240117T090000\nEXDATE;VALUE=DATE:20240118\nEND:VEVENT\n\
+             BEGIN:VEVENT\nUID:standup\nRECURRENCE-ID:20240119T090000\n\
+             DTSTART:20240119T110000\nEND:VEVENT\n\
+             BEGIN:VEVENT\nUID:standup\nRECURRENCE-ID:20240122T090000\n\
+             DTSTART:20240122T090000\nSTATUS:CANCELLED\nEND:VEVENT\n\
+             BEGIN:VEVENT\nUID:gone\nDTSTART:20240122T090000\nSTATUS:CANCELLED\nEND:VEVENT\n\
+             BEGIN:VEVENT\nUID:no-start\nSUMMARY:Nothing\nEND:VEVENT\n",
+        ));
+        assert_eq!(events.len(), 3);
+        assert_eq!(
+            events[0].exceptions,
+            [
+                at(2024, 1, 16, 9, 0),
+                at(2024, 1, 17, 9, 0),
+                at(2024, 1, 18, 0, 0)
+            ]
+        );
+        assert_eq!(events[1].recurrence_id, Some(at(2024, 1, 19, 9, 0)));
+        assert_eq!(events[1].start, at(2024, 1, 19, 11, 0));
+        assert!(!events[1].cancelled);
+        assert_eq!(events[2].recurrence_id, Some(at(2024, 1, 22, 9, 0)));
+        assert!(events[2].cancelled);
+    }
+
+    #[test]
+    fn parses_utc_times() {
+        let events = parse(&calendar(
+            "BEGIN:VEVENT\nUID:1\nDTSTART:20240115T100000Z\nDTEND:20240115T110000Z\nEND:VEVENT\n",
+        ));
+        let utc = at(2024, 1, 15, 10, 0);
+        let local = DateTime::<Utc>::from_naive_utc_and_offset(utc, Utc).with_timezone(&Local);
+        assert_eq!(events[0].start, local.naive_local());
+        assert_eq!(events[0].duration, Duration::hours(1));
+    }
+
+    #[test]
+    fn parses_recurrences() {
+        assert_eq!(
+            parse_recurrence("FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,WE;UNTIL=20240131T235959;WKST=SU"),
+            Some(Recurrence {
+                frequency: Frequency::Weekly,
+                interval: 2,
+                until: Some(at(2024, 1, 31, 23, 59) + Duration::seconds(59)),
+                by_day: vec![(None, Weekday::Mon), (None, Weekday::Wed)],
+                ..Recurrence::default()
+            })
+        );
+        assert_eq!(
+            parse_recurrence("FREQ=MONTHLY;BYDAY=-1FR,+2TU,1SU;COUNT=5"),
+            Some(Recurrence {
+                frequency: Frequency::Monthly,
+                count: Some(5),
+                by_day: vec![
+                    (Some(-1), Weekday::Fri),
+                    (Some(2), Weekday::Tue),
+                    (Some(1), Weekday::Sun)
+                ],
+                ..Recurrence::default()
+            })
+        );
+        assert_eq!(
+            parse_recurrence("freq=yearly;bymonth=2,8;bymonthday=29,-1;until=20300101"),
+            Some(Recurrence {
+                frequency: Frequency::Yearly,
+                until: Some(at(2030, 1, 1, 0, 0)),
+                by_month_day: vec![29, -1],
+                by_month: vec![2, 8],
+                ..Recurrence::default()
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_unsupported_recurrences() {
+        assert_eq!(parse_recurrence("FREQ=HOURLY"), None);
+        assert_eq!(parse_recurrence("INTERVAL=2"), None);
+        assert_eq!(parse_recurrence("FREQ=DAILY;COUNT=many"), None);
+        assert_eq!(parse_recurrence("FREQ=WEEKLY;BYDAY=XX"), None);
+        assert_eq!(parse_recurrence("FREQ=WEEKLY;BYDAY=M"), None);
+        assert_eq!(parse_recurrence("FREQ=DAILY;BROKEN"), None);
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("PT15M"), Some(Duration::minutes(15)));
+        assert_eq!(
+            parse_duration("P1DT2H3M4S"),
+            Some(Duration::seconds(93_784))
+        );
+        assert_eq!(parse_duration("-PT15M"), Some(Duration::minutes(-15)));
+        assert_eq!(parse_duration("+P2W"), Some(Duration::weeks(2)));
+        assert_eq!(parse_duration("15M"), None);
+        assert_eq!(parse_duration("P1Y"), None);
+    }
+}
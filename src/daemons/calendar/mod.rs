@@ -0,0 +1,249 @@
+//! Calendar daemon.
+//!
+//! Reads the events from local iCalendar files, either single `.ics` files or vdir directories
+//! holding one file per event, like the ones vdirsyncer keeps in sync with a CalDAV server. The
+//! calendars to read are set in the configuration.
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
+use std::{fs, io};
+
+use chrono::NaiveDateTime;
+use tokio::sync::watch;
+
+pub mod ics;
+pub mod recurrence;
+
+pub use self::ics::Event;
+
+/// How often we check the calendars for changes.
+///
+/// Syncing tools rewrite files every few minutes at most, there's no need to watch them.
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A calendar daemon, used to get the upcoming events.
+pub struct Daemon {
+    paths: Vec<PathBuf>,
+    events: watch::Sender<Arc<Vec<Event>>>,
+    /// The calendar files with their modification time, when we last read them.
+    files: Mutex<Vec<(PathBuf, SystemTime)>>,
+}
+
+impl Daemon {
+    /// Create a new [`Daemon`] reading the calendars at these paths.
+    pub fn new(paths: Vec<PathBuf>) -> Self {
+        let daemon = Self {
+            paths,
+            events: watch::Sender::new(Arc::new(vec![])),
+            files: Mutex::new(vec![]),
+        };
+        daemon.refresh();
+        daemon
+    }
+
+    /// Subscribe to the changes of the events.
+    pub fn subscribe(&self) -> watch::Receiver<Arc<Vec<Event>>> {
+        self.events.subscribe()
+    }
+
+    /// Read the calendars again if any of their files changed.
+    pub fn refresh(&self) {
+        let mut files = vec![];
+        for path in &self.paths {
+            if let Err(err) = list_files(path, &mut files) {
+                warn!(?err, ?path, "Failed to list calendar files");
+            }
+        }
+        files.sort();
+
+        let mut current_files = self.files.lock().unwrap();
+        if *current_files == files {
+            return;
+        }
+
+        let mut events = vec![];
+        for (path, _) in &files {
+            match fs::read_to_string(path) {
+                Ok(contents) => events.extend(ics::parse(&contents)),
+                Err(err) => warn!(?err, ?path, "Failed to read calendar file"),
+            }
+        }
+        debug!(files = files.len(), events = events.len(), "Read calendars");
+
+        *current_files = files;
+        self.events.send_replace(Arc::new(events));
+    }
+
+    /// Get the occurrences of the events happening between `from` and `to`, in order.
+    pub fn occurrences(&self, from: NaiveDateTime, to: NaiveDateTime) -> Vec<Occurrence> {
+        occurrences(&self.events.borrow(), from, to)
+    }
+}
+
+/// A single occurrence of an event.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Occurrence {
+    pub summary: String,
+    pub location: Option<String>,
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    pub all_day: bool,
+}
+
+impl Occurrence {
+    fn new(event: &Event, start: NaiveDateTime) -> Self {
+        Self {
+            summary: event.summary.clone(),
+            location: event.location.clone(),
+            start,
+            end: start + event.duration,
+            all_day: event.all_day,
+        }
+    }
+
+    /// Whether this occurrence happens between `from` and `to`.
+    fn overlaps(&self, from: NaiveDateTime, to: NaiveDateTime) -> bool {
+        self.start < to && (self.end > from || self.start >= from)
+    }
+}
+
+/// Get the occurrences of these events happening between `from` and `to`, in order.
+pub fn occurrences(events: &[Event], from: NaiveDateTime, to: NaiveDateTime) -> Vec<Occurrence> {
+    // Modified occurrences replace the original ones of their series.
+    let replaced = events
+        .iter()
+        .filter_map(|event| Some((event.uid.as_str(), event.recurrence_id?)))
+        .collect::<HashSet<_>>();
+
+    let mut occurrences = vec![];
+    for event in events.iter().filter(|event| !event.cancelled) {
+        let Some(recurrence) = event
+            .recurrence
+            .as_ref()
+            .filter(|_| event.recurrence_id.is_none())
+        else {
+            occurrences.push(Occurrence::new(event, event.start));
+            continue;
+        };
+
+        // Occurrences that started before `from` may still be going on.
+        let before = to.max(from);
+        for start in recurrence.occurrences(event.start, before) {
+            if event.exceptions.contains(&start) || replaced.contains(&(event.uid.as_str(), start))
+            {
+                continue;
+            }
+            occurrences.push(Occurrence::new(event, start));
+        }
+    }
+
+    occurrences.retain(|occurrence| occurrence.overlaps(from, to));
+    // All-day events come first in their day.
+    occurrences.sort_by(|a, b| {
+        (a.start.date(), !a.all_day, a.start).cmp(&(b.start.date(), !b.all_day, b.start))
+    });
+    occurrences
+}
+
+/// List the calendar files at this path, recursing into directories.
+fn list_files(path: &Path, files: &mut Vec<(PathBuf, SystemTime)>) -> io::Result<()> {
+    let metadata = fs::metadata(path)?;
+    if metadata.is_file() {
+        files.push((path.to_path_buf(), metadata.modified()?));
+        return Ok(());
+    }
+
+    for entry in fs::read_dir(path)? {
+        let path = entry?.path();
+        let is_calendar = path.extension().is_some_and(|extension| extension == "ics");
+        if path.is_dir() || is_calendar {
+            list_files(&path, files)?;
+        }
+    }
+    Ok(())
+}
+
+static INSTANCE: OnceLock<Daemon> = OnceLock::new();
+
+pub fn get() -> &'static Daemon {
+    INSTANCE.get().expect("daemons::start() must be called")
+}
+
+pub async fn start() -> anyhow::Result<()> {
+    if INSTANCE.get().is_some() {
+        return Ok(());
+    }
+
+    let paths = crate::config::get().calendar.paths.clone();
+    // NOTE: If we already started he handled it above.
+    let _ = INSTANCE.set(Daemon::new(paths));
+
+    super::spawn("calendar-poll", async move {
+        loop {
+            async_io::Timer::after(POLL_INTERVAL).await;
+            get().refresh();
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn at(day: u32, hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 1, day)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+    }
+
+    const CALENDAR: &str = "BEGIN:VCALENDAR\r\n\
+        BEGIN:VEVENT\r\nUID:standup\r\nSUMMARY:Standup\r\nDTSTART:20240115T090000\r\n\
+        DTEND:20240115T093000\r\nRRULE:FREQ=DAILY;COUNT=5\r\nEXDATE:20240116T090000\r\nEND:VEVENT\r\n\
+        BEGIN:VEVENT\r\nUID:standup\r\nSUMMARY:Late standup\r\nRECURRENCE-ID:20240117T090000\r\n\
+        DTSTART:20240117T110000\r\nDTEND:20240117T113000\r\nEND:VEVENT\r\n\
+        BEGIN:VEVENT\r\nUID:standup\r\nRECURRENCE-ID:20240118T090000\r\n\
+        DTSTART:20240118T090000\r\nSTATUS:CANCELLED\r\nEND:VEVENT\r\n\
+        BEGIN:VEVENT\r\nUID:holiday\r\nSUMMARY:Holiday\r\nDTSTART;VALUE=DATE:20240118\r\n\
+        DTEND;VALUE=DATE:20240120\r\nEND:VEVENT\r\n\
+        END:VCALENDAR\r\n";
+
+    fn summaries(from: NaiveDateTime, to: NaiveDateTime) -> Vec<(String, NaiveDateTime)> {
+        occurrences(&ics::parse(CALENDAR), from, to)
+            .into_iter()
+            .map(|occurrence| (occurrence.summary, occurrence.start))
+            .collect()
+    }
+
+    #[test]
+    fn exceptions_and_overrides() {
+        assert_eq!(
+            summaries(at(15, 0), at(22, 0)),
+            [
+                ("Standup".to_string(), at(15, 9)),
+                // The 16th is excluded, the 17th moved and the 18th cancelled.
+                ("Late standup".to_string(), at(17, 11)),
+                ("Holiday".to_string(), at(18, 0)),
+                ("Standup".to_string(), at(19, 9)),
+            ]
+        );
+    }
+
+    #[test]
+    fn overlapping_occurrences() {
+        // The holiday started the day before, and the standup is going on.
+        assert_eq!(
+            summaries(at(19, 9) + chrono::Duration::minutes(10), at(20, 0)),
+            [
+                ("Holiday".to_string(), at(18, 0)),
+                ("Standup".to_string(), at(19, 9)),
+            ]
+        );
+        assert_eq!(summaries(at(20, 0), at(31, 0)), []);
+    }
+}
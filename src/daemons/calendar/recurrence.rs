@@ -0,0 +1,439 @@
+//! Recurrence rules (`RRULE`) expansion.
+//!
+//! Supports the daily, weekly, monthly and yearly frequencies with their interval, count and end
+//! date, and the `BYDAY` (with ordinals, like `-1FR`), `BYMONTHDAY` and `BYMONTH` parts, which
+//! cover what calendar applications create.
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+
+/// The most periods we go through, to stop on rules that never match anything.
+const MAX_PERIODS: u32 = 100_000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Frequency {
+    #[default]
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recurrence {
+    pub frequency: Frequency,
+    /// How many periods between each occurrence.
+    pub interval: u32,
+    /// How many occurrences there are, including the first one.
+    pub count: Option<u32>,
+    /// The last time an occurrence can start.
+    pub until: Option<NaiveDateTime>,
+    /// The weekdays of the occurrences, with an optional ordinal in the month.
+    pub by_day: Vec<(Option<i32>, Weekday)>,
+    /// The days of the month of the occurrences, negative ones counting from the end.
+    pub by_month_day: Vec<i32>,
+    /// The months of the occurrences, from 1 to 12.
+    pub by_month: Vec<u32>,
+}
+
+impl Default for Recurrence {
+    fn default() -> Self {
+        Self {
+            frequency: Frequency::default(),
+            interval: 1,
+            count: None,
+            until: None,
+            by_day: vec![],
+            by_month_day: vec![],
+            by_month: vec![],
+        }
+    }
+}
+
+impl Recurrence {
+    /// Get the start times of the occurrences of a series starting at `start`, that start before
+    /// `before`, in order.
+    pub fn occurrences(&self, start: NaiveDateTime, before: NaiveDateTime) -> Vec<NaiveDateTime> {
+        let mut occurrences = vec![];
+        if start >= before || self.count == Some(0) {
+            return occurrences;
+        }
+        // The start is always the first occurrence, even if it does not match the rule.
+        occurrences.push(start);
+
+        let interval = self.interval.max(1);
+        for period in 0..MAX_PERIODS {
+            let Some((period_start, dates)) = self.period(start.date(), period * interval) else {
+                break;
+            };
+            if period_start.and_time(NaiveTime::MIN) >= before {
+                break;
+            }
+
+            for date in dates {
+                let time = date.and_time(start.time());
+                if time <= start {
+                    continue;
+                }
+                let ended = self.until.is_some_and(|until| time > until)
+                    || self
+                        .count
+                        .is_some_and(|count| occurrences.len() >= count as usize);
+                if ended || time >= before {
+                    return occurrences;
+                }
+                occurrences.push(time);
+            }
+        }
+
+        occurrences
+    }
+
+    /// Get the first day of the `n`th period after `start`, and the dates of the occurrences in
+    /// it, in order.
+    fn period(&self, start: NaiveDate, n: u32) -> Option<(NaiveDate, Vec<NaiveDate>)> {
+        let (period_start, mut dates) = match self.frequency {
+            Frequency::Daily => {
+                let date = start.checked_add_signed(Duration::days(n.into()))?;
+                (date, vec![date])
+            }
+            Frequency::Weekly => {
+                let monday = start - Duration::days(start.weekday().num_days_from_monday().into());
+                let monday = monday.checked_add_signed(Duration::weeks(n.into()))?;
+                let mut weekdays = self
+                    .by_day
+                    .iter()
+                    .map(|(_, weekday)| *weekday)
+                    .collect::<Vec<_>>();
+                if weekdays.is_empty() {
+                    weekdays.push(start.weekday());
+                }
+                let dates = weekdays
+                    .into_iter()
+                    .map(|weekday| monday + Duration::days(weekday.num_days_from_monday().into()))
+                    .collect();
+                (monday, dates)
+            }
+            Frequency::Monthly => {
+                let months = start.year() * 12 + start.month0() as i32 + n as i32;
+                let (year, month) = (months.div_euclid(12), months.rem_euclid(12) as u32 + 1);
+                let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+                (first, self.month_dates(year, month, start.day()))
+            }
+            Frequency::Yearly => {
+                let year = start.year() + n as i32;
+                let first = NaiveDate::from_ymd_opt(year, 1, 1)?;
+                let months = if self.by_month.is_empty() {
+                    vec![start.month()]
+                } else {
+                    self.by_month.clone()
+                };
+                let dates = months
+                    .into_iter()
+                    .flat_map(|month| self.month_dates(year, month, start.day()))
+                    .collect();
+                (first, dates)
+            }
+        };
+
+        dates.retain(|date| self.by_month.is_empty() || self.by_month.contains(&date.month()));
+        if self.frequency == Frequency::Daily && !self.by_day.is_empty() {
+            dates.retain(|date| self.by_day.iter().any(|(_, day)| *day == date.weekday()));
+        }
+        dates.sort();
+        dates.dedup();
+        Some((period_start, dates))
+    }
+
+    /// Get the dates of the occurrences in a month, defaulting to the day of the start.
+    fn month_dates(&self, year: i32, month: u32, start_day: u32) -> Vec<NaiveDate> {
+        let Some(first) = NaiveDate::from_ymd_opt(year, month, 1) else {
+            return vec![];
+        };
+        let days_in_month = first
+            .checked_add_months(chrono::Months::new(1))
+            .map_or(31, |next| (next - first).num_days() as i32);
+        let day = |day: i32| {
+            let day = if day < 0 {
+                days_in_month + day + 1
+            } else {
+                day
+            };
+            u32::try_from(day)
+                .ok()
+                .and_then(|day| NaiveDate::from_ymd_opt(year, month, day))
+        };
+
+        if !self.by_month_day.is_empty() {
+            return self
+                .by_month_day
+                .iter()
+                .filter_map(|d| day(*d))
+                .filter(|date| {
+                    self.by_day.is_empty()
+                        || self.by_day.iter().any(|(_, day)| *day == date.weekday())
+                })
+                .collect();
+        }
+
+        if !self.by_day.is_empty() {
+            let mut dates = vec![];
+            for (ordinal, weekday) in &self.by_day {
+                let matching = (1..=days_in_month)
+                    .filter_map(day)
+                    .filter(|date| date.weekday() == *weekday)
+                    .collect::<Vec<_>>();
+                match ordinal {
+                    None => dates.extend(matching),
+                    Some(ordinal) if *ordinal > 0 => {
+                        dates.extend(matching.get(*ordinal as usize - 1));
+                    }
+                    Some(ordinal) => {
+                        let idx = matching.len().checked_sub(ordinal.unsigned_abs() as usize);
+                        dates.extend(idx.and_then(|idx| matching.get(idx)));
+                    }
+                }
+            }
+            return dates;
+        }
+
+        // Months without this day are skipped, like the RFC says.
+        day(start_day as i32).into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+    }
+
+    fn dates(occurrences: Vec<NaiveDateTime>) -> Vec<(i32, u32, u32)> {
+        occurrences
+            .into_iter()
+            .map(|occurrence| (occurrence.year(), occurrence.month(), occurrence.day()))
+            .collect()
+    }
+
+    fn recurrence(frequency: Frequency) -> Recurrence {
+        Recurrence {
+            frequency,
+            ..Recurrence::default()
+        }
+    }
+
+    #[test]
+    fn daily() {
+        let rule = recurrence(Frequency::Daily);
+        let occurrences = rule.occurrences(at(2024, 2, 27, 9), at(2024, 3, 2, 9));
+        assert_eq!(
+            occurrences,
+            [
+                at(2024, 2, 27, 9),
+                at(2024, 2, 28, 9),
+                at(2024, 2, 29, 9),
+                at(2024, 3, 1, 9)
+            ]
+        );
+    }
+
+    #[test]
+    fn count() {
+        let rule = Recurrence {
+            count: Some(3),
+            interval: 2,
+            ..recurrence(Frequency::Daily)
+        };
+        let occurrences = rule.occurrences(at(2024, 1, 1, 9), at(2025, 1, 1, 0));
+        assert_eq!(
+            dates(occurrences),
+            [(2024, 1, 1), (2024, 1, 3), (2024, 1, 5)]
+        );
+
+        let rule = Recurrence {
+            count: Some(0),
+            ..recurrence(Frequency::Daily)
+        };
+        assert_eq!(rule.occurrences(at(2024, 1, 1, 9), at(2025, 1, 1, 0)), []);
+    }
+
+    #[test]
+    fn until_is_inclusive() {
+        let rule = Recurrence {
+            until: Some(at(2024, 1, 3, 9)),
+            ..recurrence(Frequency::Daily)
+        };
+        let occurrences = rule.occurrences(at(2024, 1, 1, 9), at(2025, 1, 1, 0));
+        assert_eq!(
+            dates(occurrences),
+            [(2024, 1, 1), (2024, 1, 2), (2024, 1, 3)]
+        );
+
+        // A date-only until covers nothing of its day with a later time.
+        let rule = Recurrence {
+            until: Some(at(2024, 1, 3, 0)),
+            ..recurrence(Frequency::Daily)
+        };
+        let occurrences = rule.occurrences(at(2024, 1, 1, 9), at(2025, 1, 1, 0));
+        assert_eq!(dates(occurrences), [(2024, 1, 1), (2024, 1, 2)]);
+    }
+
+    #[test]
+    fn weekly_by_day() {
+        // Every other week on Monday, Wednesday and Friday, starting on a Wednesday.
+        let rule = Recurrence {
+            interval: 2,
+            by_day: vec![
+                (None, Weekday::Mon),
+                (None, Weekday::Wed),
+                (None, Weekday::Fri),
+            ],
+            ..recurrence(Frequency::Weekly)
+        };
+        let occurrences = rule.occurrences(at(2024, 1, 3, 9), at(2024, 1, 31, 0));
+        assert_eq!(
+            dates(occurrences),
+            [
+                (2024, 1, 3),
+                (2024, 1, 5),
+                (2024, 1, 15),
+                (2024, 1, 17),
+                (2024, 1, 19),
+                (2024, 1, 29)
+            ]
+        );
+    }
+
+    #[test]
+    fn start_is_always_an_occurrence() {
+        // Starting on a Monday, but happening on Tuesdays.
+        let rule = Recurrence {
+            by_day: vec![(None, Weekday::Tue)],
+            ..recurrence(Frequency::Weekly)
+        };
+        let occurrences = rule.occurrences(at(2024, 1, 1, 9), at(2024, 1, 10, 0));
+        assert_eq!(
+            dates(occurrences),
+            [(2024, 1, 1), (2024, 1, 2), (2024, 1, 9)]
+        );
+    }
+
+    #[test]
+    fn last_friday_of_the_month() {
+        let rule = Recurrence {
+            by_day: vec![(Some(-1), Weekday::Fri)],
+            ..recurrence(Frequency::Monthly)
+        };
+        let occurrences = rule.occurrences(at(2024, 1, 26, 17), at(2024, 6, 1, 0));
+        assert_eq!(
+            dates(occurrences),
+            [
+                (2024, 1, 26),
+                (2024, 2, 23),
+                (2024, 3, 29),
+                (2024, 4, 26),
+                (2024, 5, 31)
+            ]
+        );
+    }
+
+    #[test]
+    fn second_tuesday_of_the_month() {
+        let rule = Recurrence {
+            by_day: vec![(Some(2), Weekday::Tue)],
+            ..recurrence(Frequency::Monthly)
+        };
+        let occurrences = rule.occurrences(at(2024, 1, 9, 18), at(2024, 4, 1, 0));
+        assert_eq!(
+            dates(occurrences),
+            [(2024, 1, 9), (2024, 2, 13), (2024, 3, 12)]
+        );
+    }
+
+    #[test]
+    fn month_day_skips_short_months() {
+        let rule = Recurrence {
+            by_month_day: vec![31],
+            ..recurrence(Frequency::Monthly)
+        };
+        let occurrences = rule.occurrences(at(2024, 1, 31, 9), at(2024, 9, 1, 0));
+        assert_eq!(
+            dates(occurrences),
+            [
+                (2024, 1, 31),
+                (2024, 3, 31),
+                (2024, 5, 31),
+                (2024, 7, 31),
+                (2024, 8, 31)
+            ]
+        );
+
+        // The same without BYMONTHDAY, from the day of the start.
+        let rule = recurrence(Frequency::Monthly);
+        let occurrences = rule.occurrences(at(2024, 1, 31, 9), at(2024, 6, 1, 0));
+        assert_eq!(
+            dates(occurrences),
+            [(2024, 1, 31), (2024, 3, 31), (2024, 5, 31)]
+        );
+    }
+
+    #[test]
+    fn last_day_of_the_month() {
+        let rule = Recurrence {
+            by_month_day: vec![-1],
+            ..recurrence(Frequency::Monthly)
+        };
+        let occurrences = rule.occurrences(at(2024, 1, 31, 9), at(2024, 5, 1, 0));
+        assert_eq!(
+            dates(occurrences),
+            [(2024, 1, 31), (2024, 2, 29), (2024, 3, 31), (2024, 4, 30)]
+        );
+    }
+
+    #[test]
+    fn yearly() {
+        let rule = recurrence(Frequency::Yearly);
+        let occurrences = rule.occurrences(at(2024, 2, 29, 0), at(2033, 1, 1, 0));
+        assert_eq!(
+            dates(occurrences),
+            [(2024, 2, 29), (2028, 2, 29), (2032, 2, 29)]
+        );
+
+        // Thanksgiving, the fourth Thursday of November.
+        let rule = Recurrence {
+            by_month: vec![11],
+            by_day: vec![(Some(4), Weekday::Thu)],
+            ..recurrence(Frequency::Yearly)
+        };
+        let occurrences = rule.occurrences(at(2023, 11, 23, 0), at(2026, 1, 1, 0));
+        assert_eq!(
+            dates(occurrences),
+            [(2023, 11, 23), (2024, 11, 28), (2025, 11, 27)]
+        );
+    }
+
+    #[test]
+    fn before() {
+        let rule = recurrence(Frequency::Daily);
+        assert_eq!(rule.occurrences(at(2024, 1, 1, 9), at(2024, 1, 1, 9)), []);
+        assert_eq!(rule.occurrences(at(2024, 1, 1, 9), at(2023, 1, 1, 9)), []);
+        let occurrences = rule.occurrences(at(2024, 1, 1, 9), at(2024, 1, 3, 9));
+        assert_eq!(dates(occurrences), [(2024, 1, 1), (2024, 1, 2)]);
+    }
+
+    #[test]
+    fn never_matching_rule() {
+        // There's no 30th of February, this must stop anyway.
+        let rule = Recurrence {
+            by_month: vec![2],
+            by_month_day: vec![30],
+            ..recurrence(Frequency::Yearly)
+        };
+        let occurrences = rule.occurrences(at(2024, 1, 30, 9), at(9999, 1, 1, 0));
+        assert_eq!(dates(occurrences), [(2024, 1, 30)]);
+    }
+}
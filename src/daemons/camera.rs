@@ -0,0 +1,432 @@
+//! Camera usage daemon.
+//!
+//! There's no API telling which programs use a camera, so we look for the processes that have a
+//! `/dev/video*` device open, by going through their file descriptors in `/proc`. This only works
+//! for the processes of the current user, which is what we want anyway.
+//!
+//! NOTE: Applications going through the camera portal get the camera from PipeWire, so in this
+//! case PipeWire itself shows up as the user.
+//!
+//! Going through `/proc` is not cheap, so we only do it when a camera device gets opened or closed,
+//! which we get told about by inotify.
+#![allow(unused)]
+use std::mem::MaybeUninit;
+use std::os::fd::OwnedFd;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+use std::{fs, io};
+
+use async_io::Async;
+use rustix::fs::inotify::{self, CreateFlags, ReadFlags, WatchFlags};
+use tokio::sync::watch;
+
+/// Where the kernel exposes processes.
+pub const PROC_ROOT: &str = "/proc";
+
+/// Where the camera devices live.
+const DEV_ROOT: &str = "/dev";
+
+/// How long we wait after a camera device got opened or closed before looking for its users.
+///
+/// The process gets its file descriptor after the open event, and programs tend to open and close
+/// the device a few times in a row when starting to use it.
+const RESCAN_DELAY: Duration = Duration::from_millis(200);
+
+/// A camera daemon, used to know who is using a camera.
+pub struct Daemon {
+    proc_root: PathBuf,
+    users: watch::Sender<Vec<CameraUser>>,
+}
+
+impl Daemon {
+    /// Create a new [`Daemon`] reading processes from `proc_root`.
+    ///
+    /// `proc_root` is [`PROC_ROOT`] outside of testing.
+    pub fn new(proc_root: impl Into<PathBuf>) -> Self {
+        let proc_root = proc_root.into();
+        let users = find_camera_users(&proc_root);
+        Self {
+            proc_root,
+            users: watch::Sender::new(users),
+        }
+    }
+
+    /// Get the processes currently using a camera.
+    pub fn users(&self) -> Vec<CameraUser> {
+        self.users.borrow().clone()
+    }
+
+    /// Subscribe to the changes of the processes using a camera.
+    pub fn subscribe(&self) -> watch::Receiver<Vec<CameraUser>> {
+        self.users.subscribe()
+    }
+
+    /// Look for the processes using a camera again, notifying subscribers if anything changed.
+    pub fn refresh(&self) {
+        let users = find_camera_users(&self.proc_root);
+        self.users.send_if_modified(|current| {
+            if *current == users {
+                return false;
+            }
+            *current = users;
+            true
+        });
+    }
+}
+
+/// A process using a camera.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CameraUser {
+    pub pid: u32,
+    /// The name of the process, from `/proc/<pid>/comm`.
+    pub name: String,
+    /// The camera devices in use, IE. `/dev/video0`
+    pub devices: Vec<PathBuf>,
+}
+
+/// Find the processes with a `/dev/video*` device open, sorted by PID.
+pub fn find_camera_users(proc_root: &Path) -> Vec<CameraUser> {
+    let Ok(entries) = fs::read_dir(proc_root) else {
+        return vec![];
+    };
+
+    let mut users = vec![];
+    for entry in entries.flatten() {
+        let Some(pid) = entry
+            .file_name()
+            .to_str()
+            .and_then(|pid| pid.parse::<u32>().ok())
+        else {
+            continue; // not a process.
+        };
+        // Processes can exit at any time, and we can't look into other users' ones.
+        let Ok(devices) = open_video_devices(&entry.path()) else {
+            continue;
+        };
+        if devices.is_empty() {
+            continue;
+        }
+
+        let name = fs::read_to_string(entry.path().join("comm"))
+            .map(|name| name.trim().to_string())
+            .unwrap_or_else(|_| pid.to_string());
+        users.push(CameraUser { pid, name, devices });
+    }
+
+    users.sort_by_key(|user| user.pid);
+    users
+}
+
+fn open_video_devices(process: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut devices = vec![];
+    for fd in fs::read_dir(process.join("fd"))? {
+        let Ok(target) = fs::read_link(fd?.path()) else {
+            continue;
+        };
+        let is_video = target
+            .to_str()
+            .is_some_and(|target| target.starts_with("/dev/video"));
+        if is_video && !devices.contains(&target) {
+            devices.push(target);
+        }
+    }
+
+    devices.sort();
+    Ok(devices)
+}
+
+fn is_video_device(name: &str) -> bool {
+    name.strip_prefix("video")
+        .is_some_and(|index| !index.is_empty() && index.bytes().all(|c| c.is_ascii_digit()))
+}
+
+/// Watches the camera devices being opened and closed, and the devices coming and going.
+struct DeviceWatcher {
+    inotify: Async<OwnedFd>,
+    dev_root: PathBuf,
+    /// The watch on the device directory itself.
+    dev_watch: i32,
+    buffer: Vec<MaybeUninit<u8>>,
+}
+
+impl DeviceWatcher {
+    fn new(dev_root: impl Into<PathBuf>) -> io::Result<Self> {
+        let dev_root = dev_root.into();
+        let inotify = inotify::init(CreateFlags::CLOEXEC | CreateFlags::NONBLOCK)?;
+        let dev_watch = inotify::add_watch(
+            &inotify,
+            &dev_root,
+            WatchFlags::CREATE | WatchFlags::DELETE | WatchFlags::ONLYDIR,
+        )?;
+        let watcher = Self {
+            inotify: Async::new(inotify)?,
+            dev_root,
+            dev_watch,
+            buffer: vec![MaybeUninit::uninit(); 4096],
+        };
+
+        for entry in fs::read_dir(&watcher.dev_root)?.flatten() {
+            if let Some(name) = entry.file_name().to_str() {
+                watcher.watch_device(name);
+            }
+        }
+
+        Ok(watcher)
+    }
+
+    fn watch_device(&self, name: &str) {
+        if !is_video_device(name) {
+            return;
+        }
+        let path = self.dev_root.join(name);
+        // NOTE: The watch goes away by itself when the device gets removed.
+        let flags = WatchFlags::OPEN | WatchFlags::CLOSE_WRITE | WatchFlags::CLOSE_NOWRITE;
+        if let Err(err) = inotify::add_watch(self.inotify.get_ref(), &path, flags) {
+            warn!(?err, ?path, "Failed to watch camera device");
+        }
+    }
+
+    /// Wait until a camera device gets opened, closed, added or removed.
+    async fn changed(&mut self) -> io::Result<()> {
+        loop {
+            self.inotify.readable().await?;
+            if self.read_events()? {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Read the pending events, returning whether any of them is about a camera device.
+    fn read_events(&mut self) -> io::Result<bool> {
+        let mut created = vec![];
+        let mut changed = false;
+        let mut reader = inotify::Reader::new(self.inotify.get_ref(), &mut self.buffer);
+        loop {
+            let event = match reader.next() {
+                Ok(event) => event,
+                Err(rustix::io::Errno::AGAIN) => break,
+                Err(err) => return Err(err.into()),
+            };
+
+            if event.wd() != self.dev_watch {
+                // Removed watches report IN_IGNORED, the device removal is reported for /dev.
+                changed |= !event.events().contains(ReadFlags::IGNORED);
+                continue;
+            }
+            let Some(name) = event.file_name().and_then(|name| name.to_str().ok()) else {
+                continue;
+            };
+            if is_video_device(name) {
+                changed = true;
+                if event.events().contains(ReadFlags::CREATE) {
+                    created.push(name.to_string());
+                }
+            }
+        }
+
+        for name in created {
+            self.watch_device(&name);
+        }
+        Ok(changed)
+    }
+}
+
+static INSTANCE: OnceLock<Daemon> = OnceLock::new();
+
+pub fn get() -> &'static Daemon {
+    INSTANCE.get().expect("daemons::start() must be called")
+}
+
+pub async fn start() -> anyhow::Result<()> {
+    if INSTANCE.get().is_some() {
+        return Ok(());
+    }
+
+    // NOTE: If we already started he handled it above.
+    let _ = INSTANCE.set(Daemon::new(PROC_ROOT));
+
+    let mut watcher = match DeviceWatcher::new(DEV_ROOT) {
+        Ok(watcher) => watcher,
+        Err(err) => {
+            warn!(?err, "Failed to watch camera devices");
+            return Ok(());
+        }
+    };
+
+    super::spawn("camera-watch", async move {
+        loop {
+            watcher.changed().await?;
+            async_io::Timer::after(RESCAN_DELAY).await;
+            // Whatever happened during the delay is covered by this refresh.
+            watcher.read_events()?;
+            get().refresh();
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::os::unix::fs::symlink;
+    use std::pin::pin;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use futures_util::future::{self, Either};
+
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// A fake `/proc` or `/dev`, removed once dropped.
+    struct FakeDir(PathBuf);
+
+    impl FakeDir {
+        fn new() -> Self {
+            static COUNTER: AtomicUsize = AtomicUsize::new(0);
+            let name = format!(
+                "fht-shell-proc-{}-{}",
+                std::process::id(),
+                COUNTER.fetch_add(1, Ordering::Relaxed)
+            );
+            let root = std::env::temp_dir().join(name);
+            fs::create_dir_all(&root).unwrap();
+            Self(root)
+        }
+
+        fn add(&self, pid: &str, comm: Option<&str>, fds: &[&str]) {
+            let path = self.0.join(pid);
+            fs::create_dir_all(path.join("fd")).unwrap();
+            if let Some(comm) = comm {
+                fs::write(path.join("comm"), format!("{comm}\n")).unwrap();
+            }
+            for (fd, target) in fds.iter().enumerate() {
+                symlink(target, path.join("fd").join(fd.to_string())).unwrap();
+            }
+        }
+    }
+
+    impl Drop for FakeDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn finds_camera_users() {
+        let proc = FakeDir::new();
+        proc.add(
+            "4242",
+            Some("firefox"),
+            &["/dev/null", "/dev/video2", "/dev/video0"],
+        );
+        proc.add(
+            "1337",
+            Some("pipewire"),
+            &["/dev/video0", "/dev/video0", "socket:[1234]"],
+        );
+        proc.add("99", None, &["/dev/video1"]);
+        proc.add("100", Some("bash"), &["/dev/pts/0", "/home/user/video.mp4"]);
+        // Not processes.
+        proc.add("self", Some("self"), &["/dev/video0"]);
+        fs::write(proc.0.join("uptime"), "1.0 2.0\n").unwrap();
+        // Someone else's process, we can't read its file descriptors.
+        fs::create_dir_all(proc.0.join("1")).unwrap();
+
+        assert_eq!(
+            find_camera_users(&proc.0),
+            [
+                CameraUser {
+                    pid: 99,
+                    name: "99".to_string(),
+                    devices: vec!["/dev/video1".into()],
+                },
+                CameraUser {
+                    pid: 1337,
+                    name: "pipewire".to_string(),
+                    devices: vec!["/dev/video0".into()],
+                },
+                CameraUser {
+                    pid: 4242,
+                    name: "firefox".to_string(),
+                    devices: vec!["/dev/video0".into(), "/dev/video2".into()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn refresh_follows_changes() {
+        let proc = FakeDir::new();
+        let daemon = Daemon::new(&proc.0);
+        let mut changes = daemon.subscribe();
+        assert_eq!(daemon.users(), []);
+
+        proc.add("4242", Some("firefox"), &["/dev/video0"]);
+        daemon.refresh();
+        assert!(changes.has_changed().unwrap());
+        assert_eq!(changes.borrow_and_update()[0].pid, 4242);
+
+        daemon.refresh();
+        assert!(!changes.has_changed().unwrap());
+
+        fs::remove_dir_all(proc.0.join("4242")).unwrap();
+        daemon.refresh();
+        assert!(changes.has_changed().unwrap());
+        assert_eq!(daemon.users(), []);
+    }
+
+    #[test]
+    fn missing_proc() {
+        assert_eq!(find_camera_users(Path::new("/nonexistent/proc")), []);
+    }
+
+    /// Wait for a future, failing the test after [`TIMEOUT`].
+    async fn timeout<T>(future: impl Future<Output = T>) -> T {
+        match future::select(pin!(future), pin!(async_io::Timer::after(TIMEOUT))).await {
+            Either::Left((output, _)) => output,
+            Either::Right(_) => panic!("Timed out"),
+        }
+    }
+
+    #[test]
+    fn video_devices() {
+        assert!(is_video_device("video0"));
+        assert!(is_video_device("video12"));
+        assert!(!is_video_device("video"));
+        assert!(!is_video_device("video0p1"));
+        assert!(!is_video_device("media0"));
+        assert!(!is_video_device("v4l"));
+    }
+
+    #[test]
+    fn watches_devices() {
+        let dev = FakeDir::new();
+        fs::write(dev.0.join("video0"), "").unwrap();
+        fs::write(dev.0.join("null"), "").unwrap();
+
+        async_io::block_on(async {
+            let mut watcher = DeviceWatcher::new(&dev.0).unwrap();
+            assert!(!watcher.read_events().unwrap());
+
+            drop(fs::File::open(dev.0.join("null")).unwrap());
+            assert!(!watcher.read_events().unwrap());
+
+            drop(fs::File::open(dev.0.join("video0")).unwrap());
+            timeout(watcher.changed()).await.unwrap();
+            assert!(!watcher.read_events().unwrap());
+
+            // New devices get watched too.
+            fs::write(dev.0.join("video1"), "").unwrap();
+            timeout(watcher.changed()).await.unwrap();
+            drop(fs::File::open(dev.0.join("video1")).unwrap());
+            timeout(watcher.changed()).await.unwrap();
+
+            fs::remove_file(dev.0.join("video0")).unwrap();
+            timeout(watcher.changed()).await.unwrap();
+            assert!(!watcher.read_events().unwrap());
+        });
+    }
+}
@@ -0,0 +1,466 @@
+//! fht-compositor backend, talking to the compositor through its IPC socket.
+//!
+//! The IPC is made of JSON messages, one per line. Each connection sends a request and gets a
+//! response back, except when subscribing, in which case the compositor sends its whole state as
+//! events first, then an event every time something changes.
+//!
+//! The types here only mirror what we need from `fht-compositor-ipc`, and ignore everything else.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use super::{Backend, State, Window as ShellWindow, Workspace};
+
+/// The environment variable fht-compositor sets to the path of its IPC socket.
+const SOCKET_PATH_ENV: &str = "FHTC_SOCKET_PATH";
+/// How long to wait before connecting again when the compositor closed the socket.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Get the path of the IPC socket, if we are running under fht-compositor.
+pub fn socket_path() -> Option<PathBuf> {
+    std::env::var_os(SOCKET_PATH_ENV).map(PathBuf::from)
+}
+
+pub struct FhtBackend {
+    socket_path: PathBuf,
+}
+
+impl FhtBackend {
+    pub fn new(socket_path: PathBuf) -> Self {
+        Self { socket_path }
+    }
+
+    /// Send a single request, and wait for the response.
+    fn request(&self, request: &Request) -> anyhow::Result<serde_json::Value> {
+        let mut stream = UnixStream::connect(&self.socket_path)?;
+        let mut message = serde_json::to_string(request)?;
+        message.push('\n');
+        stream.write_all(message.as_bytes())?;
+
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line)?;
+        let response = serde_json::from_str::<serde_json::Value>(&line)?;
+        if let Some(err) = response.get("error") {
+            anyhow::bail!("fht-compositor error: {err}");
+        }
+        Ok(response)
+    }
+
+    /// Subscribe to the events and apply them to `state` until the compositor closes the socket.
+    fn subscribe(&self, state: &watch::Sender<State>) -> anyhow::Result<()> {
+        let mut stream = UnixStream::connect(&self.socket_path)?;
+        let mut message = serde_json::to_string(&Request::Subscribe)?;
+        message.push('\n');
+        stream.write_all(message.as_bytes())?;
+
+        let mut model = Model::default();
+        for line in BufReader::new(stream).lines() {
+            // Parse in two steps, so that events we don't know about don't stop us.
+            let value = serde_json::from_str::<serde_json::Value>(&line?)?;
+            let Ok(event) = serde_json::from_value::<Event>(value) else {
+                continue;
+            };
+
+            model.apply(event);
+            let new_state = model.to_state();
+            state.send_if_modified(|current| {
+                if *current == new_state {
+                    return false;
+                }
+                *current = new_state;
+                true
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl Backend for FhtBackend {
+    fn run(&self, state: watch::Sender<State>) -> anyhow::Result<()> {
+        loop {
+            if let Err(err) = self.subscribe(&state) {
+                warn!(?err, "Failed to subscribe to fht-compositor events");
+            }
+
+            warn!("fht-compositor IPC socket closed, connecting again");
+            state.send_replace(State::default());
+            std::thread::sleep(RECONNECT_DELAY);
+        }
+    }
+
+    fn focus_workspace(&self, id: u64) -> anyhow::Result<()> {
+        self.request(&Request::Action(Action::FocusWorkspace {
+            workspace_id: id as usize,
+        }))?;
+        Ok(())
+    }
+
+    fn focus_window(&self, id: u64) -> anyhow::Result<()> {
+        self.request(&Request::Action(Action::FocusWindow {
+            window_id: id as usize,
+        }))?;
+        Ok(())
+    }
+
+    fn close_window(&self, id: u64) -> anyhow::Result<()> {
+        self.request(&Request::Action(Action::CloseWindow {
+            window_id: id as usize,
+        }))?;
+        Ok(())
+    }
+
+    fn set_window_minimized(&self, _id: u64, _minimized: bool) -> anyhow::Result<()> {
+        anyhow::bail!("fht-compositor has no minimizing")
+    }
+
+    fn can_minimize(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Request {
+    Subscribe,
+    Action(Action),
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    FocusWorkspace { workspace_id: usize },
+    FocusWindow { window_id: usize },
+    CloseWindow { window_id: usize },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Event {
+    Windows(HashMap<usize, Window>),
+    WindowChanged(Window),
+    WindowClosed { id: usize },
+    FocusedWindowChanged { id: Option<usize> },
+    Workspaces(HashMap<usize, FhtWorkspace>),
+    WorkspaceChanged(FhtWorkspace),
+    WorkspaceRemoved { id: usize },
+    Space(Space),
+    ActiveWorkspaceChanged { now: usize, output: String },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Window {
+    pub id: usize,
+    pub title: Option<String>,
+    pub app_id: Option<String>,
+    pub output: String,
+    pub workspace_id: usize,
+    #[serde(default)]
+    pub urgent: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FhtWorkspace {
+    pub id: usize,
+    pub output: String,
+    pub windows: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Space {
+    pub monitors: HashMap<String, Monitor>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Monitor {
+    pub output: String,
+    /// The workspace IDs of this monitor, in order.
+    pub workspaces: Vec<usize>,
+    pub active_workspace_idx: usize,
+}
+
+/// Everything the compositor told us about.
+#[derive(Debug, Default)]
+struct Model {
+    windows: HashMap<usize, Window>,
+    focused_window_id: Option<usize>,
+    workspaces: HashMap<usize, FhtWorkspace>,
+    space: Option<Space>,
+}
+
+impl Model {
+    fn apply(&mut self, event: Event) {
+        match event {
+            Event::Windows(windows) => self.windows = windows,
+            Event::WindowChanged(window) => {
+                self.windows.insert(window.id, window);
+            }
+            Event::WindowClosed { id } => {
+                self.windows.remove(&id);
+            }
+            Event::FocusedWindowChanged { id } => self.focused_window_id = id,
+            Event::Workspaces(workspaces) => self.workspaces = workspaces,
+            Event::WorkspaceChanged(workspace) => {
+                self.workspaces.insert(workspace.id, workspace);
+            }
+            Event::WorkspaceRemoved { id } => {
+                self.workspaces.remove(&id);
+            }
+            Event::Space(space) => self.space = Some(space),
+            Event::ActiveWorkspaceChanged { now, output } => {
+                let monitor = self
+                    .space
+                    .as_mut()
+                    .and_then(|space| space.monitors.get_mut(&output));
+                if let Some(monitor) = monitor {
+                    if let Some(idx) = monitor.workspaces.iter().position(|id| *id == now) {
+                        monitor.active_workspace_idx = idx;
+                    }
+                }
+            }
+        }
+    }
+
+    fn to_state(&self) -> State {
+        let Some(space) = &self.space else {
+            return State::default();
+        };
+
+        let mut monitors = space.monitors.values().collect::<Vec<_>>();
+        monitors.sort_by(|a, b| a.output.cmp(&b.output));
+
+        let mut workspaces = vec![];
+        for monitor in monitors {
+            for (idx, id) in monitor.workspaces.iter().enumerate() {
+                let windows = self
+                    .workspaces
+                    .get(id)
+                    .map(|workspace| workspace.windows.as_slice())
+                    .unwrap_or_default();
+                workspaces.push(Workspace {
+                    id: *id as u64,
+                    name: (idx + 1).to_string(),
+                    output: Some(monitor.output.clone()),
+                    active: idx == monitor.active_workspace_idx,
+                    urgent: windows.iter().any(|window_id| {
+                        self.windows
+                            .get(window_id)
+                            .is_some_and(|window| window.urgent)
+                    }),
+                    occupied: !windows.is_empty(),
+                });
+            }
+        }
+
+        let mut windows = self
+            .windows
+            .values()
+            .map(|window| ShellWindow {
+                id: window.id as u64,
+                title: window.title.clone().unwrap_or_default(),
+                app_id: window.app_id.clone().unwrap_or_default(),
+                output: Some(window.output.clone()),
+                workspace_id: Some(window.workspace_id as u64),
+                focused: self.focused_window_id == Some(window.id),
+                // fht-compositor has no minimizing.
+                minimized: false,
+                urgent: window.urgent,
+            })
+            .collect::<Vec<_>>();
+        // IDs are given in order, so this is the order windows were opened in.
+        windows.sort_by_key(|window| window.id);
+
+        State {
+            workspaces,
+            windows,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufWriter;
+    use std::os::unix::net::UnixListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread::JoinHandle;
+
+    use serde_json::json;
+
+    use super::*;
+
+    /// What fht-compositor sends right after subscribing, and on a few changes.
+    const EVENTS: &[&str] = &[
+        r#"{"windows":{"1":{"id":1,"title":"Terminal","app_id":"foot","output":"eDP-1","workspace_id":0},"2":{"id":2,"title":null,"app_id":"firefox","output":"eDP-1","workspace_id":1,"urgent":true}}}"#,
+        r#"{"workspaces":{"0":{"id":0,"output":"eDP-1","windows":[1]},"1":{"id":1,"output":"eDP-1","windows":[2]},"2":{"id":2,"output":"eDP-1","windows":[]},"9":{"id":9,"output":"HDMI-A-1","windows":[]}}}"#,
+        r#"{"space":{"monitors":{"eDP-1":{"output":"eDP-1","workspaces":[0,1,2],"active_workspace_idx":0},"HDMI-A-1":{"output":"HDMI-A-1","workspaces":[9],"active_workspace_idx":0}}}}"#,
+        r#"{"focused-window-changed":{"id":1}}"#,
+        // Something we don't know about.
+        r#"{"layer-shells":[{"namespace":"fht-shell"}]}"#,
+        r#"{"active-workspace-changed":{"now":1,"output":"eDP-1"}}"#,
+        r#"{"window-changed":{"id":3,"title":"Files","app_id":"nautilus","output":"eDP-1","workspace_id":2}}"#,
+        r#"{"window-closed":{"id":1}}"#,
+        r#"{"focused-window-changed":{"id":3}}"#,
+    ];
+
+    /// A fake fht-compositor IPC socket, removed once dropped.
+    struct FakeCompositor {
+        socket_path: PathBuf,
+        listener: UnixListener,
+    }
+
+    impl FakeCompositor {
+        fn new() -> Self {
+            static COUNTER: AtomicUsize = AtomicUsize::new(0);
+            let name = format!(
+                "fht-shell-ipc-{}-{}.sock",
+                std::process::id(),
+                COUNTER.fetch_add(1, Ordering::Relaxed)
+            );
+            let socket_path = std::env::temp_dir().join(name);
+            let listener = UnixListener::bind(&socket_path).unwrap();
+            Self {
+                socket_path,
+                listener,
+            }
+        }
+
+        /// Answer the next `count` connections with `responses`, returning the requests.
+        fn serve(
+            &self,
+            count: usize,
+            responses: &'static [&'static str],
+        ) -> JoinHandle<Vec<String>> {
+            let listener = self.listener.try_clone().unwrap();
+            std::thread::spawn(move || {
+                let mut requests = vec![];
+                for stream in listener.incoming().take(count) {
+                    let stream = stream.unwrap();
+                    let mut request = String::new();
+                    BufReader::new(&stream).read_line(&mut request).unwrap();
+                    requests.push(request);
+
+                    let mut writer = BufWriter::new(&stream);
+                    for response in responses {
+                        writeln!(writer, "{response}").unwrap();
+                    }
+                }
+                requests
+            })
+        }
+    }
+
+    impl Drop for FakeCompositor {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.socket_path);
+        }
+    }
+
+    fn parse(request: &str) -> serde_json::Value {
+        assert!(request.ends_with('\n'), "Requests must end with a new line");
+        serde_json::from_str(request).unwrap()
+    }
+
+    #[test]
+    fn subscribe() {
+        let compositor = FakeCompositor::new();
+        let backend = FhtBackend::new(compositor.socket_path.clone());
+
+        let server = compositor.serve(1, EVENTS);
+        let state = watch::Sender::new(State::default());
+        backend.subscribe(&state).unwrap();
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(parse(&requests[0]), json!("subscribe"));
+
+        let workspace = |id, name: &str, output: &str, active, occupied, urgent| Workspace {
+            id,
+            name: name.to_string(),
+            output: Some(output.to_string()),
+            active,
+            urgent,
+            occupied,
+        };
+        let state = state.borrow();
+        assert_eq!(
+            state.workspaces,
+            [
+                // Outputs are sorted by name.
+                workspace(9, "1", "HDMI-A-1", true, false, false),
+                workspace(0, "1", "eDP-1", false, true, false),
+                workspace(1, "2", "eDP-1", true, true, true),
+                workspace(2, "3", "eDP-1", false, false, false),
+            ]
+        );
+        assert_eq!(
+            state.windows,
+            [
+                ShellWindow {
+                    id: 2,
+                    title: String::new(),
+                    app_id: "firefox".to_string(),
+                    output: Some("eDP-1".to_string()),
+                    workspace_id: Some(1),
+                    focused: false,
+                    minimized: false,
+                    urgent: true,
+                },
+                ShellWindow {
+                    id: 3,
+                    title: "Files".to_string(),
+                    app_id: "nautilus".to_string(),
+                    output: Some("eDP-1".to_string()),
+                    workspace_id: Some(2),
+                    focused: true,
+                    minimized: false,
+                    urgent: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn actions() {
+        let compositor = FakeCompositor::new();
+        let backend = FhtBackend::new(compositor.socket_path.clone());
+
+        let server = compositor.serve(3, &[r#"{"ok":null}"#]);
+        backend.focus_window(3).unwrap();
+        backend.close_window(2).unwrap();
+        backend.focus_workspace(9).unwrap();
+        let requests = server.join().unwrap();
+        assert_eq!(
+            requests
+                .iter()
+                .map(|request| parse(request))
+                .collect::<Vec<_>>(),
+            [
+                json!({ "action": { "focus-window": { "window_id": 3 } } }),
+                json!({ "action": { "close-window": { "window_id": 2 } } }),
+                json!({ "action": { "focus-workspace": { "workspace_id": 9 } } }),
+            ]
+        );
+
+        assert!(!backend.can_minimize());
+        assert!(backend.set_window_minimized(3, true).is_err());
+    }
+
+    #[test]
+    fn action_errors() {
+        let compositor = FakeCompositor::new();
+        let backend = FhtBackend::new(compositor.socket_path.clone());
+
+        let server = compositor.serve(1, &[r#"{"error":"No window with ID 42"}"#]);
+        let err = backend.focus_window(42).unwrap_err();
+        assert!(err.to_string().contains("No window with ID 42"));
+        server.join().unwrap();
+
+        drop(compositor);
+        assert!(backend.focus_window(42).is_err());
+    }
+}
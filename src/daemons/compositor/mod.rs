@@ -0,0 +1,315 @@
+//! Compositor daemon.
+//!
+//! Wayland has no single way to get the workspaces (and windows) from the compositor, so the
+//! daemon keeps a compositor-agnostic [`State`] that a [`Backend`] keeps up to date. Other parts
+//! of the shell subscribe to the state and react on changes.
+//!
+//! There are two backends:
+//! - [`fht::FhtBackend`], talking to fht-compositor through its IPC socket.
+//! - [`wayland::WaylandBackend`], using the `ext-workspace-v1` and
+//!   `wlr-foreign-toplevel-management` protocols that any compositor can implement.
+#![allow(unused)]
+use std::sync::{Arc, OnceLock};
+
+use tokio::sync::watch;
+
+pub mod fht;
+pub mod wayland;
+
+/// A compositor daemon, used to follow and control workspaces and windows.
+pub struct Daemon {
+    backend: Arc<dyn Backend>,
+    state: watch::Sender<State>,
+}
+
+impl Daemon {
+    /// Create a new [`Daemon`] on top of this [`Backend`].
+    ///
+    /// The backend gets ran on its own thread, sending state updates to the daemon.
+    pub fn new(backend: Arc<dyn Backend>) -> Self {
+        let state = watch::Sender::new(State::default());
+        let backend_state = state.clone();
+        let runner = Arc::clone(&backend);
+        super::spawn(
+            "compositor-backend",
+            async move { runner.run(backend_state) },
+        );
+        Self { backend, state }
+    }
+
+    /// Get a snapshot of the current compositor state.
+    pub fn state(&self) -> State {
+        self.state.borrow().clone()
+    }
+
+    /// Subscribe to the changes of the compositor state.
+    pub fn subscribe(&self) -> watch::Receiver<State> {
+        self.state.subscribe()
+    }
+
+    /// Focus a workspace by its ID.
+    pub fn focus_workspace(&self, id: u64) -> anyhow::Result<()> {
+        self.backend.focus_workspace(id)
+    }
+
+    /// Focus the workspace `step` places away from the active one of this output, wrapping around.
+    pub fn cycle_workspace(&self, output: Option<&str>, step: isize) -> anyhow::Result<()> {
+        let state = self.state();
+        let workspaces = state.workspaces_on(output).collect::<Vec<_>>();
+        let Some(active_idx) = workspaces.iter().position(|workspace| workspace.active) else {
+            anyhow::bail!("No active workspace");
+        };
+        let idx = (active_idx as isize + step).rem_euclid(workspaces.len() as isize);
+        self.focus_workspace(workspaces[idx as usize].id)
+    }
+
+    /// Focus a window by its ID.
+    pub fn focus_window(&self, id: u64) -> anyhow::Result<()> {
+        self.backend.focus_window(id)
+    }
+
+    /// Ask a window to close by its ID.
+    pub fn close_window(&self, id: u64) -> anyhow::Result<()> {
+        self.backend.close_window(id)
+    }
+
+    /// Minimize or restore a window by its ID.
+    pub fn set_window_minimized(&self, id: u64, minimized: bool) -> anyhow::Result<()> {
+        self.backend.set_window_minimized(id, minimized)
+    }
+
+    /// Whether windows can be minimized with [`Self::set_window_minimized`].
+    pub fn can_minimize(&self) -> bool {
+        self.backend.can_minimize()
+    }
+}
+
+/// A backend for the compositor [`Daemon`].
+pub trait Backend: Send + Sync + 'static {
+    /// Run the backend, sending every change of the compositor to `state`.
+    ///
+    /// This runs on its own thread, and should only return when the backend can't go on.
+    fn run(&self, state: watch::Sender<State>) -> anyhow::Result<()>;
+
+    /// Focus a workspace by its [`Workspace::id`].
+    fn focus_workspace(&self, id: u64) -> anyhow::Result<()>;
+
+    /// Focus a window by its [`Window::id`].
+    fn focus_window(&self, id: u64) -> anyhow::Result<()>;
+
+    /// Ask a window to close by its [`Window::id`].
+    fn close_window(&self, id: u64) -> anyhow::Result<()>;
+
+    /// Minimize or restore a window by its [`Window::id`].
+    fn set_window_minimized(&self, id: u64, minimized: bool) -> anyhow::Result<()>;
+
+    /// Whether the compositor supports minimizing windows.
+    fn can_minimize(&self) -> bool;
+}
+
+/// The state of the compositor.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct State {
+    /// All the workspaces, ordered like the compositor shows them.
+    pub workspaces: Vec<Workspace>,
+    /// All the windows, in the order they were opened.
+    pub windows: Vec<Window>,
+}
+
+impl State {
+    /// Get the workspaces of this output, by its connector name.
+    ///
+    /// When `output` is `None`, or when no workspace is on this output, all the workspaces are
+    /// returned, since some compositors don't tie workspaces to outputs.
+    pub fn workspaces_on<'a>(
+        &'a self,
+        output: Option<&'a str>,
+    ) -> impl Iterator<Item = &'a Workspace> + 'a {
+        let has_output = output.is_some_and(|output| {
+            self.workspaces
+                .iter()
+                .any(|workspace| workspace.output.as_deref() == Some(output))
+        });
+        self.workspaces
+            .iter()
+            .filter(move |workspace| !has_output || workspace.output.as_deref() == output)
+    }
+
+    /// Get the window that has the keyboard focus, if any.
+    pub fn focused_window(&self) -> Option<&Window> {
+        self.windows.iter().find(|window| window.focused)
+    }
+}
+
+/// A single workspace.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Workspace {
+    /// The unique ID of this workspace, only meaningful to the backend.
+    pub id: u64,
+    /// The name to show to the user.
+    pub name: String,
+    /// The connector name of the output this workspace is on, IE. `eDP-1`.
+    pub output: Option<String>,
+    /// Whether this workspace is the one displayed on its output.
+    pub active: bool,
+    /// Whether a window of this workspace wants the user attention.
+    pub urgent: bool,
+    /// Whether this workspace has any windows.
+    ///
+    /// Not all backends can know this, in which case this is always `false`.
+    pub occupied: bool,
+}
+
+/// A single window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Window {
+    /// The unique ID of this window, only meaningful to the backend.
+    pub id: u64,
+    pub title: String,
+    /// The application ID of this window, usually matching its desktop entry.
+    pub app_id: String,
+    /// The connector name of the output this window is on.
+    pub output: Option<String>,
+    /// The [`Workspace::id`] of the workspace this window is on, if the backend knows it.
+    pub workspace_id: Option<u64>,
+    /// Whether this window has the keyboard focus.
+    pub focused: bool,
+    pub minimized: bool,
+    /// Whether this window wants the user attention.
+    ///
+    /// Not all backends can know this, in which case this is always `false`.
+    pub urgent: bool,
+}
+
+static INSTANCE: OnceLock<Daemon> = OnceLock::new();
+
+pub fn get() -> &'static Daemon {
+    INSTANCE.get().expect("daemons::start() must be called")
+}
+
+pub async fn start() -> anyhow::Result<()> {
+    if INSTANCE.get().is_some() {
+        return Ok(());
+    }
+
+    let backend: Arc<dyn Backend> = match fht::socket_path() {
+        Some(socket_path) => Arc::new(fht::FhtBackend::new(socket_path)),
+        None => Arc::new(wayland::WaylandBackend::new()),
+    };
+
+    // NOTE: If we already started he handled it above.
+    let _ = INSTANCE.set(Daemon::new(backend));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// A [`Backend`] recording the focused workspaces.
+    #[derive(Default)]
+    struct MockBackend {
+        focused_workspaces: Mutex<Vec<u64>>,
+    }
+
+    impl Backend for MockBackend {
+        fn run(&self, _state: watch::Sender<State>) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn focus_workspace(&self, id: u64) -> anyhow::Result<()> {
+            self.focused_workspaces.lock().unwrap().push(id);
+            Ok(())
+        }
+
+        fn focus_window(&self, _id: u64) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn close_window(&self, _id: u64) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn set_window_minimized(&self, _id: u64, _minimized: bool) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn can_minimize(&self) -> bool {
+            true
+        }
+    }
+
+    fn workspace(id: u64, output: Option<&str>, active: bool) -> Workspace {
+        Workspace {
+            id,
+            name: id.to_string(),
+            output: output.map(str::to_string),
+            active,
+            urgent: false,
+            occupied: false,
+        }
+    }
+
+    /// Cycle through the workspaces from the daemon state, returning the focused workspace.
+    fn cycle(workspaces: Vec<Workspace>, output: Option<&str>, step: isize) -> Option<u64> {
+        let backend = Arc::new(MockBackend::default());
+        let daemon = Daemon::new(Arc::clone(&backend) as Arc<dyn Backend>);
+        daemon.state.send_replace(State {
+            workspaces,
+            windows: vec![],
+        });
+
+        daemon.cycle_workspace(output, step).ok()?;
+        let focused_workspaces = backend.focused_workspaces.lock().unwrap();
+        assert_eq!(focused_workspaces.len(), 1);
+        focused_workspaces.first().copied()
+    }
+
+    fn two_outputs() -> Vec<Workspace> {
+        vec![
+            workspace(1, Some("eDP-1"), false),
+            workspace(2, Some("eDP-1"), true),
+            workspace(3, Some("eDP-1"), false),
+            workspace(4, Some("HDMI-A-1"), true),
+            workspace(5, Some("HDMI-A-1"), false),
+        ]
+    }
+
+    #[test]
+    fn cycle_workspace() {
+        assert_eq!(cycle(two_outputs(), Some("eDP-1"), 1), Some(3));
+        assert_eq!(cycle(two_outputs(), Some("eDP-1"), -1), Some(1));
+        assert_eq!(cycle(two_outputs(), Some("HDMI-A-1"), 1), Some(5));
+    }
+
+    #[test]
+    fn cycle_workspace_wraps_around() {
+        assert_eq!(cycle(two_outputs(), Some("eDP-1"), 2), Some(1));
+        assert_eq!(cycle(two_outputs(), Some("eDP-1"), -2), Some(3));
+        assert_eq!(cycle(two_outputs(), Some("eDP-1"), 7), Some(3));
+        assert_eq!(cycle(two_outputs(), Some("HDMI-A-1"), -1), Some(5));
+        assert_eq!(cycle(two_outputs(), Some("HDMI-A-1"), 0), Some(4));
+    }
+
+    #[test]
+    fn cycle_workspace_without_outputs() {
+        // Unknown outputs and compositors without outputs cycle through everything, from the
+        // first active workspace.
+        assert_eq!(cycle(two_outputs(), Some("DP-2"), 1), Some(3));
+        assert_eq!(cycle(two_outputs(), None, -1), Some(1));
+
+        let workspaces = vec![workspace(1, None, true), workspace(2, None, false)];
+        assert_eq!(cycle(workspaces.clone(), None, 1), Some(2));
+        assert_eq!(cycle(workspaces, Some("eDP-1"), -1), Some(2));
+    }
+
+    #[test]
+    fn cycle_workspace_without_active() {
+        let workspaces = vec![workspace(1, None, false), workspace(2, None, false)];
+        assert_eq!(cycle(workspaces, None, 1), None);
+        assert_eq!(cycle(vec![], None, 1), None);
+    }
+}
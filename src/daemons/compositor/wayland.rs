@@ -0,0 +1,559 @@
+//! Wayland protocols backend, for compositors without a dedicated backend.
+//!
+//! Workspaces come from `ext-workspace-v1`, and windows from `wlr-foreign-toplevel-management`.
+//! Compositors may implement only one of them, in which case we only have the workspaces or the
+//! windows.
+//!
+//! We use our own Wayland connection for this, dispatched from the backend thread, separate from
+//! the one of GTK.
+//!
+//! The protocols don't tell which windows are on which workspace, so workspaces are never
+//! [`Workspace::occupied`], and windows are never [`Window::urgent`].
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::watch;
+use wayland_client::backend::ObjectId;
+use wayland_client::protocol::{wl_output, wl_registry, wl_seat};
+use wayland_client::{event_created_child, Connection, Dispatch, Proxy, QueueHandle, WEnum};
+use wayland_protocols::ext::workspace::v1::client::{
+    ext_workspace_group_handle_v1, ext_workspace_handle_v1, ext_workspace_manager_v1,
+};
+use wayland_protocols_wlr::foreign_toplevel::v1::client::{
+    zwlr_foreign_toplevel_handle_v1, zwlr_foreign_toplevel_manager_v1,
+};
+
+use super::{Backend, State, Window, Workspace};
+
+/// The `wl_output` version giving us the connector name.
+const WL_OUTPUT_VERSION: u32 = 4;
+/// The `zwlr_foreign_toplevel_manager_v1` version we know about.
+const FOREIGN_TOPLEVEL_VERSION: u32 = 3;
+
+pub struct WaylandBackend {
+    shared: Arc<Mutex<Shared>>,
+}
+
+/// What we need to send requests from other threads than the backend one.
+#[derive(Default)]
+struct Shared {
+    conn: Option<Connection>,
+    workspace_manager: Option<ext_workspace_manager_v1::ExtWorkspaceManagerV1>,
+    seat: Option<wl_seat::WlSeat>,
+    // The handles, by the ID we give to the daemon.
+    workspaces: HashMap<u64, ext_workspace_handle_v1::ExtWorkspaceHandleV1>,
+    toplevels: HashMap<u64, zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1>,
+}
+
+impl WaylandBackend {
+    pub fn new() -> Self {
+        Self {
+            shared: Arc::new(Mutex::new(Shared::default())),
+        }
+    }
+
+    /// Send a request to a toplevel, by its [`Window::id`].
+    fn with_toplevel(
+        &self,
+        id: u64,
+        f: impl FnOnce(&zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1, &Shared),
+    ) -> anyhow::Result<()> {
+        let shared = self.shared.lock().unwrap();
+        let Some(conn) = shared.conn.as_ref() else {
+            anyhow::bail!("Not connected to the compositor");
+        };
+        let Some(toplevel) = shared.toplevels.get(&id) else {
+            anyhow::bail!("No window with ID {id}");
+        };
+
+        f(toplevel, &shared);
+        conn.flush()?;
+        Ok(())
+    }
+}
+
+impl Backend for WaylandBackend {
+    fn run(&self, state: watch::Sender<State>) -> anyhow::Result<()> {
+        let conn = Connection::connect_to_env()?;
+        let mut event_queue = conn.new_event_queue();
+        let qh = event_queue.handle();
+        conn.display().get_registry(&qh, ());
+
+        let mut dispatcher = Dispatcher {
+            state,
+            shared: Arc::clone(&self.shared),
+            has_toplevel_manager: false,
+            outputs: HashMap::new(),
+            groups: HashMap::new(),
+            workspaces: HashMap::new(),
+            toplevels: HashMap::new(),
+            next_order: 0,
+        };
+        // Get the globals, then the initial state of the managers.
+        event_queue.roundtrip(&mut dispatcher)?;
+        let has_workspace_manager = self.shared.lock().unwrap().workspace_manager.is_some();
+        if !has_workspace_manager && !dispatcher.has_toplevel_manager {
+            anyhow::bail!(
+                "The compositor supports neither ext-workspace-v1 nor wlr-foreign-toplevel-management"
+            );
+        }
+        self.shared.lock().unwrap().conn = Some(conn.clone());
+
+        loop {
+            event_queue.blocking_dispatch(&mut dispatcher)?;
+        }
+    }
+
+    fn focus_workspace(&self, id: u64) -> anyhow::Result<()> {
+        let shared = self.shared.lock().unwrap();
+        let (Some(conn), Some(manager)) = (&shared.conn, &shared.workspace_manager) else {
+            anyhow::bail!("The compositor does not support ext-workspace-v1");
+        };
+        let Some(handle) = shared.workspaces.get(&id) else {
+            anyhow::bail!("No workspace with ID {id}");
+        };
+
+        handle.activate();
+        manager.commit();
+        conn.flush()?;
+        Ok(())
+    }
+
+    fn focus_window(&self, id: u64) -> anyhow::Result<()> {
+        let mut result = Ok(());
+        self.with_toplevel(id, |toplevel, shared| match &shared.seat {
+            Some(seat) => toplevel.activate(seat),
+            None => result = Err(anyhow::anyhow!("No seat to activate the window with")),
+        })?;
+        result
+    }
+
+    fn close_window(&self, id: u64) -> anyhow::Result<()> {
+        self.with_toplevel(id, |toplevel, _| toplevel.close())
+    }
+
+    fn set_window_minimized(&self, id: u64, minimized: bool) -> anyhow::Result<()> {
+        self.with_toplevel(id, |toplevel, _| {
+            if minimized {
+                toplevel.set_minimized();
+            } else {
+                toplevel.unset_minimized();
+            }
+        })
+    }
+
+    fn can_minimize(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Default)]
+struct Group {
+    outputs: Vec<ObjectId>,
+    workspaces: Vec<ObjectId>,
+}
+
+#[derive(Debug)]
+struct WorkspaceData {
+    handle: ext_workspace_handle_v1::ExtWorkspaceHandleV1,
+    name: String,
+    coordinates: Vec<u32>,
+    state: ext_workspace_handle_v1::State,
+    // When we got this workspace, to keep the compositor order without coordinates.
+    order: usize,
+}
+
+#[derive(Debug)]
+struct ToplevelData {
+    handle: zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1,
+    title: String,
+    app_id: String,
+    outputs: Vec<ObjectId>,
+    activated: bool,
+    minimized: bool,
+    order: usize,
+}
+
+struct Dispatcher {
+    state: watch::Sender<State>,
+    shared: Arc<Mutex<Shared>>,
+    has_toplevel_manager: bool,
+    /// The outputs, with their connector name once we got it.
+    outputs: HashMap<ObjectId, Option<String>>,
+    groups: HashMap<ObjectId, Group>,
+    workspaces: HashMap<ObjectId, WorkspaceData>,
+    toplevels: HashMap<ObjectId, ToplevelData>,
+    next_order: usize,
+}
+
+impl Dispatcher {
+    fn output_name(&self, output: &ObjectId) -> Option<String> {
+        self.outputs.get(output).cloned().flatten()
+    }
+
+    /// Send the state to the daemon, once the compositor is done sending changes.
+    fn commit(&mut self) {
+        let mut workspaces = self.workspaces.values().collect::<Vec<_>>();
+        workspaces.sort_by(|a, b| {
+            (a.coordinates.is_empty(), &a.coordinates, a.order).cmp(&(
+                b.coordinates.is_empty(),
+                &b.coordinates,
+                b.order,
+            ))
+        });
+        let workspaces = workspaces
+            .into_iter()
+            .map(|data| {
+                let id = data.handle.id();
+                let output = self
+                    .groups
+                    .values()
+                    .find(|group| group.workspaces.contains(&id))
+                    .and_then(|group| group.outputs.first())
+                    .and_then(|output| self.output_name(output));
+                Workspace {
+                    id: handle_id(&data.handle),
+                    name: data.name.clone(),
+                    output,
+                    active: data.state.contains(ext_workspace_handle_v1::State::Active),
+                    urgent: data.state.contains(ext_workspace_handle_v1::State::Urgent),
+                    occupied: false,
+                }
+            })
+            .collect();
+
+        let mut toplevels = self.toplevels.values().collect::<Vec<_>>();
+        toplevels.sort_by_key(|data| data.order);
+        let windows = toplevels
+            .into_iter()
+            .map(|data| Window {
+                id: handle_id(&data.handle),
+                title: data.title.clone(),
+                app_id: data.app_id.clone(),
+                output: data
+                    .outputs
+                    .first()
+                    .and_then(|output| self.output_name(output)),
+                workspace_id: None,
+                focused: data.activated,
+                minimized: data.minimized,
+                urgent: false,
+            })
+            .collect();
+
+        let new_state = State {
+            workspaces,
+            windows,
+        };
+        self.state.send_if_modified(|current| {
+            if *current == new_state {
+                return false;
+            }
+            *current = new_state;
+            true
+        });
+    }
+}
+
+fn handle_id(handle: &impl Proxy) -> u64 {
+    handle.id().protocol_id() as u64
+}
+
+impl Dispatch<wl_registry::WlRegistry, ()> for Dispatcher {
+    fn event(
+        dispatcher: &mut Self,
+        registry: &wl_registry::WlRegistry,
+        event: wl_registry::Event,
+        _: &(),
+        _: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        let wl_registry::Event::Global {
+            name,
+            interface,
+            version,
+        } = event
+        else {
+            // NOTE: Removed outputs leave their workspace groups and toplevels before that.
+            return;
+        };
+
+        match interface.as_str() {
+            "wl_output" => {
+                let output = registry.bind::<wl_output::WlOutput, _, _>(
+                    name,
+                    version.min(WL_OUTPUT_VERSION),
+                    qh,
+                    (),
+                );
+                dispatcher.outputs.insert(output.id(), None);
+            }
+            "wl_seat" => {
+                let mut shared = dispatcher.shared.lock().unwrap();
+                if shared.seat.is_none() {
+                    shared.seat = Some(registry.bind::<wl_seat::WlSeat, _, _>(name, 1, qh, ()));
+                }
+            }
+            "ext_workspace_manager_v1" => {
+                let manager = registry
+                    .bind::<ext_workspace_manager_v1::ExtWorkspaceManagerV1, _, _>(name, 1, qh, ());
+                dispatcher.shared.lock().unwrap().workspace_manager = Some(manager);
+            }
+            "zwlr_foreign_toplevel_manager_v1" => {
+                registry
+                    .bind::<zwlr_foreign_toplevel_manager_v1::ZwlrForeignToplevelManagerV1, _, _>(
+                        name,
+                        version.min(FOREIGN_TOPLEVEL_VERSION),
+                        qh,
+                        (),
+                    );
+                dispatcher.has_toplevel_manager = true;
+            }
+            _ => (),
+        }
+    }
+}
+
+impl Dispatch<wl_output::WlOutput, ()> for Dispatcher {
+    fn event(
+        dispatcher: &mut Self,
+        output: &wl_output::WlOutput,
+        event: wl_output::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let wl_output::Event::Name { name } = event {
+            dispatcher.outputs.insert(output.id(), Some(name));
+        }
+    }
+}
+
+impl Dispatch<wl_seat::WlSeat, ()> for Dispatcher {
+    fn event(
+        _: &mut Self,
+        _: &wl_seat::WlSeat,
+        _: wl_seat::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ext_workspace_manager_v1::ExtWorkspaceManagerV1, ()> for Dispatcher {
+    fn event(
+        dispatcher: &mut Self,
+        _: &ext_workspace_manager_v1::ExtWorkspaceManagerV1,
+        event: ext_workspace_manager_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            ext_workspace_manager_v1::Event::WorkspaceGroup { workspace_group } => {
+                dispatcher
+                    .groups
+                    .insert(workspace_group.id(), Group::default());
+            }
+            ext_workspace_manager_v1::Event::Workspace { workspace } => {
+                dispatcher
+                    .shared
+                    .lock()
+                    .unwrap()
+                    .workspaces
+                    .insert(handle_id(&workspace), workspace.clone());
+                dispatcher.workspaces.insert(
+                    workspace.id(),
+                    WorkspaceData {
+                        handle: workspace,
+                        name: String::new(),
+                        coordinates: vec![],
+                        state: ext_workspace_handle_v1::State::empty(),
+                        order: dispatcher.next_order,
+                    },
+                );
+                dispatcher.next_order += 1;
+            }
+            ext_workspace_manager_v1::Event::Done => dispatcher.commit(),
+            ext_workspace_manager_v1::Event::Finished => {
+                warn!("Compositor stopped sending workspaces");
+                dispatcher.shared.lock().unwrap().workspace_manager = None;
+            }
+            _ => (),
+        }
+    }
+
+    event_created_child!(Dispatcher, ext_workspace_manager_v1::ExtWorkspaceManagerV1, [
+        ext_workspace_manager_v1::EVT_WORKSPACE_GROUP_OPCODE =>
+            (ext_workspace_group_handle_v1::ExtWorkspaceGroupHandleV1, ()),
+        ext_workspace_manager_v1::EVT_WORKSPACE_OPCODE =>
+            (ext_workspace_handle_v1::ExtWorkspaceHandleV1, ()),
+    ]);
+}
+
+impl Dispatch<ext_workspace_group_handle_v1::ExtWorkspaceGroupHandleV1, ()> for Dispatcher {
+    fn event(
+        dispatcher: &mut Self,
+        group_handle: &ext_workspace_group_handle_v1::ExtWorkspaceGroupHandleV1,
+        event: ext_workspace_group_handle_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let ext_workspace_group_handle_v1::Event::Removed = event {
+            dispatcher.groups.remove(&group_handle.id());
+            group_handle.destroy();
+            return;
+        }
+
+        let Some(group) = dispatcher.groups.get_mut(&group_handle.id()) else {
+            return;
+        };
+        match event {
+            ext_workspace_group_handle_v1::Event::OutputEnter { output } => {
+                group.outputs.push(output.id());
+            }
+            ext_workspace_group_handle_v1::Event::OutputLeave { output } => {
+                group.outputs.retain(|id| *id != output.id());
+            }
+            ext_workspace_group_handle_v1::Event::WorkspaceEnter { workspace } => {
+                group.workspaces.push(workspace.id());
+            }
+            ext_workspace_group_handle_v1::Event::WorkspaceLeave { workspace } => {
+                group.workspaces.retain(|id| *id != workspace.id());
+            }
+            _ => (),
+        }
+    }
+}
+
+impl Dispatch<ext_workspace_handle_v1::ExtWorkspaceHandleV1, ()> for Dispatcher {
+    fn event(
+        dispatcher: &mut Self,
+        handle: &ext_workspace_handle_v1::ExtWorkspaceHandleV1,
+        event: ext_workspace_handle_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let ext_workspace_handle_v1::Event::Removed = event {
+            dispatcher.workspaces.remove(&handle.id());
+            let mut shared = dispatcher.shared.lock().unwrap();
+            shared.workspaces.remove(&handle_id(handle));
+            handle.destroy();
+            return;
+        }
+
+        let Some(data) = dispatcher.workspaces.get_mut(&handle.id()) else {
+            return;
+        };
+        match event {
+            ext_workspace_handle_v1::Event::Name { name } => data.name = name,
+            ext_workspace_handle_v1::Event::Coordinates { coordinates } => {
+                data.coordinates = coordinates
+                    .chunks_exact(4)
+                    .map(|bytes| u32::from_ne_bytes(bytes.try_into().unwrap()))
+                    .collect();
+            }
+            ext_workspace_handle_v1::Event::State {
+                state: WEnum::Value(state),
+            } => data.state = state,
+            _ => (),
+        }
+    }
+}
+
+impl Dispatch<zwlr_foreign_toplevel_manager_v1::ZwlrForeignToplevelManagerV1, ()> for Dispatcher {
+    fn event(
+        dispatcher: &mut Self,
+        _: &zwlr_foreign_toplevel_manager_v1::ZwlrForeignToplevelManagerV1,
+        event: zwlr_foreign_toplevel_manager_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_foreign_toplevel_manager_v1::Event::Toplevel { toplevel } => {
+                dispatcher
+                    .shared
+                    .lock()
+                    .unwrap()
+                    .toplevels
+                    .insert(handle_id(&toplevel), toplevel.clone());
+                dispatcher.toplevels.insert(
+                    toplevel.id(),
+                    ToplevelData {
+                        handle: toplevel,
+                        title: String::new(),
+                        app_id: String::new(),
+                        outputs: vec![],
+                        activated: false,
+                        minimized: false,
+                        order: dispatcher.next_order,
+                    },
+                );
+                dispatcher.next_order += 1;
+            }
+            zwlr_foreign_toplevel_manager_v1::Event::Finished => {
+                warn!("Compositor stopped sending windows");
+                dispatcher.has_toplevel_manager = false;
+            }
+            _ => (),
+        }
+    }
+
+    event_created_child!(Dispatcher, zwlr_foreign_toplevel_manager_v1::ZwlrForeignToplevelManagerV1, [
+        zwlr_foreign_toplevel_manager_v1::EVT_TOPLEVEL_OPCODE =>
+            (zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1, ()),
+    ]);
+}
+
+impl Dispatch<zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1, ()> for Dispatcher {
+    fn event(
+        dispatcher: &mut Self,
+        handle: &zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1,
+        event: zwlr_foreign_toplevel_handle_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let zwlr_foreign_toplevel_handle_v1::Event::Closed = event {
+            dispatcher.toplevels.remove(&handle.id());
+            let mut shared = dispatcher.shared.lock().unwrap();
+            shared.toplevels.remove(&handle_id(handle));
+            drop(shared);
+            handle.destroy();
+            dispatcher.commit();
+            return;
+        }
+
+        let Some(data) = dispatcher.toplevels.get_mut(&handle.id()) else {
+            return;
+        };
+        match event {
+            zwlr_foreign_toplevel_handle_v1::Event::Title { title } => data.title = title,
+            zwlr_foreign_toplevel_handle_v1::Event::AppId { app_id } => data.app_id = app_id,
+            zwlr_foreign_toplevel_handle_v1::Event::OutputEnter { output } => {
+                data.outputs.push(output.id());
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::OutputLeave { output } => {
+                data.outputs.retain(|id| *id != output.id());
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::State { state } => {
+                let states = state
+                    .chunks_exact(4)
+                    .map(|bytes| u32::from_ne_bytes(bytes.try_into().unwrap()))
+                    .collect::<Vec<_>>();
+                let has_state = |expected: zwlr_foreign_toplevel_handle_v1::State| {
+                    states.contains(&(expected as u32))
+                };
+                data.activated = has_state(zwlr_foreign_toplevel_handle_v1::State::Activated);
+                data.minimized = has_state(zwlr_foreign_toplevel_handle_v1::State::Minimized);
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::Done => dispatcher.commit(),
+            _ => (),
+        }
+    }
+}
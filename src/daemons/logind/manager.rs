@@ -0,0 +1,97 @@
+//! # D-Bus interface proxy for: `org.freedesktop.login1.Manager`
+//!
+//! This code was generated by `zbus-xmlgen` `5.1.0` from D-Bus introspection data.
+//! Source: `Interface '/org/freedesktop/login1' from service 'org.freedesktop.login1' on system
+//! bus`.
+//!
+//! You may prefer to adapt it, instead of using it verbatim.
+//!
+//! More information can be found in the [Writing a client proxy] section of the zbus
+//! documentation.
+//!
+//! This type implements the [D-Bus standard interfaces], (`org.freedesktop.DBus.*`) for which the
+//! following zbus API can be used:
+//!
+//! * [`zbus::fdo::PropertiesProxy`]
+//! * [`zbus::fdo::IntrospectableProxy`]
+//! * [`zbus::fdo::PeerProxy`]
+//!
+//! Consequently `zbus-xmlgen` did not generate code for the above interfaces.
+//!
+//! [Writing a client proxy]: https://dbus2.github.io/zbus/client.html
+//! [D-Bus standard interfaces]: https://dbus.freedesktop.org/doc/dbus-specification.html#standard-interfaces,
+
+use zbus::proxy;
+
+/// An inhibitor lock as listed by logind: what, who, why, mode, UID and PID.
+pub type RawInhibitor = (String, String, String, String, u32, u32);
+
+#[proxy(
+    interface = "org.freedesktop.login1.Manager",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1"
+)]
+pub trait Manager {
+    /// CanHibernate method
+    fn can_hibernate(&self) -> zbus::Result<String>;
+
+    /// CanHybridSleep method
+    fn can_hybrid_sleep(&self) -> zbus::Result<String>;
+
+    /// CanPowerOff method
+    fn can_power_off(&self) -> zbus::Result<String>;
+
+    /// CanReboot method
+    fn can_reboot(&self) -> zbus::Result<String>;
+
+    /// CanSuspend method
+    fn can_suspend(&self) -> zbus::Result<String>;
+
+    /// GetSession method
+    fn get_session(&self, session_id: &str) -> zbus::Result<zbus::zvariant::OwnedObjectPath>;
+
+    /// GetSessionByPID method
+    #[zbus(name = "GetSessionByPID")]
+    fn get_session_by_pid(&self, pid: u32) -> zbus::Result<zbus::zvariant::OwnedObjectPath>;
+
+    /// Hibernate method
+    fn hibernate(&self, interactive: bool) -> zbus::Result<()>;
+
+    /// HybridSleep method
+    fn hybrid_sleep(&self, interactive: bool) -> zbus::Result<()>;
+
+    /// Inhibit method
+    fn inhibit(
+        &self,
+        what: &str,
+        who: &str,
+        why: &str,
+        mode: &str,
+    ) -> zbus::Result<zbus::zvariant::OwnedFd>;
+
+    /// ListInhibitors method
+    fn list_inhibitors(&self) -> zbus::Result<Vec<RawInhibitor>>;
+
+    /// LockSession method
+    fn lock_session(&self, session_id: &str) -> zbus::Result<()>;
+
+    /// PowerOff method
+    fn power_off(&self, interactive: bool) -> zbus::Result<()>;
+
+    /// Reboot method
+    fn reboot(&self, interactive: bool) -> zbus::Result<()>;
+
+    /// Suspend method
+    fn suspend(&self, interactive: bool) -> zbus::Result<()>;
+
+    /// TerminateSession method
+    fn terminate_session(&self, session_id: &str) -> zbus::Result<()>;
+
+    /// PrepareForShutdown signal
+    #[zbus(signal)]
+    fn prepare_for_shutdown(&self, start: bool) -> zbus::Result<()>;
+
+    /// PrepareForSleep signal
+    #[zbus(signal)]
+    fn prepare_for_sleep(&self, start: bool) -> zbus::Result<()>;
+}
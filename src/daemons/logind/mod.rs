@@ -0,0 +1,250 @@
+#![allow(unused)]
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use tokio::sync::watch;
+use zbus::zvariant;
+
+pub mod manager;
+pub mod session;
+
+/// A `systemd-logind` daemon, used to manage the user session and the system power state.
+/// See systemd's page: <https://www.freedesktop.org/software/systemd/man/latest/org.freedesktop.login1.html>
+pub struct Daemon {
+    proxy: manager::ManagerProxy<'static>,
+    /// The session the shell is running in, if any.
+    session: Option<session::SessionProxy<'static>>,
+    // The inhibitor lock is released when the file descriptor gets closed.
+    caffeine_fd: Mutex<Option<zvariant::OwnedFd>>,
+    caffeine: watch::Sender<Caffeine>,
+    /// Disables caffeine mode once its duration runs out.
+    caffeine_timeout: Mutex<Option<glib::SourceId>>,
+}
+
+impl Daemon {
+    /// Get the underlying [`zbus::Proxy`] powering this daemon.
+    pub fn proxy(&self) -> &manager::ManagerProxy<'static> {
+        &self.proxy
+    }
+
+    /// Get the session the shell is running in.
+    ///
+    /// There's none when running outside of a logind session, IE. from a nested compositor.
+    pub fn session(&self) -> Option<&session::SessionProxy<'static>> {
+        self.session.as_ref()
+    }
+
+    /// Whether the shell runs in a logind session, which session actions, caffeine mode and
+    /// setting the brightness need.
+    pub fn has_session(&self) -> bool {
+        self.session.is_some()
+    }
+
+    fn session_or_err(&self) -> zbus::Result<&session::SessionProxy<'static>> {
+        self.session
+            .as_ref()
+            .ok_or_else(|| zbus::Error::Failure("Not running in a logind session".to_string()))
+    }
+
+    /// Check whether the user is allowed to run this [`Action`].
+    ///
+    /// This returns `true` if the action needs authentication first, since polkit will ask for it.
+    pub async fn can_run(&self, action: Action) -> bool {
+        if self.session.is_none() {
+            return false;
+        }
+        let result = match action {
+            // These only concern our own session, so they are always allowed.
+            Action::Lock | Action::LogOut => return true,
+            Action::Suspend => self.proxy.can_suspend().await,
+            Action::Hibernate => self.proxy.can_hibernate().await,
+            Action::HybridSleep => self.proxy.can_hybrid_sleep().await,
+            Action::Reboot => self.proxy.can_reboot().await,
+            Action::PowerOff => self.proxy.can_power_off().await,
+        };
+
+        match result.as_deref() {
+            Ok("yes" | "challenge") => true,
+            Ok(_) => false,
+            Err(err) => {
+                warn!(?err, ?action, "Failed to check whether action is available");
+                false
+            }
+        }
+    }
+
+    /// Run an [`Action`].
+    pub async fn run(&self, action: Action) -> zbus::Result<()> {
+        info!(?action, "Running session action");
+        // Let polkit ask for a password if needed.
+        let interactive = true;
+        match action {
+            Action::Lock => self.session_or_err()?.lock().await,
+            Action::LogOut => self.session_or_err()?.terminate().await,
+            Action::Suspend => self.proxy.suspend(interactive).await,
+            Action::Hibernate => self.proxy.hibernate(interactive).await,
+            Action::HybridSleep => self.proxy.hybrid_sleep(interactive).await,
+            Action::Reboot => self.proxy.reboot(interactive).await,
+            Action::PowerOff => self.proxy.power_off(interactive).await,
+        }
+    }
+
+    /// Enable caffeine mode, keeping the system from going idle or to sleep.
+    ///
+    /// With a `duration`, caffeine mode gets disabled by itself once it runs out. This must be
+    /// called from the GTK main thread, where that timeout runs.
+    pub async fn enable_caffeine(&self, duration: Option<Duration>) -> zbus::Result<()> {
+        self.session_or_err()?;
+        let fd = self
+            .proxy
+            .inhibit(
+                "idle:sleep",
+                "fht-shell",
+                "Caffeine mode is enabled",
+                "block",
+            )
+            .await?;
+        // NOTE: This releases the previous lock, if any.
+        *self.caffeine_fd.lock().unwrap() = Some(fd);
+
+        let until = duration
+            .and_then(|duration| chrono::Duration::from_std(duration).ok())
+            .map(|duration| chrono::Local::now() + duration);
+        info!(?until, "Enabled caffeine mode");
+        self.caffeine.send_replace(Caffeine {
+            active: true,
+            until,
+        });
+
+        let timeout = duration.map(|duration| {
+            glib::timeout_add_local_once(duration, || {
+                let daemon = get();
+                // The source is gone once it ran, we must not remove it a second time.
+                let _ = daemon.caffeine_timeout.lock().unwrap().take();
+                daemon.disable_caffeine();
+            })
+        });
+        let previous = std::mem::replace(&mut *self.caffeine_timeout.lock().unwrap(), timeout);
+        if let Some(previous) = previous {
+            previous.remove();
+        }
+
+        Ok(())
+    }
+
+    /// Disable caffeine mode, letting the system go idle and to sleep again.
+    pub fn disable_caffeine(&self) {
+        if let Some(timeout) = self.caffeine_timeout.lock().unwrap().take() {
+            timeout.remove();
+        }
+        if self.caffeine_fd.lock().unwrap().take().is_some() {
+            info!("Disabled caffeine mode");
+        }
+        self.caffeine.send_replace(Caffeine::default());
+    }
+
+    /// Subscribe to the changes of caffeine mode.
+    pub fn subscribe_caffeine(&self) -> watch::Receiver<Caffeine> {
+        self.caffeine.subscribe()
+    }
+
+    /// List the inhibitor locks currently held on the system, including ours.
+    pub async fn list_inhibitors(&self) -> zbus::Result<Vec<Inhibitor>> {
+        let inhibitors = self.proxy.list_inhibitors().await?;
+        Ok(inhibitors
+            .into_iter()
+            .map(|(what, who, why, mode, uid, pid)| Inhibitor {
+                what,
+                who,
+                why,
+                mode,
+                uid,
+                pid,
+            })
+            .collect())
+    }
+}
+
+/// The state of caffeine mode.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Caffeine {
+    pub active: bool,
+    /// When caffeine mode will get disabled by itself.
+    pub until: Option<chrono::DateTime<chrono::Local>>,
+}
+
+/// An inhibitor lock, preventing the system from going idle, to sleep, etc.
+#[derive(Debug, Clone)]
+pub struct Inhibitor {
+    /// What is inhibited, as a colon-separated list, IE. `idle:sleep:shutdown`
+    pub what: String,
+    /// A human readable name of who took the lock.
+    pub who: String,
+    /// A human readable reason of why the lock was taken.
+    pub why: String,
+    /// Either `block` or `delay`.
+    pub mode: String,
+    pub uid: u32,
+    pub pid: u32,
+}
+
+/// An action on the user session or the system power state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Lock,
+    LogOut,
+    Suspend,
+    Hibernate,
+    HybridSleep,
+    Reboot,
+    PowerOff,
+}
+
+impl Action {
+    pub const ALL: [Self; 7] = [
+        Self::Lock,
+        Self::LogOut,
+        Self::Suspend,
+        Self::Hibernate,
+        Self::HybridSleep,
+        Self::Reboot,
+        Self::PowerOff,
+    ];
+}
+
+static INSTANCE: OnceLock<Daemon> = OnceLock::new();
+
+pub fn get() -> &'static Daemon {
+    INSTANCE.get().expect("daemons::start() must be called")
+}
+
+pub async fn start() -> anyhow::Result<()> {
+    if INSTANCE.get().is_some() {
+        return Ok(());
+    }
+
+    let conn = super::system_connection().inner();
+    let proxy = manager::ManagerProxy::new(conn).await?;
+    // "auto" resolves to the session we are running in, there's none IE. in a nested compositor.
+    let session = match proxy.get_session("auto").await {
+        Ok(session_path) => Some(session::SessionProxy::new(conn, session_path).await?),
+        Err(err) => {
+            warn!(
+                ?err,
+                "Not running in a logind session, disabling session actions"
+            );
+            None
+        }
+    };
+
+    // NOTE: If we already started he handled it above.
+    let _ = INSTANCE.set(Daemon {
+        proxy,
+        session,
+        caffeine_fd: Mutex::new(None),
+        caffeine: watch::Sender::new(Caffeine::default()),
+        caffeine_timeout: Mutex::new(None),
+    });
+
+    Ok(())
+}
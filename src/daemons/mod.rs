@@ -2,10 +2,25 @@
 ///
 /// Daemons are singletons that live for the entire program (IE. they life for 'static)
 /// Other parts of the application get them and use channels to communicate from/to them.
+use std::future::Future;
 use std::sync::OnceLock;
 
+pub mod audio;
+pub mod backlight;
+pub mod bluez;
+pub mod calendar;
+pub mod camera;
+pub mod compositor;
+pub mod logind;
+pub mod mpris;
 pub mod network_manager;
 pub mod notifications;
+pub mod power_profiles;
+pub mod status_notifier;
+#[cfg(test)]
+mod test_bus;
+pub mod timedate;
+pub mod timers;
 pub mod upower;
 
 /// Get the connection to the system bus
@@ -15,15 +30,44 @@ pub fn system_connection() -> &'static zbus::blocking::Connection {
 }
 
 /// Get the connection to the session bus
-#[allow(unused)]
 fn session_connection() -> &'static zbus::blocking::Connection {
     static RUNTIME: OnceLock<zbus::blocking::Connection> = OnceLock::new();
     RUNTIME.get_or_init(|| zbus::blocking::Connection::session().unwrap())
 }
 
+/// Spawn a task for a daemon, running on its own thread for the entire program.
+///
+/// This is used to keep the daemon state up to date independently from the GTK main loop.
+fn spawn(name: &str, future: impl Future<Output = anyhow::Result<()>> + Send + 'static) {
+    let thread_name = name.to_string();
+    std::thread::Builder::new()
+        .name(name.to_string())
+        .spawn(move || {
+            if let Err(err) = async_io::block_on(future) {
+                error!(?err, name = thread_name, "Daemon task failed");
+            }
+        })
+        .expect("Failed to spawn daemon thread");
+}
+
 /// Start all the daemons.
 pub async fn start() -> anyhow::Result<()> {
     upower::start().await?;
+    logind::start().await?;
+    // Writes go through the logind session.
+    backlight::start().await?;
+    power_profiles::start().await?;
     network_manager::start().await?;
+    audio::start().await?;
+    camera::start().await?;
+    mpris::start().await?;
+    bluez::start().await?;
+    status_notifier::start().await?;
+    compositor::start().await?;
+    calendar::start().await?;
+    timedate::start().await?;
+    // Before anything sending notifications, so that we get to show them.
+    notifications::start().await?;
+    timers::start().await?;
     Ok(())
 }
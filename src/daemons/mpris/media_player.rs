@@ -0,0 +1,49 @@
+//! # D-Bus interface proxy for: `org.mpris.MediaPlayer2`
+//!
+//! This code was generated by `zbus-xmlgen` `5.1.0` from D-Bus introspection data.
+//! Source: `org.mpris.MediaPlayer2.xml`.
+//!
+//! You may prefer to adapt it, instead of using it verbatim.
+//!
+//! More information can be found in the [Writing a client proxy] section of the zbus
+//! documentation.
+//!
+//! This type implements the [D-Bus standard interfaces], (`org.freedesktop.DBus.*`) for which the
+//! following zbus API can be used:
+//!
+//! * [`zbus::fdo::PropertiesProxy`]
+//! * [`zbus::fdo::IntrospectableProxy`]
+//! * [`zbus::fdo::PeerProxy`]
+//!
+//! Consequently `zbus-xmlgen` did not generate code for the above interfaces.
+//!
+//! [Writing a client proxy]: https://dbus2.github.io/zbus/client.html
+//! [D-Bus standard interfaces]: https://dbus.freedesktop.org/doc/dbus-specification.html#standard-interfaces,
+use zbus::proxy;
+#[proxy(
+    interface = "org.mpris.MediaPlayer2",
+    default_path = "/org/mpris/MediaPlayer2"
+)]
+pub trait MediaPlayer2 {
+    /// Quit method
+    fn quit(&self) -> zbus::Result<()>;
+
+    /// Raise method
+    fn raise(&self) -> zbus::Result<()>;
+
+    /// CanQuit property
+    #[zbus(property)]
+    fn can_quit(&self) -> zbus::Result<bool>;
+
+    /// CanRaise property
+    #[zbus(property)]
+    fn can_raise(&self) -> zbus::Result<bool>;
+
+    /// DesktopEntry property
+    #[zbus(property)]
+    fn desktop_entry(&self) -> zbus::Result<String>;
+
+    /// Identity property
+    #[zbus(property)]
+    fn identity(&self) -> zbus::Result<String>;
+}
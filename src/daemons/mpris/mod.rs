@@ -0,0 +1,639 @@
+#![allow(unused)]
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use tokio::sync::{broadcast, watch};
+use zbus::zvariant::OwnedValue;
+
+pub mod media_player;
+pub mod player;
+
+/// The prefix of the bus names of MPRIS players.
+const BUS_NAME_PREFIX: &str = "org.mpris.MediaPlayer2.";
+
+/// A MPRIS daemon, used to monitor and control media players.
+/// See the specification: <https://specifications.freedesktop.org/mpris-spec/latest/>
+pub struct Daemon {
+    conn: zbus::Connection,
+    players: RwLock<Vec<Player>>,
+    sender: broadcast::Sender<Event>,
+    selected: watch::Sender<Option<Player>>,
+}
+
+impl Daemon {
+    /// Create a new [`Daemon`] looking for players on this connection.
+    ///
+    /// Outside of testing, this is the session bus.
+    pub async fn new(conn: zbus::Connection) -> zbus::Result<Self> {
+        let dbus = zbus::fdo::DBusProxy::new(&conn).await?;
+        let mut players = vec![];
+        for name in dbus.list_names().await? {
+            if !name.starts_with(BUS_NAME_PREFIX) {
+                continue;
+            }
+            // The player can leave the bus right after we listed it.
+            let owner = match dbus.get_name_owner(name.as_ref()).await {
+                Ok(owner) => owner,
+                Err(err) => {
+                    warn!(?err, ?name, "Failed to get MPRIS player owner");
+                    continue;
+                }
+            };
+            match Player::new(&conn, &name, &owner).await {
+                Ok(player) => players.push(player),
+                Err(err) => warn!(?err, ?name, "Failed to add MPRIS player"),
+            }
+        }
+
+        let selected = pick_player(&players).await;
+        let (sender, _) = broadcast::channel(32);
+        Ok(Self {
+            conn,
+            players: RwLock::new(players),
+            sender,
+            selected: watch::Sender::new(selected),
+        })
+    }
+
+    /// Get all the players currently on the bus.
+    pub fn players(&self) -> Vec<Player> {
+        self.players.read().unwrap().clone()
+    }
+
+    /// Subscribe to the events of this [`Daemon`].
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+
+    /// Subscribe to the selected player.
+    ///
+    /// This is the player the shell shows. It's the last one that started playing, unless the
+    /// user selected another one with [`Self::select`].
+    pub fn subscribe_selected(&self) -> watch::Receiver<Option<Player>> {
+        self.selected.subscribe()
+    }
+
+    /// Select the player with this bus name.
+    pub fn select(&self, bus_name: &str) {
+        if let Some(player) = self
+            .players
+            .read()
+            .unwrap()
+            .iter()
+            .find(|player| &*player.bus_name == bus_name)
+        {
+            self.selected.send_replace(Some(player.clone()));
+        }
+    }
+
+    /// Follow the players appearing, disappearing and starting to play.
+    ///
+    /// This runs for as long as the connection is alive.
+    pub async fn run(&self) -> anyhow::Result<()> {
+        let dbus = zbus::fdo::DBusProxy::new(&self.conn).await?;
+        let mut name_owner_changes = dbus.receive_name_owner_changed().await?;
+        // Players must emit PropertiesChanged when they start playing, watching this is much
+        // cheaper than having a proxy stream for every player.
+        let rule = zbus::MatchRule::builder()
+            .msg_type(zbus::message::Type::Signal)
+            .interface("org.freedesktop.DBus.Properties")?
+            .member("PropertiesChanged")?
+            .path("/org/mpris/MediaPlayer2")?
+            .build();
+        let mut properties_changes =
+            zbus::MessageStream::for_match_rule(rule, &self.conn, None).await?;
+
+        loop {
+            futures_util::select! {
+                changed = name_owner_changes.next() => {
+                    let Some(changed) = changed else { break };
+                    let Ok(args) = changed.args() else { continue };
+                    if !args.name.starts_with(BUS_NAME_PREFIX) {
+                        continue;
+                    }
+                    let name = args.name.to_string();
+                    if let Some(old_owner) = &*args.old_owner {
+                        self.remove_player(&name);
+                    }
+                    if let Some(new_owner) = &*args.new_owner {
+                        match Player::new(&self.conn, &name, new_owner).await {
+                            Ok(player) => self.add_player(player),
+                            Err(err) => warn!(?err, ?name, "Failed to add MPRIS player"),
+                        }
+                    }
+                }
+                message = properties_changes.next() => {
+                    let Some(message) = message else { break };
+                    let Ok(message) = message else { continue };
+                    let Some(sender) = message.header().sender().map(|sender| sender.to_string())
+                    else {
+                        continue;
+                    };
+                    let Ok((interface, changed, _)) = message
+                        .body()
+                        .deserialize::<(String, HashMap<String, OwnedValue>, Vec<String>)>()
+                    else {
+                        continue;
+                    };
+                    let started_playing = interface == "org.mpris.MediaPlayer2.Player"
+                        && changed
+                            .get("PlaybackStatus")
+                            .and_then(|status| <&str>::try_from(status).ok())
+                            == Some("Playing");
+                    if started_playing {
+                        self.select_owner(&sender);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn add_player(&self, player: Player) {
+        debug!(bus_name = ?player.bus_name, "MPRIS player added");
+        self.players.write().unwrap().push(player.clone());
+        if self.selected.borrow().is_none() {
+            self.selected.send_replace(Some(player.clone()));
+        }
+        let _ = self.sender.send(Event::PlayerAdded(player));
+    }
+
+    fn remove_player(&self, bus_name: &str) {
+        let mut players = self.players.write().unwrap();
+        let Some(index) = players
+            .iter()
+            .position(|player| &*player.bus_name == bus_name)
+        else {
+            return;
+        };
+        let player = players.remove(index);
+        debug!(bus_name = ?player.bus_name, "MPRIS player removed");
+
+        let was_selected = self
+            .selected
+            .borrow()
+            .as_ref()
+            .is_some_and(|selected| selected.bus_name == player.bus_name);
+        if was_selected {
+            self.selected.send_replace(players.first().cloned());
+        }
+        drop(players);
+        let _ = self.sender.send(Event::PlayerRemoved(player.bus_name));
+    }
+
+    fn select_owner(&self, owner: &str) {
+        let players = self.players.read().unwrap();
+        let Some(player) = players.iter().find(|player| &*player.owner == owner) else {
+            return;
+        };
+        let already_selected = self
+            .selected
+            .borrow()
+            .as_ref()
+            .is_some_and(|selected| selected.bus_name == player.bus_name);
+        if !already_selected {
+            self.selected.send_replace(Some(player.clone()));
+        }
+    }
+}
+
+/// Pick the player to select at startup, preferring one that is playing.
+async fn pick_player(players: &[Player]) -> Option<Player> {
+    for player in players {
+        if player.proxy.playback_status().await.as_deref() == Ok("Playing") {
+            return Some(player.clone());
+        }
+    }
+    players.first().cloned()
+}
+
+/// A MPRIS media player.
+#[derive(Clone, Debug)]
+pub struct Player {
+    bus_name: Arc<str>,
+    // The unique name of the connection owning the bus name, signals come from it.
+    owner: Arc<str>,
+    identity: Arc<str>,
+    desktop_entry: Option<Arc<str>>,
+    proxy: player::PlayerProxy<'static>,
+}
+
+impl Player {
+    async fn new(conn: &zbus::Connection, bus_name: &str, owner: &str) -> zbus::Result<Self> {
+        let media_player = media_player::MediaPlayer2Proxy::builder(conn)
+            .destination(bus_name.to_string())?
+            .build()
+            .await?;
+        let proxy = player::PlayerProxy::builder(conn)
+            .destination(bus_name.to_string())?
+            .build()
+            .await?;
+
+        // Identity is mandatory, but some players don't set it.
+        let identity = match media_player.identity().await {
+            Ok(identity) if !identity.is_empty() => identity,
+            _ => bus_name
+                .strip_prefix(BUS_NAME_PREFIX)
+                .unwrap_or(bus_name)
+                .to_string(),
+        };
+        let desktop_entry = media_player
+            .desktop_entry()
+            .await
+            .ok()
+            .filter(|entry| !entry.is_empty());
+
+        Ok(Self {
+            bus_name: bus_name.into(),
+            owner: owner.into(),
+            identity: identity.into(),
+            desktop_entry: desktop_entry.map(Into::into),
+            proxy,
+        })
+    }
+
+    /// Get the bus name of this [`Player`], IE. `org.mpris.MediaPlayer2.spotify`
+    pub fn bus_name(&self) -> Arc<str> {
+        Arc::clone(&self.bus_name)
+    }
+
+    /// Get a human readable name of this [`Player`].
+    pub fn identity(&self) -> Arc<str> {
+        Arc::clone(&self.identity)
+    }
+
+    /// Get the desktop entry of this [`Player`], without the `.desktop` suffix.
+    pub fn desktop_entry(&self) -> Option<Arc<str>> {
+        self.desktop_entry.clone()
+    }
+
+    /// Get the underlying [`zbus::Proxy`] behind this interface.
+    pub fn proxy(&self) -> &player::PlayerProxy<'static> {
+        &self.proxy
+    }
+
+    /// Get the metadata of the current track.
+    pub async fn metadata(&self) -> zbus::Result<Metadata> {
+        self.proxy.metadata().await.map(Metadata::from)
+    }
+
+    /// Get the playback status.
+    pub async fn playback_status(&self) -> zbus::Result<PlaybackStatus> {
+        let status = self.proxy.playback_status().await?;
+        Ok(match status.as_str() {
+            "Playing" => PlaybackStatus::Playing,
+            "Paused" => PlaybackStatus::Paused,
+            _ => PlaybackStatus::Stopped,
+        })
+    }
+
+    /// Get the position in the current track.
+    pub async fn position(&self) -> zbus::Result<Duration> {
+        let position = self.proxy.position().await?;
+        Ok(Duration::from_micros(position.max(0) as u64))
+    }
+
+    /// Get the volume, `1.0` being 100%.
+    pub async fn volume(&self) -> zbus::Result<f64> {
+        self.proxy.volume().await
+    }
+
+    /// Set the volume, `1.0` being 100%.
+    pub async fn set_volume(&self, volume: f64) -> zbus::Result<()> {
+        self.proxy.set_volume(volume.max(0.0)).await
+    }
+
+    /// Seek to a position in the current track.
+    pub async fn set_position(&self, position: Duration) -> zbus::Result<()> {
+        let metadata = self.metadata().await?;
+        let Some(track_id) = metadata.track_id else {
+            return Err(zbus::Error::Failure("Track has no ID".to_string()));
+        };
+        let track_id = zbus::zvariant::ObjectPath::try_from(track_id.as_str())?;
+        self.proxy
+            .set_position(&track_id, position.as_micros() as i64)
+            .await
+    }
+}
+
+/// The playback status of a [`Player`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaybackStatus {
+    Playing,
+    Paused,
+    Stopped,
+}
+
+/// The metadata of a track.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metadata {
+    /// The track ID, as a D-Bus object path.
+    pub track_id: Option<String>,
+    pub title: Option<String>,
+    pub artists: Vec<String>,
+    pub album: Option<String>,
+    /// The URL of the album art, usually a `file://` or a `https://` URL.
+    pub art_url: Option<String>,
+    pub length: Option<Duration>,
+}
+
+impl From<HashMap<String, OwnedValue>> for Metadata {
+    fn from(metadata: HashMap<String, OwnedValue>) -> Self {
+        let string = |key: &str| {
+            let value = metadata.get(key)?;
+            <&str>::try_from(value)
+                .ok()
+                .map(str::to_string)
+                .or_else(|| {
+                    <&zbus::zvariant::ObjectPath>::try_from(value)
+                        .ok()
+                        .map(|path| path.to_string())
+                })
+                .filter(|value| !value.is_empty())
+        };
+        let artists = metadata
+            .get("xesam:artist")
+            .and_then(|artists| artists.try_clone().ok())
+            .and_then(|artists| Vec::<String>::try_from(artists).ok())
+            .unwrap_or_default();
+        // The spec says this is a 64-bit integer, but some players use an unsigned one.
+        let length = metadata.get("mpris:length").and_then(|length| {
+            i64::try_from(length)
+                .ok()
+                .map(|length| length.max(0) as u64)
+                .or_else(|| u64::try_from(length).ok())
+        });
+
+        Self {
+            track_id: string("mpris:trackid"),
+            title: string("xesam:title"),
+            artists,
+            album: string("xesam:album"),
+            art_url: string("mpris:artUrl"),
+            length: length.map(Duration::from_micros),
+        }
+    }
+}
+
+/// An event sent by the MPRIS [`Daemon`].
+#[derive(Clone, Debug)]
+pub enum Event {
+    /// A new player appeared on the bus.
+    PlayerAdded(Player),
+    /// A player went away, with its bus name.
+    PlayerRemoved(Arc<str>),
+}
+
+static INSTANCE: OnceLock<Daemon> = OnceLock::new();
+
+pub fn get() -> &'static Daemon {
+    INSTANCE.get().expect("daemons::start() must be called")
+}
+
+pub async fn start() -> anyhow::Result<()> {
+    if INSTANCE.get().is_some() {
+        return Ok(());
+    }
+
+    let conn = super::session_connection().inner().clone();
+    let daemon = Daemon::new(conn).await?;
+
+    // NOTE: If we already started he handled it above.
+    let _ = INSTANCE.set(daemon);
+
+    super::spawn("mpris-players", async move { get().run().await });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::pin::pin;
+
+    use futures_util::future::{self, Either};
+    use zbus::zvariant::{ObjectPath, Value};
+
+    use super::*;
+    use crate::daemons::test_bus::TestBus;
+
+    /// How long we wait for the daemon to notice something before failing.
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    impl TestBus {
+        /// Serve a fake player as `org.mpris.MediaPlayer2.<name>`.
+        async fn serve_player(&self, name: &str, status: &str) -> zbus::Connection {
+            let media_player = FakeMediaPlayer {
+                identity: name.to_uppercase(),
+            };
+            let player = FakePlayer {
+                status: status.to_string(),
+                title: format!("{name} song"),
+                volume: 1.0,
+            };
+            self.builder()
+                .name(format!("{BUS_NAME_PREFIX}{name}"))
+                .unwrap()
+                .serve_at("/org/mpris/MediaPlayer2", media_player)
+                .unwrap()
+                .serve_at("/org/mpris/MediaPlayer2", player)
+                .unwrap()
+                .build()
+                .await
+                .unwrap()
+        }
+    }
+
+    struct FakeMediaPlayer {
+        identity: String,
+    }
+
+    #[zbus::interface(name = "org.mpris.MediaPlayer2")]
+    impl FakeMediaPlayer {
+        #[zbus(property)]
+        fn identity(&self) -> String {
+            self.identity.clone()
+        }
+
+        #[zbus(property)]
+        fn desktop_entry(&self) -> String {
+            "org.example.FakePlayer".to_string()
+        }
+    }
+
+    struct FakePlayer {
+        status: String,
+        title: String,
+        volume: f64,
+    }
+
+    #[zbus::interface(name = "org.mpris.MediaPlayer2.Player")]
+    impl FakePlayer {
+        #[zbus(property)]
+        fn playback_status(&self) -> String {
+            self.status.clone()
+        }
+
+        #[zbus(property)]
+        fn metadata(&self) -> HashMap<String, OwnedValue> {
+            let owned = |value: Value| value.try_to_owned().unwrap();
+            HashMap::from([
+                (
+                    "mpris:trackid".to_string(),
+                    owned(ObjectPath::from_static_str_unchecked("/org/example/track/1").into()),
+                ),
+                ("xesam:title".to_string(), owned(self.title.as_str().into())),
+                ("xesam:artist".to_string(), owned(vec!["Artist"].into())),
+                ("mpris:length".to_string(), owned(180_000_000_i64.into())),
+            ])
+        }
+
+        #[zbus(property)]
+        fn volume(&self) -> f64 {
+            self.volume
+        }
+
+        #[zbus(property)]
+        fn set_volume(&mut self, volume: f64) {
+            self.volume = volume;
+        }
+    }
+
+    /// Wait for a future, failing the test after [`TIMEOUT`].
+    async fn timeout<T>(future: impl Future<Output = T>) -> T {
+        match future::select(pin!(future), pin!(async_io::Timer::after(TIMEOUT))).await {
+            Either::Left((output, _)) => output,
+            Either::Right(_) => panic!("Timed out"),
+        }
+    }
+
+    fn selected_name(selected: &watch::Receiver<Option<Player>>) -> Option<Arc<str>> {
+        selected.borrow().as_ref().map(Player::bus_name)
+    }
+
+    #[test]
+    fn follows_players() {
+        let bus = TestBus::new().expect("dbus-daemon is needed to run the D-Bus tests");
+
+        async_io::block_on(async {
+            let first = bus.serve_player("first", "Paused").await;
+            let daemon = Daemon::new(bus.builder().build().await.unwrap())
+                .await
+                .unwrap();
+
+            // Players already on the bus.
+            let players = daemon.players();
+            assert_eq!(players.len(), 1);
+            let player = &players[0];
+            assert_eq!(&*player.bus_name(), "org.mpris.MediaPlayer2.first");
+            assert_eq!(&*player.identity(), "FIRST");
+            assert_eq!(
+                player.desktop_entry().as_deref(),
+                Some("org.example.FakePlayer")
+            );
+            assert_eq!(
+                player.metadata().await.unwrap(),
+                Metadata {
+                    track_id: Some("/org/example/track/1".to_string()),
+                    title: Some("first song".to_string()),
+                    artists: vec!["Artist".to_string()],
+                    album: None,
+                    art_url: None,
+                    length: Some(Duration::from_secs(180)),
+                }
+            );
+            assert_eq!(
+                player.playback_status().await.unwrap(),
+                PlaybackStatus::Paused
+            );
+            assert_eq!(player.volume().await.unwrap(), 1.0);
+
+            let mut events = daemon.subscribe();
+            let mut selected = daemon.subscribe_selected();
+            assert_eq!(
+                selected_name(&selected).as_deref(),
+                Some("org.mpris.MediaPlayer2.first")
+            );
+
+            let test = async {
+                // Give the daemon the time to follow the bus.
+                async_io::Timer::after(Duration::from_millis(200)).await;
+
+                // A player appearing.
+                let second = bus.serve_player("second", "Stopped").await;
+                let Event::PlayerAdded(player) = timeout(events.recv()).await.unwrap() else {
+                    panic!("Expected a new player");
+                };
+                assert_eq!(&*player.bus_name(), "org.mpris.MediaPlayer2.second");
+                assert_eq!(
+                    selected_name(&selected).as_deref(),
+                    Some("org.mpris.MediaPlayer2.first")
+                );
+
+                // The player starting to play, and changing track and volume.
+                let proxy = player.proxy();
+                let mut status_changes = proxy.receive_playback_status_changed().await;
+                let mut metadata_changes = proxy.receive_metadata_changed().await;
+                let mut volume_changes = proxy.receive_volume_changed().await;
+                // The streams start with the current values.
+                timeout(status_changes.next()).await.unwrap();
+                timeout(metadata_changes.next()).await.unwrap();
+                timeout(volume_changes.next()).await.unwrap();
+
+                let iface = second
+                    .object_server()
+                    .interface::<_, FakePlayer>("/org/mpris/MediaPlayer2")
+                    .await
+                    .unwrap();
+                {
+                    let mut fake = iface.get_mut().await;
+                    fake.status = "Playing".to_string();
+                    fake.title = "another song".to_string();
+                    let emitter = iface.signal_emitter();
+                    fake.playback_status_changed(emitter).await.unwrap();
+                    fake.metadata_changed(emitter).await.unwrap();
+                }
+                timeout(selected.changed()).await.unwrap();
+                assert_eq!(
+                    selected_name(&selected).as_deref(),
+                    Some("org.mpris.MediaPlayer2.second")
+                );
+                let changed = timeout(status_changes.next()).await.unwrap();
+                assert_eq!(changed.get().await.unwrap(), "Playing");
+                let changed = timeout(metadata_changes.next()).await.unwrap();
+                let metadata = Metadata::from(changed.get().await.unwrap());
+                assert_eq!(metadata.title.as_deref(), Some("another song"));
+
+                player.set_volume(0.25).await.unwrap();
+                let changed = timeout(volume_changes.next()).await.unwrap();
+                assert_eq!(changed.get().await.unwrap(), 0.25);
+                assert_eq!(iface.get().await.volume, 0.25);
+
+                // The selected player going away.
+                second
+                    .release_name("org.mpris.MediaPlayer2.second")
+                    .await
+                    .unwrap();
+                let Event::PlayerRemoved(bus_name) = timeout(events.recv()).await.unwrap() else {
+                    panic!("Expected a removed player");
+                };
+                assert_eq!(&*bus_name, "org.mpris.MediaPlayer2.second");
+                assert_eq!(
+                    selected_name(&selected).as_deref(),
+                    Some("org.mpris.MediaPlayer2.first")
+                );
+                assert_eq!(daemon.players().len(), 1);
+                drop(first);
+            };
+
+            let (run, test) = (pin!(daemon.run()), pin!(test));
+            let result = future::select(run, test).await;
+            if let Either::Left((result, _)) = result {
+                panic!("The daemon stopped: {result:?}");
+            }
+        });
+    }
+}
@@ -0,0 +1,124 @@
+//! # D-Bus interface proxy for: `org.mpris.MediaPlayer2.Player`
+//!
+//! This code was generated by `zbus-xmlgen` `5.1.0` from D-Bus introspection data.
+//! Source: `org.mpris.MediaPlayer2.xml`.
+//!
+//! You may prefer to adapt it, instead of using it verbatim.
+//!
+//! More information can be found in the [Writing a client proxy] section of the zbus
+//! documentation.
+//!
+//! This type implements the [D-Bus standard interfaces], (`org.freedesktop.DBus.*`) for which the
+//! following zbus API can be used:
+//!
+//! * [`zbus::fdo::PropertiesProxy`]
+//! * [`zbus::fdo::IntrospectableProxy`]
+//! * [`zbus::fdo::PeerProxy`]
+//!
+//! Consequently `zbus-xmlgen` did not generate code for the above interfaces.
+//!
+//! [Writing a client proxy]: https://dbus2.github.io/zbus/client.html
+//! [D-Bus standard interfaces]: https://dbus.freedesktop.org/doc/dbus-specification.html#standard-interfaces,
+use zbus::proxy;
+#[proxy(
+    interface = "org.mpris.MediaPlayer2.Player",
+    default_path = "/org/mpris/MediaPlayer2"
+)]
+pub trait Player {
+    /// Next method
+    fn next(&self) -> zbus::Result<()>;
+
+    /// OpenUri method
+    fn open_uri(&self, uri: &str) -> zbus::Result<()>;
+
+    /// Pause method
+    fn pause(&self) -> zbus::Result<()>;
+
+    /// Play method
+    fn play(&self) -> zbus::Result<()>;
+
+    /// PlayPause method
+    fn play_pause(&self) -> zbus::Result<()>;
+
+    /// Previous method
+    fn previous(&self) -> zbus::Result<()>;
+
+    /// Seek method
+    fn seek(&self, offset: i64) -> zbus::Result<()>;
+
+    /// SetPosition method
+    fn set_position(
+        &self,
+        track_id: &zbus::zvariant::ObjectPath<'_>,
+        position: i64,
+    ) -> zbus::Result<()>;
+
+    /// Stop method
+    fn stop(&self) -> zbus::Result<()>;
+
+    /// Seeked signal
+    #[zbus(signal)]
+    fn seeked(&self, position: i64) -> zbus::Result<()>;
+
+    /// CanControl property
+    #[zbus(property)]
+    fn can_control(&self) -> zbus::Result<bool>;
+
+    /// CanGoNext property
+    #[zbus(property)]
+    fn can_go_next(&self) -> zbus::Result<bool>;
+
+    /// CanGoPrevious property
+    #[zbus(property)]
+    fn can_go_previous(&self) -> zbus::Result<bool>;
+
+    /// CanPause property
+    #[zbus(property)]
+    fn can_pause(&self) -> zbus::Result<bool>;
+
+    /// CanPlay property
+    #[zbus(property)]
+    fn can_play(&self) -> zbus::Result<bool>;
+
+    /// CanSeek property
+    #[zbus(property)]
+    fn can_seek(&self) -> zbus::Result<bool>;
+
+    /// LoopStatus property
+    #[zbus(property)]
+    fn loop_status(&self) -> zbus::Result<String>;
+    #[zbus(property)]
+    fn set_loop_status(&self, value: &str) -> zbus::Result<()>;
+
+    /// Metadata property
+    #[zbus(property)]
+    fn metadata(
+        &self,
+    ) -> zbus::Result<std::collections::HashMap<String, zbus::zvariant::OwnedValue>>;
+
+    /// PlaybackStatus property
+    #[zbus(property)]
+    fn playback_status(&self) -> zbus::Result<String>;
+
+    /// Position property
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn position(&self) -> zbus::Result<i64>;
+
+    /// Rate property
+    #[zbus(property)]
+    fn rate(&self) -> zbus::Result<f64>;
+    #[zbus(property)]
+    fn set_rate(&self, value: f64) -> zbus::Result<()>;
+
+    /// Shuffle property
+    #[zbus(property)]
+    fn shuffle(&self) -> zbus::Result<bool>;
+    #[zbus(property)]
+    fn set_shuffle(&self, value: bool) -> zbus::Result<()>;
+
+    /// Volume property
+    #[zbus(property)]
+    fn volume(&self) -> zbus::Result<f64>;
+    #[zbus(property)]
+    fn set_volume(&self, value: f64) -> zbus::Result<()>;
+}
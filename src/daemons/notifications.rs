@@ -1,35 +1,267 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
 use std::time::Duration;
 
+use futures_util::{Stream, StreamExt};
 use tokio::sync::broadcast;
-use zbus::zvariant;
+use zbus::object_server::{InterfaceRef, SignalEmitter};
+use zbus::{zvariant, MatchRule, MessageStream};
+
+const NOTIFICATIONS_NAME: &str = "org.freedesktop.Notifications";
+const NOTIFICATIONS_PATH: &str = "/org/freedesktop/Notifications";
+const NOTIFICATIONS_INTERFACE: &str = "org.freedesktop.Notifications";
+
+/// How long notifications stay on screen when the sender lets us choose.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+static INSTANCE: OnceLock<Daemon> = OnceLock::new();
 
 pub(super) async fn start() -> anyhow::Result<()> {
+    if INSTANCE.get().is_some() {
+        return Ok(());
+    }
+
+    let daemon = Daemon::new(super::session_connection().inner()).await?;
+    if daemon.server.is_none() {
+        info!("Another notification daemon is running, using it instead");
+    }
+    // NOTE: If we already started he handled it above.
+    let _ = INSTANCE.set(daemon);
+
+    Ok(())
+}
+
+pub fn get() -> &'static Daemon {
+    INSTANCE.get().expect("daemons::start() must be called")
+}
+
+/// The notifications daemon, to show the notifications sent to our server.
+///
+/// When another notification daemon is running, no requests are ever sent.
+pub struct Daemon {
+    sender: broadcast::Sender<Request>,
+    /// Our notification server, if we got the notifications name.
+    server: Option<InterfaceRef<NotificationServer>>,
+}
+
+impl Daemon {
+    async fn new(conn: &zbus::Connection) -> anyhow::Result<Self> {
+        let (sender, _) = broadcast::channel(128);
+        let interface = NotificationServer {
+            sender: sender.clone(),
+            id_counter: 0,
+            shown: HashSet::new(),
+        };
+
+        conn.object_server()
+            .at(NOTIFICATIONS_PATH, interface)
+            .await?;
+        let reply = conn
+            .request_name_with_flags(
+                NOTIFICATIONS_NAME,
+                zbus::fdo::RequestNameFlags::DoNotQueue.into(),
+            )
+            .await;
+        let server = if matches!(reply, Ok(zbus::fdo::RequestNameReply::PrimaryOwner)) {
+            Some(
+                conn.object_server()
+                    .interface::<_, NotificationServer>(NOTIFICATIONS_PATH)
+                    .await?,
+            )
+        } else {
+            conn.object_server()
+                .remove::<NotificationServer, _>(NOTIFICATIONS_PATH)
+                .await?;
+            None
+        };
+
+        Ok(Self { sender, server })
+    }
+
+    /// Subscribe to the notifications to show and close.
+    pub fn subscribe(&self) -> broadcast::Receiver<Request> {
+        self.sender.subscribe()
+    }
+
+    /// Tell the sender that the user picked an action of a notification, then close it.
+    pub async fn invoke_action(&self, id: u32, action_key: &str) {
+        let Some(server) = &self.server else {
+            return;
+        };
+        let emitter = server.signal_emitter();
+        if let Err(err) = NotificationServer::action_invoked(emitter, id, action_key).await {
+            error!(?err, id, "Failed to emit ActionInvoked");
+        }
+        server
+            .get_mut()
+            .await
+            .close(id, CloseReason::Dismissed, emitter)
+            .await;
+    }
+
+    /// Close a notification the user dismissed.
+    pub async fn dismiss(&self, id: u32) {
+        self.close(id, CloseReason::Dismissed).await;
+    }
+
+    /// Close a notification that stayed on screen for its whole timeout.
+    pub async fn expire(&self, id: u32) {
+        self.close(id, CloseReason::Expired).await;
+    }
+
+    async fn close(&self, id: u32, reason: CloseReason) {
+        let Some(server) = &self.server else {
+            return;
+        };
+        let emitter = server.signal_emitter();
+        server.get_mut().await.close(id, reason, emitter).await;
+    }
+}
+
+/// The urgency of a notification.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Urgency {
+    Low = 0,
+    Normal = 1,
+    Critical = 2,
+}
+
+impl Urgency {
+    /// Get the urgency from the `urgency` hint of a notification.
+    fn from_hints(hints: &HashMap<&str, zvariant::Value<'_>>) -> Self {
+        match hints.get("urgency") {
+            Some(zvariant::Value::U8(0)) => Self::Low,
+            Some(zvariant::Value::U8(2)) => Self::Critical,
+            _ => Self::Normal,
+        }
+    }
+}
+
+/// Why a notification got closed, as sent in the `NotificationClosed` signal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum CloseReason {
+    Expired = 1,
+    Dismissed = 2,
+    /// The sender closed it with `CloseNotification`.
+    Closed = 3,
+}
+
+/// Send a notification from the shell itself, returning its ID.
+///
+/// This goes through the session bus, so it gets displayed by whoever owns the notifications
+/// name, be it us or another notification daemon.
+///
+/// The `actions` are pairs of keys and labels, the key of the action the user picks is sent
+/// through [`receive_events`].
+pub async fn send(
+    summary: &str,
+    body: &str,
+    icon: &str,
+    urgency: Urgency,
+    replaces_id: u32,
+    actions: &[(&str, &str)],
+) -> zbus::Result<u32> {
+    let conn = super::session_connection().inner();
+    let proxy = NotificationsProxy::new(conn).await?;
+
+    let urgency = zvariant::Value::U8(urgency as u8);
+    let hints = HashMap::from([("urgency", &urgency)]);
+    let actions = actions
+        .iter()
+        .flat_map(|(key, label)| [*key, *label])
+        .collect::<Vec<_>>();
+    proxy
+        .notify(
+            "fht-shell",
+            replaces_id,
+            icon,
+            summary,
+            body,
+            &actions,
+            hints,
+            -1,
+        )
+        .await
+}
+
+/// Close a notification that was sent with [`send`].
+pub async fn close(id: u32) -> zbus::Result<()> {
     let conn = super::session_connection().inner();
+    let proxy = NotificationsProxy::new(conn).await?;
+    proxy.close_notification(id).await
+}
 
-    let (sender, receiver) = broadcast::channel(128);
-    let interface = NotificationServer {
-        sender,
-        receiver,
-        id_counter: 0,
-    };
+/// What happened to the notifications sent with [`send`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// The user picked an action of the notification.
+    ActionInvoked { id: u32, action_key: String },
+    /// The notification got closed, for any reason.
+    Closed { id: u32 },
+}
 
-    let inserted = conn
-        .object_server()
-        .at("/org/freedesktop/Notifications", interface)
-        .await?;
-    assert!(inserted, "Another notification daemon is running");
+/// Get what happens to the notifications.
+///
+/// Both signals come through the same stream so that they stay in order, since picking an action
+/// also closes the notification.
+pub async fn receive_events() -> zbus::Result<impl Stream<Item = Event>> {
+    events(super::session_connection().inner()).await
+}
 
-    Ok(())
+async fn events(conn: &zbus::Connection) -> zbus::Result<impl Stream<Item = Event>> {
+    let rule = MatchRule::builder()
+        .msg_type(zbus::message::Type::Signal)
+        .interface(NOTIFICATIONS_INTERFACE)?
+        .path(NOTIFICATIONS_PATH)?
+        .build();
+    let messages = MessageStream::for_match_rule(rule, conn, None).await?;
+    Ok(messages.filter_map(|message| async move {
+        let message = message.ok()?;
+        if let Some(signal) = ActionInvoked::from_message(message.clone()) {
+            let args = signal.args().ok()?;
+            return Some(Event::ActionInvoked {
+                id: args.id,
+                action_key: args.action_key.to_string(),
+            });
+        }
+        let signal = NotificationClosed::from_message(message)?;
+        let args = signal.args().ok()?;
+        Some(Event::Closed { id: args.id })
+    }))
 }
 
-pub fn get() -> zbus::blocking::object_server::InterfaceRef<NotificationServer> {
-    let conn = super::session_connection();
-    let obj_server = conn.object_server();
-    let iface = obj_server
-        .interface("/org/freedesktop/Notifications")
-        .unwrap();
-    iface
+#[zbus::proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+trait Notifications {
+    /// Notify method
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, &zvariant::Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+
+    /// CloseNotification method
+    fn close_notification(&self, id: u32) -> zbus::Result<()>;
+
+    /// ActionInvoked signal
+    #[zbus(signal)]
+    fn action_invoked(&self, id: u32, action_key: &str) -> zbus::Result<()>;
+
+    /// NotificationClosed signal
+    #[zbus(signal)]
+    fn notification_closed(&self, id: u32, reason: u32) -> zbus::Result<()>;
 }
 
 #[derive(Clone, Debug)]
@@ -39,7 +271,13 @@ pub struct Notification {
     pub app_icon: Option<String>,
     pub summary: String,
     pub body: Option<String>,
+    /// The actions, as their key and label.
+    ///
+    /// The `default` one is picked by clicking the notification itself.
+    pub actions: Vec<(String, String)>,
+    pub urgency: Urgency,
     // FIXME: HINTS
+    /// How long to show the notification, or [`None`] to keep it until it gets dismissed.
     pub timeout: Option<Duration>,
 }
 
@@ -53,12 +291,25 @@ pub enum Request {
 }
 
 struct NotificationServer {
-    // We keep both the sender and receiver for the rest of the codebase
-    // to get as many receivers as needed.
+    // Anyone showing the notifications gets a receiver from the daemon.
     sender: broadcast::Sender<Request>,
-    receiver: broadcast::Receiver<Request>,
     // The ID counter.
     id_counter: u32,
+    /// The notifications that are currently shown.
+    shown: HashSet<u32>,
+}
+
+impl NotificationServer {
+    /// Close a shown notification, telling its sender.
+    async fn close(&mut self, id: u32, reason: CloseReason, emitter: &SignalEmitter<'_>) {
+        if !self.shown.remove(&id) {
+            return;
+        }
+        let _ = self.sender.send(Request::CloseNotification(id));
+        if let Err(err) = Self::notification_closed(emitter, id, reason as u32).await {
+            error!(?err, id, "Failed to emit NotificationClosed");
+        }
+    }
 }
 
 #[zbus::interface(name = "org.freedesktop.Notifications")]
@@ -67,17 +318,19 @@ impl NotificationServer {
         ("fht-shell", "FHT", "0.0.0", "1.2")
     }
 
+    /// Only what the popups can show, labels render Pango markup which includes links but not
+    /// images.
     async fn get_capabilities(&self) -> Vec<&str> {
         vec![
-            // "actions",
+            "actions",
             "body",
             "body-hyperlinks",
-            "body-images",
             "body-markup",
             "icon-static",
         ]
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn notify(
         &mut self,
         app_name: String,
@@ -85,40 +338,279 @@ impl NotificationServer {
         app_icon: String,
         summary: String,
         body: String,
-        _actions: Vec<String>,
-        _hints: HashMap<&str, zvariant::Value<'_>>,
+        actions: Vec<String>,
+        hints: HashMap<&str, zvariant::Value<'_>>,
         expire_timeout: i32,
     ) -> u32 {
-        let id = {
+        // Replacing keeps the same ID, unless the notification is already gone.
+        let replace = (replace != 0 && self.shown.contains(&replace)).then_some(replace);
+        let id = replace.unwrap_or_else(|| {
             self.id_counter += 1;
             self.id_counter
-        };
+        });
+        self.shown.insert(id);
+
+        let urgency = Urgency::from_hints(&hints);
         let notification = Notification {
             id,
             app_name: (!app_name.is_empty()).then_some(app_name),
             app_icon: (!app_icon.is_empty()).then_some(app_icon),
             summary,
             body: (!body.is_empty()).then_some(body),
+            actions: actions
+                .chunks_exact(2)
+                .map(|action| (action[0].clone(), action[1].clone()))
+                .collect(),
+            urgency,
+            // Critical notifications stay until the user saw them.
             timeout: match expire_timeout {
-                -1 => Some(Duration::from_secs(10)),
+                _ if urgency == Urgency::Critical => None,
                 0 => None,
-                x => Some(Duration::from_secs(x as u64)),
+                x if x > 0 => Some(Duration::from_millis(x as u64)),
+                _ => Some(DEFAULT_TIMEOUT),
             },
         };
 
-        let replace = (replace != 0).then_some(replace);
-        let _ = self
-            .sender
-            .send(Request::NewNotification {
+        let _ = self.sender.send(Request::NewNotification {
+            replace,
+            notification,
+        });
+
+        id
+    }
+
+    async fn close_notification(
+        &mut self,
+        id: u32,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) {
+        self.close(id, CloseReason::Closed, &emitter).await;
+    }
+
+    #[zbus(signal)]
+    async fn action_invoked(
+        emitter: &SignalEmitter<'_>,
+        id: u32,
+        action_key: &str,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn notification_closed(
+        emitter: &SignalEmitter<'_>,
+        id: u32,
+        reason: u32,
+    ) -> zbus::Result<()>;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::pin::pin;
+
+    use futures_util::future::{self, Either};
+
+    use super::*;
+    use crate::daemons::test_bus::TestBus;
+
+    /// How long we wait for a signal before failing.
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn server() -> (NotificationServer, broadcast::Receiver<Request>) {
+        let (sender, receiver) = broadcast::channel(16);
+        let server = NotificationServer {
+            sender,
+            id_counter: 0,
+            shown: HashSet::new(),
+        };
+        (server, receiver)
+    }
+
+    async fn send_notification(
+        server: &mut NotificationServer,
+        replace: u32,
+        actions: &[&str],
+        urgency: Option<u8>,
+        expire_timeout: i32,
+    ) -> u32 {
+        let hints = urgency
+            .map(|urgency| HashMap::from([("urgency", zvariant::Value::U8(urgency))]))
+            .unwrap_or_default();
+        let actions = actions.iter().map(|action| action.to_string()).collect();
+        server
+            .notify(
+                "test".to_string(),
                 replace,
+                String::new(),
+                "Summary".to_string(),
+                String::new(),
+                actions,
+                hints,
+                expire_timeout,
+            )
+            .await
+    }
+
+    fn new_notification(
+        receiver: &mut broadcast::Receiver<Request>,
+    ) -> (Notification, Option<u32>) {
+        match receiver.try_recv().unwrap() {
+            Request::NewNotification {
                 notification,
-            })
-            .unwrap();
+                replace,
+            } => (notification, replace),
+            request => panic!("Expected a new notification, got {request:?}"),
+        }
+    }
 
-        id
+    #[test]
+    fn notifications() {
+        async_io::block_on(async {
+            let (mut server, mut receiver) = server();
+
+            let id = send_notification(
+                &mut server,
+                0,
+                &["default", "Open", "snooze", "Snooze"],
+                None,
+                -1,
+            )
+            .await;
+            let (notification, replace) = new_notification(&mut receiver);
+            assert_eq!((notification.id, replace), (id, None));
+            assert_eq!(notification.app_name.as_deref(), Some("test"));
+            assert_eq!(notification.app_icon, None);
+            assert_eq!(notification.body, None);
+            assert_eq!(
+                notification.actions,
+                [
+                    ("default".to_string(), "Open".to_string()),
+                    ("snooze".to_string(), "Snooze".to_string())
+                ]
+            );
+            assert_eq!(notification.urgency, Urgency::Normal);
+            assert_eq!(notification.timeout, Some(DEFAULT_TIMEOUT));
+
+            // Replacing keeps the ID.
+            let replaced = send_notification(&mut server, id, &["dangling"], Some(0), 1500).await;
+            assert_eq!(replaced, id);
+            let (notification, replace) = new_notification(&mut receiver);
+            assert_eq!((notification.id, replace), (id, Some(id)));
+            assert_eq!(notification.actions, []);
+            assert_eq!(notification.urgency, Urgency::Low);
+            assert_eq!(notification.timeout, Some(Duration::from_millis(1500)));
+
+            // Unless there's nothing to replace anymore.
+            let other = send_notification(&mut server, 42, &[], None, 0).await;
+            assert_ne!(other, id);
+            let (notification, replace) = new_notification(&mut receiver);
+            assert_eq!((notification.id, replace), (other, None));
+            assert_eq!(notification.timeout, None);
+        });
     }
 
-    async fn close_notification(&self, id: u32) {
-        let _ = self.sender.send(Request::CloseNotification(id)).unwrap();
+    #[test]
+    fn critical_notifications_stay() {
+        async_io::block_on(async {
+            let (mut server, mut receiver) = server();
+            for expire_timeout in [-1, 0, 5000] {
+                send_notification(&mut server, 0, &[], Some(2), expire_timeout).await;
+                let (notification, _) = new_notification(&mut receiver);
+                assert_eq!(notification.urgency, Urgency::Critical);
+                assert_eq!(notification.timeout, None);
+            }
+        });
+    }
+
+    /// Wait for a future, failing the test after [`TIMEOUT`].
+    async fn timeout<T>(future: impl Future<Output = T>) -> T {
+        match future::select(pin!(future), pin!(async_io::Timer::after(TIMEOUT))).await {
+            Either::Left((output, _)) => output,
+            Either::Right(_) => panic!("Timed out"),
+        }
+    }
+
+    #[test]
+    fn signals() {
+        let bus = TestBus::new().expect("dbus-daemon is needed to run the D-Bus tests");
+
+        async_io::block_on(async {
+            let daemon = Daemon::new(&bus.builder().build().await.unwrap())
+                .await
+                .unwrap();
+            assert!(daemon.server.is_some());
+            let mut requests = daemon.subscribe();
+
+            let client = bus.builder().build().await.unwrap();
+            let mut events = pin!(events(&client).await.unwrap());
+            let proxy = NotificationsProxy::new(&client).await.unwrap();
+            let notify = |actions: &'static [&'static str]| {
+                let proxy = proxy.clone();
+                async move {
+                    let urgency = zvariant::Value::U8(Urgency::Critical as u8);
+                    let hints = HashMap::from([("urgency", &urgency)]);
+                    proxy
+                        .notify("test", 0, "", "Summary", "", actions, hints, -1)
+                        .await
+                        .unwrap()
+                }
+            };
+
+            // Picking an action closes the notification.
+            let id = notify(&["snooze", "Snooze"]).await;
+            let Ok(Request::NewNotification { notification, .. }) = requests.recv().await else {
+                panic!("Expected a new notification");
+            };
+            assert_eq!(notification.id, id);
+            daemon.invoke_action(id, "snooze").await;
+            assert_eq!(
+                timeout(events.next()).await,
+                Some(Event::ActionInvoked {
+                    id,
+                    action_key: "snooze".to_string()
+                })
+            );
+            assert_eq!(timeout(events.next()).await, Some(Event::Closed { id }));
+            assert!(matches!(
+                requests.recv().await,
+                Ok(Request::CloseNotification(closed)) if closed == id
+            ));
+
+            // Closed by the sender, then dismissed again by the user.
+            let id = notify(&[]).await;
+            proxy.close_notification(id).await.unwrap();
+            assert_eq!(timeout(events.next()).await, Some(Event::Closed { id }));
+            daemon.dismiss(id).await;
+
+            // Only the next notification gets closed.
+            let next_id = notify(&[]).await;
+            daemon.expire(next_id).await;
+            assert_eq!(
+                timeout(events.next()).await,
+                Some(Event::Closed { id: next_id })
+            );
+        });
+    }
+
+    #[test]
+    fn another_daemon_is_running() {
+        let bus = TestBus::new().expect("dbus-daemon is needed to run the D-Bus tests");
+
+        async_io::block_on(async {
+            let _other = bus
+                .builder()
+                .name(NOTIFICATIONS_NAME)
+                .unwrap()
+                .build()
+                .await
+                .unwrap();
+            let conn = bus.builder().build().await.unwrap();
+            let daemon = Daemon::new(&conn).await.unwrap();
+            assert!(daemon.server.is_none());
+            assert!(conn
+                .object_server()
+                .interface::<_, NotificationServer>(NOTIFICATIONS_PATH)
+                .await
+                .is_err());
+        });
     }
 }
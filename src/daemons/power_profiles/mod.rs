@@ -0,0 +1,216 @@
+#![allow(unused)]
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use serde::Deserialize;
+use zbus::zvariant::OwnedValue;
+
+pub mod service;
+
+/// A `power-profiles-daemon` daemon, used to switch between power saving and performance.
+/// See the project page: <https://gitlab.freedesktop.org/upower/power-profiles-daemon>
+pub struct Daemon {
+    proxy: service::PowerProfilesProxy<'static>,
+}
+
+impl Daemon {
+    /// Get the currently active [`Profile`].
+    pub async fn active_profile(&self) -> zbus::Result<Profile> {
+        let profile = self.proxy.active_profile().await?;
+        Profile::from_name(&profile)
+            .ok_or_else(|| zbus::Error::Failure(format!("Unknown power profile: {profile}")))
+    }
+
+    /// Switch to another [`Profile`].
+    pub async fn set_active_profile(&self, profile: Profile) -> zbus::Result<()> {
+        info!(?profile, "Switching power profile");
+        self.proxy.set_active_profile(profile.name()).await
+    }
+
+    /// Get the profiles available on this system.
+    ///
+    /// Only [`Profile::Performance`] can be missing, when the hardware doesn't support it.
+    pub async fn profiles(&self) -> zbus::Result<Vec<Profile>> {
+        let profiles = self.proxy.profiles().await?;
+        Ok(profiles
+            .iter()
+            .filter_map(|profile| get_string(profile, "Profile"))
+            .filter_map(|name| Profile::from_name(&name))
+            .collect())
+    }
+
+    /// Get why the performance profile is running degraded, if it is.
+    pub async fn performance_degraded(&self) -> zbus::Result<Option<Degradation>> {
+        let reason = self.proxy.performance_degraded().await?;
+        Ok(match reason.as_str() {
+            "" => None,
+            "lap-detected" => Some(Degradation::LapDetected),
+            "high-operating-temperature" => Some(Degradation::HighOperatingTemperature),
+            _ => Some(Degradation::Other(reason)),
+        })
+    }
+
+    /// Get the profile holds requested by applications.
+    ///
+    /// While there's a hold, the active profile is the one requested by the application, and
+    /// changing it releases all the holds.
+    pub async fn active_profile_holds(&self) -> zbus::Result<Vec<Hold>> {
+        let holds = self.proxy.active_profile_holds().await?;
+        Ok(holds
+            .iter()
+            .filter_map(|hold| {
+                let profile = Profile::from_name(&get_string(hold, "Profile")?)?;
+                Some(Hold {
+                    profile,
+                    reason: get_string(hold, "Reason").unwrap_or_default(),
+                    application_id: get_string(hold, "ApplicationId").unwrap_or_default(),
+                })
+            })
+            .collect())
+    }
+
+    /// Get the underlying [`zbus::Proxy`] powering this daemon.
+    pub fn proxy(&self) -> &service::PowerProfilesProxy<'static> {
+        &self.proxy
+    }
+}
+
+fn get_string(dict: &HashMap<String, OwnedValue>, key: &str) -> Option<String> {
+    let value = dict.get(key)?.try_clone().ok()?;
+    String::try_from(value).ok()
+}
+
+/// A power profile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Profile {
+    PowerSaver,
+    Balanced,
+    Performance,
+}
+
+impl Profile {
+    pub const ALL: [Self; 3] = [Self::PowerSaver, Self::Balanced, Self::Performance];
+
+    /// Get the profile with this D-Bus name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "power-saver" => Some(Self::PowerSaver),
+            "balanced" => Some(Self::Balanced),
+            "performance" => Some(Self::Performance),
+            _ => None,
+        }
+    }
+
+    /// Get the D-Bus name of this profile.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::PowerSaver => "power-saver",
+            Self::Balanced => "balanced",
+            Self::Performance => "performance",
+        }
+    }
+
+    /// Get a human readable label for this profile.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::PowerSaver => "Power saver",
+            Self::Balanced => "Balanced",
+            Self::Performance => "Performance",
+        }
+    }
+
+    pub fn icon_name(self) -> &'static str {
+        match self {
+            Self::PowerSaver => "power-profile-power-saver-symbolic",
+            Self::Balanced => "power-profile-balanced-symbolic",
+            Self::Performance => "power-profile-performance-symbolic",
+        }
+    }
+}
+
+/// The reason why the performance profile is running degraded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Degradation {
+    /// The computer is sitting on someone's lap.
+    LapDetected,
+    /// The computer is too hot.
+    HighOperatingTemperature,
+    /// A reason we don't know about yet.
+    Other(String),
+}
+
+impl Degradation {
+    /// Describe this degradation to the user.
+    pub fn describe(&self) -> String {
+        match self {
+            Self::LapDetected => {
+                "Performance is limited because the computer is on your lap".to_string()
+            }
+            Self::HighOperatingTemperature => {
+                "Performance is limited because the computer is too hot".to_string()
+            }
+            Self::Other(reason) => format!("Performance is limited ({reason})"),
+        }
+    }
+}
+
+/// A profile hold, requested by an application.
+#[derive(Debug, Clone)]
+pub struct Hold {
+    pub profile: Profile,
+    pub reason: String,
+    pub application_id: String,
+}
+
+static INSTANCE: OnceLock<Daemon> = OnceLock::new();
+
+pub fn get() -> &'static Daemon {
+    INSTANCE.get().expect("daemons::start() must be called")
+}
+
+pub async fn start() -> anyhow::Result<()> {
+    if INSTANCE.get().is_some() {
+        return Ok(());
+    }
+
+    // NOTE: power-profiles-daemon is optional, if it's missing calls to the proxy will just fail
+    // and the widgets using it will stay hidden.
+    let conn = super::system_connection().inner();
+    let proxy = service::PowerProfilesProxy::new(conn).await?;
+
+    // NOTE: If we already started he handled it above.
+    let _ = INSTANCE.set(Daemon { proxy });
+
+    Ok(())
+}
+
+/// Switch to the profile from the config for this power source, if any.
+pub async fn apply_power_source(on_battery: bool) {
+    let config = &crate::config::get().power.profiles;
+    let profile = if on_battery {
+        config.on_battery
+    } else {
+        config.on_ac
+    };
+    let Some(profile) = profile else {
+        return;
+    };
+
+    let daemon = get();
+    match daemon.profiles().await {
+        Ok(profiles) if profiles.contains(&profile) => (),
+        Ok(_) => {
+            warn!(?profile, "Configured power profile is not available");
+            return;
+        }
+        Err(err) => {
+            warn!(?err, "Failed to get available power profiles");
+            return;
+        }
+    }
+
+    if let Err(err) = daemon.set_active_profile(profile).await {
+        error!(?err, ?profile, "Failed to switch power profile");
+    }
+}
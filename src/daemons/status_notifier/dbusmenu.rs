@@ -0,0 +1,128 @@
+//! # D-Bus interface proxy for: `com.canonical.dbusmenu`
+//!
+//! This code was generated by `zbus-xmlgen` `5.1.0` from D-Bus introspection data.
+//! Source: `com.canonical.dbusmenu.xml`.
+//!
+//! You may prefer to adapt it, instead of using it verbatim.
+//!
+//! More information can be found in the [Writing a client proxy] section of the zbus
+//! documentation.
+//!
+//! This type implements the [D-Bus standard interfaces], (`org.freedesktop.DBus.*`) for which the
+//! following zbus API can be used:
+//!
+//! * [`zbus::fdo::PropertiesProxy`]
+//! * [`zbus::fdo::IntrospectableProxy`]
+//! * [`zbus::fdo::PeerProxy`]
+//!
+//! Consequently `zbus-xmlgen` did not generate code for the above interfaces.
+//!
+//! [Writing a client proxy]: https://dbus2.github.io/zbus/client.html
+//! [D-Bus standard interfaces]: https://dbus.freedesktop.org/doc/dbus-specification.html#standard-interfaces,
+
+use std::collections::HashMap;
+
+use zbus::proxy;
+use zbus::zvariant::{OwnedValue, Value};
+
+/// A menu layout sent over D-Bus, as `(id, properties, children)`.
+///
+/// The children are variants of layouts themselves.
+pub type RawLayout = (i32, HashMap<String, OwnedValue>, Vec<OwnedValue>);
+
+#[proxy(interface = "com.canonical.dbusmenu", assume_defaults = false)]
+pub trait DBusMenu {
+    /// AboutToShow method
+    fn about_to_show(&self, id: i32) -> zbus::Result<bool>;
+
+    /// Event method
+    fn event(&self, id: i32, event_id: &str, data: &Value<'_>, timestamp: u32) -> zbus::Result<()>;
+
+    /// GetLayout method
+    fn get_layout(
+        &self,
+        parent_id: i32,
+        recursion_depth: i32,
+        property_names: &[&str],
+    ) -> zbus::Result<(u32, RawLayout)>;
+
+    /// ItemsPropertiesUpdated signal
+    #[zbus(signal)]
+    fn items_properties_updated(
+        &self,
+        updated_props: Vec<(i32, HashMap<String, OwnedValue>)>,
+        removed_props: Vec<(i32, Vec<String>)>,
+    ) -> zbus::Result<()>;
+
+    /// LayoutUpdated signal
+    #[zbus(signal)]
+    fn layout_updated(&self, revision: u32, parent: i32) -> zbus::Result<()>;
+
+    /// Status property
+    #[zbus(property)]
+    fn status(&self) -> zbus::Result<String>;
+
+    /// Version property
+    #[zbus(property)]
+    fn version(&self) -> zbus::Result<u32>;
+}
+
+/// An entry of a menu exported through `com.canonical.dbusmenu`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MenuItem {
+    pub id: i32,
+    /// The label, with `_` before the mnemonic character.
+    pub label: String,
+    pub enabled: bool,
+    pub visible: bool,
+    pub is_separator: bool,
+    pub icon_name: Option<String>,
+    pub toggle: Option<Toggle>,
+    pub children: Vec<MenuItem>,
+}
+
+/// The state of a menu entry that can be toggled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Toggle {
+    Checkmark(bool),
+    Radio(bool),
+}
+
+impl MenuItem {
+    /// Parse a menu layout, along with all of its children.
+    pub fn from_layout((id, properties, children): RawLayout) -> Self {
+        let string = |name: &str| {
+            properties
+                .get(name)
+                .and_then(|value| String::try_from(value.try_clone().ok()?).ok())
+        };
+        let boolean = |name: &str| {
+            properties
+                .get(name)
+                .and_then(|value| bool::try_from(value).ok())
+        };
+        let toggle_state = properties
+            .get("toggle-state")
+            .and_then(|value| i32::try_from(value).ok())
+            .is_some_and(|state| state == 1);
+
+        Self {
+            id,
+            label: string("label").unwrap_or_default(),
+            enabled: boolean("enabled").unwrap_or(true),
+            visible: boolean("visible").unwrap_or(true),
+            is_separator: string("type").is_some_and(|type_| type_ == "separator"),
+            icon_name: string("icon-name").filter(|name| !name.is_empty()),
+            toggle: match string("toggle-type").as_deref() {
+                Some("checkmark") => Some(Toggle::Checkmark(toggle_state)),
+                Some("radio") => Some(Toggle::Radio(toggle_state)),
+                _ => None,
+            },
+            children: children
+                .into_iter()
+                .filter_map(|child| RawLayout::try_from(child).ok())
+                .map(Self::from_layout)
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! # D-Bus interface proxy for: `org.kde.StatusNotifierItem`
+//!
+//! This code was generated by `zbus-xmlgen` `5.1.0` from D-Bus introspection data.
+//! Source: `org.kde.StatusNotifierItem.xml`.
+//!
+//! You may prefer to adapt it, instead of using it verbatim.
+//!
+//! More information can be found in the [Writing a client proxy] section of the zbus
+//! documentation.
+//!
+//! This type implements the [D-Bus standard interfaces], (`org.freedesktop.DBus.*`) for which the
+//! following zbus API can be used:
+//!
+//! * [`zbus::fdo::PropertiesProxy`]
+//! * [`zbus::fdo::IntrospectableProxy`]
+//! * [`zbus::fdo::PeerProxy`]
+//!
+//! Consequently `zbus-xmlgen` did not generate code for the above interfaces.
+//!
+//! [Writing a client proxy]: https://dbus2.github.io/zbus/client.html
+//! [D-Bus standard interfaces]: https://dbus.freedesktop.org/doc/dbus-specification.html#standard-interfaces,
+
+use zbus::proxy;
+
+/// An icon sent over D-Bus, as `(width, height, ARGB32 data in network byte order)`.
+pub type RawPixmap = (i32, i32, Vec<u8>);
+
+/// A tooltip sent over D-Bus, as `(icon name, icon pixmaps, title, description)`.
+pub type RawToolTip = (String, Vec<RawPixmap>, String, String);
+
+// NOTE: Items never emit PropertiesChanged, but the New* signals below instead, so caching the
+// properties would only give us stale values.
+#[proxy(interface = "org.kde.StatusNotifierItem", assume_defaults = false)]
+pub trait StatusNotifierItem {
+    /// Activate method
+    fn activate(&self, x: i32, y: i32) -> zbus::Result<()>;
+
+    /// ContextMenu method
+    fn context_menu(&self, x: i32, y: i32) -> zbus::Result<()>;
+
+    /// Scroll method
+    fn scroll(&self, delta: i32, orientation: &str) -> zbus::Result<()>;
+
+    /// SecondaryActivate method
+    fn secondary_activate(&self, x: i32, y: i32) -> zbus::Result<()>;
+
+    /// NewAttentionIcon signal
+    #[zbus(signal)]
+    fn new_attention_icon(&self) -> zbus::Result<()>;
+
+    /// NewIcon signal
+    #[zbus(signal)]
+    fn new_icon(&self) -> zbus::Result<()>;
+
+    /// NewOverlayIcon signal
+    #[zbus(signal)]
+    fn new_overlay_icon(&self) -> zbus::Result<()>;
+
+    /// NewStatus signal
+    #[zbus(signal)]
+    fn new_status(&self, status: &str) -> zbus::Result<()>;
+
+    /// NewTitle signal
+    #[zbus(signal)]
+    fn new_title(&self) -> zbus::Result<()>;
+
+    /// NewToolTip signal
+    #[zbus(signal)]
+    fn new_tool_tip(&self) -> zbus::Result<()>;
+
+    /// AttentionIconName property
+    #[zbus(property)]
+    fn attention_icon_name(&self) -> zbus::Result<String>;
+
+    /// AttentionIconPixmap property
+    #[zbus(property)]
+    fn attention_icon_pixmap(&self) -> zbus::Result<Vec<RawPixmap>>;
+
+    /// Category property
+    #[zbus(property)]
+    fn category(&self) -> zbus::Result<String>;
+
+    /// IconName property
+    #[zbus(property)]
+    fn icon_name(&self) -> zbus::Result<String>;
+
+    /// IconPixmap property
+    #[zbus(property)]
+    fn icon_pixmap(&self) -> zbus::Result<Vec<RawPixmap>>;
+
+    /// IconThemePath property
+    #[zbus(property)]
+    fn icon_theme_path(&self) -> zbus::Result<String>;
+
+    /// Id property
+    #[zbus(property)]
+    fn id(&self) -> zbus::Result<String>;
+
+    /// ItemIsMenu property
+    #[zbus(property)]
+    fn item_is_menu(&self) -> zbus::Result<bool>;
+
+    /// Menu property
+    #[zbus(property)]
+    fn menu(&self) -> zbus::Result<zbus::zvariant::OwnedObjectPath>;
+
+    /// Status property
+    #[zbus(property)]
+    fn status(&self) -> zbus::Result<String>;
+
+    /// Title property
+    #[zbus(property)]
+    fn title(&self) -> zbus::Result<String>;
+
+    /// ToolTip property
+    #[zbus(property)]
+    fn tool_tip(&self) -> zbus::Result<RawToolTip>;
+}
@@ -0,0 +1,363 @@
+//! StatusNotifierItem daemon, IE. the system tray.
+//!
+//! We serve the `org.kde.StatusNotifierWatcher` ourselves, unless another program already does,
+//! and register as a host to follow the items of the watcher for the panel to display them.
+//! See the specification: <https://www.freedesktop.org/wiki/Specifications/StatusNotifierItem/>
+
+use std::sync::{Arc, OnceLock, RwLock};
+
+use futures_util::StreamExt;
+use tokio::sync::broadcast;
+
+pub mod dbusmenu;
+pub mod item;
+pub mod watcher;
+
+/// The size we pick pixmaps for, in pixels.
+const ICON_SIZE: i32 = 16;
+
+pub struct Daemon {
+    items: RwLock<Vec<Item>>,
+    sender: broadcast::Sender<Event>,
+}
+
+impl Daemon {
+    /// Get all the items currently registered.
+    pub fn items(&self) -> Vec<Item> {
+        self.items.read().unwrap().clone()
+    }
+
+    /// Get an item by its ID.
+    pub fn item(&self, id: &str) -> Option<Item> {
+        let items = self.items.read().unwrap();
+        items.iter().find(|item| &*item.id == id).cloned()
+    }
+
+    /// Subscribe to the events of this [`Daemon`].
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+
+    async fn add_item(&self, service: &str) {
+        if self.item(service).is_some() {
+            return;
+        }
+
+        match Item::new(service).await {
+            Ok(item) => {
+                self.items.write().unwrap().push(item.clone());
+                let _ = self.sender.send(Event::ItemAdded(item));
+            }
+            Err(err) => warn!(?err, ?service, "Failed to add status notifier item"),
+        }
+    }
+
+    fn remove_item(&self, service: &str) {
+        let mut items = self.items.write().unwrap();
+        let len = items.len();
+        items.retain(|item| &*item.id != service);
+        if items.len() != len {
+            let _ = self.sender.send(Event::ItemRemoved(service.into()));
+        }
+    }
+}
+
+/// An item of the system tray.
+///
+/// The unique ID of the item is what it registered to the watcher, IE.
+/// `:1.42/org/ayatana/NotificationItem/nextcloud`
+#[derive(Clone, Debug)]
+pub struct Item {
+    id: Arc<str>,
+    proxy: item::StatusNotifierItemProxy<'static>,
+}
+
+impl Item {
+    async fn new(service: &str) -> zbus::Result<Self> {
+        let conn = super::session_connection().inner();
+        let bus_name = watcher::item_bus_name(service);
+        let path = &service[bus_name.len()..];
+        let proxy = item::StatusNotifierItemProxy::builder(conn)
+            .destination(bus_name.to_string())?
+            .path(path.to_string())?
+            .cache_properties(zbus::proxy::CacheProperties::No)
+            .build()
+            .await?;
+
+        Ok(Self {
+            id: service.into(),
+            proxy,
+        })
+    }
+
+    /// Get the unique ID of this [`Item`].
+    pub fn id(&self) -> Arc<str> {
+        Arc::clone(&self.id)
+    }
+
+    /// Get the underlying [`zbus::Proxy`] behind this interface.
+    pub fn proxy(&self) -> &item::StatusNotifierItemProxy<'static> {
+        &self.proxy
+    }
+
+    /// Get the status of this item.
+    pub async fn status(&self) -> Status {
+        match self.proxy.status().await.as_deref() {
+            Ok("Passive") => Status::Passive,
+            Ok("NeedsAttention") => Status::NeedsAttention,
+            _ => Status::Active,
+        }
+    }
+
+    /// Get the icon to display for this item, depending on its status.
+    pub async fn icon(&self) -> Option<Icon> {
+        if self.status().await == Status::NeedsAttention {
+            let name = self.proxy.attention_icon_name().await.unwrap_or_default();
+            let pixmaps = self.proxy.attention_icon_pixmap().await.unwrap_or_default();
+            if let Some(icon) = self.make_icon(name, pixmaps).await {
+                return Some(icon);
+            }
+        }
+
+        let name = self.proxy.icon_name().await.unwrap_or_default();
+        let pixmaps = self.proxy.icon_pixmap().await.unwrap_or_default();
+        self.make_icon(name, pixmaps).await
+    }
+
+    async fn make_icon(&self, name: String, pixmaps: Vec<item::RawPixmap>) -> Option<Icon> {
+        if !name.is_empty() {
+            // Icons can be paths too, even if the specification does not allow it.
+            let theme_path = self
+                .proxy
+                .icon_theme_path()
+                .await
+                .ok()
+                .filter(|path| !path.is_empty());
+            return Some(Icon::Name { name, theme_path });
+        }
+
+        Pixmap::best_for_size(pixmaps, ICON_SIZE).map(Icon::Pixmap)
+    }
+
+    /// Get the tooltip of this item, as `(title, description)`.
+    ///
+    /// The description may contain markup.
+    pub async fn tooltip(&self) -> Option<(String, String)> {
+        match self.proxy.tool_tip().await {
+            Ok((_, _, title, description)) if !title.is_empty() || !description.is_empty() => {
+                Some((title, description))
+            }
+            // Fallback to the title, which is what most items expect to be shown.
+            _ => self
+                .proxy
+                .title()
+                .await
+                .ok()
+                .filter(|title| !title.is_empty())
+                .map(|title| (title, String::new())),
+        }
+    }
+
+    /// Get the menu exported by this item, if any.
+    pub async fn menu(&self) -> Option<dbusmenu::DBusMenuProxy<'static>> {
+        let path = self.proxy.menu().await.ok()?;
+        if path.as_str() == "/" {
+            return None;
+        }
+
+        dbusmenu::DBusMenuProxy::builder(self.proxy.inner().connection())
+            .destination(self.proxy.inner().destination().to_owned())
+            .ok()?
+            .path(path)
+            .ok()?
+            .cache_properties(zbus::proxy::CacheProperties::No)
+            .build()
+            .await
+            .ok()
+    }
+}
+
+/// The status of an [`Item`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    /// The item does not need to be shown.
+    Passive,
+    Active,
+    /// The item wants the user to look at it.
+    NeedsAttention,
+}
+
+/// An icon of an [`Item`].
+#[derive(Clone, Debug)]
+pub enum Icon {
+    /// A named icon, to look up in the icon theme and in the given path.
+    Name {
+        name: String,
+        theme_path: Option<String>,
+    },
+    Pixmap(Pixmap),
+}
+
+/// A raw image, in RGBA with straight alpha.
+#[derive(Clone, Debug)]
+pub struct Pixmap {
+    pub width: i32,
+    pub height: i32,
+    pub data: Vec<u8>,
+}
+
+impl Pixmap {
+    /// Pick the smallest pixmap that's at least `size` wide, or the largest one, and convert it.
+    fn best_for_size(pixmaps: Vec<item::RawPixmap>, size: i32) -> Option<Self> {
+        let (width, height, data) = pixmaps
+            .into_iter()
+            .filter(|(width, height, data)| {
+                // Sizes come from other programs, they can be anything.
+                let (Ok(width), Ok(height)) = (usize::try_from(*width), usize::try_from(*height))
+                else {
+                    return false;
+                };
+                let len = width.checked_mul(height).and_then(|len| len.checked_mul(4));
+                width > 0 && height > 0 && len == Some(data.len())
+            })
+            .min_by_key(|(width, _, _)| {
+                if *width >= size {
+                    (0, *width)
+                } else {
+                    (1, -*width)
+                }
+            })?;
+
+        Some(Self {
+            width,
+            height,
+            data: argb_to_rgba(&data),
+        })
+    }
+}
+
+/// Convert pixmap data to RGBA.
+///
+/// Pixmaps are ARGB32 in network byte order, IE. bytes are A, R, G, B.
+fn argb_to_rgba(data: &[u8]) -> Vec<u8> {
+    data.chunks_exact(4)
+        .flat_map(|argb| [argb[1], argb[2], argb[3], argb[0]])
+        .collect()
+}
+
+/// An event sent by the status notifier [`Daemon`].
+#[derive(Clone, Debug)]
+pub enum Event {
+    ItemAdded(Item),
+    ItemRemoved(Arc<str>),
+}
+
+static INSTANCE: OnceLock<Daemon> = OnceLock::new();
+
+pub fn get() -> &'static Daemon {
+    INSTANCE.get().expect("daemons::start() must be called")
+}
+
+pub async fn start() -> anyhow::Result<()> {
+    if INSTANCE.get().is_some() {
+        return Ok(());
+    }
+
+    let conn = super::session_connection().inner();
+    if !watcher::serve(conn).await? {
+        info!("Another status notifier watcher is running, using it instead");
+    }
+
+    let host_name = format!("org.kde.StatusNotifierHost-{}", std::process::id());
+    conn.request_name(host_name.as_str()).await?;
+
+    let watcher = watcher::StatusNotifierWatcherProxy::new(conn).await?;
+    // Subscribe before listing items so that we don't miss anything in between.
+    let mut registered = watcher.receive_status_notifier_item_registered().await?;
+    let mut unregistered = watcher.receive_status_notifier_item_unregistered().await?;
+    watcher.register_status_notifier_host(&host_name).await?;
+
+    let (sender, _) = broadcast::channel(32);
+    let daemon = Daemon {
+        items: RwLock::new(vec![]),
+        sender,
+    };
+    for service in watcher
+        .registered_status_notifier_items()
+        .await
+        .unwrap_or_default()
+    {
+        daemon.add_item(&service).await;
+    }
+
+    // NOTE: If we already started he handled it above.
+    let _ = INSTANCE.set(daemon);
+
+    super::spawn("status-notifier-host", async move {
+        loop {
+            futures_util::select! {
+                signal = registered.next() => {
+                    let Some(signal) = signal else { break };
+                    match signal.args() {
+                        Ok(args) => get().add_item(args.service).await,
+                        Err(err) => warn!(?err, "Invalid StatusNotifierItemRegistered signal"),
+                    }
+                }
+                signal = unregistered.next() => {
+                    let Some(signal) = signal else { break };
+                    match signal.args() {
+                        Ok(args) => get().remove_item(args.service),
+                        Err(err) => warn!(?err, "Invalid StatusNotifierItemUnregistered signal"),
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn argb_conversion() {
+        assert_eq!(
+            argb_to_rgba(&[0xff, 0x10, 0x20, 0x30, 0x80, 0xaa, 0xbb, 0xcc]),
+            [0x10, 0x20, 0x30, 0xff, 0xaa, 0xbb, 0xcc, 0x80]
+        );
+        assert!(argb_to_rgba(&[]).is_empty());
+    }
+
+    #[test]
+    fn best_pixmap() {
+        let pixmap = |size: i32| (size, size, vec![0; (size * size * 4) as usize]);
+        let best = |pixmaps: Vec<item::RawPixmap>, size| {
+            Pixmap::best_for_size(pixmaps, size).map(|pixmap| (pixmap.width, pixmap.height))
+        };
+
+        // The smallest one large enough.
+        assert_eq!(
+            best(vec![pixmap(64), pixmap(22), pixmap(32)], 24),
+            Some((32, 32))
+        );
+        assert_eq!(best(vec![pixmap(64), pixmap(24)], 24), Some((24, 24)));
+        // Or the largest one.
+        assert_eq!(best(vec![pixmap(16), pixmap(22)], 24), Some((22, 22)));
+        assert_eq!(best(vec![], 24), None);
+
+        // Broken pixmaps are skipped.
+        assert_eq!(best(vec![(2, 2, vec![0; 15]), pixmap(1)], 24), Some((1, 1)));
+        assert_eq!(best(vec![(0, 0, vec![]), (-1, -1, vec![0; 4])], 24), None);
+        assert_eq!(best(vec![(i32::MAX, i32::MAX, vec![0; 4])], 24), None);
+    }
+
+    #[test]
+    fn pixmap_data_is_converted() {
+        let pixmap = Pixmap::best_for_size(vec![(1, 1, vec![0x80, 1, 2, 3])], 24).unwrap();
+        assert_eq!(pixmap.data, [1, 2, 3, 0x80]);
+    }
+}
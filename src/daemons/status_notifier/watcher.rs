@@ -0,0 +1,351 @@
+//! Our `org.kde.StatusNotifierWatcher` implementation, and a proxy to talk to any watcher.
+//!
+//! The watcher keeps the list of registered items, and tells hosts (the programs displaying them)
+//! when they come and go. There's a single watcher for the whole session, but there can be many
+//! hosts.
+
+use futures_util::StreamExt;
+use zbus::message::Header;
+use zbus::object_server::SignalEmitter;
+use zbus::proxy;
+
+pub const WATCHER_NAME: &str = "org.kde.StatusNotifierWatcher";
+pub const WATCHER_PATH: &str = "/StatusNotifierWatcher";
+/// Where items are served when they only register their bus name.
+const DEFAULT_ITEM_PATH: &str = "/StatusNotifierItem";
+
+#[derive(Default, Debug)]
+pub(super) struct Watcher {
+    /// The registered items, as `{bus name}{object path}`.
+    items: Vec<String>,
+    /// The bus names of the registered hosts.
+    hosts: Vec<String>,
+}
+
+impl Watcher {
+    /// Forget about everything registered by a bus name that left the bus.
+    ///
+    /// Failing to emit a signal doesn't stop us from emitting the next ones.
+    async fn remove_bus_name(&mut self, bus_name: &str, emitter: &SignalEmitter<'_>) {
+        let (removed, kept) = std::mem::take(&mut self.items)
+            .into_iter()
+            .partition::<Vec<_>, _>(|item| item_bus_name(item) == bus_name);
+        self.items = kept;
+        for item in &removed {
+            debug!(?item, "Status notifier item unregistered");
+            if let Err(err) = Self::status_notifier_item_unregistered(emitter, item).await {
+                error!(?err, ?item, "Failed to emit StatusNotifierItemUnregistered");
+            }
+        }
+        if !removed.is_empty() {
+            if let Err(err) = self.registered_status_notifier_items_changed(emitter).await {
+                error!(?err, "Failed to emit RegisteredStatusNotifierItems change");
+            }
+        }
+
+        let hosts_len = self.hosts.len();
+        self.hosts.retain(|host| host != bus_name);
+        if self.hosts.len() != hosts_len {
+            if let Err(err) = Self::status_notifier_host_unregistered(emitter).await {
+                error!(?err, "Failed to emit StatusNotifierHostUnregistered");
+            }
+            if let Err(err) = self
+                .is_status_notifier_host_registered_changed(emitter)
+                .await
+            {
+                error!(?err, "Failed to emit IsStatusNotifierHostRegistered change");
+            }
+        }
+    }
+}
+
+#[zbus::interface(name = "org.kde.StatusNotifierWatcher")]
+impl Watcher {
+    async fn register_status_notifier_item(
+        &mut self,
+        service: &str,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> zbus::fdo::Result<()> {
+        let sender = header
+            .sender()
+            .ok_or_else(|| zbus::fdo::Error::InvalidArgs("Unknown sender".to_string()))?;
+        // Some implementations (libappindicator) register an object path, others a bus name.
+        let item = if service.starts_with('/') {
+            format!("{sender}{service}")
+        } else {
+            format!("{service}{DEFAULT_ITEM_PATH}")
+        };
+
+        if !self.items.contains(&item) {
+            debug!(?item, "Status notifier item registered");
+            self.items.push(item.clone());
+            Self::status_notifier_item_registered(&emitter, &item).await?;
+            self.registered_status_notifier_items_changed(&emitter)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn register_status_notifier_host(
+        &mut self,
+        service: &str,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> zbus::fdo::Result<()> {
+        if !self.hosts.iter().any(|host| host == service) {
+            self.hosts.push(service.to_string());
+            Self::status_notifier_host_registered(&emitter).await?;
+            self.is_status_notifier_host_registered_changed(&emitter)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    #[zbus(property)]
+    fn registered_status_notifier_items(&self) -> Vec<String> {
+        self.items.clone()
+    }
+
+    #[zbus(property)]
+    fn is_status_notifier_host_registered(&self) -> bool {
+        !self.hosts.is_empty()
+    }
+
+    #[zbus(property)]
+    fn protocol_version(&self) -> i32 {
+        0
+    }
+
+    #[zbus(signal)]
+    async fn status_notifier_item_registered(
+        emitter: &SignalEmitter<'_>,
+        service: &str,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn status_notifier_item_unregistered(
+        emitter: &SignalEmitter<'_>,
+        service: &str,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn status_notifier_host_registered(emitter: &SignalEmitter<'_>) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn status_notifier_host_unregistered(emitter: &SignalEmitter<'_>) -> zbus::Result<()>;
+}
+
+/// Get the bus name of an item registered as `{bus name}{object path}`.
+pub fn item_bus_name(item: &str) -> &str {
+    item.split_once('/').map_or(item, |(bus_name, _)| bus_name)
+}
+
+/// Serve our watcher, unregistering items and hosts when their program exits.
+///
+/// Returns `false` if another program already is the watcher.
+pub(super) async fn serve(conn: &zbus::Connection) -> anyhow::Result<bool> {
+    conn.object_server()
+        .at(WATCHER_PATH, Watcher::default())
+        .await?;
+    let reply = conn
+        .request_name_with_flags(WATCHER_NAME, zbus::fdo::RequestNameFlags::DoNotQueue.into())
+        .await;
+    if !matches!(reply, Ok(zbus::fdo::RequestNameReply::PrimaryOwner)) {
+        conn.object_server()
+            .remove::<Watcher, _>(WATCHER_PATH)
+            .await?;
+        return Ok(false);
+    }
+
+    let dbus_proxy = zbus::fdo::DBusProxy::new(conn).await?;
+    let mut name_owner_changes = dbus_proxy.receive_name_owner_changed().await?;
+    let watcher = conn
+        .object_server()
+        .interface::<_, Watcher>(WATCHER_PATH)
+        .await?;
+    super::super::spawn("status-notifier-watcher", async move {
+        while let Some(changed) = name_owner_changes.next().await {
+            let args = match changed.args() {
+                Ok(args) => args,
+                Err(err) => {
+                    warn!(?err, "Invalid NameOwnerChanged signal");
+                    continue;
+                }
+            };
+            if args.new_owner.is_some() {
+                continue;
+            }
+
+            let emitter = watcher.signal_emitter();
+            watcher
+                .get_mut()
+                .await
+                .remove_bus_name(&args.name, emitter)
+                .await;
+        }
+
+        Ok(())
+    });
+
+    Ok(true)
+}
+
+#[proxy(
+    interface = "org.kde.StatusNotifierWatcher",
+    default_service = "org.kde.StatusNotifierWatcher",
+    default_path = "/StatusNotifierWatcher"
+)]
+pub trait StatusNotifierWatcher {
+    /// RegisterStatusNotifierHost method
+    fn register_status_notifier_host(&self, service: &str) -> zbus::Result<()>;
+
+    /// RegisterStatusNotifierItem method
+    fn register_status_notifier_item(&self, service: &str) -> zbus::Result<()>;
+
+    /// StatusNotifierItemRegistered signal
+    #[zbus(signal)]
+    fn status_notifier_item_registered(&self, service: &str) -> zbus::Result<()>;
+
+    /// StatusNotifierItemUnregistered signal
+    #[zbus(signal)]
+    fn status_notifier_item_unregistered(&self, service: &str) -> zbus::Result<()>;
+
+    /// RegisteredStatusNotifierItems property
+    #[zbus(property)]
+    fn registered_status_notifier_items(&self) -> zbus::Result<Vec<String>>;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::pin::pin;
+    use std::time::Duration;
+
+    use futures_util::future::{self, Either};
+
+    use super::*;
+    use crate::daemons::test_bus::TestBus;
+
+    /// How long we wait for a signal before failing.
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Wait for a future, failing the test after [`TIMEOUT`].
+    async fn timeout<T>(future: impl Future<Output = T>) -> T {
+        match future::select(pin!(future), pin!(async_io::Timer::after(TIMEOUT))).await {
+            Either::Left((output, _)) => output,
+            Either::Right(_) => panic!("Timed out"),
+        }
+    }
+
+    #[test]
+    fn item_bus_names() {
+        assert_eq!(
+            item_bus_name(":1.42/org/ayatana/NotificationItem/nm"),
+            ":1.42"
+        );
+        assert_eq!(
+            item_bus_name("org.kde.StatusNotifierItem-4242-1/StatusNotifierItem"),
+            "org.kde.StatusNotifierItem-4242-1"
+        );
+        assert_eq!(item_bus_name(":1.42"), ":1.42");
+    }
+
+    #[test]
+    fn registrations() {
+        let bus = TestBus::new().expect("dbus-daemon is needed to run the D-Bus tests");
+
+        async_io::block_on(async {
+            let conn = bus.builder().build().await.unwrap();
+            assert!(serve(&conn).await.unwrap());
+            let watcher = conn
+                .object_server()
+                .interface::<_, Watcher>(WATCHER_PATH)
+                .await
+                .unwrap();
+            // There's a single watcher on the bus.
+            let other_conn = bus.builder().build().await.unwrap();
+            assert!(!serve(&other_conn).await.unwrap());
+
+            let host_conn = bus.builder().build().await.unwrap();
+            let host = StatusNotifierWatcherProxy::new(&host_conn).await.unwrap();
+            let mut registered = host
+                .receive_status_notifier_item_registered()
+                .await
+                .unwrap();
+            let mut unregistered = host
+                .receive_status_notifier_item_unregistered()
+                .await
+                .unwrap();
+            host.register_status_notifier_host(host_conn.unique_name().unwrap())
+                .await
+                .unwrap();
+
+            // Items either register their object path, or their bus name.
+            let path_item_conn = bus.builder().build().await.unwrap();
+            let path_item = StatusNotifierWatcherProxy::new(&path_item_conn)
+                .await
+                .unwrap();
+            path_item
+                .register_status_notifier_item("/org/ayatana/NotificationItem/test")
+                .await
+                .unwrap();
+            path_item
+                .register_status_notifier_item("/org/ayatana/NotificationItem/test")
+                .await
+                .unwrap();
+            let path_item_name = format!(
+                "{}/org/ayatana/NotificationItem/test",
+                path_item_conn.unique_name().unwrap()
+            );
+
+            let name_item_conn = bus
+                .builder()
+                .name("org.kde.StatusNotifierItem-1-1")
+                .unwrap()
+                .build()
+                .await
+                .unwrap();
+            let name_item = StatusNotifierWatcherProxy::new(&name_item_conn)
+                .await
+                .unwrap();
+            name_item
+                .register_status_notifier_item("org.kde.StatusNotifierItem-1-1")
+                .await
+                .unwrap();
+            let name_item_name = "org.kde.StatusNotifierItem-1-1/StatusNotifierItem";
+
+            for expected in [&path_item_name, name_item_name] {
+                let signal = timeout(registered.next()).await.unwrap();
+                assert_eq!(signal.args().unwrap().service, expected);
+            }
+            {
+                let watcher = watcher.get().await;
+                assert_eq!(watcher.items, [path_item_name.as_str(), name_item_name]);
+                assert!(watcher.is_status_notifier_host_registered());
+            }
+
+            // Everything registered by a program goes away with it.
+            drop((name_item, name_item_conn));
+            let signal = timeout(unregistered.next()).await.unwrap();
+            assert_eq!(signal.args().unwrap().service, name_item_name);
+            assert_eq!(watcher.get().await.items, [path_item_name.as_str()]);
+
+            drop((path_item, path_item_conn));
+            let signal = timeout(unregistered.next()).await.unwrap();
+            assert_eq!(signal.args().unwrap().service, path_item_name);
+            assert!(watcher.get().await.items.is_empty());
+
+            let host_name = host_conn.unique_name().unwrap().to_string();
+            assert_eq!(watcher.get().await.hosts, [host_name]);
+            drop((host, registered, unregistered, host_conn));
+            timeout(async {
+                while watcher.get().await.is_status_notifier_host_registered() {
+                    async_io::Timer::after(Duration::from_millis(10)).await;
+                }
+            })
+            .await;
+        });
+    }
+}
@@ -0,0 +1,41 @@
+//! A private D-Bus session bus for the daemon tests.
+
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+
+/// A private session bus, stopped once dropped.
+pub struct TestBus {
+    dbus_daemon: Child,
+    address: String,
+}
+
+impl TestBus {
+    /// Start the bus, or return [`None`] if `dbus-daemon` is not available.
+    pub fn new() -> Option<Self> {
+        let mut dbus_daemon = Command::new("dbus-daemon")
+            .args(["--session", "--print-address", "--nofork"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+        let mut address = String::new();
+        BufReader::new(dbus_daemon.stdout.take()?)
+            .read_line(&mut address)
+            .ok()?;
+        Some(Self {
+            dbus_daemon,
+            address: address.trim().to_string(),
+        })
+    }
+
+    pub fn builder(&self) -> zbus::connection::Builder<'static> {
+        zbus::connection::Builder::address(self.address.as_str()).unwrap()
+    }
+}
+
+impl Drop for TestBus {
+    fn drop(&mut self) {
+        let _ = self.dbus_daemon.kill();
+        let _ = self.dbus_daemon.wait();
+    }
+}
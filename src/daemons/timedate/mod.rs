@@ -0,0 +1,38 @@
+//! systemd-timedated daemon, used to follow the system timezone.
+#![allow(unused)]
+use std::sync::OnceLock;
+
+pub mod service;
+
+pub struct Daemon {
+    proxy: service::TimedateProxy<'static>,
+}
+
+impl Daemon {
+    /// Get the underlying [`zbus::Proxy`] powering this daemon.
+    pub fn proxy(&self) -> &service::TimedateProxy<'static> {
+        &self.proxy
+    }
+}
+
+static INSTANCE: OnceLock<Daemon> = OnceLock::new();
+
+pub fn get() -> &'static Daemon {
+    INSTANCE.get().expect("daemons::start() must be called")
+}
+
+pub async fn start() -> anyhow::Result<()> {
+    if INSTANCE.get().is_some() {
+        return Ok(());
+    }
+
+    // NOTE: timedated gets started on demand by the bus, and exits when idle. It's running again
+    // when something changes the timezone, so we still get the change.
+    let conn = super::system_connection().inner();
+    let proxy = service::TimedateProxy::new(conn).await?;
+
+    // NOTE: If we already started he handled it above.
+    let _ = INSTANCE.set(Daemon { proxy });
+
+    Ok(())
+}
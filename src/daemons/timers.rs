@@ -0,0 +1,794 @@
+//! Timers daemon.
+//!
+//! Keeps the countdown timers, the alarms and the stopwatch, ringing them with a notification
+//! that can be snoozed. Everything is saved in the state directory, so it survives restarting the
+//! shell; timers that ended while the shell was not running ring right when it starts again.
+use std::collections::HashMap;
+use std::fs;
+use std::pin::pin;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
+
+use chrono::{DateTime, Datelike, Local, NaiveTime, TimeZone, Utc, Weekday};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use super::notifications::{self, Event, Urgency};
+
+/// The longest we wait before checking the timers again.
+///
+/// Waiting does not count the time spent suspended, so we have to check the clock regularly.
+const MAX_WAIT: Duration = Duration::from_secs(10);
+
+/// How late an alarm can still ring, IE. if the shell was not running or the computer was asleep.
+const MISSED_ALARM_GRACE: Duration = Duration::from_secs(15 * 60);
+
+const STATE_FILE: &str = "fht/shell/timers.json";
+
+static INSTANCE: OnceLock<Daemon> = OnceLock::new();
+
+pub(super) async fn start() -> anyhow::Result<()> {
+    if INSTANCE.get().is_some() {
+        return Ok(());
+    }
+
+    let daemon = Daemon::new(State::load());
+    // NOTE: If we already started he handled it above.
+    let _ = INSTANCE.set(daemon);
+
+    super::spawn("timers", async {
+        let daemon = get();
+        loop {
+            // Subscribe before ringing, so that changes made meanwhile wake us up.
+            let mut changes = daemon.state.subscribe();
+            let wait = daemon.ring_due().await.min(MAX_WAIT);
+            let timeout = pin!(async_io::Timer::after(wait));
+            let changed = pin!(changes.changed());
+            futures_util::future::select(timeout, changed).await;
+        }
+    });
+
+    super::spawn("timers-notifications", async {
+        let mut events = pin!(notifications::receive_events().await?);
+        while let Some(event) = events.next().await {
+            match event {
+                Event::ActionInvoked { id, action_key } => {
+                    let Some(ringing) = get().ringing.lock().unwrap().remove(&id) else {
+                        continue;
+                    };
+                    if action_key == "snooze" {
+                        get().snooze(ringing);
+                    }
+                }
+                // Dismissed or replaced, there's nothing to snooze anymore.
+                Event::Closed { id } => {
+                    get().ringing.lock().unwrap().remove(&id);
+                }
+            }
+        }
+        Ok(())
+    });
+
+    Ok(())
+}
+
+pub fn get() -> &'static Daemon {
+    INSTANCE.get().expect("daemons::start() must be called")
+}
+
+/// The timers daemon, to manage the timers, alarms and the stopwatch.
+pub struct Daemon {
+    state: watch::Sender<State>,
+    /// What the notifications we sent are ringing for, to snooze it from the notification.
+    ringing: Mutex<HashMap<u32, Ringing>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ringing {
+    Timer(u64),
+    Alarm(u64),
+}
+
+impl Daemon {
+    fn new(mut state: State) -> Self {
+        state.skip_missed_alarms(SystemTime::now());
+        Self {
+            state: watch::Sender::new(state),
+            ringing: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Subscribe to the changes of the timers, alarms and stopwatch.
+    pub fn subscribe(&self) -> watch::Receiver<State> {
+        self.state.subscribe()
+    }
+
+    /// Modify the state then save it.
+    fn modify(&self, modify: impl FnOnce(&mut State, SystemTime)) {
+        let now = SystemTime::now();
+        self.state.send_modify(|state| modify(state, now));
+        if let Err(err) = self.state.borrow().save() {
+            warn!(?err, "Failed to save timers");
+        }
+    }
+
+    /// Start a new countdown timer.
+    pub fn add_timer(&self, label: Option<String>, duration: Duration) {
+        self.modify(|state, now| {
+            let id = state.next_id();
+            state.timers.push(Timer {
+                id,
+                label,
+                duration,
+                state: TimerState::Running {
+                    ends_at: now + duration,
+                },
+            });
+        });
+    }
+
+    pub fn pause_timer(&self, id: u64) {
+        self.modify_timer(id, |timer, now| {
+            if let TimerState::Running { .. } = timer.state {
+                timer.state = TimerState::Paused {
+                    remaining: timer.remaining(now),
+                };
+            }
+        });
+    }
+
+    pub fn resume_timer(&self, id: u64) {
+        self.modify_timer(id, |timer, now| {
+            if let TimerState::Paused { remaining } = timer.state {
+                timer.state = TimerState::Running {
+                    ends_at: now + remaining,
+                };
+            }
+        });
+    }
+
+    /// Start the timer again from its full duration.
+    pub fn restart_timer(&self, id: u64) {
+        self.modify_timer(id, |timer, now| {
+            timer.state = TimerState::Running {
+                ends_at: now + timer.duration,
+            };
+        });
+    }
+
+    pub fn remove_timer(&self, id: u64) {
+        self.modify(|state, _| state.timers.retain(|timer| timer.id != id));
+    }
+
+    fn modify_timer(&self, id: u64, modify: impl FnOnce(&mut Timer, SystemTime)) {
+        self.modify(|state, now| {
+            if let Some(timer) = state.timers.iter_mut().find(|timer| timer.id == id) {
+                modify(timer, now);
+            }
+        });
+    }
+
+    /// Add an alarm ringing at this time of the day.
+    pub fn add_alarm(&self, label: Option<String>, time: NaiveTime, repeat: Repeat) {
+        self.modify(|state, now| {
+            let id = state.next_id();
+            let mut alarm = Alarm {
+                id,
+                label,
+                time,
+                repeat,
+                next_ring: None,
+            };
+            alarm.schedule(now);
+            state.alarms.push(alarm);
+        });
+    }
+
+    pub fn set_alarm_enabled(&self, id: u64, enabled: bool) {
+        self.modify(|state, now| {
+            let Some(alarm) = state.alarms.iter_mut().find(|alarm| alarm.id == id) else {
+                return;
+            };
+            if enabled {
+                alarm.schedule(now);
+            } else {
+                alarm.next_ring = None;
+            }
+        });
+    }
+
+    pub fn remove_alarm(&self, id: u64) {
+        self.modify(|state, _| state.alarms.retain(|alarm| alarm.id != id));
+    }
+
+    pub fn start_stopwatch(&self) {
+        self.modify(|state, now| {
+            state.stopwatch.started_at.get_or_insert(now);
+        });
+    }
+
+    pub fn pause_stopwatch(&self) {
+        self.modify(|state, now| {
+            let stopwatch = &mut state.stopwatch;
+            stopwatch.elapsed = stopwatch.elapsed(now);
+            stopwatch.started_at = None;
+        });
+    }
+
+    /// Record the current time of the stopwatch as a lap.
+    pub fn lap_stopwatch(&self) {
+        self.modify(|state, now| {
+            let elapsed = state.stopwatch.elapsed(now);
+            state.stopwatch.laps.push(elapsed);
+        });
+    }
+
+    pub fn reset_stopwatch(&self) {
+        self.modify(|state, _| state.stopwatch = Stopwatch::default());
+    }
+
+    /// Ring again in a few minutes.
+    fn snooze(&self, ringing: Ringing) {
+        let snooze =
+            Duration::from_secs(u64::from(crate::config::get().timers.snooze_minutes) * 60);
+        self.modify(|state, now| state.snooze(ringing, now + snooze));
+    }
+
+    /// Ring the timers and alarms that are due, returning how long until the next one is.
+    async fn ring_due(&self) -> Duration {
+        let now = SystemTime::now();
+        let mut due = vec![];
+        self.state.send_if_modified(|state| {
+            due = state.take_due(now);
+            !due.is_empty()
+        });
+        if !due.is_empty() {
+            if let Err(err) = self.state.borrow().save() {
+                warn!(?err, "Failed to save timers");
+            }
+        }
+
+        let snooze_label = format!("Snooze {} min", crate::config::get().timers.snooze_minutes);
+        let actions = [("snooze", snooze_label.as_str()), ("dismiss", "Dismiss")];
+        for (ringing, (summary, body, icon)) in due {
+            info!(?ringing, summary, "Ringing");
+            match notifications::send(&summary, &body, icon, Urgency::Critical, 0, &actions).await {
+                Ok(id) => {
+                    self.ringing.lock().unwrap().insert(id, ringing);
+                }
+                Err(err) => warn!(?err, "Failed to send timer notification"),
+            }
+        }
+
+        self.state.borrow().until_next_due(now)
+    }
+}
+
+/// The timers, alarms and stopwatch, as saved in the state directory.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct State {
+    pub timers: Vec<Timer>,
+    pub alarms: Vec<Alarm>,
+    pub stopwatch: Stopwatch,
+    /// The ID to give to the next timer or alarm.
+    last_id: u64,
+}
+
+impl State {
+    fn load() -> Self {
+        let path = crate::BASE_DIRECTORIES.get_state_home().join(STATE_FILE);
+        match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|err| {
+                warn!(?err, ?path, "Failed to parse timers");
+                Self::default()
+            }),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(err) => {
+                warn!(?err, ?path, "Failed to read timers");
+                Self::default()
+            }
+        }
+    }
+
+    fn save(&self) -> anyhow::Result<()> {
+        let path = crate::BASE_DIRECTORIES.place_state_file(STATE_FILE)?;
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Stop the alarms we missed for too long, they are not worth ringing anymore.
+    fn skip_missed_alarms(&mut self, now: SystemTime) {
+        for alarm in &mut self.alarms {
+            if alarm
+                .next_ring
+                .is_some_and(|next_ring| next_ring + MISSED_ALARM_GRACE < now)
+            {
+                if alarm.repeat == Repeat::Once {
+                    alarm.next_ring = None;
+                } else {
+                    alarm.schedule(now);
+                }
+            }
+        }
+    }
+
+    /// Finish the timers and move on the alarms that are due, returning what should ring with
+    /// its notification.
+    fn take_due(&mut self, now: SystemTime) -> Vec<(Ringing, (String, String, &'static str))> {
+        let mut due = vec![];
+        for timer in &mut self.timers {
+            if let TimerState::Running { ends_at } = timer.state {
+                if ends_at <= now {
+                    timer.state = TimerState::Finished;
+                    due.push((Ringing::Timer(timer.id), timer.notification()));
+                }
+            }
+        }
+        for alarm in &mut self.alarms {
+            if alarm.next_ring.is_some_and(|next_ring| next_ring <= now) {
+                due.push((Ringing::Alarm(alarm.id), alarm.notification()));
+                if alarm.repeat == Repeat::Once {
+                    alarm.next_ring = None;
+                } else {
+                    alarm.schedule(now);
+                }
+            }
+        }
+        due
+    }
+
+    /// Get how long until the next timer or alarm is due.
+    fn until_next_due(&self, now: SystemTime) -> Duration {
+        let timers = self.timers.iter().filter_map(|timer| match timer.state {
+            TimerState::Running { ends_at } => Some(ends_at),
+            _ => None,
+        });
+        let alarms = self.alarms.iter().filter_map(|alarm| alarm.next_ring);
+        timers.chain(alarms).min().map_or(Duration::MAX, |next| {
+            next.duration_since(now).unwrap_or_default()
+        })
+    }
+
+    /// Ring again at `ring_at`.
+    fn snooze(&mut self, ringing: Ringing, ring_at: SystemTime) {
+        match ringing {
+            Ringing::Timer(id) => {
+                if let Some(timer) = self.timers.iter_mut().find(|timer| timer.id == id) {
+                    timer.state = TimerState::Running { ends_at: ring_at };
+                }
+            }
+            Ringing::Alarm(id) => {
+                if let Some(alarm) = self.alarms.iter_mut().find(|alarm| alarm.id == id) {
+                    alarm.next_ring = Some(ring_at);
+                }
+            }
+        }
+    }
+
+    fn next_id(&mut self) -> u64 {
+        self.last_id += 1;
+        self.last_id
+    }
+
+    /// Get the running timer ending first.
+    pub fn next_timer(&self) -> Option<&Timer> {
+        self.timers
+            .iter()
+            .filter_map(|timer| match timer.state {
+                TimerState::Running { ends_at } => Some((ends_at, timer)),
+                _ => None,
+            })
+            .min_by_key(|(ends_at, _)| *ends_at)
+            .map(|(_, timer)| timer)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Timer {
+    pub id: u64,
+    pub label: Option<String>,
+    pub duration: Duration,
+    pub state: TimerState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TimerState {
+    Running { ends_at: SystemTime },
+    Paused { remaining: Duration },
+    Finished,
+}
+
+impl Timer {
+    pub fn remaining(&self, now: SystemTime) -> Duration {
+        match self.state {
+            TimerState::Running { ends_at } => ends_at.duration_since(now).unwrap_or_default(),
+            TimerState::Paused { remaining } => remaining,
+            TimerState::Finished => Duration::ZERO,
+        }
+    }
+
+    pub fn title(&self) -> String {
+        match &self.label {
+            Some(label) => label.clone(),
+            None => format!("{} timer", format_duration(self.duration)),
+        }
+    }
+
+    fn notification(&self) -> (String, String, &'static str) {
+        let body = match &self.label {
+            Some(_) => format!("{} timer is over", format_duration(self.duration)),
+            None => "Time is up".to_string(),
+        };
+        (self.title(), body, "preferences-system-time-symbolic")
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Alarm {
+    pub id: u64,
+    pub label: Option<String>,
+    #[serde(with = "naive_time")]
+    pub time: NaiveTime,
+    pub repeat: Repeat,
+    /// When the alarm rings next, or [`None`] if it's disabled.
+    pub next_ring: Option<SystemTime>,
+}
+
+/// The days an alarm rings on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Repeat {
+    /// Ring only once, then disable the alarm.
+    Once,
+    Daily,
+    /// Monday to Friday.
+    Weekdays,
+}
+
+impl Repeat {
+    fn includes(self, weekday: Weekday) -> bool {
+        match self {
+            Self::Once | Self::Daily => true,
+            Self::Weekdays => !matches!(weekday, Weekday::Sat | Weekday::Sun),
+        }
+    }
+}
+
+impl Alarm {
+    pub fn enabled(&self) -> bool {
+        self.next_ring.is_some()
+    }
+
+    /// Schedule the alarm for the next time it should ring after `now`.
+    fn schedule(&mut self, now: SystemTime) {
+        self.schedule_in(now, &Local);
+    }
+
+    fn schedule_in<Tz: TimeZone>(&mut self, now: SystemTime, timezone: &Tz) {
+        let now = DateTime::<Utc>::from(now).with_timezone(timezone);
+        let mut day = now.date_naive();
+        // The next weekday is at most 3 days away.
+        self.next_ring = (0..4).find_map(|_| {
+            let time = day.and_time(self.time);
+            let ring = timezone
+                .from_local_datetime(&time)
+                .earliest()
+                // Times skipped when moving to DST ring once the clock moved.
+                .or_else(|| {
+                    let after_gap = time + chrono::Duration::hours(1);
+                    timezone.from_local_datetime(&after_gap).earliest()
+                })
+                .filter(|ring| self.repeat.includes(day.weekday()) && *ring > now);
+            day = day.succ_opt()?;
+            ring.map(SystemTime::from)
+        });
+    }
+
+    pub fn title(&self) -> String {
+        self.label.clone().unwrap_or_else(|| "Alarm".to_string())
+    }
+
+    fn notification(&self) -> (String, String, &'static str) {
+        let body = match self.repeat {
+            Repeat::Once => "Alarm",
+            Repeat::Daily => "Daily alarm",
+            Repeat::Weekdays => "Weekdays alarm",
+        };
+        (self.title(), body.to_string(), "alarm-symbolic")
+    }
+}
+
+/// A stopwatch, counting while it's started.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Stopwatch {
+    /// When the stopwatch got started, if it's running.
+    pub started_at: Option<SystemTime>,
+    /// The time counted before it got started.
+    elapsed: Duration,
+    /// The time of the stopwatch at each lap.
+    pub laps: Vec<Duration>,
+}
+
+impl Stopwatch {
+    pub fn elapsed(&self, now: SystemTime) -> Duration {
+        let running = self
+            .started_at
+            .and_then(|started_at| now.duration_since(started_at).ok())
+            .unwrap_or_default();
+        self.elapsed + running
+    }
+}
+
+/// Format a duration as hours, minutes and seconds, IE. `1:05:00` or `25:00`.
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes:02}:{seconds:02}")
+    }
+}
+
+/// Chrono only implements serde with its feature.
+mod naive_time {
+    use chrono::NaiveTime;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    const FORMAT: &str = "%H:%M";
+
+    pub fn serialize<S: Serializer>(time: &NaiveTime, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&time.format(FORMAT).to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveTime, D::Error> {
+        let time = String::deserialize(deserializer)?;
+        NaiveTime::parse_from_str(&time, FORMAT).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{FixedOffset, LocalResult, NaiveDate, NaiveDateTime};
+
+    use super::*;
+
+    /// Paris in 2024, on UTC+1 and on UTC+2 from the 31st of March to the 27th of October.
+    #[derive(Debug, Clone, Copy)]
+    struct Paris;
+
+    impl TimeZone for Paris {
+        type Offset = FixedOffset;
+
+        fn from_offset(_: &FixedOffset) -> Self {
+            Paris
+        }
+
+        fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<FixedOffset> {
+            self.offset_from_local_datetime(&local.and_time(NaiveTime::MIN))
+        }
+
+        fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset> {
+            // The largest offset gives the earliest time.
+            let offsets = [2, 1]
+                .map(|hours| FixedOffset::east_opt(hours * 3600).unwrap())
+                .into_iter()
+                .filter(|offset| {
+                    let utc = *local - chrono::Duration::seconds(offset.local_minus_utc().into());
+                    self.offset_from_utc_datetime(&utc) == *offset
+                })
+                .collect::<Vec<_>>();
+            match offsets[..] {
+                [offset] => LocalResult::Single(offset),
+                [earliest, latest] => LocalResult::Ambiguous(earliest, latest),
+                _ => LocalResult::None,
+            }
+        }
+
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+            self.offset_from_utc_datetime(&utc.and_time(NaiveTime::MIN))
+        }
+
+        fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> FixedOffset {
+            let dst = at(2024, 3, 31, 1, 0)..at(2024, 10, 27, 1, 0);
+            let hours = if dst.contains(utc) { 2 } else { 1 };
+            FixedOffset::east_opt(hours * 3600).unwrap()
+        }
+    }
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    fn minutes(minutes: u64) -> Duration {
+        Duration::from_secs(minutes * 60)
+    }
+
+    fn utc(time: NaiveDateTime) -> SystemTime {
+        Utc.from_utc_datetime(&time).into()
+    }
+
+    fn paris(time: NaiveDateTime) -> SystemTime {
+        Paris.from_local_datetime(&time).earliest().unwrap().into()
+    }
+
+    fn alarm(id: u64, time: &str, repeat: Repeat) -> Alarm {
+        Alarm {
+            id,
+            label: None,
+            time: NaiveTime::parse_from_str(time, "%H:%M").unwrap(),
+            repeat,
+            next_ring: None,
+        }
+    }
+
+    /// Schedule an alarm in Paris, returning when it rings there.
+    fn schedule(time: &str, repeat: Repeat, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut alarm = alarm(1, time, repeat);
+        alarm.schedule_in(paris(now), &Paris);
+        let next_ring = DateTime::<Utc>::from(alarm.next_ring?);
+        Some(next_ring.with_timezone(&Paris).naive_local())
+    }
+
+    #[test]
+    fn alarm_schedule() {
+        // The 15th of January 2024 is a Monday.
+        let monday = at(2024, 1, 15, 8, 0);
+        assert_eq!(
+            schedule("09:00", Repeat::Daily, monday),
+            Some(at(2024, 1, 15, 9, 0))
+        );
+        assert_eq!(
+            schedule("07:00", Repeat::Daily, monday),
+            Some(at(2024, 1, 16, 7, 0))
+        );
+        assert_eq!(
+            schedule("08:00", Repeat::Once, monday),
+            Some(at(2024, 1, 16, 8, 0))
+        );
+        assert_eq!(
+            schedule("07:00", Repeat::Weekdays, monday),
+            Some(at(2024, 1, 16, 7, 0))
+        );
+
+        // Weekends are skipped, which is as far as the next ring can be.
+        let friday = at(2024, 1, 19, 8, 0);
+        assert_eq!(
+            schedule("07:00", Repeat::Weekdays, friday),
+            Some(at(2024, 1, 22, 7, 0))
+        );
+        assert_eq!(
+            schedule("07:00", Repeat::Weekdays, at(2024, 1, 20, 12, 0)),
+            Some(at(2024, 1, 22, 7, 0))
+        );
+        assert_eq!(
+            schedule("07:00", Repeat::Daily, friday),
+            Some(at(2024, 1, 20, 7, 0))
+        );
+    }
+
+    #[test]
+    fn alarm_schedule_across_dst() {
+        // 02:30 does not exist when moving to DST, it rings an hour later.
+        let mut alarm = alarm(1, "02:30", Repeat::Daily);
+        alarm.schedule_in(paris(at(2024, 3, 30, 12, 0)), &Paris);
+        assert_eq!(alarm.next_ring, Some(utc(at(2024, 3, 31, 1, 30))));
+
+        // 02:30 happens twice when moving out of DST, it only rings the first time.
+        alarm.schedule_in(paris(at(2024, 10, 26, 12, 0)), &Paris);
+        assert_eq!(alarm.next_ring, Some(utc(at(2024, 10, 27, 0, 30))));
+        alarm.schedule_in(alarm.next_ring.unwrap(), &Paris);
+        assert_eq!(alarm.next_ring, Some(utc(at(2024, 10, 28, 1, 30))));
+    }
+
+    #[test]
+    fn missed_alarms() {
+        let now = SystemTime::now();
+        let mut state = State::default();
+        for (id, repeat, next_ring) in [
+            (1, Repeat::Once, now - minutes(10)),
+            (2, Repeat::Once, now - minutes(20)),
+            (3, Repeat::Daily, now - minutes(20)),
+            (4, Repeat::Once, now + minutes(60)),
+        ] {
+            let mut alarm = alarm(id, "07:00", repeat);
+            alarm.next_ring = Some(next_ring);
+            state.alarms.push(alarm);
+        }
+
+        state.skip_missed_alarms(now);
+        // Alarms missed by a little still ring.
+        assert_eq!(state.alarms[0].next_ring, Some(now - minutes(10)));
+        assert_eq!(state.alarms[1].next_ring, None);
+        assert!(state.alarms[2].next_ring.is_some_and(|next| next > now));
+        assert_eq!(state.alarms[3].next_ring, Some(now + minutes(60)));
+    }
+
+    #[test]
+    fn ringing_and_snoozing() {
+        let now = SystemTime::now();
+        let timer = |id, state| Timer {
+            id,
+            label: None,
+            duration: minutes(5),
+            state,
+        };
+        let mut state = State {
+            timers: vec![
+                timer(1, TimerState::Running { ends_at: now }),
+                timer(
+                    2,
+                    TimerState::Running {
+                        ends_at: now + minutes(3),
+                    },
+                ),
+                timer(
+                    3,
+                    TimerState::Paused {
+                        remaining: minutes(1),
+                    },
+                ),
+            ],
+            ..State::default()
+        };
+        for (id, repeat) in [(4, Repeat::Once), (5, Repeat::Daily)] {
+            let mut alarm = alarm(id, "07:00", repeat);
+            alarm.next_ring = Some(now - minutes(1));
+            state.alarms.push(alarm);
+        }
+
+        let due = state.take_due(now);
+        assert_eq!(
+            due.iter().map(|(ringing, _)| *ringing).collect::<Vec<_>>(),
+            [Ringing::Timer(1), Ringing::Alarm(4), Ringing::Alarm(5)]
+        );
+        assert_eq!(due[0].1 .0, "05:00 timer");
+        assert_eq!(state.timers[0].state, TimerState::Finished);
+        assert_eq!(
+            state.timers[1].state,
+            TimerState::Running {
+                ends_at: now + minutes(3)
+            }
+        );
+        assert_eq!(
+            state.timers[2].state,
+            TimerState::Paused {
+                remaining: minutes(1)
+            }
+        );
+        assert_eq!(state.alarms[0].next_ring, None);
+        assert!(state.alarms[1].next_ring.is_some_and(|next| next > now));
+        // Nothing rings twice.
+        assert!(state.take_due(now).is_empty());
+        assert_eq!(state.until_next_due(now), minutes(3));
+
+        state.snooze(Ringing::Timer(1), now + minutes(2));
+        state.snooze(Ringing::Alarm(4), now + minutes(2));
+        state.snooze(Ringing::Timer(42), now + minutes(2));
+        assert_eq!(
+            state.timers[0].state,
+            TimerState::Running {
+                ends_at: now + minutes(2)
+            }
+        );
+        assert_eq!(state.alarms[0].next_ring, Some(now + minutes(2)));
+        assert_eq!(state.until_next_due(now), minutes(2));
+
+        let later = now + minutes(2);
+        assert_eq!(
+            state
+                .take_due(later)
+                .iter()
+                .map(|(ringing, _)| *ringing)
+                .collect::<Vec<_>>(),
+            [Ringing::Timer(1), Ringing::Alarm(4)]
+        );
+        assert_eq!(State::default().until_next_due(now), Duration::MAX);
+    }
+}
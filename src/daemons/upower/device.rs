@@ -88,6 +88,29 @@ pub enum WarningLevel {
     Action = 5,
 }
 
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    serde_repr::Deserialize_repr,
+    serde_repr::Serialize_repr,
+    zvariant::Type,
+    zvariant::OwnedValue,
+)]
+#[repr(u32)]
+pub enum BatteryLevel {
+    Unknown = 0,
+    /// The device reports a percentage instead of a coarse level.
+    None = 1,
+    Low = 3,
+    Critical = 4,
+    Normal = 6,
+    High = 7,
+    Full = 8,
+}
+
 #[derive(
     Debug,
     Clone,
@@ -132,6 +155,99 @@ pub enum Type {
     BluetoothGeneric = 28,
 }
 
+impl BatteryLevel {
+    /// Get a human readable description of this level.
+    ///
+    /// Devices that don't report a coarse level use their `percentage` instead.
+    pub fn describe(self, percentage: f64) -> String {
+        match self {
+            Self::Unknown | Self::None => format!("{percentage:.0}%"),
+            Self::Low => String::from("Low"),
+            Self::Critical => String::from("Critical"),
+            Self::Normal => String::from("Normal"),
+            Self::High => String::from("High"),
+            Self::Full => String::from("Full"),
+        }
+    }
+
+    /// Whether a device at this level, or at this `percentage` when it has no coarse level, is
+    /// low on battery.
+    ///
+    /// Some devices report 0% when they don't know their level yet, that's not low.
+    pub fn is_low(self, percentage: f64, low_threshold: f64) -> bool {
+        match self {
+            Self::Low | Self::Critical => true,
+            Self::Normal | Self::High | Self::Full => false,
+            Self::Unknown | Self::None => percentage > 0.0 && percentage <= low_threshold,
+        }
+    }
+}
+
+impl Type {
+    /// Get a human readable name for this device type.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Unknown => "Device",
+            Self::LinePower => "AC adapter",
+            Self::Battery => "Battery",
+            Self::Ups => "UPS",
+            Self::Monitor => "Monitor",
+            Self::Mouse => "Mouse",
+            Self::Keyboard => "Keyboard",
+            Self::Pda => "PDA",
+            Self::Phone => "Phone",
+            Self::MediaPlayer => "Media player",
+            Self::Tablet => "Tablet",
+            Self::Computer => "Computer",
+            Self::GamingInput => "Controller",
+            Self::Pen => "Pen",
+            Self::Touchpad => "Touchpad",
+            Self::Modem => "Modem",
+            Self::Network => "Network device",
+            Self::Headset => "Headset",
+            Self::Speakers => "Speakers",
+            Self::Headphones => "Headphones",
+            Self::Video => "Video device",
+            Self::OtherAudio => "Audio device",
+            Self::RemoteControl => "Remote control",
+            Self::Printer => "Printer",
+            Self::Scanner => "Scanner",
+            Self::Camera => "Camera",
+            Self::Wearable => "Wearable",
+            Self::Toy => "Toy",
+            Self::BluetoothGeneric => "Bluetooth device",
+        }
+    }
+
+    /// Get the symbolic icon name for this device type.
+    pub fn icon_name(self) -> &'static str {
+        match self {
+            Self::LinePower => "ac-adapter-symbolic",
+            Self::Battery => "battery-symbolic",
+            Self::Ups => "uninterruptible-power-supply-symbolic",
+            Self::Monitor => "video-display-symbolic",
+            Self::Mouse => "input-mouse-symbolic",
+            Self::Keyboard => "input-keyboard-symbolic",
+            Self::Pda | Self::Phone => "phone-symbolic",
+            Self::MediaPlayer => "multimedia-player-symbolic",
+            Self::Tablet | Self::Pen => "input-tablet-symbolic",
+            Self::Computer => "computer-symbolic",
+            Self::GamingInput => "input-gaming-symbolic",
+            Self::Touchpad => "input-touchpad-symbolic",
+            Self::Modem => "modem-symbolic",
+            Self::Network => "network-wired-symbolic",
+            Self::Headset => "audio-headset-symbolic",
+            Self::Speakers | Self::OtherAudio => "audio-speakers-symbolic",
+            Self::Headphones => "audio-headphones-symbolic",
+            Self::Video | Self::Camera => "camera-web-symbolic",
+            Self::Printer => "printer-symbolic",
+            Self::Scanner => "scanner-symbolic",
+            Self::BluetoothGeneric => "bluetooth-symbolic",
+            Self::Unknown | Self::RemoteControl | Self::Wearable | Self::Toy => "battery-symbolic",
+        }
+    }
+}
+
 #[proxy(
     interface = "org.freedesktop.UPower.Device",
     default_service = "org.freedesktop.UPower"
@@ -156,7 +272,7 @@ pub trait Device {
 
     /// BatteryLevel property
     #[zbus(property)]
-    fn battery_level(&self) -> zbus::Result<u32>;
+    fn battery_level(&self) -> zbus::Result<BatteryLevel>;
 
     /// Capacity property
     #[zbus(property)]
@@ -290,3 +406,22 @@ pub trait Device {
     #[zbus(property)]
     fn warning_level(&self) -> zbus::Result<WarningLevel>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_low() {
+        assert!(BatteryLevel::None.is_low(10.0, 20.0));
+        assert!(BatteryLevel::None.is_low(20.0, 20.0));
+        assert!(!BatteryLevel::None.is_low(21.0, 20.0));
+        // Not known yet.
+        assert!(!BatteryLevel::None.is_low(0.0, 20.0));
+        assert!(!BatteryLevel::Unknown.is_low(0.0, 20.0));
+        // Coarse levels win over the percentage.
+        assert!(BatteryLevel::Critical.is_low(0.0, 20.0));
+        assert!(BatteryLevel::Low.is_low(50.0, 20.0));
+        assert!(!BatteryLevel::High.is_low(10.0, 20.0));
+    }
+}
@@ -1,21 +1,56 @@
 #![allow(unused)]
-use std::sync::{Arc, OnceLock};
+use std::sync::{Arc, OnceLock, RwLock};
+
+use futures_util::StreamExt;
+use tokio::sync::broadcast;
 
 pub mod device;
+pub mod kbd_backlight;
 pub mod service;
 
 /// A `UPower` daemon, used to monitor power devices.
 /// See freedesktop's page: <https://upower.freedesktop.org/>
 pub struct Daemon {
     proxy: service::UPowerProxy<'static>,
-    devices: Vec<Device>,
+    devices: RwLock<Vec<Device>>,
+    display_device: Device,
+    kbd_backlight: kbd_backlight::KbdBacklightProxy<'static>,
+    sender: broadcast::Sender<Event>,
 }
 
 impl Daemon {
-    /// Get all the devices registered for this [`Daemon`].
-    /// **NOTE**: The device list gets populated only on startup!
-    pub fn devices(&self) -> &[Device] {
-        &self.devices
+    /// Get all the devices currently registered for this [`Daemon`].
+    ///
+    /// The list is kept up to date when devices get plugged in or out, use [`Self::subscribe`] to
+    /// get notified about these changes.
+    pub fn devices(&self) -> Vec<Device> {
+        self.devices.read().unwrap().clone()
+    }
+
+    /// Get a device by its ID.
+    pub fn device(&self, id: &str) -> Option<Device> {
+        let devices = self.devices.read().unwrap();
+        devices.iter().find(|device| &*device.id == id).cloned()
+    }
+
+    /// Get the display device.
+    ///
+    /// This is a composite device that UPower keeps around to represent the overall power status
+    /// of the system, for example to use in a panel icon.
+    pub fn display_device(&self) -> &Device {
+        &self.display_device
+    }
+
+    /// Get the keyboard backlight.
+    ///
+    /// Calls fail on machines without one, which is the simplest way to check for it.
+    pub fn kbd_backlight(&self) -> &kbd_backlight::KbdBacklightProxy<'static> {
+        &self.kbd_backlight
+    }
+
+    /// Subscribe to the events of this [`Daemon`].
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
     }
 
     /// Get the underlying [`zbus::Proxy`] powering this daemon.
@@ -28,21 +63,47 @@ impl Daemon {
 ///
 /// The unique ID of the device is the end of it's D-Bus path, IE.
 /// `/org/freedesktop/UPower/devices/battery_BAT0` -> `battery_BAT0`
+#[derive(Clone, Debug)]
 pub struct Device {
     id: Arc<str>,
     proxy: device::DeviceProxy<'static>,
 }
 
 impl Device {
+    async fn new(path: zbus::zvariant::OwnedObjectPath) -> zbus::Result<Self> {
+        let conn = super::system_connection().inner();
+        let id: Arc<str> = path.rsplit('/').next().expect("Invalid device path").into();
+        let proxy = device::DeviceProxy::new(conn, path).await?;
+        Ok(Self { id, proxy })
+    }
+
     /// Get the unique ID of this [`Device`].
     pub fn id(&self) -> Arc<str> {
         Arc::clone(&self.id)
     }
 
     /// Get the underlying [`zbus::Proxy`] behind this interface.
-    pub fn proxy(&self) -> &device::DeviceProxy<'_> {
+    pub fn proxy(&self) -> &device::DeviceProxy<'static> {
         &self.proxy
     }
+
+    /// Whether this device is a peripheral with its own battery, like a mouse or a headset.
+    ///
+    /// Unlike laptop batteries, these don't power the system itself.
+    pub async fn is_peripheral(&self) -> zbus::Result<bool> {
+        let type_ = self.proxy.type_().await?;
+        let power_supply = self.proxy.power_supply().await?;
+        Ok(type_ != device::Type::LinePower && !power_supply)
+    }
+}
+
+/// An event sent by the `UPower` [`Daemon`].
+#[derive(Clone, Debug)]
+pub enum Event {
+    /// A new device was plugged in.
+    DeviceAdded(Device),
+    /// A device was removed, with its ID.
+    DeviceRemoved(Arc<str>),
 }
 
 static INSTANCE: OnceLock<Daemon> = OnceLock::new();
@@ -57,29 +118,75 @@ pub async fn start() -> anyhow::Result<()> {
     }
 
     let conn = super::system_connection().inner();
+    let proxy = service::UPowerProxy::new(conn).await?;
+    let display_device = Device::new(proxy.get_display_device().await?).await?;
+    let kbd_backlight = kbd_backlight::KbdBacklightProxy::new(conn).await?;
 
-    let mut daemon = Daemon {
-        proxy: service::UPowerProxy::new(conn).await?,
-        devices: vec![],
-    };
-
-    let devices = daemon.proxy.enumerate_devices().await?;
-    for device_path in devices {
-        let device_id: Arc<str> = device_path
-            .split('/')
-            .last()
-            .expect("Invalid device path")
-            .into();
-        let device_proxy = device::DeviceProxy::new(conn, device_path).await?;
-
-        daemon.devices.push(Device {
-            id: device_id,
-            proxy: device_proxy,
-        });
+    let mut devices = vec![];
+    for device_path in proxy.enumerate_devices().await? {
+        devices.push(Device::new(device_path).await?);
     }
 
+    // Subscribe before spawning the watcher so that we don't miss anything in between.
+    let mut device_added = proxy.receive_device_added().await?;
+    let mut device_removed = proxy.receive_device_removed().await?;
+
+    let (sender, _) = broadcast::channel(32);
+    let daemon = Daemon {
+        proxy,
+        devices: RwLock::new(devices),
+        display_device,
+        kbd_backlight,
+        sender,
+    };
+
     // NOTE: If we already started he handled it above.
     let _ = INSTANCE.set(daemon);
 
+    super::spawn("upower-devices", async move {
+        loop {
+            futures_util::select! {
+                added = device_added.next() => {
+                    let Some(added) = added else { break };
+                    let path = match added.args() {
+                        Ok(args) => args.device.into(),
+                        Err(err) => {
+                            warn!(?err, "Invalid UPower DeviceAdded signal");
+                            continue;
+                        }
+                    };
+                    // The device may already be gone, IE. when unplugged right away.
+                    let device = match Device::new(path).await {
+                        Ok(device) => device,
+                        Err(err) => {
+                            warn!(?err, "Failed to follow added UPower device");
+                            continue;
+                        }
+                    };
+                    debug!(device_id = ?device.id, "UPower device added");
+
+                    let daemon = get();
+                    daemon.devices.write().unwrap().push(device.clone());
+                    let _ = daemon.sender.send(Event::DeviceAdded(device));
+                }
+                removed = device_removed.next() => {
+                    let Some(removed) = removed else { break };
+                    let Ok(args) = removed.args() else {
+                        warn!("Invalid UPower DeviceRemoved signal");
+                        continue;
+                    };
+                    let id: Arc<str> = args.device.rsplit('/').next().expect("Invalid device path").into();
+                    debug!(device_id = ?id, "UPower device removed");
+
+                    let daemon = get();
+                    daemon.devices.write().unwrap().retain(|device| device.id != id);
+                    let _ = daemon.sender.send(Event::DeviceRemoved(id));
+                }
+            }
+        }
+
+        Ok(())
+    });
+
     Ok(())
 }
@@ -0,0 +1,127 @@
+//! Fuzzy matching of search queries.
+//!
+//! A query matches a candidate when all its characters appear in the candidate, in order. The
+//! score favors matches at the start of the candidate and of its words, and consecutive
+//! characters, so that `ff` ranks `Firefox` above `Diff Viewer` and `term` ranks `Terminal`
+//! above `Mastermind`.
+
+/// The score of a matched character.
+const MATCH: i64 = 16;
+/// The bonus when a character follows the previously matched one.
+const CONSECUTIVE: i64 = 24;
+/// The bonus when a character starts a word.
+const WORD_START: i64 = 32;
+/// The bonus when the first character of the query starts the candidate.
+const PREFIX: i64 = 48;
+/// The penalty for each character skipped between two matches.
+const GAP: i64 = 2;
+
+/// Score how well `query` matches `candidate`, ignoring case, or `None` if it does not.
+///
+/// Higher is better. An empty query matches everything with a score of zero.
+pub fn score(query: &str, candidate: &str) -> Option<i64> {
+    let query = query.to_lowercase().chars().collect::<Vec<_>>();
+    if query.is_empty() {
+        return Some(0);
+    }
+
+    let mut score = 0;
+    let mut query_idx = 0;
+    let mut last_match: Option<usize> = None;
+    let mut previous = None;
+    for (idx, c) in candidate.chars().enumerate() {
+        let is_word_start = previous.map_or(true, |previous: char| {
+            !previous.is_alphanumeric() || (previous.is_lowercase() && c.is_uppercase())
+        });
+        previous = Some(c);
+
+        if query_idx == query.len() {
+            break;
+        }
+        if !c.to_lowercase().eq(std::iter::once(query[query_idx])) {
+            continue;
+        }
+
+        score += MATCH;
+        if is_word_start {
+            score += WORD_START;
+        }
+        match last_match {
+            Some(last) if last + 1 == idx => score += CONSECUTIVE,
+            Some(last) => score -= GAP * (idx - last - 1) as i64,
+            None if idx == 0 => score += PREFIX,
+            None => score -= GAP * idx as i64,
+        }
+        last_match = Some(idx);
+        query_idx += 1;
+    }
+
+    (query_idx == query.len()).then_some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sort the candidates by how well they match `query`, dropping the ones that don't.
+    fn rank<'a>(query: &str, candidates: &[&'a str]) -> Vec<&'a str> {
+        let mut matches = candidates
+            .iter()
+            .filter_map(|candidate| Some((score(query, candidate)?, *candidate)))
+            .collect::<Vec<_>>();
+        matches.sort_by(|(a, _), (b, _)| b.cmp(a));
+        matches
+            .into_iter()
+            .map(|(_, candidate)| candidate)
+            .collect()
+    }
+
+    #[test]
+    fn ranks_word_starts_first() {
+        assert_eq!(
+            rank("ff", &["Diff Viewer", "Firefox"]),
+            ["Firefox", "Diff Viewer"]
+        );
+        assert_eq!(
+            rank("term", &["Mastermind", "Terminal"]),
+            ["Terminal", "Mastermind"]
+        );
+    }
+
+    #[test]
+    fn ranks_prefixes_first() {
+        assert_eq!(
+            rank("fire", &["Bonfire", "Firewall"]),
+            ["Firewall", "Bonfire"]
+        );
+    }
+
+    #[test]
+    fn ranks_consecutive_matches_first() {
+        assert!(score("ire", "Firefox") > score("ire", "Stairwell"));
+    }
+
+    #[test]
+    fn matches_camel_case_words() {
+        assert!(score("ws", "WebStorm") > score("ws", "Weather Stats Viewer"));
+    }
+
+    #[test]
+    fn ignores_case() {
+        assert_eq!(score("FIRE", "firefox"), score("fire", "Firefox"));
+    }
+
+    #[test]
+    fn needs_every_character_in_order() {
+        assert_eq!(score("xf", "Firefox"), None);
+        assert_eq!(score("firefoxes", "Firefox"), None);
+        assert!(score("frx", "Firefox").is_some());
+    }
+
+    #[test]
+    fn empty_query_matches_everything() {
+        assert_eq!(score("", "Firefox"), Some(0));
+        assert_eq!(score("", ""), Some(0));
+        assert_eq!(score("a", ""), None);
+    }
+}
@@ -0,0 +1,256 @@
+//! Application launcher.
+//!
+//! A search field over the installed applications, and everything else the [`providers`] can
+//! find, opened from the panel or with the `toggle-launcher` action. The keyboard stays in the
+//! launcher while it's open: type to search, use the arrows to pick a result, Enter to launch it
+//! and Escape to close.
+
+mod fuzzy;
+mod providers;
+
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::{gdk, gio, glib};
+use gtk4_layer_shell::{Edge, LayerShell};
+
+use self::providers::{ResultIcon, SearchProvider, SearchResult};
+use crate::application::Application;
+
+/// The most results shown at once, the search is precise enough past that.
+const MAX_RESULTS: usize = 30;
+
+mod imp {
+    use std::cell::{OnceCell, RefCell};
+    use std::rc::Rc;
+
+    use adw::prelude::AdwApplicationWindowExt;
+    use adw::subclass::prelude::AdwApplicationWindowImpl;
+
+    use super::*;
+
+    #[derive(Default, Debug)]
+    pub struct LauncherWindow {
+        pub(super) entry: gtk::SearchEntry,
+        pub(super) list: gtk::ListBox,
+        scrolled_window: gtk::ScrolledWindow,
+        providers: OnceCell<Vec<Box<dyn SearchProvider>>>,
+        /// The results currently shown, in the order of the list rows.
+        pub(super) results: RefCell<Vec<SearchResult>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for LauncherWindow {
+        const NAME: &'static str = "LauncherWindow";
+        type Type = super::LauncherWindow;
+        type ParentType = adw::ApplicationWindow;
+    }
+
+    impl ObjectImpl for LauncherWindow {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.add_css_class("launcher-window");
+
+            let content = gtk::Box::builder()
+                .orientation(gtk::Orientation::Vertical)
+                .spacing(12)
+                .css_classes(["launcher"])
+                .build();
+            self.entry.set_placeholder_text(Some("Search"));
+            content.append(&self.entry);
+
+            self.list.set_css_classes(&["boxed-list"]);
+            self.list.set_selection_mode(gtk::SelectionMode::Browse);
+            self.scrolled_window
+                .set_hscrollbar_policy(gtk::PolicyType::Never);
+            self.scrolled_window.set_propagate_natural_height(true);
+            self.scrolled_window.set_max_content_height(480);
+            self.scrolled_window.set_width_request(480);
+            self.scrolled_window.set_child(Some(&self.list));
+            content.append(&self.scrolled_window);
+            obj.set_content(Some(&content));
+
+            let weak_obj = obj.downgrade();
+            self.entry.connect_search_changed(move |_| {
+                if let Some(obj) = weak_obj.upgrade() {
+                    obj.imp().search();
+                }
+            });
+            let weak_obj = obj.downgrade();
+            self.entry.connect_activate(move |_| {
+                if let Some(obj) = weak_obj.upgrade() {
+                    if let Some(row) = obj.imp().list.selected_row() {
+                        obj.imp().launch(row.index());
+                    }
+                }
+            });
+            let weak_obj = obj.downgrade();
+            self.entry.connect_stop_search(move |_| {
+                if let Some(obj) = weak_obj.upgrade() {
+                    obj.set_visible(false);
+                }
+            });
+            let weak_obj = obj.downgrade();
+            self.list.connect_row_activated(move |_, row| {
+                if let Some(obj) = weak_obj.upgrade() {
+                    obj.imp().launch(row.index());
+                }
+            });
+
+            // Keep the focus in the search entry while moving through the results.
+            let key_controller = gtk::EventControllerKey::new();
+            key_controller.set_propagation_phase(gtk::PropagationPhase::Capture);
+            let weak_obj = obj.downgrade();
+            key_controller.connect_key_pressed(move |_, key, _, _| {
+                let Some(obj) = weak_obj.upgrade() else {
+                    return glib::Propagation::Proceed;
+                };
+                let step = match key {
+                    gdk::Key::Up => -1,
+                    gdk::Key::Down => 1,
+                    _ => return glib::Propagation::Proceed,
+                };
+                obj.imp().move_selection(step);
+                glib::Propagation::Stop
+            });
+            self.entry.add_controller(key_controller);
+        }
+    }
+
+    impl LauncherWindow {
+        /// Show the results for the current query.
+        pub(super) fn search(&self) {
+            let providers = self.providers.get_or_init(providers::all);
+            let mut results = providers::search(providers, &self.entry.text());
+            results.truncate(MAX_RESULTS);
+
+            self.list.remove_all();
+            for result in &results {
+                self.list.append(&result_row(result));
+            }
+            self.list.select_row(self.list.row_at_index(0).as_ref());
+            self.results.replace(results);
+        }
+
+        fn move_selection(&self, step: i32) {
+            let Some(row) = self.list.selected_row() else {
+                return;
+            };
+            let Some(row) = self.list.row_at_index(row.index() + step) else {
+                return;
+            };
+            self.list.select_row(Some(&row));
+
+            // Selecting does not scroll, only focusing does, and the focus stays in the entry.
+            if let Some((_, y)) = row.translate_coordinates(&self.list, 0.0, 0.0) {
+                let height = f64::from(row.height());
+                self.scrolled_window.vadjustment().clamp_page(y, y + height);
+            }
+        }
+
+        fn launch(&self, idx: i32) {
+            let Some(activate) = self
+                .results
+                .borrow()
+                .get(idx as usize)
+                .map(|result| Rc::clone(&result.activate))
+            else {
+                return;
+            };
+            let obj = self.obj();
+            activate(&obj.display());
+            obj.set_visible(false);
+        }
+    }
+
+    impl WidgetImpl for LauncherWindow {}
+    impl WindowImpl for LauncherWindow {}
+    impl ApplicationWindowImpl for LauncherWindow {}
+    impl AdwApplicationWindowImpl for LauncherWindow {}
+}
+
+glib::wrapper! {
+    pub struct LauncherWindow(ObjectSubclass<imp::LauncherWindow>)
+        @extends adw::ApplicationWindow, gtk::Widget, gtk::Window, gtk::ApplicationWindow,
+        @implements gio::ActionMap, gio::ActionGroup, gtk::Root;
+}
+
+impl LauncherWindow {
+    pub fn new(app: &Application) -> Self {
+        let window: Self = glib::Object::builder().property("application", app).build();
+        window.init_layer_shell();
+        window.set_namespace("fht.desktop.Shell.Launcher");
+        window.set_layer(gtk4_layer_shell::Layer::Overlay);
+        window.set_keyboard_mode(gtk4_layer_shell::KeyboardMode::Exclusive);
+        window.set_anchor(Edge::Bottom, true);
+        // Stay above the panel.
+        window.set_margin(Edge::Bottom, 72);
+        window
+    }
+
+    /// Open the launcher with an empty search, or close it if it's already open.
+    pub fn toggle(&self) {
+        if self.is_visible() {
+            self.set_visible(false);
+            return;
+        }
+
+        let imp = self.imp();
+        imp.entry.set_text("");
+        // Changing an empty text does not emit anything.
+        imp.search();
+        self.present();
+        imp.entry.grab_focus();
+    }
+}
+
+fn result_row(result: &SearchResult) -> gtk::Box {
+    let row = gtk::Box::builder()
+        .orientation(gtk::Orientation::Horizontal)
+        .spacing(12)
+        .css_classes(["launcher-result"])
+        .build();
+    let icon: gtk::Widget = match &result.icon {
+        Some(ResultIcon::Icon(gicon)) => gtk::Image::builder()
+            .gicon(gicon)
+            .pixel_size(32)
+            .build()
+            .upcast(),
+        Some(ResultIcon::Text(text)) => gtk::Label::builder()
+            .label(text)
+            .width_request(32)
+            .css_classes(["title-2"])
+            .build()
+            .upcast(),
+        None => gtk::Image::builder()
+            .icon_name("application-x-executable")
+            .pixel_size(32)
+            .build()
+            .upcast(),
+    };
+    row.append(&icon);
+
+    let labels = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .valign(gtk::Align::Center)
+        .build();
+    labels.append(
+        &gtk::Label::builder()
+            .label(&result.title)
+            .xalign(0.0)
+            .ellipsize(gtk::pango::EllipsizeMode::End)
+            .build(),
+    );
+    if let Some(subtitle) = &result.subtitle {
+        labels.append(
+            &gtk::Label::builder()
+                .label(subtitle)
+                .xalign(0.0)
+                .ellipsize(gtk::pango::EllipsizeMode::End)
+                .css_classes(["dim-label", "caption"])
+                .build(),
+        );
+    }
+    row.append(&labels);
+    row
+}
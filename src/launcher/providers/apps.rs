@@ -0,0 +1,223 @@
+//! Installed applications, ranked by how often they got launched.
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use gtk::prelude::*;
+use gtk::{gdk, gio};
+
+use super::{ResultIcon, SearchProvider, SearchResult};
+use crate::launcher::fuzzy;
+
+/// How much each past launch of an application adds to its score.
+const LAUNCH_BONUS: i64 = 8;
+/// The most launches counted, so that a very used application doesn't match everything.
+const MAX_LAUNCHES: u32 = 20;
+
+#[derive(Debug)]
+pub struct AppsProvider {
+    apps: RefCell<Option<Vec<App>>>,
+    history: Rc<RefCell<History>>,
+    /// Whether the installed applications changed since we listed them.
+    outdated: Rc<Cell<bool>>,
+    // GIO only sends changes while we hold a reference to the monitor.
+    _monitor: gio::AppInfoMonitor,
+}
+
+#[derive(Debug)]
+struct App {
+    app_info: gio::DesktopAppInfo,
+    id: String,
+    name: String,
+    generic_name: Option<String>,
+    keywords: Vec<String>,
+    executable: String,
+}
+
+impl AppsProvider {
+    pub fn new() -> Self {
+        let outdated = Rc::new(Cell::new(false));
+        let monitor = gio::AppInfoMonitor::get();
+        let monitor_outdated = Rc::clone(&outdated);
+        monitor.connect_changed(move |_| monitor_outdated.set(true));
+
+        Self {
+            apps: RefCell::new(None),
+            history: Rc::new(RefCell::new(History::load())),
+            outdated,
+            _monitor: monitor,
+        }
+    }
+
+    fn result(&self, app: &App, action: Option<String>, score: i64) -> SearchResult {
+        let app_info = &app.app_info;
+        let (title, subtitle) = match &action {
+            Some(action) => (
+                app_info.action_name(action).to_string(),
+                Some(app.name.clone()),
+            ),
+            None => (app.name.clone(), app_info.description().map(String::from)),
+        };
+
+        let launched_app_info = app_info.clone();
+        let history = Rc::clone(&self.history);
+        let id = app.id.clone();
+        SearchResult {
+            title,
+            subtitle,
+            icon: app_info.icon().map(ResultIcon::Icon),
+            score,
+            activate: Rc::new(move |display: &gdk::Display| {
+                match &action {
+                    Some(action) => {
+                        let context = display.app_launch_context();
+                        launched_app_info.launch_action(action, Some(&context));
+                    }
+                    None => crate::app_info::launch(&launched_app_info, display),
+                }
+                history.borrow_mut().record(&id);
+            }),
+        }
+    }
+}
+
+impl SearchProvider for AppsProvider {
+    fn name(&self) -> &'static str {
+        "apps"
+    }
+
+    fn default_priority(&self) -> i32 {
+        50
+    }
+
+    /// Search the applications and their actions.
+    ///
+    /// With an empty query, all the applications are returned, most launched first.
+    fn search(&self, query: &str) -> Vec<SearchResult> {
+        if self.outdated.replace(false) {
+            self.apps.replace(None);
+        }
+        let mut apps = self.apps.borrow_mut();
+        let apps = apps.get_or_insert_with(list_apps);
+
+        let history = self.history.borrow();
+        let mut results = vec![];
+        for app in apps.iter() {
+            let launches = history.launches(&app.id).min(MAX_LAUNCHES);
+            let bonus = launches as i64 * LAUNCH_BONUS;
+
+            if let Some(score) = app.score(query) {
+                results.push(self.result(app, None, score + bonus));
+            }
+
+            // Actions only show up when searched for, IE. `firefox private`.
+            if query.is_empty() {
+                continue;
+            }
+            for action in app.app_info.list_actions() {
+                let action_name = app.app_info.action_name(&action);
+                let Some(score) = fuzzy::score(query, &format!("{} {action_name}", app.name))
+                else {
+                    continue;
+                };
+                results.push(self.result(app, Some(action.to_string()), score + bonus));
+            }
+        }
+
+        // Keep the list alphabetical for an empty query without history.
+        results.sort_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then_with(|| a.title.to_lowercase().cmp(&b.title.to_lowercase()))
+        });
+        results
+    }
+}
+
+/// List the applications that should be shown in menus.
+fn list_apps() -> Vec<App> {
+    gio::AppInfo::all()
+        .into_iter()
+        .filter(|app_info| app_info.should_show())
+        .filter_map(|app_info| app_info.downcast::<gio::DesktopAppInfo>().ok())
+        .filter_map(|app_info| {
+            Some(App {
+                id: app_info.id()?.to_string(),
+                name: app_info.display_name().to_string(),
+                generic_name: app_info.generic_name().map(String::from),
+                keywords: app_info.keywords().into_iter().map(String::from).collect(),
+                executable: app_info
+                    .executable()
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                app_info,
+            })
+        })
+        .collect()
+}
+
+impl App {
+    /// Score how well the query matches any of the fields of this application.
+    fn score(&self, query: &str) -> Option<i64> {
+        // The name is what users search for most of the time.
+        let name = fuzzy::score(query, &self.name).map(|score| score * 2);
+        let others = self
+            .generic_name
+            .iter()
+            .chain(&self.keywords)
+            .chain(std::iter::once(&self.executable))
+            .filter_map(|field| fuzzy::score(query, field));
+        name.into_iter().chain(others).max()
+    }
+}
+
+/// How many times each application got launched, persisted in the state directory.
+#[derive(Debug, Default)]
+struct History {
+    launches: HashMap<String, u32>,
+}
+
+impl History {
+    fn path() -> PathBuf {
+        crate::BASE_DIRECTORIES
+            .get_state_home()
+            .join("fht/shell/launcher-history.json")
+    }
+
+    fn load() -> Self {
+        let path = Self::path();
+        let launches = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|err| {
+                warn!(?err, ?path, "Failed to parse launcher history");
+                HashMap::new()
+            }),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(err) => {
+                warn!(?err, ?path, "Failed to read launcher history");
+                HashMap::new()
+            }
+        };
+        Self { launches }
+    }
+
+    fn launches(&self, id: &str) -> u32 {
+        self.launches.get(id).copied().unwrap_or(0)
+    }
+
+    fn record(&mut self, id: &str) {
+        *self.launches.entry(id.to_string()).or_default() += 1;
+        if let Err(err) = self.save() {
+            warn!(?err, "Failed to save launcher history");
+        }
+    }
+
+    fn save(&self) -> anyhow::Result<()> {
+        let path = crate::BASE_DIRECTORIES.place_state_file("fht/shell/launcher-history.json")?;
+        fs::write(path, serde_json::to_string(&self.launches)?)?;
+        Ok(())
+    }
+}
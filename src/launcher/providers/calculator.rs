@@ -0,0 +1,469 @@
+//! Inline calculator, with unit conversions.
+//!
+//! Handles the usual operators (`+ - * / % ^`), parentheses, a few functions like `sqrt` and
+//! `sin`, and the `pi` and `e` constants. A conversion is written `<expression> <unit> to <unit>`
+//! (or `in`), IE. `3 * 2 km to mi` or `100 f in c`.
+//!
+//! Picking the result copies it to the clipboard.
+
+use std::rc::Rc;
+
+use gtk::prelude::*;
+use gtk::{gdk, gio};
+
+use super::{ResultIcon, SearchProvider, SearchResult};
+
+#[derive(Debug)]
+pub struct CalculatorProvider;
+
+impl SearchProvider for CalculatorProvider {
+    fn name(&self) -> &'static str {
+        "calculator"
+    }
+
+    fn default_priority(&self) -> i32 {
+        100
+    }
+
+    fn search(&self, query: &str) -> Vec<SearchResult> {
+        let Some(result) = calculate(query) else {
+            return vec![];
+        };
+
+        let text = result.clone();
+        vec![SearchResult {
+            title: format!("= {result}"),
+            subtitle: Some("Copy to clipboard".to_string()),
+            icon: Some(ResultIcon::Icon(
+                gio::ThemedIcon::new("accessories-calculator-symbolic").into(),
+            )),
+            score: 0,
+            activate: Rc::new(move |display: &gdk::Display| display.clipboard().set_text(&text)),
+        }]
+    }
+}
+
+/// Calculate the query, if it's a calculation or a conversion.
+fn calculate(query: &str) -> Option<String> {
+    if let Some(converted) = convert(query) {
+        return Some(converted);
+    }
+
+    let tokens = tokenize(query)?;
+    // A lone number or constant is not worth a result.
+    if tokens.len() < 2 {
+        return None;
+    }
+    format_number(Parser::new(tokens).parse()?)
+}
+
+/// Convert between units, IE. `10 km to mi`.
+fn convert(query: &str) -> Option<String> {
+    let (value, target) = query
+        .rsplit_once(" to ")
+        .or_else(|| query.rsplit_once(" in "))?;
+    let target = Unit::find(target.trim())?;
+
+    let mut tokens = tokenize(value)?;
+    let Some(Token::Ident(source)) = tokens.pop() else {
+        return None;
+    };
+    let source = Unit::find(&source)?;
+    if source.dimension != target.dimension {
+        return None;
+    }
+
+    let value = Parser::new(tokens).parse()?;
+    let base = value * source.scale + source.offset;
+    let converted = (base - target.offset) / target.scale;
+    Some(format!("{} {}", format_number(converted)?, target.symbol))
+}
+
+/// Format a result without useless digits, or `None` if it's not a number.
+fn format_number(value: f64) -> Option<String> {
+    if !value.is_finite() {
+        return None;
+    }
+    if value != 0.0 && (value.abs() >= 1e15 || value.abs() < 1e-6) {
+        return Some(format!("{value:e}"));
+    }
+
+    let formatted = format!("{value:.10}");
+    let formatted = formatted.trim_end_matches('0').trim_end_matches('.');
+    Some(match formatted {
+        "-0" => "0".to_string(),
+        formatted => formatted.to_string(),
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dimension {
+    Length,
+    Mass,
+    Time,
+    Data,
+    Temperature,
+}
+
+/// A unit, converted to the base unit of its dimension with `value * scale + offset`.
+#[derive(Debug, Clone, Copy)]
+struct Unit {
+    names: &'static [&'static str],
+    symbol: &'static str,
+    dimension: Dimension,
+    scale: f64,
+    offset: f64,
+}
+
+impl Unit {
+    const fn new(
+        names: &'static [&'static str],
+        symbol: &'static str,
+        dimension: Dimension,
+        scale: f64,
+    ) -> Self {
+        Self {
+            names,
+            symbol,
+            dimension,
+            scale,
+            offset: 0.0,
+        }
+    }
+
+    fn find(name: &str) -> Option<Self> {
+        let name = name.to_lowercase();
+        UNITS
+            .iter()
+            .find(|unit| unit.names.contains(&name.as_str()))
+            .copied()
+    }
+}
+
+#[rustfmt::skip]
+const UNITS: &[Unit] = &[
+    Unit::new(&["mm", "millimeter", "millimeters"], "mm", Dimension::Length, 0.001),
+    Unit::new(&["cm", "centimeter", "centimeters"], "cm", Dimension::Length, 0.01),
+    Unit::new(&["m", "meter", "meters"], "m", Dimension::Length, 1.0),
+    Unit::new(&["km", "kilometer", "kilometers"], "km", Dimension::Length, 1000.0),
+    Unit::new(&["in", "inch", "inches"], "in", Dimension::Length, 0.0254),
+    Unit::new(&["ft", "foot", "feet"], "ft", Dimension::Length, 0.3048),
+    Unit::new(&["yd", "yard", "yards"], "yd", Dimension::Length, 0.9144),
+    Unit::new(&["mi", "mile", "miles"], "mi", Dimension::Length, 1609.344),
+    Unit::new(&["mg", "milligram", "milligrams"], "mg", Dimension::Mass, 0.001),
+    Unit::new(&["g", "gram", "grams"], "g", Dimension::Mass, 1.0),
+    Unit::new(&["kg", "kilogram", "kilograms"], "kg", Dimension::Mass, 1000.0),
+    Unit::new(&["t", "ton", "tons", "tonne", "tonnes"], "t", Dimension::Mass, 1_000_000.0),
+    Unit::new(&["oz", "ounce", "ounces"], "oz", Dimension::Mass, 28.349523125),
+    Unit::new(&["lb", "lbs", "pound", "pounds"], "lb", Dimension::Mass, 453.59237),
+    Unit::new(&["ms", "millisecond", "milliseconds"], "ms", Dimension::Time, 0.001),
+    Unit::new(&["s", "sec", "second", "seconds"], "s", Dimension::Time, 1.0),
+    Unit::new(&["min", "minute", "minutes"], "min", Dimension::Time, 60.0),
+    Unit::new(&["h", "hour", "hours"], "h", Dimension::Time, 3600.0),
+    Unit::new(&["d", "day", "days"], "d", Dimension::Time, 86400.0),
+    Unit::new(&["week", "weeks"], "weeks", Dimension::Time, 604800.0),
+    Unit::new(&["b", "byte", "bytes"], "B", Dimension::Data, 1.0),
+    Unit::new(&["kb", "kilobyte", "kilobytes"], "kB", Dimension::Data, 1e3),
+    Unit::new(&["mb", "megabyte", "megabytes"], "MB", Dimension::Data, 1e6),
+    Unit::new(&["gb", "gigabyte", "gigabytes"], "GB", Dimension::Data, 1e9),
+    Unit::new(&["tb", "terabyte", "terabytes"], "TB", Dimension::Data, 1e12),
+    Unit::new(&["kib", "kibibyte", "kibibytes"], "KiB", Dimension::Data, 1024.0),
+    Unit::new(&["mib", "mebibyte", "mebibytes"], "MiB", Dimension::Data, 1048576.0),
+    Unit::new(&["gib", "gibibyte", "gibibytes"], "GiB", Dimension::Data, 1073741824.0),
+    Unit::new(&["tib", "tebibyte", "tebibytes"], "TiB", Dimension::Data, 1099511627776.0),
+    Unit::new(&["k", "kelvin"], "K", Dimension::Temperature, 1.0),
+    Unit {
+        names: &["c", "celsius"], symbol: "°C", dimension: Dimension::Temperature,
+        scale: 1.0, offset: 273.15,
+    },
+    Unit {
+        names: &["f", "fahrenheit"], symbol: "°F", dimension: Dimension::Temperature,
+        scale: 5.0 / 9.0, offset: 459.67 * 5.0 / 9.0,
+    },
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Operator(char),
+    OpenParen,
+    CloseParen,
+}
+
+fn tokenize(input: &str) -> Option<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars = input.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '0'..='9' | '.' => {
+                let mut number = String::new();
+                while let Some(&c) = chars.peek() {
+                    match c {
+                        '0'..='9' | '.' => number.push(c),
+                        // Thousands separators, IE. `1,000,000`.
+                        ',' | '_' => (),
+                        _ => break,
+                    }
+                    chars.next();
+                }
+                tokens.push(Token::Number(number.parse().ok()?));
+            }
+            c if c.is_alphabetic() => {
+                let mut ident = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !c.is_alphabetic() {
+                        break;
+                    }
+                    ident.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Ident(ident));
+            }
+            '+' | '-' | '*' | '/' | '%' | '^' => {
+                tokens.push(Token::Operator(c));
+                chars.next();
+            }
+            '×' => {
+                tokens.push(Token::Operator('*'));
+                chars.next();
+            }
+            '÷' => {
+                tokens.push(Token::Operator('/'));
+                chars.next();
+            }
+            '(' => {
+                tokens.push(Token::OpenParen);
+                chars.next();
+            }
+            ')' => {
+                tokens.push(Token::CloseParen);
+                chars.next();
+            }
+            _ => return None,
+        }
+    }
+
+    Some(tokens)
+}
+
+/// A recursive descent parser, evaluating the expression as it goes.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn new(tokens: Vec<Token>) -> Self {
+        Self {
+            tokens,
+            position: 0,
+        }
+    }
+
+    /// Evaluate all the tokens, or `None` if they are not a valid expression.
+    fn parse(mut self) -> Option<f64> {
+        let value = self.expression()?;
+        (self.position == self.tokens.len()).then_some(value)
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn eat_operator(&mut self, operators: &[char]) -> Option<char> {
+        match self.peek() {
+            Some(Token::Operator(op)) if operators.contains(op) => {
+                let op = *op;
+                self.position += 1;
+                Some(op)
+            }
+            _ => None,
+        }
+    }
+
+    // expression = term (("+" | "-") term)*
+    fn expression(&mut self) -> Option<f64> {
+        let mut value = self.term()?;
+        while let Some(op) = self.eat_operator(&['+', '-']) {
+            let rhs = self.term()?;
+            value = if op == '+' { value + rhs } else { value - rhs };
+        }
+        Some(value)
+    }
+
+    // term = unary (("*" | "/" | "%") unary)*
+    fn term(&mut self) -> Option<f64> {
+        let mut value = self.unary()?;
+        while let Some(op) = self.eat_operator(&['*', '/', '%']) {
+            let rhs = self.unary()?;
+            value = match op {
+                '*' => value * rhs,
+                '/' => value / rhs,
+                _ => value % rhs,
+            };
+        }
+        Some(value)
+    }
+
+    // unary = ("-" | "+") unary | power
+    fn unary(&mut self) -> Option<f64> {
+        match self.eat_operator(&['-', '+']) {
+            Some('-') => Some(-self.unary()?),
+            Some(_) => self.unary(),
+            None => self.power(),
+        }
+    }
+
+    // power = primary ("^" unary)?
+    fn power(&mut self) -> Option<f64> {
+        let base = self.primary()?;
+        if self.eat_operator(&['^']).is_some() {
+            return Some(base.powf(self.unary()?));
+        }
+        Some(base)
+    }
+
+    // primary = number | constant | function "(" expression ")" | "(" expression ")"
+    fn primary(&mut self) -> Option<f64> {
+        match self.next()? {
+            Token::Number(value) => Some(value),
+            Token::OpenParen => {
+                let value = self.expression()?;
+                (self.next()? == Token::CloseParen).then_some(value)
+            }
+            Token::Ident(ident) => {
+                let function: fn(f64) -> f64 = match ident.to_lowercase().as_str() {
+                    "pi" => return Some(std::f64::consts::PI),
+                    "e" => return Some(std::f64::consts::E),
+                    "sqrt" => f64::sqrt,
+                    "abs" => f64::abs,
+                    "ln" => f64::ln,
+                    "log" => f64::log10,
+                    "sin" => f64::sin,
+                    "cos" => f64::cos,
+                    "tan" => f64::tan,
+                    "round" => f64::round,
+                    "floor" => f64::floor,
+                    "ceil" => f64::ceil,
+                    _ => return None,
+                };
+                if self.next()? != Token::OpenParen {
+                    return None;
+                }
+                let value = self.expression()?;
+                (self.next()? == Token::CloseParen).then(|| function(value))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn precedence() {
+        assert_eq!(calculate("1 + 2 * 3").as_deref(), Some("7"));
+        assert_eq!(calculate("(1 + 2) * 3").as_deref(), Some("9"));
+        assert_eq!(calculate("10 - 4 - 3").as_deref(), Some("3"));
+        assert_eq!(calculate("12 / 2 / 3").as_deref(), Some("2"));
+        assert_eq!(calculate("7 % 3 + 1").as_deref(), Some("2"));
+        assert_eq!(calculate("2 × 3 ÷ 4").as_deref(), Some("1.5"));
+        assert_eq!(calculate("sqrt(16) + 1").as_deref(), Some("5"));
+        assert_eq!(calculate("1,000 * 3").as_deref(), Some("3000"));
+    }
+
+    #[test]
+    fn power_is_right_associative() {
+        assert_eq!(calculate("2 ^ 3 ^ 2").as_deref(), Some("512"));
+        assert_eq!(calculate("(2 ^ 3) ^ 2").as_deref(), Some("64"));
+        assert_eq!(calculate("2 * 3 ^ 2").as_deref(), Some("18"));
+    }
+
+    #[test]
+    fn unary_minus() {
+        assert_eq!(calculate("-2 ^ 2").as_deref(), Some("-4"));
+        assert_eq!(calculate("(-2) ^ 2").as_deref(), Some("4"));
+        assert_eq!(calculate("2 ^ -1").as_deref(), Some("0.5"));
+        assert_eq!(calculate("3 - -2").as_deref(), Some("5"));
+        assert_eq!(calculate("-(1 + 2) * 2").as_deref(), Some("-6"));
+        assert_eq!(calculate("-0 * 1").as_deref(), Some("0"));
+    }
+
+    #[test]
+    fn temperatures() {
+        assert_eq!(calculate("212 f to c").as_deref(), Some("100 °C"));
+        assert_eq!(calculate("100 c to f").as_deref(), Some("212 °F"));
+        assert_eq!(calculate("0 c in f").as_deref(), Some("32 °F"));
+        assert_eq!(
+            calculate("-40 fahrenheit to celsius").as_deref(),
+            Some("-40 °C")
+        );
+        assert_eq!(calculate("0 k to c").as_deref(), Some("-273.15 °C"));
+        assert_eq!(calculate("32 F to K").as_deref(), Some("273.15 K"));
+    }
+
+    #[test]
+    fn conversions() {
+        assert_eq!(
+            calculate("3 * 2 km to mi").as_deref(),
+            Some("3.7282271534 mi")
+        );
+        assert_eq!(calculate("2 h in min").as_deref(), Some("120 min"));
+        assert_eq!(calculate("1 gib to mb").as_deref(), Some("1073.741824 MB"));
+        assert_eq!(calculate("10 kg to m"), None);
+        assert_eq!(calculate("10 kg to parsecs"), None);
+    }
+
+    #[test]
+    fn in_as_unit_and_separator() {
+        assert_eq!(
+            calculate("10 m in in").as_deref(),
+            Some("393.7007874016 in")
+        );
+        assert_eq!(calculate("5 in to cm").as_deref(), Some("12.7 cm"));
+        assert_eq!(calculate("1 ft in in").as_deref(), Some("12 in"));
+        assert_eq!(calculate("254 cm in inches").as_deref(), Some("100 in"));
+    }
+
+    #[test]
+    fn not_calculations() {
+        assert_eq!(calculate(""), None);
+        assert_eq!(calculate("firefox"), None);
+        assert_eq!(calculate("hello world"), None);
+        assert_eq!(calculate("what is love"), None);
+        assert_eq!(calculate("1 +"), None);
+        assert_eq!(calculate("(1 + 2"), None);
+        assert_eq!(calculate("sqrt 4"), None);
+        assert_eq!(calculate("1 / 0"), None);
+        assert_eq!(calculate("1 $ 2"), None);
+    }
+
+    #[test]
+    fn single_tokens() {
+        assert_eq!(calculate("42"), None);
+        assert_eq!(calculate("3.14"), None);
+        assert_eq!(calculate("pi"), None);
+        assert_eq!(calculate("e"), None);
+        assert_eq!(calculate("pi * 2").as_deref(), Some("6.2831853072"));
+    }
+
+    #[test]
+    fn number_format() {
+        assert_eq!(format_number(0.1 + 0.2).as_deref(), Some("0.3"));
+        assert_eq!(format_number(1.0 / 3.0).as_deref(), Some("0.3333333333"));
+        assert_eq!(format_number(1e20).as_deref(), Some("1e20"));
+        assert_eq!(format_number(f64::NAN), None);
+    }
+}
@@ -0,0 +1,48 @@
+//! Shell commands, IE. `> notify-send hello`.
+
+use std::ffi::OsStr;
+use std::rc::Rc;
+
+use gtk::{gdk, gio};
+
+use super::{ResultIcon, SearchProvider, SearchResult};
+
+#[derive(Debug)]
+pub struct CommandProvider;
+
+impl SearchProvider for CommandProvider {
+    fn name(&self) -> &'static str {
+        "command"
+    }
+
+    fn default_priority(&self) -> i32 {
+        90
+    }
+
+    fn prefix(&self) -> Option<&'static str> {
+        Some(">")
+    }
+
+    fn search(&self, query: &str) -> Vec<SearchResult> {
+        if query.is_empty() {
+            return vec![];
+        }
+
+        let command = query.to_string();
+        vec![SearchResult {
+            title: query.to_string(),
+            subtitle: Some("Run command".to_string()),
+            icon: Some(ResultIcon::Icon(
+                gio::ThemedIcon::new("utilities-terminal-symbolic").into(),
+            )),
+            score: 0,
+            activate: Rc::new(move |_: &gdk::Display| {
+                // GIO reaps the process once it exits, we don't need to wait for it.
+                let argv: [&OsStr; 3] = ["sh".as_ref(), "-c".as_ref(), command.as_ref()];
+                if let Err(err) = gio::Subprocess::newv(&argv, gio::SubprocessFlags::NONE) {
+                    warn!(?err, ?command, "Failed to run command");
+                }
+            }),
+        }]
+    }
+}
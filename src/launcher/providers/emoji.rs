@@ -0,0 +1,193 @@
+//! Emoji and unicode characters, copied to the clipboard.
+//!
+//! Emoji names come from the data GTK uses for its own emoji chooser, so we don't ship our own.
+//! Other characters can be searched by their code point, IE. `U+00E9`.
+
+use std::cell::OnceCell;
+use std::rc::Rc;
+
+use gtk::prelude::*;
+use gtk::{gdk, gio, glib};
+
+use super::{ResultIcon, SearchProvider, SearchResult};
+use crate::launcher::fuzzy;
+
+/// The emoji data bundled in GTK resources.
+const EMOJI_DATA_PATH: &str = "/org/gtk/libgtk/emoji/en.data";
+/// The type of the emoji data, each emoji being its code points, its name, its localized name,
+/// its keywords, its localized keywords, and its group.
+const EMOJI_DATA_TYPE: &str = "a(aussasasu)";
+/// The most emoji shown, short queries match a lot of them.
+const MAX_RESULTS: usize = 8;
+/// The shortest query searching emoji, a single character matches most of them.
+const MIN_QUERY_LEN: usize = 2;
+
+#[derive(Debug, Default)]
+pub struct EmojiProvider {
+    emoji: OnceCell<Vec<Emoji>>,
+}
+
+#[derive(Debug)]
+struct Emoji {
+    text: String,
+    name: String,
+    /// Words describing the emoji, like `hand` or `up` for 👍.
+    keywords: Vec<String>,
+}
+
+impl SearchProvider for EmojiProvider {
+    fn name(&self) -> &'static str {
+        "emoji"
+    }
+
+    fn default_priority(&self) -> i32 {
+        30
+    }
+
+    fn search(&self, query: &str) -> Vec<SearchResult> {
+        if let Some(c) = parse_code_point(query) {
+            return vec![character_result(
+                c.to_string(),
+                format!("U+{:04X}", c as u32),
+                0,
+            )];
+        }
+        if query.chars().count() < MIN_QUERY_LEN {
+            return vec![];
+        }
+
+        let emoji = self.emoji.get_or_init(|| {
+            load_emoji().unwrap_or_else(|err| {
+                warn!(?err, "Failed to load emoji data");
+                vec![]
+            })
+        });
+        let mut matches = emoji
+            .iter()
+            .filter_map(|emoji| {
+                let score = std::iter::once(&emoji.name)
+                    .chain(&emoji.keywords)
+                    .filter_map(|name| fuzzy::score(query, name))
+                    .max()?;
+                Some((score, emoji))
+            })
+            .collect::<Vec<_>>();
+        matches.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+        matches
+            .into_iter()
+            .take(MAX_RESULTS)
+            .map(|(score, emoji)| character_result(emoji.text.clone(), emoji.name.clone(), score))
+            .collect()
+    }
+}
+
+fn character_result(text: String, name: String, score: i64) -> SearchResult {
+    SearchResult {
+        title: name,
+        subtitle: Some("Copy to clipboard".to_string()),
+        icon: Some(ResultIcon::Text(text.clone())),
+        score,
+        activate: Rc::new(move |display: &gdk::Display| display.clipboard().set_text(&text)),
+    }
+}
+
+/// Parse a unicode code point, like `U+1F600`.
+fn parse_code_point(query: &str) -> Option<char> {
+    let hex = query
+        .strip_prefix("U+")
+        .or_else(|| query.strip_prefix("u+"))?;
+    let c = char::from_u32(u32::from_str_radix(hex, 16).ok()?)?;
+    (!c.is_control()).then_some(c)
+}
+
+fn load_emoji() -> anyhow::Result<Vec<Emoji>> {
+    let bytes = gio::resources_lookup_data(EMOJI_DATA_PATH, gio::ResourceLookupFlags::NONE)?;
+    let data = glib::Variant::from_bytes_with_type(&bytes, glib::VariantTy::new(EMOJI_DATA_TYPE)?);
+    Ok(parse_emoji(&data))
+}
+
+/// Parse the emoji data of GTK, see [`EMOJI_DATA_TYPE`].
+fn parse_emoji(data: &glib::Variant) -> Vec<Emoji> {
+    let mut emoji = vec![];
+    for item in data.iter() {
+        let (Some(code_points), Some(name), Some(keywords)) = (
+            item.child_value(0).get::<Vec<u32>>(),
+            item.child_value(1).get::<String>(),
+            item.child_value(3).get::<Vec<String>>(),
+        ) else {
+            continue;
+        };
+        // Zero code points are where GTK inserts the skin tone modifiers.
+        let text = code_points
+            .into_iter()
+            .filter(|code_point| *code_point != 0)
+            .filter_map(char::from_u32)
+            .collect();
+        emoji.push(Emoji {
+            text,
+            name,
+            keywords,
+        });
+    }
+
+    emoji
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_points() {
+        assert_eq!(parse_code_point("U+00E9"), Some('é'));
+        assert_eq!(parse_code_point("u+1f600"), Some('😀'));
+        assert_eq!(parse_code_point("U+0007"), None);
+        assert_eq!(parse_code_point("U+D800"), None);
+        assert_eq!(parse_code_point("U+"), None);
+        assert_eq!(parse_code_point("00E9"), None);
+    }
+
+    #[test]
+    fn parses_emoji_data() {
+        let data = vec![
+            (
+                vec![0x1f44d_u32, 0],
+                "thumbs up".to_string(),
+                "pouce vers le haut".to_string(),
+                vec!["+1".to_string(), "hand".to_string()],
+                vec!["main".to_string()],
+                1_u32,
+            ),
+            (
+                vec![0x1f1eb, 0x1f1f7],
+                "flag: France".to_string(),
+                "drapeau : France".to_string(),
+                vec!["flag".to_string()],
+                vec!["drapeau".to_string()],
+                9,
+            ),
+        ]
+        .to_variant();
+        assert_eq!(data.type_().as_str(), EMOJI_DATA_TYPE);
+
+        let emoji = parse_emoji(&data);
+        assert_eq!(emoji.len(), 2);
+        assert_eq!(emoji[0].text, "👍");
+        assert_eq!(emoji[0].name, "thumbs up");
+        assert_eq!(emoji[0].keywords, ["+1", "hand"]);
+        assert_eq!(emoji[1].text, "🇫🇷");
+        assert_eq!(emoji[1].name, "flag: France");
+    }
+
+    #[test]
+    fn loads_gtk_emoji() {
+        // GTK registers its resources when initialized.
+        gtk::init().expect("a display is needed to initialize GTK");
+
+        let emoji = load_emoji().unwrap();
+        let thumbs_up = emoji.iter().find(|emoji| emoji.text == "👍").unwrap();
+        assert_eq!(thumbs_up.name, "thumbs up");
+        assert!(thumbs_up.keywords.iter().any(|keyword| keyword == "hand"));
+    }
+}
@@ -0,0 +1,68 @@
+//! Recently used files, from `recently-used.xbel`.
+
+use std::rc::Rc;
+
+use gtk::prelude::*;
+use gtk::{gdk, gio};
+
+use super::{ResultIcon, SearchProvider, SearchResult};
+use crate::launcher::fuzzy;
+
+/// The most files shown, they should not drown the other results.
+const MAX_RESULTS: usize = 5;
+
+#[derive(Debug)]
+pub struct FilesProvider;
+
+impl SearchProvider for FilesProvider {
+    fn name(&self) -> &'static str {
+        "files"
+    }
+
+    fn default_priority(&self) -> i32 {
+        20
+    }
+
+    fn search(&self, query: &str) -> Vec<SearchResult> {
+        if query.is_empty() {
+            return vec![];
+        }
+
+        // GTK keeps the list of recent files up to date for us.
+        let mut items = gtk::RecentManager::default()
+            .items()
+            .into_iter()
+            .filter(|item| item.exists())
+            .filter_map(|item| {
+                let score = fuzzy::score(query, &item.display_name())?;
+                Some((score, item))
+            })
+            .collect::<Vec<_>>();
+        items.sort_by(|(a_score, a), (b_score, b)| {
+            b_score
+                .cmp(a_score)
+                .then_with(|| b.modified().to_unix().cmp(&a.modified().to_unix()))
+        });
+
+        items
+            .into_iter()
+            .take(MAX_RESULTS)
+            .map(|(score, item)| {
+                let uri = item.uri().to_string();
+                SearchResult {
+                    title: item.display_name().to_string(),
+                    subtitle: item.uri_display().map(String::from),
+                    icon: item.gicon().map(ResultIcon::Icon),
+                    score,
+                    activate: Rc::new(move |display: &gdk::Display| {
+                        let context = display.app_launch_context();
+                        if let Err(err) = gio::AppInfo::launch_default_for_uri(&uri, Some(&context))
+                        {
+                            warn!(?err, ?uri, "Failed to open recent file");
+                        }
+                    }),
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,120 @@
+//! Launcher search providers.
+//!
+//! Each provider searches its own kind of results, and the launcher merges them. Results are
+//! ordered by the priority of their provider first, then by their own score, so a matching
+//! calculation always comes before applications, and the web search always comes last.
+//!
+//! A provider can also have a prefix, like `>` for shell commands. Queries starting with it only
+//! go to that provider.
+
+mod apps;
+mod calculator;
+mod command;
+mod emoji;
+mod files;
+mod web;
+
+use std::fmt;
+use std::rc::Rc;
+
+use gtk::{gdk, gio};
+
+/// A source of launcher results.
+pub trait SearchProvider: fmt::Debug {
+    /// A short unique name, used to change the priority in the configuration.
+    fn name(&self) -> &'static str;
+
+    /// The priority of the results, when the configuration does not change it.
+    ///
+    /// Results with a higher priority are shown first, whatever their score.
+    fn default_priority(&self) -> i32;
+
+    /// The prefix queries must start with to go to this provider, and to this provider only.
+    fn prefix(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Get the results for this query, without its prefix and surrounding whitespace.
+    fn search(&self, query: &str) -> Vec<SearchResult>;
+}
+
+/// A single launcher result.
+pub struct SearchResult {
+    pub title: String,
+    pub subtitle: Option<String>,
+    pub icon: Option<ResultIcon>,
+    /// How well the result matches the query, higher is better.
+    ///
+    /// Only meaningful between results of the same provider.
+    pub score: i64,
+    /// What to do when the user picks this result.
+    pub activate: Rc<dyn Fn(&gdk::Display)>,
+}
+
+impl fmt::Debug for SearchResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SearchResult")
+            .field("title", &self.title)
+            .field("subtitle", &self.subtitle)
+            .field("score", &self.score)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ResultIcon {
+    Icon(gio::Icon),
+    /// Text to show in place of an icon, IE. an emoji.
+    Text(String),
+}
+
+/// Create all the built-in providers.
+pub fn all() -> Vec<Box<dyn SearchProvider>> {
+    vec![
+        Box::new(calculator::CalculatorProvider),
+        Box::new(command::CommandProvider),
+        Box::new(apps::AppsProvider::new()),
+        Box::new(emoji::EmojiProvider::default()),
+        Box::new(files::FilesProvider),
+        Box::new(web::WebProvider),
+    ]
+}
+
+/// Search all the providers, and merge their results.
+pub fn search(providers: &[Box<dyn SearchProvider>], query: &str) -> Vec<SearchResult> {
+    let query = query.trim();
+    let prefixed = providers.iter().find_map(|provider| {
+        let rest = query.strip_prefix(provider.prefix()?)?;
+        Some((provider, rest.trim()))
+    });
+    let searches = match prefixed {
+        Some((provider, rest)) => vec![(provider, rest)],
+        None => providers
+            .iter()
+            .filter(|provider| provider.prefix().is_none())
+            .map(|provider| (provider, query))
+            .collect(),
+    };
+
+    let priorities = &crate::config::get().launcher.priorities;
+    let mut results = vec![];
+    for (provider, query) in searches {
+        let priority = priorities
+            .get(provider.name())
+            .copied()
+            .unwrap_or_else(|| provider.default_priority());
+        results.extend(
+            provider
+                .search(query)
+                .into_iter()
+                .map(|result| (priority, result)),
+        );
+    }
+
+    results.sort_by(|(a_priority, a), (b_priority, b)| {
+        b_priority
+            .cmp(a_priority)
+            .then_with(|| b.score.cmp(&a.score))
+    });
+    results.into_iter().map(|(_, result)| result).collect()
+}
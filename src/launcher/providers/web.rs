@@ -0,0 +1,47 @@
+//! Web search, always offered last.
+
+use std::rc::Rc;
+
+use gtk::prelude::*;
+use gtk::{gdk, gio, glib};
+
+use super::{ResultIcon, SearchProvider, SearchResult};
+
+#[derive(Debug)]
+pub struct WebProvider;
+
+impl SearchProvider for WebProvider {
+    fn name(&self) -> &'static str {
+        "web"
+    }
+
+    fn default_priority(&self) -> i32 {
+        0
+    }
+
+    fn search(&self, query: &str) -> Vec<SearchResult> {
+        if query.is_empty() {
+            return vec![];
+        }
+
+        let escaped = glib::Uri::escape_string(query, None, false);
+        let url = crate::config::get()
+            .launcher
+            .web_search_url
+            .replace("{}", &escaped);
+        vec![SearchResult {
+            title: format!("Search the web for “{query}”"),
+            subtitle: Some(url.clone()),
+            icon: Some(ResultIcon::Icon(
+                gio::ThemedIcon::new("web-browser-symbolic").into(),
+            )),
+            score: 0,
+            activate: Rc::new(move |display: &gdk::Display| {
+                let context = display.app_launch_context();
+                if let Err(err) = gio::AppInfo::launch_default_for_uri(&url, Some(&context)) {
+                    warn!(?err, ?url, "Failed to open web search");
+                }
+            }),
+        }]
+    }
+}
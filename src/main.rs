@@ -1,8 +1,15 @@
 #[macro_use]
 extern crate tracing;
 
+mod app_info;
 mod application;
+mod battery_warnings;
+mod bluetooth_agent;
+mod config;
 mod daemons;
+mod launcher;
+mod notifications;
+mod osd;
 mod panel;
 mod sass;
 mod widgets;
@@ -0,0 +1,112 @@
+//! Notification popups.
+//!
+//! The notifications sent to our notification server pop up in the top right corner, newest on
+//! top, with their actions as buttons. They go away after their timeout, except the critical ones
+//! that stay until they get dismissed.
+
+mod popup;
+
+use adw::prelude::*;
+use gtk::glib;
+use gtk4_layer_shell::{Edge, LayerShell};
+use tokio::sync::broadcast;
+
+use self::popup::NotificationPopup;
+use crate::application::Application;
+use crate::daemons::notifications::{self, Notification, Request};
+
+/// How many popups we show at once, the oldest ones get dismissed past it.
+const MAX_POPUPS: usize = 5;
+
+/// Start showing the notifications sent to our notification server.
+pub fn start(app: &Application) {
+    let mut requests = notifications::get().subscribe();
+    let window = new_window(app);
+    let popups = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(8)
+        .width_request(380)
+        .css_classes(["notifications"])
+        .build();
+    window.set_content(Some(&popups));
+
+    glib::spawn_future_local(async move {
+        loop {
+            let request = match requests.recv().await {
+                Ok(request) => request,
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+
+            match request {
+                Request::NewNotification {
+                    notification,
+                    replace,
+                } => show(&popups, &notification, replace),
+                Request::CloseNotification(id) => {
+                    if let Some(popup) = find_popup(&popups, id) {
+                        popup.stop_timeout();
+                        popups.remove(&popup);
+                    }
+                }
+            }
+            window.set_visible(popups.first_child().is_some());
+        }
+    });
+}
+
+fn new_window(app: &Application) -> adw::ApplicationWindow {
+    let window = adw::ApplicationWindow::builder()
+        .application(app)
+        .css_classes(["notifications-window"])
+        .build();
+    window.init_layer_shell();
+    window.set_namespace("fht.desktop.Shell.Notifications");
+    window.set_layer(gtk4_layer_shell::Layer::Overlay);
+    window.set_keyboard_mode(gtk4_layer_shell::KeyboardMode::None);
+    window.set_anchor(Edge::Top, true);
+    window.set_anchor(Edge::Right, true);
+    window.set_margin(Edge::Top, 12);
+    window.set_margin(Edge::Right, 12);
+    window
+}
+
+fn show(popups: &gtk::Box, notification: &Notification, replace: Option<u32>) {
+    let popup = NotificationPopup::new(notification);
+    // Replaced notifications keep their place.
+    if let Some(previous) = replace.and_then(|id| find_popup(popups, id)) {
+        previous.stop_timeout();
+        popups.insert_child_after(&popup, Some(&previous));
+        popups.remove(&previous);
+        return;
+    }
+
+    popups.prepend(&popup);
+    let mut child = popups.first_child();
+    for _ in 0..MAX_POPUPS {
+        child = child.and_then(|child| child.next_sibling());
+    }
+    while let Some(current) = child {
+        child = current.next_sibling();
+        let Ok(popup) = current.downcast::<NotificationPopup>() else {
+            continue;
+        };
+        // Critical notifications only go away when the user dismisses them.
+        if !popup.has_css_class("critical") {
+            glib::spawn_future_local(notifications::get().dismiss(popup.id()));
+        }
+    }
+}
+
+fn find_popup(popups: &gtk::Box, id: u32) -> Option<NotificationPopup> {
+    let mut child = popups.first_child();
+    while let Some(current) = child {
+        child = current.next_sibling();
+        if let Ok(popup) = current.downcast::<NotificationPopup>() {
+            if popup.id() == id {
+                return Some(popup);
+            }
+        }
+    }
+    None
+}
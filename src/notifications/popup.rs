@@ -0,0 +1,238 @@
+//! A single notification popup.
+
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::{gio, glib, pango};
+
+use crate::daemons::notifications::{self, Notification, Urgency};
+
+/// The action picked by clicking the notification itself.
+const DEFAULT_ACTION: &str = "default";
+
+mod imp {
+    use std::cell::{Cell, RefCell};
+
+    use super::*;
+
+    #[derive(Default, Debug)]
+    pub struct NotificationPopup {
+        pub(super) id: Cell<u32>,
+        pub(super) expire_timeout: RefCell<Option<glib::SourceId>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for NotificationPopup {
+        const NAME: &'static str = "NotificationPopup";
+        type Type = super::NotificationPopup;
+        type ParentType = gtk::Box;
+    }
+
+    impl ObjectImpl for NotificationPopup {
+        fn dispose(&self) {
+            if let Some(expire_timeout) = self.expire_timeout.take() {
+                expire_timeout.remove();
+            }
+        }
+    }
+
+    impl WidgetImpl for NotificationPopup {}
+    impl BoxImpl for NotificationPopup {}
+}
+
+glib::wrapper! {
+    pub struct NotificationPopup(ObjectSubclass<imp::NotificationPopup>)
+        @extends gtk::Box, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Orientable;
+}
+
+impl NotificationPopup {
+    pub fn new(notification: &Notification) -> Self {
+        let obj: Self = glib::Object::builder()
+            .property("orientation", gtk::Orientation::Vertical)
+            .property("spacing", 8)
+            .build();
+        let id = notification.id;
+        obj.imp().id.set(id);
+        obj.add_css_class("notification");
+        if notification.urgency == Urgency::Critical {
+            obj.add_css_class("critical");
+        }
+
+        let header = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(12)
+            .build();
+        header.append(&icon(notification));
+
+        let text = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(2)
+            .hexpand(true)
+            .build();
+        let summary = gtk::Label::builder()
+            .label(notification.summary.as_str())
+            .xalign(0.0)
+            .wrap(true)
+            .wrap_mode(pango::WrapMode::WordChar)
+            .css_classes(["heading"])
+            .build();
+        text.append(&summary);
+        if let Some(app_name) = &notification.app_name {
+            summary.set_tooltip_text(Some(app_name));
+        }
+        if let Some(body) = &notification.body {
+            let label = gtk::Label::builder()
+                .xalign(0.0)
+                .wrap(true)
+                .wrap_mode(pango::WrapMode::WordChar)
+                .lines(4)
+                .ellipsize(pango::EllipsizeMode::End)
+                .build();
+            // Senders are supposed to send markup, but plenty send plain text with stray `&`.
+            if is_label_markup(body) {
+                label.set_markup(body);
+            } else {
+                label.set_text(body);
+            }
+            text.append(&label);
+        }
+        header.append(&text);
+
+        let close_button = gtk::Button::builder()
+            .icon_name("window-close-symbolic")
+            .valign(gtk::Align::Start)
+            .css_classes(["flat", "circular"])
+            .build();
+        close_button.connect_clicked(move |_| {
+            glib::spawn_future_local(notifications::get().dismiss(id));
+        });
+        header.append(&close_button);
+        obj.append(&header);
+
+        let buttons = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(6)
+            .homogeneous(true)
+            .build();
+        for (key, label) in &notification.actions {
+            if key == DEFAULT_ACTION {
+                continue;
+            }
+            let button = gtk::Button::with_label(label);
+            let key = key.clone();
+            button.connect_clicked(move |_| {
+                let key = key.clone();
+                glib::spawn_future_local(async move {
+                    notifications::get().invoke_action(id, &key).await;
+                });
+            });
+            buttons.append(&button);
+        }
+        if buttons.first_child().is_some() {
+            obj.append(&buttons);
+        }
+
+        // Clicking the notification picks its default action, or just dismisses it.
+        let has_default = notification
+            .actions
+            .iter()
+            .any(|(key, _)| key == DEFAULT_ACTION);
+        let click_gesture = gtk::GestureClick::builder()
+            .button(gtk::gdk::BUTTON_PRIMARY)
+            .build();
+        click_gesture.connect_released(move |_, _, _, _| {
+            glib::spawn_future_local(async move {
+                if has_default {
+                    notifications::get().invoke_action(id, DEFAULT_ACTION).await;
+                } else {
+                    notifications::get().dismiss(id).await;
+                }
+            });
+        });
+        header.add_controller(click_gesture);
+
+        if let Some(timeout) = notification.timeout {
+            let weak_obj = obj.downgrade();
+            let expire_timeout = glib::timeout_add_local_once(timeout, move || {
+                if let Some(obj) = weak_obj.upgrade() {
+                    // The source is gone once it ran, we must not remove it a second time.
+                    let _ = obj.imp().expire_timeout.take();
+                }
+                glib::spawn_future_local(notifications::get().expire(id));
+            });
+            obj.imp().expire_timeout.replace(Some(expire_timeout));
+        }
+
+        obj
+    }
+
+    pub fn id(&self) -> u32 {
+        self.imp().id.get()
+    }
+
+    /// Stop expiring the notification, IE. when it gets replaced or closed.
+    pub fn stop_timeout(&self) {
+        if let Some(expire_timeout) = self.imp().expire_timeout.take() {
+            expire_timeout.remove();
+        }
+    }
+}
+
+/// Get the icon of a notification, either an icon name or a file.
+fn icon(notification: &Notification) -> gtk::Image {
+    let image = gtk::Image::builder()
+        .pixel_size(32)
+        .valign(gtk::Align::Start)
+        .build();
+    match notification.app_icon.as_deref() {
+        Some(icon) if icon.starts_with("file://") => {
+            image.set_from_gicon(&gio::FileIcon::new(&gio::File::for_uri(icon)));
+        }
+        Some(icon) if icon.starts_with('/') => image.set_from_file(Some(icon)),
+        Some(icon) => image.set_icon_name(Some(icon)),
+        None => image.set_icon_name(Some("dialog-information-symbolic")),
+    }
+    image
+}
+
+/// Check whether some text is valid markup for a label.
+///
+/// Labels support links on top of Pango markup, which Pango alone doesn't know about.
+fn is_label_markup(text: &str) -> bool {
+    let mut without_links = String::with_capacity(text.len());
+    let mut rest = text;
+    let next_link_tag = |text: &str| {
+        [text.find("<a "), text.find("</a>")]
+            .into_iter()
+            .flatten()
+            .min()
+    };
+    while let Some(start) = next_link_tag(rest) {
+        without_links.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            return false;
+        };
+        rest = &rest[start + end + 1..];
+    }
+    without_links.push_str(rest);
+    pango::parse_markup(&without_links, '\0').is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn label_markup() {
+        assert!(is_label_markup("Plain text"));
+        assert!(is_label_markup("<b>Bold</b> and <i>italic</i>"));
+        assert!(is_label_markup(
+            "See <a href=\"https://example.com/?a=1&amp;b=2\">the <b>page</b></a> and <a href=\"x\">this</a>"
+        ));
+        assert!(!is_label_markup("Fish & chips"));
+        assert!(!is_label_markup(
+            "<img src=\"/tmp/image.png\" alt=\"image\"/>"
+        ));
+        assert!(!is_label_markup("<a href=\"https://example.com\""));
+    }
+}
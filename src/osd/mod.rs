@@ -0,0 +1,309 @@
+//! On-screen display.
+//!
+//! A small popup at the bottom of the screen, showing an icon with an optional level bar and
+//! label when something like the screen brightness or the volume changes, for example from a
+//! keybinding. It's shown on every output, or only the one with the focused window.
+//!
+//! Scripts can show it too with the `show-osd` action, IE. for the caps lock from a compositor
+//! keybinding:
+//!
+//! ```sh
+//! gapplication action fht.desktop.Shell show-osd \
+//!     "{'icon': <'input-caps-lock-symbolic'>, 'label': <'Caps Lock On'>}"
+//! ```
+//!
+//! The optional `level` goes from `0.0` to `1.0`, and shows the level bar.
+
+mod window;
+
+use futures_util::StreamExt;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::{gdk, glib};
+
+use self::window::OsdWindow;
+use crate::application::Application;
+use crate::config::OsdOutput;
+use crate::daemons::{audio, backlight, compositor, network_manager, upower};
+
+/// What the OSD shows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Content {
+    pub icon_name: String,
+    /// The level shown in the bar, from `0.0` to `1.0`, if any.
+    pub level: Option<f64>,
+    /// The text next to the bar, the level in percents by default.
+    pub label: Option<String>,
+}
+
+impl Content {
+    pub fn level(icon_name: &str, level: f64) -> Self {
+        Self {
+            icon_name: icon_name.to_string(),
+            level: Some(level),
+            label: None,
+        }
+    }
+
+    pub fn label(icon_name: &str, label: &str) -> Self {
+        Self {
+            icon_name: icon_name.to_string(),
+            level: None,
+            label: Some(label.to_string()),
+        }
+    }
+
+    /// Get the content from a `a{sv}` dictionary with the `icon`, and optionally the `level` and
+    /// `label`.
+    pub fn from_variant(variant: &glib::Variant) -> Option<Self> {
+        let dict = glib::VariantDict::new(Some(variant));
+        Some(Self {
+            icon_name: dict.lookup::<String>("icon").ok().flatten()?,
+            level: dict.lookup::<f64>("level").ok().flatten(),
+            label: dict.lookup::<String>("label").ok().flatten(),
+        })
+    }
+}
+
+mod imp {
+    use std::cell::{OnceCell, RefCell};
+
+    use glib::WeakRef;
+
+    use super::*;
+
+    #[derive(Default, Debug)]
+    pub struct Osd {
+        pub(super) app: OnceCell<WeakRef<Application>>,
+        /// The windows, one per output or a single one following the focused output.
+        pub(super) windows: RefCell<Vec<OsdWindow>>,
+        /// The content to show once we get back to the main loop.
+        pub(super) pending: RefCell<Option<Content>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for Osd {
+        const NAME: &'static str = "Osd";
+        type Type = super::Osd;
+    }
+
+    impl ObjectImpl for Osd {
+        fn dispose(&self) {
+            for window in self.windows.take() {
+                window.destroy();
+            }
+        }
+    }
+}
+
+glib::wrapper! {
+    /// The on-screen display, managing its windows on the outputs.
+    pub struct Osd(ObjectSubclass<imp::Osd>);
+}
+
+impl Osd {
+    pub fn new(app: &Application) -> Self {
+        let osd: Self = glib::Object::new();
+        osd.imp().app.set(app.downgrade()).unwrap();
+        osd
+    }
+
+    /// Show the OSD with this content, replacing what it was showing.
+    ///
+    /// Changes coming in a burst, IE. when holding the volume key, only show the last one.
+    pub fn show(&self, content: Content) {
+        if self.imp().pending.replace(Some(content)).is_some() {
+            return; // already scheduled.
+        }
+
+        let weak_self = self.downgrade();
+        glib::idle_add_local_once(move || {
+            let Some(osd) = weak_self.upgrade() else {
+                return;
+            };
+            let Some(content) = osd.imp().pending.take() else {
+                return;
+            };
+            osd.show_now(&content);
+        });
+    }
+
+    /// Show the OSD with an icon and a level, from `0.0` to `1.0`.
+    pub fn show_level(&self, icon_name: &str, level: f64) {
+        self.show(Content::level(icon_name, level));
+    }
+
+    fn show_now(&self, content: &Content) {
+        let Some(app) = self.imp().app.get().and_then(|app| app.upgrade()) else {
+            return;
+        };
+        let Some(display) = gdk::Display::default() else {
+            return;
+        };
+        let monitors = display
+            .monitors()
+            .into_iter()
+            .filter_map(|monitor| monitor.ok()?.downcast::<gdk::Monitor>().ok())
+            .collect::<Vec<_>>();
+
+        let mut windows = self.imp().windows.borrow_mut();
+        match crate::config::get().osd.output {
+            OsdOutput::All => {
+                // Follow the outputs getting plugged and unplugged.
+                windows.retain(|window| {
+                    let plugged = window
+                        .monitor()
+                        .is_some_and(|monitor| monitors.contains(&monitor));
+                    if !plugged {
+                        window.destroy();
+                    }
+                    plugged
+                });
+                for monitor in &monitors {
+                    if !windows
+                        .iter()
+                        .any(|window| window.monitor().as_ref() == Some(monitor))
+                    {
+                        windows.push(OsdWindow::new(&app, Some(monitor)));
+                    }
+                }
+                for window in windows.iter() {
+                    window.show_content(content, None);
+                }
+            }
+            OsdOutput::Focused => {
+                if windows.is_empty() {
+                    windows.push(OsdWindow::new(&app, None));
+                }
+                let monitor = focused_monitor(&monitors);
+                windows[0].show_content(content, monitor.as_ref());
+            }
+        }
+    }
+}
+
+/// Get the monitor of the output with the focused window.
+fn focused_monitor(monitors: &[gdk::Monitor]) -> Option<gdk::Monitor> {
+    let state = compositor::get().state();
+    let output = state.focused_window()?.output.as_deref()?;
+    monitors
+        .iter()
+        .find(|monitor| monitor.connector().as_deref() == Some(output))
+        .cloned()
+}
+
+/// Create the OSD and start showing it on changes.
+pub fn start(app: &Application) -> Osd {
+    let osd = Osd::new(app);
+    watch_backlight(&osd);
+    watch_kbd_backlight(&osd);
+    watch_volume(&osd);
+    watch_wireless(&osd);
+    osd
+}
+
+fn watch_backlight(osd: &Osd) {
+    let mut backlight = backlight::get().subscribe();
+    let weak_osd = osd.downgrade();
+    glib::spawn_future_local(async move {
+        let mut previous = backlight.borrow_and_update().clone();
+        while backlight.changed().await.is_ok() {
+            let current = backlight.borrow_and_update().clone();
+            let Some(osd) = weak_osd.upgrade() else {
+                break;
+            };
+
+            // Only show actual brightness changes, not the device changing.
+            if let (Some(previous), Some(current)) = (&previous, &current) {
+                if previous.name == current.name && previous.brightness != current.brightness {
+                    osd.show_level("display-brightness-symbolic", current.fraction());
+                }
+            }
+            previous = current;
+        }
+    });
+}
+
+fn watch_kbd_backlight(osd: &Osd) {
+    let weak_osd = osd.downgrade();
+    glib::spawn_future_local(async move {
+        let proxy = upower::get().kbd_backlight();
+        let Ok(max_brightness) = proxy.get_max_brightness().await else {
+            return; // no keyboard backlight.
+        };
+        let Ok(mut changes) = proxy.receive_brightness_changed_with_source().await else {
+            return;
+        };
+
+        while let Some(changed) = changes.next().await {
+            let Ok(args) = changed.args() else {
+                continue;
+            };
+            // Changes from the hardware keys are "internal", the "external" ones come from
+            // programs like our own quick controls.
+            if args.source != "internal" || max_brightness <= 0 {
+                continue;
+            }
+
+            let Some(osd) = weak_osd.upgrade() else {
+                break;
+            };
+            let level = f64::from(args.value) / f64::from(max_brightness);
+            osd.show_level("keyboard-brightness-symbolic", level);
+        }
+    });
+}
+
+fn watch_volume(osd: &Osd) {
+    let mut state = audio::get().subscribe();
+    let weak_osd = osd.downgrade();
+    glib::spawn_future_local(async move {
+        let mut previous = state.borrow_and_update().default_sink().cloned();
+        while state.changed().await.is_ok() {
+            let current = state.borrow_and_update().default_sink().cloned();
+            let Some(osd) = weak_osd.upgrade() else {
+                break;
+            };
+
+            // Switching devices is not a volume change.
+            if let (Some(previous), Some(current)) = (&previous, &current) {
+                let changed = previous.volume != current.volume || previous.muted != current.muted;
+                if previous.id == current.id && changed {
+                    let level = if current.muted { 0.0 } else { current.volume };
+                    osd.show_level(current.volume_icon_name(), level);
+                }
+            }
+            previous = current;
+        }
+    });
+}
+
+fn watch_wireless(osd: &Osd) {
+    let weak_osd = osd.downgrade();
+    glib::spawn_future_local(async move {
+        let proxy = network_manager::get().proxy();
+        let mut changes = proxy.receive_wireless_enabled_changed().await;
+        let mut enabled = proxy.wireless_enabled().await.ok();
+
+        while let Some(changed) = changes.next().await {
+            let Ok(new_enabled) = changed.get().await else {
+                continue;
+            };
+            // The first value we get is the current one.
+            if enabled == Some(new_enabled) {
+                continue;
+            }
+            enabled = Some(new_enabled);
+
+            let Some(osd) = weak_osd.upgrade() else {
+                break;
+            };
+            let content = if new_enabled {
+                Content::label("network-wireless-symbolic", "Wi-Fi On")
+            } else {
+                Content::label("airplane-mode-symbolic", "Wi-Fi Off")
+            };
+            osd.show(content);
+        }
+    });
+}
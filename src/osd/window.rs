@@ -0,0 +1,244 @@
+//! The layer-shell window of the on-screen display on a single output.
+
+use std::time::Duration;
+
+use fht_animation::Animation;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::{gdk, gio, glib};
+use gtk4_layer_shell::{Edge, LayerShell};
+
+use super::Content;
+use crate::application::Application;
+
+/// How long it takes to fully show or hide the OSD.
+const ANIMATION_DURATION: Duration = Duration::from_millis(200);
+
+/// How far the OSD slides up when it gets shown, in pixels.
+const SLIDE_DISTANCE: f64 = 12.0;
+
+mod imp {
+    use std::cell::{Cell, OnceCell, RefCell};
+
+    use adw::prelude::AdwApplicationWindowExt;
+    use adw::subclass::prelude::AdwApplicationWindowImpl;
+
+    use super::*;
+
+    #[derive(Default, Debug)]
+    pub struct OsdWindow {
+        pub(super) content: OnceCell<gtk::Box>,
+        pub(super) icon: OnceCell<gtk::Image>,
+        pub(super) level_bar: OnceCell<gtk::LevelBar>,
+        pub(super) label: OnceCell<gtk::Label>,
+        pub(super) hide_timeout: RefCell<Option<glib::SourceId>>,
+        /// How much the OSD is shown, from `0.0` when hidden to `1.0`.
+        pub(super) progress: Cell<f64>,
+        /// Whether we are showing the OSD, or hiding it.
+        pub(super) shown: Cell<bool>,
+        pub(super) animation: RefCell<Option<Animation>>,
+        pub(super) tick_callback: RefCell<Option<gtk::TickCallbackId>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for OsdWindow {
+        const NAME: &'static str = "OsdWindow";
+        type Type = super::OsdWindow;
+        type ParentType = adw::ApplicationWindow;
+    }
+
+    impl ObjectImpl for OsdWindow {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.add_css_class("osd-window");
+
+            let content = gtk::Box::builder()
+                .orientation(gtk::Orientation::Horizontal)
+                .spacing(12)
+                .css_classes(["osd"])
+                .build();
+            let icon = gtk::Image::builder().pixel_size(24).build();
+            let level_bar = gtk::LevelBar::builder()
+                .width_request(200)
+                .valign(gtk::Align::Center)
+                .hexpand(true)
+                .build();
+            let label = gtk::Label::builder()
+                .xalign(1.0)
+                .css_classes(["numeric"])
+                .build();
+            content.append(&icon);
+            content.append(&level_bar);
+            content.append(&label);
+            obj.set_content(Some(&content));
+
+            self.content.set(content).unwrap();
+            self.icon.set(icon).unwrap();
+            self.level_bar.set(level_bar).unwrap();
+            self.label.set(label).unwrap();
+            obj.set_progress(0.0);
+        }
+
+        fn dispose(&self) {
+            if let Some(hide_timeout) = self.hide_timeout.take() {
+                hide_timeout.remove();
+            }
+            if let Some(tick_callback) = self.tick_callback.take() {
+                tick_callback.remove();
+            }
+        }
+    }
+
+    impl WidgetImpl for OsdWindow {}
+    impl WindowImpl for OsdWindow {}
+    impl ApplicationWindowImpl for OsdWindow {}
+    impl AdwApplicationWindowImpl for OsdWindow {}
+}
+
+glib::wrapper! {
+    pub struct OsdWindow(ObjectSubclass<imp::OsdWindow>)
+        @extends adw::ApplicationWindow, gtk::Widget, gtk::Window, gtk::ApplicationWindow,
+        @implements gio::ActionMap, gio::ActionGroup, gtk::Root;
+}
+
+impl OsdWindow {
+    pub fn new(app: &Application, monitor: Option<&gdk::Monitor>) -> Self {
+        let window: Self = glib::Object::builder().property("application", app).build();
+        window.init_layer_shell();
+        window.set_namespace("fht.desktop.Shell.Osd");
+        window.set_layer(gtk4_layer_shell::Layer::Overlay);
+        window.set_keyboard_mode(gtk4_layer_shell::KeyboardMode::None);
+        window.set_anchor(Edge::Bottom, true);
+        // Stay above the panel.
+        window.set_margin(Edge::Bottom, 120);
+        if let Some(monitor) = monitor {
+            window.set_monitor(monitor);
+        }
+        window
+    }
+
+    /// Show the OSD with this content, on this monitor if any.
+    ///
+    /// Showing it again while it's visible just updates it and restarts the timeout.
+    pub fn show_content(&self, content: &Content, monitor: Option<&gdk::Monitor>) {
+        let imp = self.imp();
+        let icon = imp.icon.get().unwrap();
+        icon.set_icon_name(Some(&content.icon_name));
+
+        let level_bar = imp.level_bar.get().unwrap();
+        level_bar.set_visible(content.level.is_some());
+        if let Some(level) = content.level {
+            level_bar.set_value(level.clamp(0.0, 1.0));
+        }
+
+        let label = imp.label.get().unwrap();
+        let text = match (&content.label, content.level) {
+            (Some(text), _) => text.clone(),
+            (None, Some(level)) => format!("{:.0}%", level.clamp(0.0, 1.0) * 100.0),
+            (None, None) => String::new(),
+        };
+        // Keep the level bar from moving with the percentage.
+        let width_chars = if content.label.is_none() { 4 } else { -1 };
+        label.set_width_chars(width_chars);
+        label.set_visible(!text.is_empty());
+        label.set_text(&text);
+
+        if let Some(monitor) = monitor {
+            if self.monitor().as_ref() != Some(monitor) {
+                // Move it right away, sliding from another output would look odd.
+                self.set_visible(false);
+                imp.shown.set(false);
+                imp.animation.replace(None);
+                self.set_progress(0.0);
+                self.set_monitor(monitor);
+            }
+        }
+
+        self.present();
+        self.animate(true);
+
+        let weak_self = self.downgrade();
+        let timeout = crate::config::get().osd.timeout;
+        let hide_timeout =
+            glib::timeout_add_local_once(Duration::from_millis(timeout.into()), move || {
+                let Some(window) = weak_self.upgrade() else {
+                    return;
+                };
+                // The source is gone once it ran, we must not remove it a second time.
+                let _ = window.imp().hide_timeout.take();
+                window.animate(false);
+            });
+        if let Some(previous) = imp.hide_timeout.replace(Some(hide_timeout)) {
+            previous.remove();
+        }
+    }
+
+    /// Animate showing or hiding the OSD, from wherever it currently is.
+    fn animate(&self, shown: bool) {
+        let imp = self.imp();
+        if imp.shown.replace(shown) == shown && imp.animation.borrow().is_none() {
+            return;
+        }
+
+        let to = if shown { 1.0 } else { 0.0 };
+        let animations_enabled = self.settings().is_gtk_enable_animations();
+        if !animations_enabled {
+            imp.animation.replace(None);
+            self.set_progress(to);
+            self.animation_done();
+            return;
+        }
+
+        // Going back midway should not take the full duration.
+        let from = imp.progress.get();
+        let duration = ANIMATION_DURATION.mul_f64((to - from).abs());
+        imp.animation
+            .replace(Some(Animation::new(from, to, duration)));
+        if imp.tick_callback.borrow().is_some() {
+            return;
+        }
+
+        let tick_callback = self.add_tick_callback(|window, frame_clock| {
+            let imp = window.imp();
+            let now = Duration::from_micros(frame_clock.frame_time() as u64);
+            let mut animation = imp.animation.borrow_mut();
+            let finished = match animation.as_mut() {
+                Some(animation) => {
+                    animation.tick(now);
+                    window.set_progress(*animation.value());
+                    animation.is_finished()
+                }
+                None => true,
+            };
+            if !finished {
+                return glib::ControlFlow::Continue;
+            }
+
+            *animation = None;
+            drop(animation);
+            // GTK removes the callback itself when we break.
+            let _ = imp.tick_callback.take();
+            window.animation_done();
+            glib::ControlFlow::Break
+        });
+        imp.tick_callback.replace(Some(tick_callback));
+    }
+
+    fn animation_done(&self) {
+        if !self.imp().shown.get() {
+            self.set_visible(false);
+        }
+    }
+
+    /// Fade and slide the content, without resizing the window.
+    fn set_progress(&self, progress: f64) {
+        let imp = self.imp();
+        imp.progress.set(progress);
+        let content = imp.content.get().unwrap();
+        content.set_opacity(progress);
+        let offset = (SLIDE_DISTANCE * (1.0 - progress)).round() as i32;
+        content.set_margin_top(offset);
+        content.set_margin_bottom(SLIDE_DISTANCE as i32 - offset);
+    }
+}
@@ -1,22 +1,27 @@
+use gtk::prelude::WidgetExt;
 use gtk::subclass::prelude::*;
 use gtk::{gio, glib};
 
 use crate::application::Application;
 
 mod imp {
+    use std::cell::OnceCell;
     use std::sync::LazyLock;
 
     use adw::subclass::prelude::AdwApplicationWindowImpl;
     use glib::object::ObjectExt;
     use glib::subclass::Signal;
-    use gtk::prelude::{BoxExt, ButtonExt, WidgetExt};
+    use gtk::prelude::{BoxExt, ButtonExt, PopoverExt, WidgetExt};
 
     use super::*;
+    use crate::daemons::logind;
     use crate::widgets;
 
     #[derive(Default, Debug, gtk::CompositeTemplate)]
     #[template(resource = "/fht/desktop/Shell/ui/panel-window.ui")]
     pub struct PanelWindow {
+        #[template_child]
+        pub(super) banner: TemplateChild<adw::Banner>,
         #[template_child]
         centerbox: TemplateChild<gtk::CenterBox>,
         #[template_child]
@@ -25,6 +30,7 @@ mod imp {
         right_box: TemplateChild<gtk::Box>,
         #[template_child]
         middle_box: TemplateChild<gtk::Box>,
+        controls_popover: OnceCell<gtk::Popover>,
     }
 
     #[glib::object_subclass]
@@ -48,6 +54,22 @@ mod imp {
             self.parent_constructed();
             self.obj().add_css_class("panel-window");
 
+            self.left_box.append(
+                &gtk::Button::builder()
+                    .css_classes(["flat"])
+                    .icon_name("view-app-grid-symbolic")
+                    .tooltip_text("Applications")
+                    .action_name("app.toggle-launcher")
+                    .build(),
+            );
+            self.left_box
+                .append(&widgets::workspaces::WorkspacesWidget::new());
+            self.left_box.append(&widgets::taskbar::Taskbar::new());
+            self.left_box.append(&widgets::media::MediaWidget::new());
+
+            self.middle_box
+                .append(&widgets::focused_window::FocusedWindowWidget::new());
+
             self.right_box.append(
                 &gtk::Label::builder()
                     .use_markup(true)
@@ -58,6 +80,12 @@ mod imp {
             self.right_box
                 .append(&gtk::Separator::new(gtk::Orientation::Vertical));
 
+            self.right_box.append(&widgets::tray::Tray::new());
+
+            // Keep it close to the status section, but not in it since it has its own popover.
+            self.right_box
+                .append(&widgets::privacy::PrivacyIndicator::new());
+
             let status_widget = widgets::status::StatusWidget::new();
             let status_widget_button = gtk::Button::builder()
                 .css_classes(["flat"])
@@ -70,9 +98,91 @@ mod imp {
             }));
             self.right_box.append(&status_widget_button);
 
+            let controls_popover = gtk::Popover::builder()
+                .position(gtk::PositionType::Top)
+                .child(&widgets::controls::QuickControls::new())
+                .build();
+            controls_popover.set_parent(&status_widget_button);
+            self.obj().connect_local(
+                "toggle-controls",
+                false,
+                glib::clone!(@weak controls_popover => @default-return None, move |_| {
+                    if controls_popover.is_visible() {
+                        controls_popover.popdown();
+                    } else {
+                        controls_popover.popup();
+                    }
+                    None
+                }),
+            );
+            self.controls_popover.set(controls_popover).unwrap();
+
             self.right_box
                 .append(&gtk::Separator::new(gtk::Orientation::Vertical));
-            self.right_box.append(&widgets::time::TimeWidget::new());
+            let calendar = widgets::calendar::CalendarWidget::new();
+            let clock_stack = gtk::Stack::builder()
+                .transition_type(gtk::StackTransitionType::Crossfade)
+                .vhomogeneous(false)
+                .build();
+            clock_stack.add_titled(&calendar, Some("calendar"), "Calendar");
+            clock_stack.add_titled(
+                &widgets::timers::TimersWidget::new(),
+                Some("timers"),
+                "Timers",
+            );
+            let clock_popover_content = gtk::Box::builder()
+                .orientation(gtk::Orientation::Vertical)
+                .spacing(12)
+                .build();
+            clock_popover_content.append(
+                &gtk::StackSwitcher::builder()
+                    .stack(&clock_stack)
+                    .halign(gtk::Align::Center)
+                    .build(),
+            );
+            clock_popover_content.append(&clock_stack);
+            let clock_popover = gtk::Popover::builder()
+                .position(gtk::PositionType::Top)
+                .child(&clock_popover_content)
+                .build();
+            clock_popover.connect_show(glib::clone!(@weak calendar => move |_| {
+                calendar.show_today();
+            }));
+            let clock = gtk::Box::builder()
+                .orientation(gtk::Orientation::Horizontal)
+                .spacing(8)
+                .build();
+            clock.append(&widgets::timers::TimerBadge::new());
+            clock.append(&widgets::time::TimeWidget::new());
+            self.right_box.append(
+                &gtk::MenuButton::builder()
+                    .css_classes(["flat"])
+                    .child(&clock)
+                    .popover(&clock_popover)
+                    .build(),
+            );
+
+            // Without a logind session, there's nothing the power menu can do.
+            if logind::get().has_session() {
+                self.right_box
+                    .append(&gtk::Separator::new(gtk::Orientation::Vertical));
+                let power_menu = widgets::power_menu::PowerMenu::new();
+                let power_menu_popover = gtk::Popover::builder()
+                    .position(gtk::PositionType::Top)
+                    .child(&power_menu)
+                    .build();
+                // Closing the menu should never let a pending power off go through.
+                power_menu_popover.connect_closed(glib::clone!(@weak power_menu => move |_| {
+                    power_menu.cancel();
+                }));
+                self.right_box.append(
+                    &gtk::MenuButton::builder()
+                        .css_classes(["flat"])
+                        .icon_name("system-shutdown-symbolic")
+                        .popover(&power_menu_popover)
+                        .build(),
+                );
+            }
         }
 
         fn signals() -> &'static [glib::subclass::Signal] {
@@ -80,6 +190,12 @@ mod imp {
                 LazyLock::new(|| vec![Signal::builder("toggle-controls").build()]);
             &SIGNALS
         }
+
+        fn dispose(&self) {
+            if let Some(controls_popover) = self.controls_popover.get() {
+                controls_popover.unparent();
+            }
+        }
     }
 
     impl WidgetImpl for PanelWindow {}
@@ -110,4 +226,17 @@ impl PanelWindow {
     pub fn new(app: &Application) -> Self {
         glib::Object::builder().property("application", app).build()
     }
+
+    /// Show a full-width banner on top of the panel, replacing the previous one if any.
+    pub fn show_banner(&self, title: &str, css_classes: &[&str]) {
+        let banner = &self.imp().banner;
+        banner.set_title(title);
+        banner.set_css_classes(css_classes);
+        banner.set_revealed(true);
+    }
+
+    /// Hide the banner shown with [`Self::show_banner`].
+    pub fn hide_banner(&self) {
+        self.imp().banner.set_revealed(false);
+    }
 }
@@ -0,0 +1,245 @@
+//! Calendar popover of the clock.
+//!
+//! A month calendar with week numbers, and the agenda of the events from the calendar daemon,
+//! starting at the selected day. Days with events are marked in the calendar.
+
+use chrono::{Datelike, Duration, Local, NaiveDate, NaiveTime};
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+
+use crate::daemons::calendar::{self, Occurrence};
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, Debug)]
+    pub struct CalendarWidget {
+        pub(super) calendar: gtk::Calendar,
+        pub(super) agenda: gtk::Box,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for CalendarWidget {
+        const NAME: &'static str = "CalendarWidget";
+        type Type = super::CalendarWidget;
+        type ParentType = gtk::Box;
+    }
+
+    impl ObjectImpl for CalendarWidget {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.add_css_class("calendar");
+
+            let config = &crate::config::get().calendar;
+            self.calendar
+                .set_show_week_numbers(config.show_week_numbers);
+            obj.append(&self.calendar);
+
+            self.agenda.set_orientation(gtk::Orientation::Vertical);
+            self.agenda.set_spacing(6);
+            self.agenda.add_css_class("agenda");
+            let scrolled_window = gtk::ScrolledWindow::builder()
+                .hscrollbar_policy(gtk::PolicyType::Never)
+                .propagate_natural_height(true)
+                .max_content_height(300)
+                .child(&self.agenda)
+                .build();
+            obj.append(&scrolled_window);
+
+            let weak_obj = obj.downgrade();
+            self.calendar.connect_day_selected(move |_| {
+                if let Some(obj) = weak_obj.upgrade() {
+                    obj.imp().sync_agenda();
+                }
+            });
+            for property in ["month", "year"] {
+                let weak_obj = obj.downgrade();
+                self.calendar
+                    .connect_notify_local(Some(property), move |_, _| {
+                        if let Some(obj) = weak_obj.upgrade() {
+                            obj.imp().sync_marks();
+                        }
+                    });
+            }
+
+            let mut events = calendar::get().subscribe();
+            let weak_obj = obj.downgrade();
+            glib::spawn_future_local(async move {
+                loop {
+                    events.borrow_and_update();
+                    let Some(obj) = weak_obj.upgrade() else {
+                        break;
+                    };
+                    obj.imp().sync_marks();
+                    obj.imp().sync_agenda();
+                    drop(obj);
+
+                    if events.changed().await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    }
+
+    impl CalendarWidget {
+        fn selected_date(&self) -> NaiveDate {
+            let date = self.calendar.date();
+            NaiveDate::from_ymd_opt(date.year(), date.month() as u32, date.day_of_month() as u32)
+                .unwrap_or_else(|| Local::now().date_naive())
+        }
+
+        /// Mark the days with events in the shown month.
+        pub(super) fn sync_marks(&self) {
+            self.calendar.clear_marks();
+            // Navigating months changes the selected day, it's always in the shown month.
+            let Some(first) = self.selected_date().with_day(1) else {
+                return;
+            };
+            let Some(next_first) = first.checked_add_months(chrono::Months::new(1)) else {
+                return;
+            };
+
+            let from = first.and_time(NaiveTime::MIN);
+            let to = next_first.and_time(NaiveTime::MIN);
+            for occurrence in calendar::get().occurrences(from, to) {
+                // Mark all the days of events lasting several days.
+                let mut day = occurrence.start.date().max(first);
+                loop {
+                    self.calendar.mark_day(day.day());
+                    day += Duration::days(1);
+                    let day_start = day.and_time(NaiveTime::MIN);
+                    if day >= next_first || day_start >= occurrence.end {
+                        break;
+                    }
+                }
+            }
+        }
+
+        /// Show the events of the next days, from the selected one.
+        pub(super) fn sync_agenda(&self) {
+            while let Some(child) = self.agenda.first_child() {
+                self.agenda.remove(&child);
+            }
+
+            let from = self.selected_date().and_time(NaiveTime::MIN);
+            let days = crate::config::get().calendar.agenda_days.max(1);
+            let to = from + Duration::days(days.into());
+            let occurrences = calendar::get().occurrences(from, to);
+            if occurrences.is_empty() {
+                self.agenda.append(
+                    &gtk::Label::builder()
+                        .label("No events")
+                        .css_classes(["dim-label"])
+                        .build(),
+                );
+                return;
+            }
+
+            let mut current_day = None;
+            for occurrence in occurrences {
+                // Events that started earlier are shown in the first day.
+                let day = occurrence.start.date().max(from.date());
+                if current_day != Some(day) {
+                    current_day = Some(day);
+                    self.agenda.append(
+                        &gtk::Label::builder()
+                            .label(day_title(day))
+                            .xalign(0.0)
+                            .css_classes(["heading"])
+                            .build(),
+                    );
+                }
+                self.agenda.append(&occurrence_row(&occurrence));
+            }
+        }
+    }
+
+    impl WidgetImpl for CalendarWidget {}
+    impl BoxImpl for CalendarWidget {}
+}
+
+glib::wrapper! {
+    pub struct CalendarWidget(ObjectSubclass<imp::CalendarWidget>)
+        @extends gtk::Box, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Orientable;
+}
+
+impl CalendarWidget {
+    pub fn new() -> Self {
+        glib::Object::builder()
+            .property("orientation", gtk::Orientation::Vertical)
+            .property("spacing", 12)
+            .build()
+    }
+
+    /// Go back to today, IE. when opening the calendar again.
+    pub fn show_today(&self) {
+        if let Ok(now) = glib::DateTime::now_local() {
+            self.imp().calendar.select_day(&now);
+        }
+    }
+}
+
+fn day_title(day: NaiveDate) -> String {
+    let today = Local::now().date_naive();
+    if day == today {
+        "Today".to_string()
+    } else if Some(day) == today.succ_opt() {
+        "Tomorrow".to_string()
+    } else {
+        day.format("%A %-d %B").to_string()
+    }
+}
+
+fn occurrence_row(occurrence: &Occurrence) -> gtk::Box {
+    let row = gtk::Box::builder()
+        .orientation(gtk::Orientation::Horizontal)
+        .spacing(12)
+        .css_classes(["agenda-event"])
+        .build();
+    let time = if occurrence.all_day {
+        "All day".to_string()
+    } else {
+        occurrence
+            .start
+            .format(super::time::time_format())
+            .to_string()
+    };
+    row.append(
+        &gtk::Label::builder()
+            .label(time)
+            .width_chars(7)
+            .xalign(0.0)
+            .valign(gtk::Align::Start)
+            .css_classes(["numeric", "dim-label"])
+            .build(),
+    );
+
+    let labels = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .build();
+    labels.append(
+        &gtk::Label::builder()
+            .label(&occurrence.summary)
+            .xalign(0.0)
+            .wrap(true)
+            .max_width_chars(30)
+            .build(),
+    );
+    if let Some(location) = &occurrence.location {
+        labels.append(
+            &gtk::Label::builder()
+                .label(location)
+                .xalign(0.0)
+                .ellipsize(gtk::pango::EllipsizeMode::End)
+                .max_width_chars(30)
+                .css_classes(["dim-label", "caption"])
+                .build(),
+        );
+    }
+    row.append(&labels);
+    row
+}
@@ -0,0 +1,332 @@
+//! Battery section of the quick controls.
+//!
+//! Lists the charge of each system battery, and when the firmware supports it, a toggle to stop
+//! charging at the end threshold configured in UPower. Keeping a laptop that sits docked all day
+//! below 100% helps a lot with battery longevity.
+
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+
+mod imp {
+    use futures_util::StreamExt;
+    use tokio::sync::broadcast;
+
+    use super::*;
+    use crate::daemons::upower::{self, device};
+
+    #[derive(Default, Debug)]
+    pub struct BatterySection {}
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for BatterySection {
+        const NAME: &'static str = "BatterySection";
+        type Type = super::BatterySection;
+        type ParentType = gtk::Box;
+    }
+
+    impl ObjectImpl for BatterySection {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.add_css_class("battery-section");
+            // We only show ourselves once we find an actual battery.
+            obj.set_visible(false);
+
+            for device in upower::get().devices() {
+                self.add_device(device);
+            }
+
+            // Follow batteries getting plugged in or out.
+            let mut events = upower::get().subscribe();
+            let weak_obj = obj.downgrade();
+            glib::spawn_future_local(async move {
+                loop {
+                    let event = match events.recv().await {
+                        Ok(event) => event,
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    };
+
+                    let Some(obj) = weak_obj.upgrade() else {
+                        break;
+                    };
+                    match event {
+                        upower::Event::DeviceAdded(device) => obj.imp().add_device(device),
+                        upower::Event::DeviceRemoved(id) => obj.imp().remove_device(&id),
+                    }
+                }
+            });
+        }
+    }
+
+    impl BatterySection {
+        fn add_device(&self, device: upower::Device) {
+            let weak_obj = self.obj().downgrade();
+            glib::spawn_future_local(async move {
+                let proxy = device.proxy();
+                let is_battery = matches!(proxy.type_().await, Ok(device::Type::Battery));
+                // Peripherals (mice, keyboards, etc.) are also batteries, but they don't power
+                // the system itself.
+                if !is_battery || !proxy.power_supply().await.unwrap_or(false) {
+                    return;
+                }
+
+                match add_battery(&device).await {
+                    Ok(battery_box) => {
+                        let Some(obj) = weak_obj.upgrade() else {
+                            return;
+                        };
+                        obj.append(&battery_box);
+                        obj.set_visible(true);
+                    }
+                    Err(err) => {
+                        error!(?err, device_id = ?device.id(), "Failed to add battery");
+                    }
+                }
+            });
+        }
+
+        fn remove_device(&self, id: &str) {
+            let obj = self.obj();
+            let mut child = obj.first_child();
+            while let Some(battery_box) = child {
+                child = battery_box.next_sibling();
+                if battery_box.widget_name().as_str() == id {
+                    obj.remove(&battery_box);
+                }
+            }
+
+            obj.set_visible(obj.first_child().is_some());
+        }
+    }
+
+    async fn add_battery(device: &upower::Device) -> anyhow::Result<gtk::Box> {
+        let proxy = device.proxy();
+        let battery_box = gtk::Box::builder()
+            .name(&*device.id())
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(6)
+            .css_classes(["battery"])
+            .build();
+
+        let header_box = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(6)
+            .build();
+        let model = proxy.model().await.unwrap_or_default();
+        let name_label = gtk::Label::builder()
+            .label(if model.is_empty() {
+                "Battery"
+            } else {
+                model.as_str()
+            })
+            .css_classes(["heading"])
+            .hexpand(true)
+            .xalign(0.0)
+            .build();
+        let percentage_label = gtk::Label::new(Some(&format!("{:.0}%", proxy.percentage().await?)));
+        header_box.append(&name_label);
+        header_box.append(&percentage_label);
+        battery_box.append(&header_box);
+        battery_box.append(&add_charge_threshold_row(proxy.clone()).await?);
+
+        let mut percentage_changes = proxy.receive_percentage_changed().await;
+        let weak_percentage_label = percentage_label.downgrade();
+        glib::spawn_future_local(async move {
+            while let Some(changed) = percentage_changes.next().await {
+                let Ok(percentage) = changed.get().await else {
+                    continue;
+                };
+
+                let Some(percentage_label) = weak_percentage_label.upgrade() else {
+                    break; // label does not exist anymore?
+                };
+                percentage_label.set_text(&format!("{percentage:.0}%"));
+            }
+        });
+
+        Ok(battery_box)
+    }
+
+    async fn add_charge_threshold_row(
+        proxy: device::DeviceProxy<'static>,
+    ) -> anyhow::Result<gtk::Box> {
+        let row = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(3)
+            .css_classes(["charge-threshold"])
+            .visible(proxy.charge_threshold_supported().await.unwrap_or(false))
+            .build();
+
+        let title_label = gtk::Label::builder().hexpand(true).xalign(0.0).build();
+        update_threshold_labels(
+            &title_label,
+            proxy.charge_start_threshold().await.unwrap_or(0),
+            proxy.charge_end_threshold().await.unwrap_or(0),
+        );
+
+        let enabled = proxy.charge_threshold_enabled().await.unwrap_or(false);
+        let switch = gtk::Switch::builder()
+            .active(enabled)
+            .state(enabled)
+            .valign(gtk::Align::Center)
+            .build();
+        let error_label = gtk::Label::builder()
+            .css_classes(["error", "caption"])
+            .wrap(true)
+            .xalign(0.0)
+            .visible(false)
+            .build();
+
+        let switch_box = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(6)
+            .build();
+        switch_box.append(&title_label);
+        switch_box.append(&switch);
+        row.append(&switch_box);
+        row.append(&error_label);
+
+        let weak_error_label = error_label.downgrade();
+        let switch_proxy = proxy.clone();
+        switch.connect_state_set(move |switch, enabled| {
+            // This also gets called when we sync the switch back to what UPower reports.
+            if switch.state() == enabled {
+                return glib::Propagation::Proceed;
+            }
+
+            // Wait for UPower to reply before changing the state, since this goes through polkit
+            // and the user might not be allowed to do it.
+            switch.set_sensitive(false);
+            let proxy = switch_proxy.clone();
+            let weak_switch = switch.downgrade();
+            let weak_error_label = weak_error_label.clone();
+            glib::spawn_future_local(async move {
+                let result = proxy.enable_charge_threshold(enabled).await;
+                let (Some(switch), Some(error_label)) =
+                    (weak_switch.upgrade(), weak_error_label.upgrade())
+                else {
+                    return;
+                };
+
+                switch.set_sensitive(true);
+                match result {
+                    Ok(()) => {
+                        switch.set_state(enabled);
+                        error_label.set_visible(false);
+                    }
+                    Err(err) => {
+                        warn!(?err, "Failed to toggle battery charge threshold");
+                        switch.set_active(switch.state());
+                        error_label.set_text(&describe_error(&err));
+                        error_label.set_visible(true);
+                    }
+                }
+            });
+
+            glib::Propagation::Stop
+        });
+
+        // Reflect changes made from outside the shell too.
+        let mut enabled_changes = proxy.receive_charge_threshold_enabled_changed().await;
+        let weak_switch = switch.downgrade();
+        glib::spawn_future_local(async move {
+            while let Some(changed) = enabled_changes.next().await {
+                let Ok(enabled) = changed.get().await else {
+                    continue;
+                };
+
+                let Some(switch) = weak_switch.upgrade() else {
+                    break;
+                };
+                switch.set_state(enabled);
+                switch.set_active(enabled);
+            }
+        });
+
+        let mut supported_changes = proxy.receive_charge_threshold_supported_changed().await;
+        let weak_row = row.downgrade();
+        glib::spawn_future_local(async move {
+            while let Some(changed) = supported_changes.next().await {
+                let Ok(supported) = changed.get().await else {
+                    continue;
+                };
+
+                let Some(row) = weak_row.upgrade() else {
+                    break;
+                };
+                row.set_visible(supported);
+            }
+        });
+
+        let mut end_threshold_changes = proxy.receive_charge_end_threshold_changed().await;
+        let weak_title_label = title_label.downgrade();
+        glib::spawn_future_local(async move {
+            while let Some(changed) = end_threshold_changes.next().await {
+                let Ok(end) = changed.get().await else {
+                    continue;
+                };
+                let start = proxy.charge_start_threshold().await.unwrap_or(0);
+
+                let Some(title_label) = weak_title_label.upgrade() else {
+                    break;
+                };
+                update_threshold_labels(&title_label, start, end);
+            }
+        });
+
+        Ok(row)
+    }
+
+    fn update_threshold_labels(title_label: &gtk::Label, start: u32, end: u32) {
+        // UPower reports 0 for thresholds it could not read.
+        if end > 0 && end < 100 {
+            title_label.set_text(&format!("Limit charging to {end}%"));
+        } else {
+            title_label.set_text("Limit charging");
+        }
+
+        if start > 0 && start < end {
+            title_label.set_tooltip_text(Some(&format!("Charging resumes below {start}%")));
+        } else {
+            title_label.set_tooltip_text(None);
+        }
+    }
+
+    /// Get a message we can show to the user from a failed UPower call.
+    fn describe_error(err: &zbus::Error) -> String {
+        match err {
+            zbus::Error::MethodError(name, _, _)
+                if matches!(
+                    name.as_str(),
+                    "org.freedesktop.DBus.Error.AccessDenied"
+                        | "org.freedesktop.DBus.Error.InteractiveAuthorizationRequired"
+                        | "org.freedesktop.PolicyKit1.Error.NotAuthorized"
+                ) =>
+            {
+                String::from("You are not authorized to change the charge limit")
+            }
+            zbus::Error::MethodError(_, Some(detail), _) => detail.clone(),
+            err => err.to_string(),
+        }
+    }
+
+    impl WidgetImpl for BatterySection {}
+    impl BoxImpl for BatterySection {}
+}
+
+glib::wrapper! {
+    pub struct BatterySection(ObjectSubclass<imp::BatterySection>)
+        @extends gtk::Box, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Orientable;
+}
+
+impl BatterySection {
+    pub fn new() -> Self {
+        glib::Object::builder()
+            .property("orientation", gtk::Orientation::Vertical)
+            .property("spacing", 12)
+            .build()
+    }
+}
@@ -0,0 +1,515 @@
+//! Bluetooth section of the quick controls.
+//!
+//! A toggle to power the adapter, the list of paired devices to connect or disconnect them, and
+//! when searching, the list of nearby devices to pair with. Pairing itself may need the user to
+//! confirm a code, which goes through the dialogs of [`crate::bluetooth_agent`].
+
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+
+mod imp {
+    use std::cell::{Cell, OnceCell, RefCell};
+
+    use futures_util::StreamExt;
+    use tokio::sync::broadcast;
+
+    use super::*;
+    use crate::daemons::bluez;
+
+    #[derive(Default, Debug)]
+    pub struct BluetoothSection {
+        powered_switch: OnceCell<gtk::Switch>,
+        discoverable_switch: OnceCell<gtk::Switch>,
+        scan_button: OnceCell<gtk::ToggleButton>,
+        paired_box: OnceCell<gtk::Box>,
+        available_box: OnceCell<gtk::Box>,
+        available_spinner: OnceCell<gtk::Spinner>,
+        // Tracks the properties of the current adapter.
+        adapter_tracker: RefCell<Option<glib::JoinHandle<()>>>,
+        // Whether we are updating the switches ourselves, to not write the value back.
+        syncing: Cell<bool>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for BluetoothSection {
+        const NAME: &'static str = "BluetoothSection";
+        type Type = super::BluetoothSection;
+        type ParentType = gtk::Box;
+    }
+
+    impl ObjectImpl for BluetoothSection {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.add_css_class("bluetooth-section");
+            // We only show ourselves once we find an adapter.
+            obj.set_visible(false);
+
+            let header_box = gtk::Box::builder()
+                .orientation(gtk::Orientation::Horizontal)
+                .spacing(6)
+                .build();
+            header_box.append(&gtk::Image::from_icon_name("bluetooth-symbolic"));
+            header_box.append(
+                &gtk::Label::builder()
+                    .label("Bluetooth")
+                    .css_classes(["heading"])
+                    .hexpand(true)
+                    .xalign(0.0)
+                    .build(),
+            );
+            let powered_switch = gtk::Switch::builder().valign(gtk::Align::Center).build();
+            powered_switch.connect_active_notify(glib::clone!(@weak obj => move |switch| {
+                if obj.imp().syncing.get() {
+                    return;
+                }
+
+                let powered = switch.is_active();
+                glib::spawn_future_local(async move {
+                    let Some(adapter) = bluez::get().adapter() else {
+                        return;
+                    };
+                    if let Err(err) = adapter.set_powered(powered).await {
+                        error!(?err, "Failed to toggle Bluetooth adapter");
+                    }
+                });
+            }));
+            header_box.append(&powered_switch);
+            obj.append(&header_box);
+
+            let discoverable_box = gtk::Box::builder()
+                .orientation(gtk::Orientation::Horizontal)
+                .spacing(6)
+                .build();
+            discoverable_box.append(
+                &gtk::Label::builder()
+                    .label("Visible to nearby devices")
+                    .hexpand(true)
+                    .xalign(0.0)
+                    .build(),
+            );
+            let discoverable_switch = gtk::Switch::builder().valign(gtk::Align::Center).build();
+            discoverable_switch.connect_active_notify(glib::clone!(@weak obj => move |switch| {
+                if obj.imp().syncing.get() {
+                    return;
+                }
+
+                let discoverable = switch.is_active();
+                glib::spawn_future_local(async move {
+                    let Some(adapter) = bluez::get().adapter() else {
+                        return;
+                    };
+                    if let Err(err) = adapter.set_discoverable(discoverable).await {
+                        error!(?err, "Failed to toggle Bluetooth discoverability");
+                    }
+                });
+            }));
+            discoverable_box.append(&discoverable_switch);
+            obj.append(&discoverable_box);
+
+            let paired_box = gtk::Box::builder()
+                .orientation(gtk::Orientation::Vertical)
+                .spacing(6)
+                .css_classes(["paired-devices"])
+                .build();
+            obj.append(&paired_box);
+
+            let scan_box = gtk::Box::builder()
+                .orientation(gtk::Orientation::Horizontal)
+                .spacing(6)
+                .build();
+            let scan_button = gtk::ToggleButton::builder()
+                .label("Search for devices")
+                .hexpand(true)
+                .build();
+            scan_button.connect_toggled(glib::clone!(@weak obj => move |button| {
+                if obj.imp().syncing.get() {
+                    return;
+                }
+
+                let scan = button.is_active();
+                glib::spawn_future_local(async move {
+                    let Some(adapter) = bluez::get().adapter() else {
+                        return;
+                    };
+                    let result = if scan {
+                        adapter.start_discovery().await
+                    } else {
+                        adapter.stop_discovery().await
+                    };
+                    if let Err(err) = result {
+                        error!(?err, scan, "Failed to toggle Bluetooth discovery");
+                    }
+                });
+            }));
+            let available_spinner = gtk::Spinner::new();
+            scan_box.append(&scan_button);
+            scan_box.append(&available_spinner);
+            obj.append(&scan_box);
+
+            let available_box = gtk::Box::builder()
+                .orientation(gtk::Orientation::Vertical)
+                .spacing(6)
+                .css_classes(["available-devices"])
+                .visible(false)
+                .build();
+            obj.append(&available_box);
+
+            // Searching drains the battery, and there's no point doing it while nobody looks.
+            obj.connect_unmap(glib::clone!(@weak scan_button => move |_| {
+                scan_button.set_active(false);
+            }));
+
+            self.powered_switch.set(powered_switch).unwrap();
+            self.discoverable_switch.set(discoverable_switch).unwrap();
+            self.scan_button.set(scan_button).unwrap();
+            self.paired_box.set(paired_box).unwrap();
+            self.available_box.set(available_box).unwrap();
+            self.available_spinner.set(available_spinner).unwrap();
+
+            let mut adapter = bluez::get().subscribe_adapter();
+            let weak_obj = obj.downgrade();
+            glib::spawn_future_local(async move {
+                loop {
+                    let adapter = adapter.borrow_and_update().clone();
+                    let Some(obj) = weak_obj.upgrade() else {
+                        break;
+                    };
+                    obj.imp().track_adapter(adapter);
+                    drop(obj);
+
+                    if adapter.changed().await.is_err() {
+                        break;
+                    }
+                }
+            });
+
+            for device in bluez::get().devices() {
+                self.add_device(device);
+            }
+
+            let mut events = bluez::get().subscribe();
+            let weak_obj = obj.downgrade();
+            glib::spawn_future_local(async move {
+                loop {
+                    let event = match events.recv().await {
+                        Ok(event) => event,
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    };
+
+                    let Some(obj) = weak_obj.upgrade() else {
+                        break;
+                    };
+                    match event {
+                        bluez::Event::DeviceAdded(device) => obj.imp().add_device(device),
+                        bluez::Event::DeviceChanged(device) => {
+                            obj.imp().remove_device(&device.id());
+                            obj.imp().add_device(device);
+                        }
+                        bluez::Event::DeviceRemoved(id) => obj.imp().remove_device(&id),
+                    }
+                }
+            });
+        }
+
+        fn dispose(&self) {
+            if let Some(tracker) = self.adapter_tracker.take() {
+                tracker.abort();
+            }
+        }
+    }
+
+    impl BluetoothSection {
+        fn track_adapter(&self, adapter: Option<bluez::adapter::Adapter1Proxy<'static>>) {
+            if let Some(tracker) = self.adapter_tracker.take() {
+                tracker.abort();
+            }
+
+            let obj = self.obj();
+            obj.set_visible(adapter.is_some());
+            let Some(adapter) = adapter else {
+                return;
+            };
+
+            let weak_obj = obj.downgrade();
+            let tracker = glib::spawn_future_local(async move {
+                let mut changes = futures_util::stream::select_all([
+                    adapter
+                        .receive_powered_changed()
+                        .await
+                        .map(|_| ())
+                        .boxed_local(),
+                    adapter
+                        .receive_discoverable_changed()
+                        .await
+                        .map(|_| ())
+                        .boxed_local(),
+                    adapter
+                        .receive_discovering_changed()
+                        .await
+                        .map(|_| ())
+                        .boxed_local(),
+                ]);
+
+                loop {
+                    let powered = adapter.powered().await.unwrap_or(false);
+                    let discoverable = adapter.discoverable().await.unwrap_or(false);
+                    let discovering = adapter.discovering().await.unwrap_or(false);
+
+                    let Some(obj) = weak_obj.upgrade() else {
+                        break;
+                    };
+                    obj.imp().sync_adapter(powered, discoverable, discovering);
+                    drop(obj);
+
+                    if changes.next().await.is_none() {
+                        break;
+                    }
+                }
+            });
+            self.adapter_tracker.replace(Some(tracker));
+        }
+
+        fn sync_adapter(&self, powered: bool, discoverable: bool, discovering: bool) {
+            self.syncing.set(true);
+            let powered_switch = self.powered_switch.get().unwrap();
+            powered_switch.set_active(powered);
+            let discoverable_switch = self.discoverable_switch.get().unwrap();
+            discoverable_switch.set_active(discoverable);
+            discoverable_switch.set_sensitive(powered);
+            let scan_button = self.scan_button.get().unwrap();
+            scan_button.set_active(discovering);
+            scan_button.set_sensitive(powered);
+            self.syncing.set(false);
+
+            self.paired_box.get().unwrap().set_sensitive(powered);
+            self.available_box
+                .get()
+                .unwrap()
+                .set_visible(powered && discovering);
+            self.available_spinner
+                .get()
+                .unwrap()
+                .set_spinning(powered && discovering);
+        }
+
+        fn add_device(&self, device: bluez::Device) {
+            let weak_obj = self.obj().downgrade();
+            glib::spawn_future_local(async move {
+                let paired = device.proxy().paired().await.unwrap_or(false);
+                let row = match device_row(&device).await {
+                    Ok(row) => row,
+                    Err(err) => {
+                        error!(?err, device_id = ?device.id(), "Failed to add Bluetooth device");
+                        return;
+                    }
+                };
+
+                let Some(obj) = weak_obj.upgrade() else {
+                    return;
+                };
+                obj.imp().place_row(&row, paired);
+                drop(obj);
+
+                // Devices move to the paired list once pairing is done.
+                let mut paired_changes = device.proxy().receive_paired_changed().await;
+                let weak_row = row.downgrade();
+                while let Some(changed) = paired_changes.next().await {
+                    let Ok(paired) = changed.get().await else {
+                        continue;
+                    };
+
+                    let (Some(obj), Some(row)) = (weak_obj.upgrade(), weak_row.upgrade()) else {
+                        break;
+                    };
+                    if row.parent().is_none() {
+                        break; // removed in the meantime
+                    }
+                    obj.imp().place_row(&row, paired);
+                }
+            });
+        }
+
+        fn place_row(&self, row: &gtk::Box, paired: bool) {
+            let (target, other) = if paired {
+                (
+                    self.paired_box.get().unwrap(),
+                    self.available_box.get().unwrap(),
+                )
+            } else {
+                (
+                    self.available_box.get().unwrap(),
+                    self.paired_box.get().unwrap(),
+                )
+            };
+
+            if row.parent().as_ref() == Some(other.upcast_ref()) {
+                other.remove(row);
+            }
+            if row.parent().is_none() {
+                target.append(row);
+            }
+        }
+
+        fn remove_device(&self, id: &str) {
+            for devices_box in [
+                self.paired_box.get().unwrap(),
+                self.available_box.get().unwrap(),
+            ] {
+                let mut child = devices_box.first_child();
+                while let Some(row) = child {
+                    child = row.next_sibling();
+                    if row.widget_name().as_str() == id {
+                        devices_box.remove(&row);
+                    }
+                }
+            }
+        }
+    }
+
+    async fn device_row(device: &bluez::Device) -> anyhow::Result<gtk::Box> {
+        let proxy = device.proxy().clone();
+        let icon_name = match proxy.icon().await {
+            Ok(icon) if !icon.is_empty() => format!("{icon}-symbolic"),
+            _ => String::from("bluetooth-symbolic"),
+        };
+
+        let row = gtk::Box::builder()
+            .name(&*device.id())
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(6)
+            .css_classes(["bluetooth-device"])
+            .build();
+        let name_label = gtk::Label::builder()
+            .label(device.display_name().await)
+            .ellipsize(gtk::pango::EllipsizeMode::End)
+            .hexpand(true)
+            .xalign(0.0)
+            .build();
+        let battery_label = gtk::Label::builder()
+            .css_classes(["dim-label"])
+            .visible(false)
+            .build();
+        let button = gtk::Button::builder()
+            .css_classes(["flat"])
+            .valign(gtk::Align::Center)
+            .build();
+        row.append(&gtk::Image::from_icon_name(&icon_name));
+        row.append(&name_label);
+        row.append(&battery_label);
+        row.append(&button);
+
+        let button_proxy = proxy.clone();
+        button.connect_clicked(move |button| {
+            let proxy = button_proxy.clone();
+            let weak_button = button.downgrade();
+            button.set_sensitive(false);
+            glib::spawn_future_local(async move {
+                let result = if !proxy.paired().await.unwrap_or(false) {
+                    pair(&proxy).await
+                } else if proxy.connected().await.unwrap_or(false) {
+                    proxy.disconnect().await
+                } else {
+                    proxy.connect().await
+                };
+                if let Err(err) = result {
+                    warn!(?err, "Failed to update Bluetooth device");
+                }
+
+                if let Some(button) = weak_button.upgrade() {
+                    button.set_sensitive(true);
+                }
+            });
+        });
+
+        let mut changes = futures_util::stream::select_all([
+            proxy
+                .receive_connected_changed()
+                .await
+                .map(|_| ())
+                .boxed_local(),
+            proxy
+                .receive_paired_changed()
+                .await
+                .map(|_| ())
+                .boxed_local(),
+            proxy
+                .receive_alias_changed()
+                .await
+                .map(|_| ())
+                .boxed_local(),
+            device
+                .battery()
+                .receive_percentage_changed()
+                .await
+                .map(|_| ())
+                .boxed_local(),
+        ]);
+        let device = device.clone();
+        let weak_row = row.downgrade();
+        glib::spawn_future_local(async move {
+            loop {
+                let paired = proxy.paired().await.unwrap_or(false);
+                let connected = proxy.connected().await.unwrap_or(false);
+                let name = device.display_name().await;
+                // Devices without a battery don't have the interface at all.
+                let percentage = device.battery().percentage().await.ok();
+
+                let Some(row) = weak_row.upgrade() else {
+                    break; // row does not exist anymore?
+                };
+                name_label.set_text(&name);
+                button.set_label(match (paired, connected) {
+                    (false, _) => "Pair",
+                    (true, false) => "Connect",
+                    (true, true) => "Disconnect",
+                });
+                battery_label.set_visible(connected && percentage.is_some());
+                if let Some(percentage) = percentage {
+                    battery_label.set_text(&format!("{percentage}%"));
+                }
+                if connected {
+                    row.add_css_class("connected");
+                } else {
+                    row.remove_css_class("connected");
+                }
+                drop(row);
+
+                if changes.next().await.is_none() {
+                    break;
+                }
+            }
+        });
+
+        Ok(row)
+    }
+
+    /// Pair with a new device, then connect to it.
+    ///
+    /// We trust the devices the user pairs with, so that they can connect back by themselves.
+    async fn pair(proxy: &bluez::device::Device1Proxy<'static>) -> zbus::Result<()> {
+        proxy.pair().await?;
+        proxy.set_trusted(true).await?;
+        proxy.connect().await
+    }
+
+    impl WidgetImpl for BluetoothSection {}
+    impl BoxImpl for BluetoothSection {}
+}
+
+glib::wrapper! {
+    pub struct BluetoothSection(ObjectSubclass<imp::BluetoothSection>)
+        @extends gtk::Box, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Orientable;
+}
+
+impl BluetoothSection {
+    pub fn new() -> Self {
+        glib::Object::builder()
+            .property("orientation", gtk::Orientation::Vertical)
+            .property("spacing", 6)
+            .build()
+    }
+}
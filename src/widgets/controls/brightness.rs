@@ -0,0 +1,105 @@
+//! Brightness section of the quick controls.
+//!
+//! A slider for the screen backlight, hidden on machines without one.
+
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+
+mod imp {
+    use std::cell::{Cell, OnceCell};
+
+    use super::*;
+    use crate::daemons::{backlight, logind};
+
+    #[derive(Default, Debug)]
+    pub struct BrightnessSection {
+        scale: OnceCell<gtk::Scale>,
+        // Whether we are updating the scale ourselves, to not write the value back.
+        syncing: Cell<bool>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for BrightnessSection {
+        const NAME: &'static str = "BrightnessSection";
+        type Type = super::BrightnessSection;
+        type ParentType = gtk::Box;
+    }
+
+    impl ObjectImpl for BrightnessSection {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.add_css_class("brightness-section");
+
+            let scale = gtk::Scale::with_range(gtk::Orientation::Horizontal, 0.0, 1.0, 0.01);
+            scale.set_hexpand(true);
+            scale.connect_value_changed(glib::clone!(@weak obj => move |scale| {
+                if obj.imp().syncing.get() {
+                    return;
+                }
+
+                let fraction = scale.value();
+                glib::spawn_future_local(async move {
+                    if let Err(err) = backlight::get().set_fraction(fraction).await {
+                        error!(?err, "Failed to set brightness");
+                    }
+                });
+            }));
+            obj.append(&gtk::Image::from_icon_name("display-brightness-symbolic"));
+            obj.append(&scale);
+            self.scale.set(scale).unwrap();
+
+            let mut backlight = backlight::get().subscribe();
+            let weak_obj = obj.downgrade();
+            glib::spawn_future_local(async move {
+                loop {
+                    let current = backlight.borrow_and_update().clone();
+                    let Some(obj) = weak_obj.upgrade() else {
+                        break;
+                    };
+                    obj.imp().sync(current.as_ref());
+                    drop(obj);
+
+                    if backlight.changed().await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    }
+
+    impl BrightnessSection {
+        fn sync(&self, backlight: Option<&backlight::Backlight>) {
+            let obj = self.obj();
+            // Setting the brightness goes through the logind session.
+            let Some(backlight) = backlight.filter(|_| logind::get().has_session()) else {
+                obj.set_visible(false);
+                return;
+            };
+
+            obj.set_visible(true);
+            self.syncing.set(true);
+            self.scale.get().unwrap().set_value(backlight.fraction());
+            self.syncing.set(false);
+        }
+    }
+
+    impl WidgetImpl for BrightnessSection {}
+    impl BoxImpl for BrightnessSection {}
+}
+
+glib::wrapper! {
+    pub struct BrightnessSection(ObjectSubclass<imp::BrightnessSection>)
+        @extends gtk::Box, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Orientable;
+}
+
+impl BrightnessSection {
+    pub fn new() -> Self {
+        glib::Object::builder()
+            .property("orientation", gtk::Orientation::Horizontal)
+            .property("spacing", 6)
+            .build()
+    }
+}
@@ -0,0 +1,245 @@
+//! Caffeine section of the quick controls.
+//!
+//! Caffeine mode takes an idle and sleep inhibitor lock through logind, to keep the system awake,
+//! for example while watching something. Since other programs can take these locks too, we also
+//! list them here to know who is keeping the system from suspending.
+
+use std::time::Duration;
+
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+
+/// The durations offered for caffeine mode, `None` meaning until turned off.
+const DURATIONS: [(&str, Option<Duration>); 3] = [
+    ("For 30 minutes", Some(Duration::from_secs(30 * 60))),
+    ("For 1 hour", Some(Duration::from_secs(60 * 60))),
+    ("Until turned off", None),
+];
+
+mod imp {
+    use std::cell::OnceCell;
+
+    use super::*;
+    use crate::daemons::logind;
+
+    #[derive(Default, Debug)]
+    pub struct CaffeineSection {
+        duration_dropdown: OnceCell<gtk::DropDown>,
+        inhibitors_box: OnceCell<gtk::Box>,
+        inhibitors_expander: OnceCell<gtk::Expander>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for CaffeineSection {
+        const NAME: &'static str = "CaffeineSection";
+        type Type = super::CaffeineSection;
+        type ParentType = gtk::Box;
+    }
+
+    impl ObjectImpl for CaffeineSection {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.add_css_class("caffeine-section");
+
+            let header_box = gtk::Box::builder()
+                .orientation(gtk::Orientation::Horizontal)
+                .spacing(6)
+                .build();
+            let title_box = gtk::Box::builder()
+                .orientation(gtk::Orientation::Vertical)
+                .hexpand(true)
+                .build();
+            let status_label = gtk::Label::builder()
+                .css_classes(["caption", "dim-label"])
+                .xalign(0.0)
+                .visible(false)
+                .build();
+            title_box.append(
+                &gtk::Label::builder()
+                    .label("Keep awake")
+                    .xalign(0.0)
+                    .build(),
+            );
+            title_box.append(&status_label);
+            let switch = gtk::Switch::builder().valign(gtk::Align::Center).build();
+            header_box.append(&gtk::Image::from_icon_name("caffeine-symbolic"));
+            header_box.append(&title_box);
+            header_box.append(&switch);
+            obj.append(&header_box);
+
+            let duration_dropdown = gtk::DropDown::from_strings(&DURATIONS.map(|(label, _)| label));
+            duration_dropdown.set_selected(DURATIONS.len() as u32 - 1);
+            duration_dropdown.connect_selected_notify(glib::clone!(@weak obj => move |_| {
+                // Apply the new duration right away if caffeine mode is already enabled.
+                if logind::get().subscribe_caffeine().borrow().active {
+                    obj.imp().enable();
+                }
+            }));
+            obj.append(&duration_dropdown);
+            self.duration_dropdown.set(duration_dropdown).unwrap();
+
+            switch.connect_state_set(glib::clone!(@weak obj => @default-return glib::Propagation::Proceed, move |switch, active| {
+                // This also gets called when we sync the switch back to the daemon state.
+                if switch.state() == active {
+                    return glib::Propagation::Proceed;
+                }
+
+                if active {
+                    obj.imp().enable();
+                } else {
+                    logind::get().disable_caffeine();
+                }
+                // The switch state gets updated once the daemon state changes.
+                glib::Propagation::Stop
+            }));
+
+            let mut caffeine = logind::get().subscribe_caffeine();
+            let weak_switch = switch.downgrade();
+            let weak_status_label = status_label.downgrade();
+            glib::spawn_future_local(async move {
+                loop {
+                    let state = *caffeine.borrow_and_update();
+                    let (Some(switch), Some(status_label)) =
+                        (weak_switch.upgrade(), weak_status_label.upgrade())
+                    else {
+                        break;
+                    };
+
+                    switch.set_state(state.active);
+                    switch.set_active(state.active);
+                    if let Some(until) = state.until {
+                        status_label.set_text(&format!("Until {}", until.format("%H:%M")));
+                        status_label.set_visible(true);
+                    } else {
+                        status_label.set_visible(false);
+                    }
+
+                    if caffeine.changed().await.is_err() {
+                        break;
+                    }
+                }
+            });
+
+            let inhibitors_box = gtk::Box::builder()
+                .orientation(gtk::Orientation::Vertical)
+                .spacing(6)
+                .build();
+            let inhibitors_expander = gtk::Expander::builder()
+                .label("Blocking suspend")
+                .child(&inhibitors_box)
+                .visible(false)
+                .build();
+            obj.append(&inhibitors_expander);
+            self.inhibitors_box.set(inhibitors_box).unwrap();
+            self.inhibitors_expander.set(inhibitors_expander).unwrap();
+
+            // Inhibitors don't have change notifications, refresh them every time we get shown.
+            obj.connect_map(|obj| obj.imp().refresh_inhibitors());
+        }
+    }
+
+    impl CaffeineSection {
+        fn enable(&self) {
+            let selected = self.duration_dropdown.get().unwrap().selected() as usize;
+            let (_, duration) = DURATIONS.get(selected).copied().unwrap_or(DURATIONS[2]);
+            glib::spawn_future_local(async move {
+                if let Err(err) = logind::get().enable_caffeine(duration).await {
+                    error!(?err, "Failed to enable caffeine mode");
+                    // Put the switch back in its place.
+                    logind::get().disable_caffeine();
+                }
+            });
+        }
+
+        fn refresh_inhibitors(&self) {
+            let weak_inhibitors_box = self.inhibitors_box.get().unwrap().downgrade();
+            let weak_inhibitors_expander = self.inhibitors_expander.get().unwrap().downgrade();
+            glib::spawn_future_local(async move {
+                let inhibitors = match logind::get().list_inhibitors().await {
+                    Ok(inhibitors) => inhibitors,
+                    Err(err) => {
+                        warn!(?err, "Failed to list inhibitors");
+                        return;
+                    }
+                };
+
+                let (Some(inhibitors_box), Some(inhibitors_expander)) = (
+                    weak_inhibitors_box.upgrade(),
+                    weak_inhibitors_expander.upgrade(),
+                ) else {
+                    return;
+                };
+                while let Some(child) = inhibitors_box.first_child() {
+                    inhibitors_box.remove(&child);
+                }
+
+                let own_pid = std::process::id();
+                for inhibitor in inhibitors.into_iter().filter(|inhibitor| {
+                    let blocks_suspend = inhibitor.mode == "block"
+                        && inhibitor
+                            .what
+                            .split(':')
+                            .any(|what| what == "sleep" || what == "idle");
+                    blocks_suspend && inhibitor.pid != own_pid
+                }) {
+                    inhibitors_box.append(&inhibitor_row(&inhibitor));
+                }
+
+                // Only bother the user if there's anything to show.
+                inhibitors_expander.set_visible(inhibitors_box.first_child().is_some());
+            });
+        }
+    }
+
+    fn inhibitor_row(inhibitor: &logind::Inhibitor) -> gtk::Box {
+        let row = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .css_classes(["inhibitor"])
+            .build();
+
+        let what = inhibitor.what.replace(':', ", ");
+        let description = match inhibitor.mode.as_str() {
+            "block" => format!("Blocks {what}"),
+            _ => format!("Delays {what}"),
+        };
+        row.append(
+            &gtk::Label::builder()
+                .label(&inhibitor.who)
+                .css_classes(["heading"])
+                .xalign(0.0)
+                .build(),
+        );
+        row.append(
+            &gtk::Label::builder()
+                .label(&description)
+                .css_classes(["caption"])
+                .xalign(0.0)
+                .build(),
+        );
+        if !inhibitor.why.is_empty() {
+            row.set_tooltip_text(Some(&inhibitor.why));
+        }
+
+        row
+    }
+
+    impl WidgetImpl for CaffeineSection {}
+    impl BoxImpl for CaffeineSection {}
+}
+
+glib::wrapper! {
+    pub struct CaffeineSection(ObjectSubclass<imp::CaffeineSection>)
+        @extends gtk::Box, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Orientable;
+}
+
+impl CaffeineSection {
+    pub fn new() -> Self {
+        glib::Object::builder()
+            .property("orientation", gtk::Orientation::Vertical)
+            .property("spacing", 6)
+            .build()
+    }
+}
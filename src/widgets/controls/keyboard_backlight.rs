@@ -0,0 +1,137 @@
+//! Keyboard backlight section of the quick controls.
+//!
+//! Keyboard backlights only have a few levels, so this is a slider snapping to each one of them.
+//! Hidden on machines without a keyboard backlight.
+
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+
+mod imp {
+    use std::cell::{Cell, OnceCell};
+
+    use futures_util::StreamExt;
+
+    use super::*;
+    use crate::daemons::upower;
+
+    #[derive(Default, Debug)]
+    pub struct KeyboardBacklightSection {
+        scale: OnceCell<gtk::Scale>,
+        // Whether we are updating the scale ourselves, to not write the value back.
+        syncing: Cell<bool>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for KeyboardBacklightSection {
+        const NAME: &'static str = "KeyboardBacklightSection";
+        type Type = super::KeyboardBacklightSection;
+        type ParentType = gtk::Box;
+    }
+
+    impl ObjectImpl for KeyboardBacklightSection {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.add_css_class("keyboard-backlight-section");
+            // We only show ourselves once we know there's a keyboard backlight.
+            obj.set_visible(false);
+
+            let scale = gtk::Scale::builder()
+                .orientation(gtk::Orientation::Horizontal)
+                .round_digits(0)
+                .hexpand(true)
+                .build();
+            scale.connect_value_changed(glib::clone!(@weak obj => move |scale| {
+                if obj.imp().syncing.get() {
+                    return;
+                }
+
+                let value = scale.value().round() as i32;
+                glib::spawn_future_local(async move {
+                    let proxy = upower::get().kbd_backlight();
+                    if let Err(err) = proxy.set_brightness(value).await {
+                        error!(?err, "Failed to set keyboard brightness");
+                    }
+                });
+            }));
+            obj.append(&gtk::Image::from_icon_name("keyboard-brightness-symbolic"));
+            obj.append(&scale);
+            self.scale.set(scale).unwrap();
+
+            let weak_obj = obj.downgrade();
+            glib::spawn_future_local(async move {
+                let proxy = upower::get().kbd_backlight();
+                let max_brightness = match proxy.get_max_brightness().await {
+                    Ok(max_brightness) if max_brightness > 0 => max_brightness,
+                    Ok(_) => return,
+                    Err(err) => {
+                        debug!(?err, "No keyboard backlight");
+                        return;
+                    }
+                };
+                let Ok(mut brightness_changes) = proxy.receive_brightness_changed().await else {
+                    return;
+                };
+
+                if let Some(obj) = weak_obj.upgrade() {
+                    obj.imp().setup_levels(max_brightness);
+                }
+
+                let mut brightness = proxy.get_brightness().await.unwrap_or(0);
+                loop {
+                    let Some(obj) = weak_obj.upgrade() else {
+                        break;
+                    };
+                    obj.imp().sync(brightness);
+                    drop(obj);
+
+                    let Some(changed) = brightness_changes.next().await else {
+                        break;
+                    };
+                    if let Ok(args) = changed.args() {
+                        brightness = args.value;
+                    }
+                }
+            });
+        }
+    }
+
+    impl KeyboardBacklightSection {
+        fn setup_levels(&self, max_brightness: i32) {
+            let scale = self.scale.get().unwrap();
+            self.syncing.set(true);
+            scale.set_range(0.0, f64::from(max_brightness));
+            scale.set_increments(1.0, 1.0);
+            for level in 0..=max_brightness {
+                scale.add_mark(f64::from(level), gtk::PositionType::Bottom, None);
+            }
+            self.syncing.set(false);
+            self.obj().set_visible(true);
+        }
+
+        fn sync(&self, brightness: i32) {
+            self.syncing.set(true);
+            self.scale.get().unwrap().set_value(f64::from(brightness));
+            self.syncing.set(false);
+        }
+    }
+
+    impl WidgetImpl for KeyboardBacklightSection {}
+    impl BoxImpl for KeyboardBacklightSection {}
+}
+
+glib::wrapper! {
+    pub struct KeyboardBacklightSection(ObjectSubclass<imp::KeyboardBacklightSection>)
+        @extends gtk::Box, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Orientable;
+}
+
+impl KeyboardBacklightSection {
+    pub fn new() -> Self {
+        glib::Object::builder()
+            .property("orientation", gtk::Orientation::Horizontal)
+            .property("spacing", 6)
+            .build()
+    }
+}
@@ -0,0 +1,544 @@
+//! Media section of the quick controls.
+//!
+//! A card for the selected MPRIS player, with the album art, the track title and artists, the
+//! playback controls, a seek bar and the player volume. When there are multiple players, a dropdown lets the user
+//! switch between them.
+
+use std::time::Duration;
+
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::{gdk, gio, glib};
+
+use crate::daemons::mpris::{self, PlaybackStatus, Player};
+
+/// How often we update the seek bar while playing.
+const POSITION_INTERVAL: Duration = Duration::from_secs(1);
+
+mod imp {
+    use std::cell::{Cell, OnceCell, RefCell};
+
+    use futures_util::StreamExt;
+    use tokio::sync::broadcast;
+
+    use super::*;
+
+    #[derive(Default, Debug)]
+    pub struct MediaSection {
+        players_dropdown: OnceCell<gtk::DropDown>,
+        // The bus names of the players in the dropdown, in order.
+        player_names: RefCell<Vec<String>>,
+        art: OnceCell<gtk::Image>,
+        title_label: OnceCell<gtk::Label>,
+        artists_label: OnceCell<gtk::Label>,
+        previous_button: OnceCell<gtk::Button>,
+        play_pause_button: OnceCell<gtk::Button>,
+        next_button: OnceCell<gtk::Button>,
+        seek_box: OnceCell<gtk::Box>,
+        seek_scale: OnceCell<gtk::Scale>,
+        position_label: OnceCell<gtk::Label>,
+        length_label: OnceCell<gtk::Label>,
+        volume_box: OnceCell<gtk::Box>,
+        volume_scale: OnceCell<gtk::Scale>,
+        pub(super) player: RefCell<Option<Player>>,
+        pub(super) status: Cell<Option<PlaybackStatus>>,
+        // The future following the selected player, if any.
+        tracker: RefCell<Option<glib::JoinHandle<()>>>,
+        position_timeout: RefCell<Option<glib::SourceId>>,
+        // Whether we are updating the widgets ourselves, to not write the values back.
+        syncing: Cell<bool>,
+        // The art URL currently shown, to not load it again.
+        art_url: RefCell<Option<String>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MediaSection {
+        const NAME: &'static str = "MediaSection";
+        type Type = super::MediaSection;
+        type ParentType = gtk::Box;
+    }
+
+    impl ObjectImpl for MediaSection {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.add_css_class("media-section");
+            obj.add_css_class("card");
+            obj.set_visible(false);
+
+            let players_dropdown = gtk::DropDown::builder()
+                .model(&gtk::StringList::new(&[]))
+                .css_classes(["flat"])
+                .halign(gtk::Align::Start)
+                .visible(false)
+                .build();
+            players_dropdown.connect_selected_notify(glib::clone!(@weak obj => move |dropdown| {
+                let imp = obj.imp();
+                if imp.syncing.get() {
+                    return;
+                }
+                let names = imp.player_names.borrow();
+                if let Some(name) = names.get(dropdown.selected() as usize) {
+                    mpris::get().select(name);
+                }
+            }));
+            obj.append(&players_dropdown);
+
+            let track_box = gtk::Box::builder()
+                .orientation(gtk::Orientation::Horizontal)
+                .spacing(12)
+                .build();
+            let art = gtk::Image::builder()
+                .icon_name("audio-x-generic-symbolic")
+                .pixel_size(64)
+                .css_classes(["album-art"])
+                .build();
+            let labels_box = gtk::Box::builder()
+                .orientation(gtk::Orientation::Vertical)
+                .valign(gtk::Align::Center)
+                .hexpand(true)
+                .build();
+            let title_label = gtk::Label::builder()
+                .css_classes(["heading"])
+                .ellipsize(gtk::pango::EllipsizeMode::End)
+                .xalign(0.0)
+                .build();
+            let artists_label = gtk::Label::builder()
+                .css_classes(["dim-label"])
+                .ellipsize(gtk::pango::EllipsizeMode::End)
+                .xalign(0.0)
+                .build();
+            labels_box.append(&title_label);
+            labels_box.append(&artists_label);
+            track_box.append(&art);
+            track_box.append(&labels_box);
+            obj.append(&track_box);
+
+            let seek_box = gtk::Box::builder()
+                .orientation(gtk::Orientation::Horizontal)
+                .spacing(6)
+                .build();
+            let position_label = gtk::Label::builder()
+                .css_classes(["caption", "numeric"])
+                .build();
+            let seek_scale = gtk::Scale::with_range(gtk::Orientation::Horizontal, 0.0, 1.0, 1.0);
+            seek_scale.set_hexpand(true);
+            // Only called when the user moves the slider.
+            seek_scale.connect_change_value(
+                glib::clone!(@weak obj => @default-return glib::Propagation::Proceed, move |_, _, value| {
+                    let Some(player) = obj.imp().player.borrow().clone() else {
+                        return glib::Propagation::Proceed;
+                    };
+                    glib::spawn_future_local(async move {
+                        let position = Duration::from_secs_f64(value.max(0.0));
+                        if let Err(err) = player.set_position(position).await {
+                            warn!(?err, "Failed to seek");
+                        }
+                    });
+                    glib::Propagation::Proceed
+                }),
+            );
+            let length_label = gtk::Label::builder()
+                .css_classes(["caption", "numeric"])
+                .build();
+            seek_box.append(&position_label);
+            seek_box.append(&seek_scale);
+            seek_box.append(&length_label);
+            obj.append(&seek_box);
+            self.seek_box.set(seek_box).unwrap();
+
+            let buttons_box = gtk::Box::builder()
+                .orientation(gtk::Orientation::Horizontal)
+                .spacing(6)
+                .halign(gtk::Align::Center)
+                .build();
+            let previous_button = control_button("media-skip-backward-symbolic", "Previous");
+            let play_pause_button = control_button("media-playback-start-symbolic", "Play");
+            let next_button = control_button("media-skip-forward-symbolic", "Next");
+            previous_button.connect_clicked(glib::clone!(@weak obj => move |_| {
+                obj.imp().run(|player| async move { player.proxy().previous().await });
+            }));
+            play_pause_button.connect_clicked(glib::clone!(@weak obj => move |_| {
+                obj.imp().run(|player| async move { player.proxy().play_pause().await });
+            }));
+            next_button.connect_clicked(glib::clone!(@weak obj => move |_| {
+                obj.imp().run(|player| async move { player.proxy().next().await });
+            }));
+            buttons_box.append(&previous_button);
+            buttons_box.append(&play_pause_button);
+            buttons_box.append(&next_button);
+            obj.append(&buttons_box);
+
+            let volume_box = gtk::Box::builder()
+                .orientation(gtk::Orientation::Horizontal)
+                .spacing(6)
+                .visible(false)
+                .build();
+            let volume_scale = gtk::Scale::with_range(gtk::Orientation::Horizontal, 0.0, 1.0, 0.05);
+            volume_scale.set_hexpand(true);
+            // Only called when the user moves the slider.
+            volume_scale.connect_change_value(
+                glib::clone!(@weak obj => @default-return glib::Propagation::Proceed, move |_, _, value| {
+                    obj.imp().run(move |player| async move { player.set_volume(value.min(1.0)).await });
+                    glib::Propagation::Proceed
+                }),
+            );
+            volume_box.append(&gtk::Image::from_icon_name("audio-volume-high-symbolic"));
+            volume_box.append(&volume_scale);
+            obj.append(&volume_box);
+
+            self.players_dropdown.set(players_dropdown).unwrap();
+            self.art.set(art).unwrap();
+            self.title_label.set(title_label).unwrap();
+            self.artists_label.set(artists_label).unwrap();
+            self.previous_button.set(previous_button).unwrap();
+            self.play_pause_button.set(play_pause_button).unwrap();
+            self.next_button.set(next_button).unwrap();
+            self.seek_scale.set(seek_scale).unwrap();
+            self.position_label.set(position_label).unwrap();
+            self.length_label.set(length_label).unwrap();
+            self.volume_box.set(volume_box).unwrap();
+            self.volume_scale.set(volume_scale).unwrap();
+
+            let mut selected = mpris::get().subscribe_selected();
+            let weak_obj = obj.downgrade();
+            glib::spawn_future_local(async move {
+                loop {
+                    let player = selected.borrow_and_update().clone();
+                    let Some(obj) = weak_obj.upgrade() else {
+                        break;
+                    };
+                    obj.imp().track_player(player);
+                    drop(obj);
+
+                    if selected.changed().await.is_err() {
+                        break;
+                    }
+                }
+            });
+
+            let mut events = mpris::get().subscribe();
+            let weak_obj = obj.downgrade();
+            glib::spawn_future_local(async move {
+                loop {
+                    let Some(obj) = weak_obj.upgrade() else {
+                        break;
+                    };
+                    obj.imp().sync_players();
+                    drop(obj);
+
+                    match events.recv().await {
+                        Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => (),
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            });
+
+            let weak_obj = obj.downgrade();
+            let position_timeout = glib::timeout_add_local(POSITION_INTERVAL, move || {
+                let Some(obj) = weak_obj.upgrade() else {
+                    return glib::ControlFlow::Continue;
+                };
+                // No need to bother the player when nobody sees us.
+                if obj.is_mapped() && obj.imp().status.get() == Some(PlaybackStatus::Playing) {
+                    obj.imp().update_position();
+                }
+                glib::ControlFlow::Continue
+            });
+            self.position_timeout.replace(Some(position_timeout));
+        }
+
+        fn dispose(&self) {
+            if let Some(position_timeout) = self.position_timeout.take() {
+                position_timeout.remove();
+            }
+            if let Some(tracker) = self.tracker.take() {
+                tracker.abort();
+            }
+        }
+    }
+
+    impl MediaSection {
+        /// Sync the players dropdown with the players on the bus.
+        fn sync_players(&self) {
+            let players = mpris::get().players();
+            let names = players
+                .iter()
+                .map(|player| player.bus_name().to_string())
+                .collect::<Vec<_>>();
+            let identities = players
+                .iter()
+                .map(|player| player.identity())
+                .collect::<Vec<_>>();
+            let identities = identities.iter().map(|id| &**id).collect::<Vec<_>>();
+
+            let dropdown = self.players_dropdown.get().unwrap();
+            self.syncing.set(true);
+            dropdown.set_model(Some(&gtk::StringList::new(&identities)));
+            self.player_names.replace(names);
+            self.sync_selected_player();
+            self.syncing.set(false);
+            dropdown.set_visible(players.len() > 1);
+        }
+
+        fn sync_selected_player(&self) {
+            let Some(player) = self.player.borrow().clone() else {
+                return;
+            };
+            let position = self
+                .player_names
+                .borrow()
+                .iter()
+                .position(|name| **name == *player.bus_name());
+            if let Some(position) = position {
+                let was_syncing = self.syncing.replace(true);
+                self.players_dropdown
+                    .get()
+                    .unwrap()
+                    .set_selected(position as u32);
+                self.syncing.set(was_syncing);
+            }
+        }
+
+        /// Start following this player, stopping to follow the previous one.
+        fn track_player(&self, player: Option<Player>) {
+            if let Some(tracker) = self.tracker.take() {
+                tracker.abort();
+            }
+            self.player.replace(player.clone());
+            self.status.set(None);
+            self.obj().set_visible(player.is_some());
+            let Some(player) = player else {
+                return;
+            };
+            self.sync_selected_player();
+
+            let weak_obj = self.obj().downgrade();
+            let handle = glib::spawn_future_local(async move {
+                let proxy = player.proxy();
+                let mut changes = futures_util::stream::select_all([
+                    proxy
+                        .receive_metadata_changed()
+                        .await
+                        .map(|_| ())
+                        .boxed_local(),
+                    proxy
+                        .receive_playback_status_changed()
+                        .await
+                        .map(|_| ())
+                        .boxed_local(),
+                    proxy
+                        .receive_can_go_next_changed()
+                        .await
+                        .map(|_| ())
+                        .boxed_local(),
+                    proxy
+                        .receive_can_go_previous_changed()
+                        .await
+                        .map(|_| ())
+                        .boxed_local(),
+                    proxy
+                        .receive_can_pause_changed()
+                        .await
+                        .map(|_| ())
+                        .boxed_local(),
+                    proxy
+                        .receive_can_seek_changed()
+                        .await
+                        .map(|_| ())
+                        .boxed_local(),
+                    proxy
+                        .receive_volume_changed()
+                        .await
+                        .map(|_| ())
+                        .boxed_local(),
+                ]);
+
+                loop {
+                    let metadata = player.metadata().await.unwrap_or_default();
+                    let status = player
+                        .playback_status()
+                        .await
+                        .unwrap_or(PlaybackStatus::Stopped);
+                    let can_go_next = proxy.can_go_next().await.unwrap_or(false);
+                    let can_go_previous = proxy.can_go_previous().await.unwrap_or(false);
+                    let can_pause = proxy.can_pause().await.unwrap_or(false);
+                    let can_play = proxy.can_play().await.unwrap_or(false);
+                    let can_seek = proxy.can_seek().await.unwrap_or(false);
+                    // Not all players have a volume, some others fail to get it.
+                    let volume = player.volume().await.ok();
+
+                    let Some(obj) = weak_obj.upgrade() else {
+                        break;
+                    };
+                    let imp = obj.imp();
+                    imp.status.set(Some(status));
+                    imp.title_label
+                        .get()
+                        .unwrap()
+                        .set_text(metadata.title.as_deref().unwrap_or("Unknown title"));
+                    let artists_label = imp.artists_label.get().unwrap();
+                    artists_label.set_text(&metadata.artists.join(", "));
+                    artists_label.set_visible(!metadata.artists.is_empty());
+                    imp.load_art(metadata.art_url.clone());
+
+                    imp.previous_button
+                        .get()
+                        .unwrap()
+                        .set_sensitive(can_go_previous);
+                    imp.next_button.get().unwrap().set_sensitive(can_go_next);
+                    let play_pause_button = imp.play_pause_button.get().unwrap();
+                    if status == PlaybackStatus::Playing {
+                        play_pause_button.set_icon_name("media-playback-pause-symbolic");
+                        play_pause_button.set_tooltip_text(Some("Pause"));
+                        play_pause_button.set_sensitive(can_pause);
+                    } else {
+                        play_pause_button.set_icon_name("media-playback-start-symbolic");
+                        play_pause_button.set_tooltip_text(Some("Play"));
+                        play_pause_button.set_sensitive(can_play);
+                    }
+
+                    let seek_scale = imp.seek_scale.get().unwrap();
+                    let length = metadata.length.unwrap_or_default();
+                    seek_scale.set_range(0.0, length.as_secs_f64().max(1.0));
+                    seek_scale.set_sensitive(can_seek && !length.is_zero());
+                    imp.length_label
+                        .get()
+                        .unwrap()
+                        .set_text(&format_duration(length));
+                    // Streams don't have a length, there's nothing to seek in them.
+                    imp.seek_box.get().unwrap().set_visible(!length.is_zero());
+                    imp.update_position();
+
+                    imp.volume_box.get().unwrap().set_visible(volume.is_some());
+                    imp.volume_scale
+                        .get()
+                        .unwrap()
+                        .set_value(volume.unwrap_or_default());
+                    drop(obj);
+
+                    if changes.next().await.is_none() {
+                        break;
+                    }
+                }
+            });
+            self.tracker.replace(Some(handle));
+        }
+
+        fn update_position(&self) {
+            let Some(player) = self.player.borrow().clone() else {
+                return;
+            };
+            let weak_obj = self.obj().downgrade();
+            glib::spawn_future_local(async move {
+                let Ok(position) = player.position().await else {
+                    return;
+                };
+                let Some(obj) = weak_obj.upgrade() else {
+                    return;
+                };
+                let imp = obj.imp();
+                imp.seek_scale
+                    .get()
+                    .unwrap()
+                    .set_value(position.as_secs_f64());
+                imp.position_label
+                    .get()
+                    .unwrap()
+                    .set_text(&format_duration(position));
+            });
+        }
+
+        fn load_art(&self, art_url: Option<String>) {
+            if *self.art_url.borrow() == art_url {
+                return;
+            }
+            self.art_url.replace(art_url.clone());
+
+            let art = self.art.get().unwrap();
+            art.set_icon_name(Some("audio-x-generic-symbolic"));
+            let Some(art_url) = art_url else {
+                return;
+            };
+
+            let weak_obj = self.obj().downgrade();
+            glib::spawn_future_local(async move {
+                // This also works for http(s) URLs when gvfs is installed.
+                let file = gio::File::for_uri(&art_url);
+                let texture = match file.load_bytes_future().await {
+                    Ok((bytes, _)) => gdk::Texture::from_bytes(&bytes),
+                    Err(err) => Err(err),
+                };
+                let texture = match texture {
+                    Ok(texture) => texture,
+                    Err(err) => {
+                        debug!(?err, art_url, "Failed to load album art");
+                        return;
+                    }
+                };
+
+                let Some(obj) = weak_obj.upgrade() else {
+                    return;
+                };
+                // The track could have changed while we were loading.
+                if obj.imp().art_url.borrow().as_deref() == Some(art_url.as_str()) {
+                    obj.imp().art.get().unwrap().set_paintable(Some(&texture));
+                }
+            });
+        }
+
+        /// Run a method on the selected player.
+        fn run<F, Fut>(&self, f: F)
+        where
+            F: FnOnce(Player) -> Fut,
+            Fut: std::future::Future<Output = zbus::Result<()>> + 'static,
+        {
+            let Some(player) = self.player.borrow().clone() else {
+                return;
+            };
+            let future = f(player);
+            glib::spawn_future_local(async move {
+                if let Err(err) = future.await {
+                    warn!(?err, "Failed to control media player");
+                }
+            });
+        }
+    }
+
+    fn control_button(icon_name: &str, tooltip: &str) -> gtk::Button {
+        gtk::Button::builder()
+            .icon_name(icon_name)
+            .tooltip_text(tooltip)
+            .css_classes(["flat", "circular"])
+            .build()
+    }
+
+    impl WidgetImpl for MediaSection {}
+    impl BoxImpl for MediaSection {}
+}
+
+/// Format a duration as `m:ss`, or `h:mm:ss` for long ones.
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, (seconds / 60) % 60, seconds % 60);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes}:{seconds:02}")
+    }
+}
+
+glib::wrapper! {
+    pub struct MediaSection(ObjectSubclass<imp::MediaSection>)
+        @extends gtk::Box, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Orientable;
+}
+
+impl MediaSection {
+    pub fn new() -> Self {
+        glib::Object::builder()
+            .property("orientation", gtk::Orientation::Vertical)
+            .property("spacing", 6)
+            .build()
+    }
+}
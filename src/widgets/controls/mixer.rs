@@ -0,0 +1,312 @@
+//! Mixer section of the quick controls.
+//!
+//! Lists the applications playing or recording audio, each with its own volume and mute, and a
+//! menu to move it to another device.
+
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::{gdk, glib};
+
+use crate::daemons::audio::{self, Node, NodeKind};
+
+mod imp {
+    use std::cell::{Cell, OnceCell, RefCell};
+    use std::collections::HashMap;
+    use std::rc::Rc;
+
+    use super::*;
+
+    #[derive(Default, Debug)]
+    pub struct MixerSection {
+        streams_box: OnceCell<gtk::Box>,
+        // The rows of the streams, by stream ID.
+        rows: RefCell<HashMap<u32, StreamRow>>,
+        // Whether we are updating the widgets ourselves, to not write the values back.
+        pub(super) syncing: Cell<bool>,
+    }
+
+    /// The controls of a single stream.
+    #[derive(Debug)]
+    struct StreamRow {
+        container: gtk::Box,
+        icon: gtk::Image,
+        name_label: gtk::Label,
+        description_label: gtk::Label,
+        mute_button: gtk::ToggleButton,
+        scale: gtk::Scale,
+        // Whether the user is holding the scale, we must not move it under them.
+        dragging: Rc<Cell<bool>>,
+        devices_dropdown: gtk::DropDown,
+        // The IDs of the devices in the dropdown, in order.
+        device_ids: RefCell<Vec<u32>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MixerSection {
+        const NAME: &'static str = "MixerSection";
+        type Type = super::MixerSection;
+        type ParentType = gtk::Box;
+    }
+
+    impl ObjectImpl for MixerSection {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.add_css_class("mixer-section");
+            // We only show ourselves when something is playing or recording.
+            obj.set_visible(false);
+
+            obj.append(
+                &gtk::Label::builder()
+                    .label("Applications")
+                    .css_classes(["heading"])
+                    .xalign(0.0)
+                    .build(),
+            );
+            let streams_box = gtk::Box::builder()
+                .orientation(gtk::Orientation::Vertical)
+                .spacing(6)
+                .build();
+            obj.append(&streams_box);
+            self.streams_box.set(streams_box).unwrap();
+
+            let mut state = audio::get().subscribe();
+            let weak_obj = obj.downgrade();
+            glib::spawn_future_local(async move {
+                loop {
+                    let current = state.borrow_and_update().clone();
+                    let Some(obj) = weak_obj.upgrade() else {
+                        break;
+                    };
+                    obj.imp().sync(&current);
+                    drop(obj);
+
+                    if state.changed().await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    }
+
+    impl MixerSection {
+        fn sync(&self, state: &audio::State) {
+            let streams = state
+                .nodes
+                .values()
+                .filter(|node| {
+                    matches!(
+                        node.kind,
+                        NodeKind::PlaybackStream | NodeKind::RecordingStream
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            let streams_box = self.streams_box.get().unwrap();
+            let mut rows = self.rows.borrow_mut();
+            // Streams come and go all the time, only remove the ones that are gone to not reset
+            // a slider the user is dragging.
+            rows.retain(|id, row| {
+                let keep = state.nodes.contains_key(id);
+                if !keep {
+                    streams_box.remove(&row.container);
+                }
+                keep
+            });
+
+            self.syncing.set(true);
+            for stream in &streams {
+                let row = rows.entry(stream.id).or_insert_with(|| {
+                    let row = self.build_row(stream.id);
+                    streams_box.append(&row.container);
+                    row
+                });
+                sync_row(row, stream, state);
+            }
+            self.syncing.set(false);
+
+            self.obj().set_visible(!streams.is_empty());
+        }
+
+        fn build_row(&self, stream_id: u32) -> StreamRow {
+            let obj = self.obj();
+            let container = gtk::Box::builder()
+                .orientation(gtk::Orientation::Vertical)
+                .spacing(3)
+                .css_classes(["stream"])
+                .build();
+
+            let header_box = gtk::Box::builder()
+                .orientation(gtk::Orientation::Horizontal)
+                .spacing(6)
+                .build();
+            let icon = gtk::Image::builder().pixel_size(24).build();
+            let labels_box = gtk::Box::builder()
+                .orientation(gtk::Orientation::Vertical)
+                .hexpand(true)
+                .build();
+            let name_label = gtk::Label::builder()
+                .ellipsize(gtk::pango::EllipsizeMode::End)
+                .xalign(0.0)
+                .build();
+            let description_label = gtk::Label::builder()
+                .css_classes(["caption", "dim-label"])
+                .ellipsize(gtk::pango::EllipsizeMode::End)
+                .xalign(0.0)
+                .build();
+            labels_box.append(&name_label);
+            labels_box.append(&description_label);
+            let devices_dropdown = gtk::DropDown::builder()
+                .model(&gtk::StringList::new(&[]))
+                .css_classes(["flat"])
+                .tooltip_text("Move to another device")
+                .build();
+            header_box.append(&icon);
+            header_box.append(&labels_box);
+            header_box.append(&devices_dropdown);
+
+            let controls_box = gtk::Box::builder()
+                .orientation(gtk::Orientation::Horizontal)
+                .spacing(6)
+                .build();
+            let mute_button = gtk::ToggleButton::builder()
+                .css_classes(["flat", "circular"])
+                .tooltip_text("Mute")
+                .build();
+            let scale = gtk::Scale::with_range(gtk::Orientation::Horizontal, 0.0, 1.0, 0.01);
+            scale.set_hexpand(true);
+            controls_box.append(&mute_button);
+            controls_box.append(&scale);
+
+            // The scale handles the drag itself, so we only look at the events going to it.
+            let dragging = Rc::new(Cell::new(false));
+            let drag_controller = gtk::EventControllerLegacy::builder()
+                .propagation_phase(gtk::PropagationPhase::Capture)
+                .build();
+            drag_controller.connect_event(glib::clone!(@strong dragging => move |_, event| {
+                match event.event_type() {
+                    gdk::EventType::ButtonPress | gdk::EventType::TouchBegin => dragging.set(true),
+                    gdk::EventType::ButtonRelease
+                    | gdk::EventType::TouchEnd
+                    | gdk::EventType::TouchCancel => dragging.set(false),
+                    _ => (),
+                }
+                glib::Propagation::Proceed
+            }));
+            scale.add_controller(drag_controller);
+
+            container.append(&header_box);
+            container.append(&controls_box);
+
+            mute_button.connect_toggled(glib::clone!(@weak obj => move |button| {
+                if obj.imp().syncing.get() {
+                    return;
+                }
+                if let Err(err) = audio::get().set_muted(stream_id, button.is_active()) {
+                    error!(?err, "Failed to mute stream");
+                }
+            }));
+            scale.connect_value_changed(glib::clone!(@weak obj => move |scale| {
+                if obj.imp().syncing.get() {
+                    return;
+                }
+                if let Err(err) = audio::get().set_volume(stream_id, scale.value()) {
+                    error!(?err, "Failed to set stream volume");
+                }
+            }));
+            devices_dropdown.connect_selected_notify(glib::clone!(@weak obj => move |dropdown| {
+                let imp = obj.imp();
+                if imp.syncing.get() {
+                    return;
+                }
+                let rows = imp.rows.borrow();
+                let Some(row) = rows.get(&stream_id) else {
+                    return;
+                };
+                let Some(device_id) = row.device_ids.borrow().get(dropdown.selected() as usize).copied()
+                else {
+                    return;
+                };
+                if let Err(err) = audio::get().move_stream(stream_id, device_id) {
+                    error!(?err, "Failed to move stream");
+                }
+            }));
+
+            StreamRow {
+                container,
+                icon,
+                name_label,
+                description_label,
+                mute_button,
+                scale,
+                dragging,
+                devices_dropdown,
+                device_ids: RefCell::new(vec![]),
+            }
+        }
+    }
+
+    fn sync_row(row: &StreamRow, stream: &Node, state: &audio::State) {
+        let icon_name = stream
+            .app_icon_name
+            .as_deref()
+            .unwrap_or("application-x-executable-symbolic");
+        row.icon.set_icon_name(Some(icon_name));
+        row.name_label
+            .set_text(stream.app_name.as_deref().unwrap_or(&stream.name));
+        row.description_label.set_text(&stream.description);
+        row.description_label
+            .set_visible(!stream.description.is_empty());
+        row.mute_button.set_active(stream.muted);
+        row.mute_button.set_icon_name(stream.volume_icon_name());
+        if !row.dragging.get() {
+            row.scale.set_value(stream.volume.min(1.0));
+        }
+
+        let (device_kind, default_device) = match stream.kind {
+            NodeKind::RecordingStream => (NodeKind::Source, state.default_source()),
+            _ => (NodeKind::Sink, state.default_sink()),
+        };
+        let devices = state.nodes(device_kind).collect::<Vec<_>>();
+        let device_ids = devices.iter().map(|device| device.id).collect::<Vec<_>>();
+        if *row.device_ids.borrow() != device_ids {
+            let descriptions = devices
+                .iter()
+                .map(|device| device.description.as_str())
+                .collect::<Vec<_>>();
+            row.devices_dropdown
+                .set_model(Some(&gtk::StringList::new(&descriptions)));
+            row.device_ids.replace(device_ids);
+        }
+        // There's nowhere to move the stream with a single device.
+        row.devices_dropdown.set_visible(devices.len() > 1);
+        // Streams that are not linked yet, or linked to something else than a device, go to the
+        // default device.
+        let device_ids = row.device_ids.borrow();
+        let position = |id| device_ids.iter().position(|device_id| *device_id == id);
+        let position = stream
+            .target
+            .and_then(position)
+            .or_else(|| default_device.and_then(|device| position(device.id)));
+        row.devices_dropdown
+            .set_selected(position.map_or(gtk::INVALID_LIST_POSITION, |position| position as u32));
+    }
+
+    impl WidgetImpl for MixerSection {}
+    impl BoxImpl for MixerSection {}
+}
+
+glib::wrapper! {
+    pub struct MixerSection(ObjectSubclass<imp::MixerSection>)
+        @extends gtk::Box, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Orientable;
+}
+
+impl MixerSection {
+    pub fn new() -> Self {
+        glib::Object::builder()
+            .property("orientation", gtk::Orientation::Vertical)
+            .property("spacing", 6)
+            .build()
+    }
+}
@@ -0,0 +1,74 @@
+//! Quick controls popup.
+//!
+//! This is the popup that opens when clicking on the status section of the panel. It is made of
+//! sections stacked vertically, each one taking care of a single daemon.
+
+pub mod battery;
+pub mod bluetooth;
+pub mod brightness;
+pub mod caffeine;
+pub mod keyboard_backlight;
+pub mod media;
+pub mod mixer;
+pub mod peripherals;
+pub mod power_profiles;
+pub mod volume;
+
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+
+mod imp {
+    use super::*;
+    use crate::daemons::logind;
+
+    #[derive(Default, Debug)]
+    pub struct QuickControls {}
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for QuickControls {
+        const NAME: &'static str = "QuickControls";
+        type Type = super::QuickControls;
+        type ParentType = gtk::Box;
+    }
+
+    impl ObjectImpl for QuickControls {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.add_css_class("quick-controls");
+            obj.append(&media::MediaSection::new());
+            obj.append(&volume::VolumeSection::new());
+            obj.append(&mixer::MixerSection::new());
+            obj.append(&brightness::BrightnessSection::new());
+            obj.append(&keyboard_backlight::KeyboardBacklightSection::new());
+            obj.append(&battery::BatterySection::new());
+            obj.append(&peripherals::PeripheralsSection::new());
+            obj.append(&bluetooth::BluetoothSection::new());
+            obj.append(&power_profiles::PowerProfilesSection::new());
+            // Caffeine mode is tied to our logind session.
+            if logind::get().has_session() {
+                obj.append(&caffeine::CaffeineSection::new());
+            }
+        }
+    }
+
+    impl WidgetImpl for QuickControls {}
+    impl BoxImpl for QuickControls {}
+}
+
+glib::wrapper! {
+    pub struct QuickControls(ObjectSubclass<imp::QuickControls>)
+        @extends gtk::Box, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Orientable;
+}
+
+impl QuickControls {
+    pub fn new() -> Self {
+        glib::Object::builder()
+            .property("orientation", gtk::Orientation::Vertical)
+            .property("spacing", 12)
+            .property("width-request", 320)
+            .build()
+    }
+}
@@ -0,0 +1,177 @@
+//! Peripherals section of the quick controls.
+//!
+//! Lists the battery level of every device that UPower knows about and that does not power the
+//! system itself: mice, keyboards, headsets, game controllers, phones, etc.
+
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+
+mod imp {
+    use futures_util::StreamExt;
+    use tokio::sync::broadcast;
+
+    use super::*;
+    use crate::daemons::upower;
+
+    #[derive(Default, Debug)]
+    pub struct PeripheralsSection {}
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for PeripheralsSection {
+        const NAME: &'static str = "PeripheralsSection";
+        type Type = super::PeripheralsSection;
+        type ParentType = gtk::Box;
+    }
+
+    impl ObjectImpl for PeripheralsSection {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.add_css_class("peripherals-section");
+            // We only show ourselves once we find a peripheral.
+            obj.set_visible(false);
+
+            for device in upower::get().devices() {
+                self.add_device(device);
+            }
+
+            // Peripherals come and go a lot more than system batteries.
+            let mut events = upower::get().subscribe();
+            let weak_obj = obj.downgrade();
+            glib::spawn_future_local(async move {
+                loop {
+                    let event = match events.recv().await {
+                        Ok(event) => event,
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    };
+
+                    let Some(obj) = weak_obj.upgrade() else {
+                        break;
+                    };
+                    match event {
+                        upower::Event::DeviceAdded(device) => obj.imp().add_device(device),
+                        upower::Event::DeviceRemoved(id) => obj.imp().remove_device(&id),
+                    }
+                }
+            });
+        }
+    }
+
+    impl PeripheralsSection {
+        fn add_device(&self, device: upower::Device) {
+            let weak_obj = self.obj().downgrade();
+            glib::spawn_future_local(async move {
+                if !device.is_peripheral().await.unwrap_or(false) {
+                    return;
+                }
+
+                match add_peripheral(&device).await {
+                    Ok(row) => {
+                        let Some(obj) = weak_obj.upgrade() else {
+                            return;
+                        };
+                        obj.append(&row);
+                        obj.set_visible(true);
+                    }
+                    Err(err) => {
+                        error!(?err, device_id = ?device.id(), "Failed to add peripheral");
+                    }
+                }
+            });
+        }
+
+        fn remove_device(&self, id: &str) {
+            let obj = self.obj();
+            let mut child = obj.first_child();
+            while let Some(row) = child {
+                child = row.next_sibling();
+                if row.widget_name().as_str() == id {
+                    obj.remove(&row);
+                }
+            }
+
+            obj.set_visible(obj.first_child().is_some());
+        }
+    }
+
+    async fn add_peripheral(device: &upower::Device) -> anyhow::Result<gtk::Box> {
+        let proxy = device.proxy();
+        let type_ = proxy.type_().await?;
+        let model = proxy.model().await.unwrap_or_default();
+
+        let row = gtk::Box::builder()
+            .name(&*device.id())
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(6)
+            .css_classes(["peripheral"])
+            .build();
+        let icon = gtk::Image::from_icon_name(type_.icon_name());
+        let name_label = gtk::Label::builder()
+            .label(if model.is_empty() {
+                type_.name()
+            } else {
+                model.as_str()
+            })
+            .ellipsize(gtk::pango::EllipsizeMode::End)
+            .hexpand(true)
+            .xalign(0.0)
+            .build();
+        let level_label = gtk::Label::new(None);
+        row.append(&icon);
+        row.append(&name_label);
+        row.append(&level_label);
+
+        let mut changes = futures_util::stream::select(
+            proxy.receive_percentage_changed().await.map(|_| ()),
+            proxy.receive_battery_level_changed().await.map(|_| ()),
+        );
+        let proxy = proxy.clone();
+        let weak_level_label = level_label.downgrade();
+        glib::spawn_future_local(async move {
+            loop {
+                let percentage = proxy.percentage().await.unwrap_or(0.0);
+                let battery_level = proxy
+                    .battery_level()
+                    .await
+                    .unwrap_or(upower::device::BatteryLevel::None);
+
+                let Some(level_label) = weak_level_label.upgrade() else {
+                    break; // label does not exist anymore?
+                };
+                level_label.set_text(&battery_level.describe(percentage));
+                let low_threshold = crate::config::get().battery.peripherals.low_threshold;
+                if battery_level.is_low(percentage, low_threshold) {
+                    level_label.add_css_class("warning");
+                } else {
+                    level_label.remove_css_class("warning");
+                }
+
+                if changes.next().await.is_none() {
+                    break;
+                }
+            }
+        });
+
+        Ok(row)
+    }
+
+    impl WidgetImpl for PeripheralsSection {}
+    impl BoxImpl for PeripheralsSection {}
+}
+
+glib::wrapper! {
+    pub struct PeripheralsSection(ObjectSubclass<imp::PeripheralsSection>)
+        @extends gtk::Box, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Orientable;
+}
+
+impl PeripheralsSection {
+    pub fn new() -> Self {
+        glib::Object::builder()
+            .property("orientation", gtk::Orientation::Vertical)
+            .property("spacing", 6)
+            .build()
+    }
+}
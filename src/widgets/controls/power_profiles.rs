@@ -0,0 +1,225 @@
+//! Power profiles section of the quick controls.
+//!
+//! A three-way selector between the power-profiles-daemon profiles, with the reason why
+//! performance is degraded, if it is, and the applications currently holding a profile.
+
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+
+use crate::daemons::power_profiles::{self, Profile};
+
+mod imp {
+    use std::cell::{Cell, OnceCell};
+
+    use futures_util::StreamExt;
+
+    use super::*;
+
+    #[derive(Default, Debug)]
+    pub struct PowerProfilesSection {
+        buttons: OnceCell<Vec<(Profile, gtk::ToggleButton)>>,
+        degraded_label: OnceCell<gtk::Label>,
+        holds_label: OnceCell<gtk::Label>,
+        // The profile the daemon reported last, to tell our own updates apart from the user's.
+        pub(super) active_profile: Cell<Option<Profile>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for PowerProfilesSection {
+        const NAME: &'static str = "PowerProfilesSection";
+        type Type = super::PowerProfilesSection;
+        type ParentType = gtk::Box;
+    }
+
+    impl ObjectImpl for PowerProfilesSection {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.add_css_class("power-profiles-section");
+            // We only show ourselves once we know power-profiles-daemon is running.
+            obj.set_visible(false);
+
+            obj.append(
+                &gtk::Label::builder()
+                    .label("Power mode")
+                    .css_classes(["heading"])
+                    .xalign(0.0)
+                    .build(),
+            );
+
+            let buttons_box = gtk::Box::builder()
+                .orientation(gtk::Orientation::Horizontal)
+                .css_classes(["linked"])
+                .homogeneous(true)
+                .build();
+            let mut buttons: Vec<(Profile, gtk::ToggleButton)> = vec![];
+            for profile in Profile::ALL {
+                let content = gtk::Box::builder()
+                    .orientation(gtk::Orientation::Vertical)
+                    .spacing(3)
+                    .build();
+                content.append(&gtk::Image::from_icon_name(profile.icon_name()));
+                content.append(
+                    &gtk::Label::builder()
+                        .label(profile.label())
+                        .css_classes(["caption"])
+                        .build(),
+                );
+                let button = gtk::ToggleButton::builder().child(&content).build();
+                if let Some((_, first)) = buttons.first() {
+                    button.set_group(Some(first));
+                }
+                button.connect_toggled(glib::clone!(@weak obj => move |button| {
+                    // Toggling a button also untoggles the previous one.
+                    if button.is_active() && obj.imp().active_profile.get() != Some(profile) {
+                        obj.imp().switch_to(profile);
+                    }
+                }));
+                buttons_box.append(&button);
+                buttons.push((profile, button));
+            }
+            obj.append(&buttons_box);
+            self.buttons.set(buttons).unwrap();
+
+            let degraded_label = gtk::Label::builder()
+                .css_classes(["warning", "caption"])
+                .wrap(true)
+                .xalign(0.0)
+                .visible(false)
+                .build();
+            obj.append(&degraded_label);
+            self.degraded_label.set(degraded_label).unwrap();
+
+            let holds_label = gtk::Label::builder()
+                .css_classes(["dim-label", "caption"])
+                .wrap(true)
+                .xalign(0.0)
+                .visible(false)
+                .build();
+            obj.append(&holds_label);
+            self.holds_label.set(holds_label).unwrap();
+
+            let weak_obj = obj.downgrade();
+            glib::spawn_future_local(async move {
+                let proxy = power_profiles::get().proxy();
+                let mut changes = futures_util::stream::select(
+                    futures_util::stream::select(
+                        proxy.receive_active_profile_changed().await.map(|_| ()),
+                        proxy.receive_profiles_changed().await.map(|_| ()),
+                    ),
+                    futures_util::stream::select(
+                        proxy
+                            .receive_performance_degraded_changed()
+                            .await
+                            .map(|_| ()),
+                        proxy
+                            .receive_active_profile_holds_changed()
+                            .await
+                            .map(|_| ()),
+                    ),
+                );
+
+                loop {
+                    let Some(obj) = weak_obj.upgrade() else {
+                        break;
+                    };
+                    obj.imp().sync().await;
+                    drop(obj);
+
+                    if changes.next().await.is_none() {
+                        break;
+                    }
+                }
+            });
+        }
+    }
+
+    impl PowerProfilesSection {
+        /// Sync the widgets with the daemon state.
+        pub(super) async fn sync(&self) {
+            let daemon = power_profiles::get();
+            let (profiles, active_profile) =
+                match futures_util::try_join!(daemon.profiles(), daemon.active_profile()) {
+                    Ok(state) => state,
+                    Err(err) => {
+                        // Most likely power-profiles-daemon is not installed.
+                        debug!(?err, "Failed to get power profiles");
+                        self.obj().set_visible(false);
+                        return;
+                    }
+                };
+            let degraded = daemon.performance_degraded().await.ok().flatten();
+            let holds = daemon.active_profile_holds().await.unwrap_or_default();
+
+            self.obj().set_visible(true);
+            self.active_profile.set(Some(active_profile));
+            for (profile, button) in self.buttons.get().unwrap() {
+                button.set_sensitive(profiles.contains(profile));
+                button.set_active(*profile == active_profile);
+            }
+
+            let degraded_label = self.degraded_label.get().unwrap();
+            match degraded {
+                Some(degraded) => {
+                    degraded_label.set_text(&degraded.describe());
+                    degraded_label.set_visible(true);
+                }
+                None => degraded_label.set_visible(false),
+            }
+
+            let holds_label = self.holds_label.get().unwrap();
+            let holds = holds
+                .iter()
+                .map(|hold| {
+                    let application = if hold.application_id.is_empty() {
+                        "An application"
+                    } else {
+                        hold.application_id.as_str()
+                    };
+                    let mut description = format!(
+                        "{application} requested {}",
+                        hold.profile.label().to_lowercase()
+                    );
+                    if !hold.reason.is_empty() {
+                        description.push_str(&format!(": {}", hold.reason));
+                    }
+                    description
+                })
+                .collect::<Vec<_>>();
+            holds_label.set_text(&holds.join("\n"));
+            holds_label.set_visible(!holds.is_empty());
+        }
+
+        fn switch_to(&self, profile: Profile) {
+            let weak_obj = self.obj().downgrade();
+            glib::spawn_future_local(async move {
+                if let Err(err) = power_profiles::get().set_active_profile(profile).await {
+                    error!(?err, ?profile, "Failed to switch power profile");
+                    // Put the buttons back in their place.
+                    if let Some(obj) = weak_obj.upgrade() {
+                        obj.imp().sync().await;
+                    }
+                }
+            });
+        }
+    }
+
+    impl WidgetImpl for PowerProfilesSection {}
+    impl BoxImpl for PowerProfilesSection {}
+}
+
+glib::wrapper! {
+    pub struct PowerProfilesSection(ObjectSubclass<imp::PowerProfilesSection>)
+        @extends gtk::Box, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Orientable;
+}
+
+impl PowerProfilesSection {
+    pub fn new() -> Self {
+        glib::Object::builder()
+            .property("orientation", gtk::Orientation::Vertical)
+            .property("spacing", 6)
+            .build()
+    }
+}
@@ -0,0 +1,213 @@
+//! Volume section of the quick controls.
+//!
+//! One row for the default output device and one for the default input device, each with a mute
+//! button, a volume slider and a menu to switch the default device.
+
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+
+use crate::daemons::audio::{self, NodeKind};
+
+mod imp {
+    use std::cell::{Cell, OnceCell, RefCell};
+
+    use super::*;
+
+    #[derive(Default, Debug)]
+    pub struct VolumeSection {
+        rows: OnceCell<[DeviceRow; 2]>,
+        // Whether we are updating the widgets ourselves, to not write the values back.
+        syncing: Cell<bool>,
+    }
+
+    /// The controls of the default device of a kind.
+    #[derive(Debug)]
+    struct DeviceRow {
+        kind: NodeKind,
+        container: gtk::Box,
+        mute_button: gtk::ToggleButton,
+        scale: gtk::Scale,
+        devices_dropdown: gtk::DropDown,
+        // The IDs of the devices in the dropdown, in order.
+        device_ids: RefCell<Vec<u32>>,
+        // The default device we are showing.
+        device_id: Cell<Option<u32>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for VolumeSection {
+        const NAME: &'static str = "VolumeSection";
+        type Type = super::VolumeSection;
+        type ParentType = gtk::Box;
+    }
+
+    impl ObjectImpl for VolumeSection {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.add_css_class("volume-section");
+
+            let rows = [NodeKind::Sink, NodeKind::Source].map(|kind| self.build_row(kind));
+            for row in &rows {
+                obj.append(&row.container);
+            }
+            self.rows.set(rows).unwrap();
+
+            let mut state = audio::get().subscribe();
+            let weak_obj = obj.downgrade();
+            glib::spawn_future_local(async move {
+                loop {
+                    let current = state.borrow_and_update().clone();
+                    let Some(obj) = weak_obj.upgrade() else {
+                        break;
+                    };
+                    obj.imp().sync(&current);
+                    drop(obj);
+
+                    if state.changed().await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    }
+
+    impl VolumeSection {
+        fn build_row(&self, kind: NodeKind) -> DeviceRow {
+            let obj = self.obj();
+            let container = gtk::Box::builder()
+                .orientation(gtk::Orientation::Horizontal)
+                .spacing(6)
+                .build();
+            let mute_button = gtk::ToggleButton::builder()
+                .css_classes(["flat", "circular"])
+                .tooltip_text("Mute")
+                .build();
+            let scale = gtk::Scale::with_range(gtk::Orientation::Horizontal, 0.0, 1.0, 0.01);
+            scale.set_hexpand(true);
+            let devices_dropdown = gtk::DropDown::builder()
+                .model(&gtk::StringList::new(&[]))
+                .css_classes(["flat"])
+                .tooltip_text(match kind {
+                    NodeKind::Sink => "Output device",
+                    _ => "Input device",
+                })
+                .build();
+            container.append(&mute_button);
+            container.append(&scale);
+            container.append(&devices_dropdown);
+
+            let index = match kind {
+                NodeKind::Sink => 0,
+                _ => 1,
+            };
+            mute_button.connect_toggled(glib::clone!(@weak obj => move |button| {
+                let imp = obj.imp();
+                if imp.syncing.get() {
+                    return;
+                }
+                let Some(id) = imp.rows.get().unwrap()[index].device_id.get() else {
+                    return;
+                };
+                if let Err(err) = audio::get().set_muted(id, button.is_active()) {
+                    error!(?err, "Failed to mute audio device");
+                }
+            }));
+            scale.connect_value_changed(glib::clone!(@weak obj => move |scale| {
+                let imp = obj.imp();
+                if imp.syncing.get() {
+                    return;
+                }
+                let Some(id) = imp.rows.get().unwrap()[index].device_id.get() else {
+                    return;
+                };
+                if let Err(err) = audio::get().set_volume(id, scale.value()) {
+                    error!(?err, "Failed to set audio device volume");
+                }
+            }));
+            devices_dropdown.connect_selected_notify(glib::clone!(@weak obj => move |dropdown| {
+                let imp = obj.imp();
+                if imp.syncing.get() {
+                    return;
+                }
+                let row = &imp.rows.get().unwrap()[index];
+                let Some(id) = row.device_ids.borrow().get(dropdown.selected() as usize).copied()
+                else {
+                    return;
+                };
+                if let Err(err) = audio::get().set_default(id) {
+                    error!(?err, "Failed to switch default audio device");
+                }
+            }));
+
+            DeviceRow {
+                kind,
+                container,
+                mute_button,
+                scale,
+                devices_dropdown,
+                device_ids: RefCell::new(vec![]),
+                device_id: Cell::new(None),
+            }
+        }
+
+        fn sync(&self, state: &audio::State) {
+            self.syncing.set(true);
+            for row in self.rows.get().unwrap() {
+                let default_device = state.default_device(row.kind);
+                row.container.set_visible(default_device.is_some());
+                row.device_id.set(default_device.map(|device| device.id));
+                let Some(device) = default_device else {
+                    continue;
+                };
+
+                row.mute_button.set_active(device.muted);
+                row.mute_button.set_icon_name(device.volume_icon_name());
+                row.scale.set_value(device.volume.min(1.0));
+
+                // Only rebuild the list when devices come and go, this happens rarely.
+                let devices = state.nodes(row.kind).collect::<Vec<_>>();
+                let device_ids = devices.iter().map(|device| device.id).collect::<Vec<_>>();
+                if *row.device_ids.borrow() != device_ids {
+                    let descriptions = devices
+                        .iter()
+                        .map(|device| device.description.as_str())
+                        .collect::<Vec<_>>();
+                    row.devices_dropdown
+                        .set_model(Some(&gtk::StringList::new(&descriptions)));
+                    row.device_ids.replace(device_ids);
+                }
+                // There's no point in switching with a single device.
+                row.devices_dropdown.set_visible(devices.len() > 1);
+                if let Some(position) = row
+                    .device_ids
+                    .borrow()
+                    .iter()
+                    .position(|id| *id == device.id)
+                {
+                    row.devices_dropdown.set_selected(position as u32);
+                }
+            }
+            self.syncing.set(false);
+        }
+    }
+
+    impl WidgetImpl for VolumeSection {}
+    impl BoxImpl for VolumeSection {}
+}
+
+glib::wrapper! {
+    pub struct VolumeSection(ObjectSubclass<imp::VolumeSection>)
+        @extends gtk::Box, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Orientable;
+}
+
+impl VolumeSection {
+    pub fn new() -> Self {
+        glib::Object::builder()
+            .property("orientation", gtk::Orientation::Vertical)
+            .property("spacing", 6)
+            .build()
+    }
+}
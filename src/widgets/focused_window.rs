@@ -0,0 +1,124 @@
+//! Panel focused window module.
+//!
+//! Shows the icon and title of the window that has the keyboard focus, as reported by the
+//! compositor daemon. Middle-clicking closes the window.
+
+use gtk::glib;
+use gtk::pango;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+
+use crate::config::Ellipsize;
+use crate::daemons::compositor::{self, State};
+
+mod imp {
+    use std::cell::Cell;
+
+    use super::*;
+
+    #[derive(Default, Debug)]
+    pub struct FocusedWindowWidget {
+        pub(super) icon: gtk::Image,
+        pub(super) title: gtk::Label,
+        /// The ID of the focused window.
+        pub(super) window_id: Cell<Option<u64>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for FocusedWindowWidget {
+        const NAME: &'static str = "FocusedWindowWidget";
+        type Type = super::FocusedWindowWidget;
+        type ParentType = gtk::Box;
+    }
+
+    impl ObjectImpl for FocusedWindowWidget {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.add_css_class("focused-window");
+            obj.set_visible(false);
+
+            let config = &crate::config::get().focused_window;
+            self.icon.set_pixel_size(16);
+            obj.append(&self.icon);
+            self.title.set_ellipsize(match config.ellipsize {
+                Ellipsize::None => pango::EllipsizeMode::None,
+                Ellipsize::Start => pango::EllipsizeMode::Start,
+                Ellipsize::Middle => pango::EllipsizeMode::Middle,
+                Ellipsize::End => pango::EllipsizeMode::End,
+            });
+            self.title.set_max_width_chars(config.max_width_chars);
+            self.title.set_single_line_mode(true);
+            obj.append(&self.title);
+
+            let middle_click = gtk::GestureClick::builder()
+                .button(gtk::gdk::BUTTON_MIDDLE)
+                .build();
+            let weak_obj = obj.downgrade();
+            middle_click.connect_released(move |_, _, _, _| {
+                let Some(id) = weak_obj.upgrade().and_then(|obj| obj.imp().window_id.get()) else {
+                    return;
+                };
+                if let Err(err) = compositor::get().close_window(id) {
+                    warn!(?err, id, "Failed to close window");
+                }
+            });
+            obj.add_controller(middle_click);
+
+            let mut state = compositor::get().subscribe();
+            let weak_obj = obj.downgrade();
+            glib::spawn_future_local(async move {
+                loop {
+                    let current = state.borrow_and_update().clone();
+                    let Some(obj) = weak_obj.upgrade() else {
+                        break;
+                    };
+                    obj.imp().sync(&current);
+                    drop(obj);
+
+                    if state.changed().await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    }
+
+    impl FocusedWindowWidget {
+        fn sync(&self, state: &State) {
+            let obj = self.obj();
+            let Some(window) = state.focused_window() else {
+                self.window_id.set(None);
+                obj.set_visible(false);
+                return;
+            };
+
+            self.window_id.set(Some(window.id));
+            match crate::app_info::icon(&window.app_id) {
+                Some(icon) => self.icon.set_from_gicon(&icon),
+                None => self.icon.set_icon_name(Some("application-x-executable")),
+            }
+            self.title.set_label(&window.title);
+            obj.set_tooltip_text(Some(&window.title));
+            obj.set_visible(true);
+        }
+    }
+
+    impl WidgetImpl for FocusedWindowWidget {}
+    impl BoxImpl for FocusedWindowWidget {}
+}
+
+glib::wrapper! {
+    pub struct FocusedWindowWidget(ObjectSubclass<imp::FocusedWindowWidget>)
+        @extends gtk::Box, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Orientable;
+}
+
+impl FocusedWindowWidget {
+    pub fn new() -> Self {
+        glib::Object::builder()
+            .property("orientation", gtk::Orientation::Horizontal)
+            .property("spacing", 6)
+            .build()
+    }
+}
@@ -0,0 +1,169 @@
+//! Panel media module.
+//!
+//! Shows what the selected MPRIS player is playing, with a play/pause button. The full controls
+//! live in the quick controls.
+
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+
+use crate::daemons::mpris::{self, PlaybackStatus, Player};
+
+mod imp {
+    use std::cell::{OnceCell, RefCell};
+
+    use futures_util::StreamExt;
+
+    use super::*;
+
+    #[derive(Default, Debug)]
+    pub struct MediaWidget {
+        label: OnceCell<gtk::Label>,
+        play_pause_button: OnceCell<gtk::Button>,
+        pub(super) player: RefCell<Option<Player>>,
+        // The future following the selected player, if any.
+        tracker: RefCell<Option<glib::JoinHandle<()>>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MediaWidget {
+        const NAME: &'static str = "MediaWidget";
+        type Type = super::MediaWidget;
+        type ParentType = gtk::Box;
+    }
+
+    impl ObjectImpl for MediaWidget {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.add_css_class("media-widget");
+            obj.set_visible(false);
+
+            let label = gtk::Label::builder()
+                .ellipsize(gtk::pango::EllipsizeMode::End)
+                .max_width_chars(32)
+                .build();
+            let play_pause_button = gtk::Button::builder()
+                .icon_name("media-playback-start-symbolic")
+                .css_classes(["flat", "circular"])
+                .build();
+            play_pause_button.connect_clicked(glib::clone!(@weak obj => move |_| {
+                let Some(player) = obj.imp().player.borrow().clone() else {
+                    return;
+                };
+                glib::spawn_future_local(async move {
+                    if let Err(err) = player.proxy().play_pause().await {
+                        warn!(?err, "Failed to control media player");
+                    }
+                });
+            }));
+            obj.append(&gtk::Image::from_icon_name("audio-x-generic-symbolic"));
+            obj.append(&label);
+            obj.append(&play_pause_button);
+            self.label.set(label).unwrap();
+            self.play_pause_button.set(play_pause_button).unwrap();
+
+            let mut selected = mpris::get().subscribe_selected();
+            let weak_obj = obj.downgrade();
+            glib::spawn_future_local(async move {
+                loop {
+                    let player = selected.borrow_and_update().clone();
+                    let Some(obj) = weak_obj.upgrade() else {
+                        break;
+                    };
+                    obj.imp().track_player(player);
+                    drop(obj);
+
+                    if selected.changed().await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+
+        fn dispose(&self) {
+            if let Some(tracker) = self.tracker.take() {
+                tracker.abort();
+            }
+        }
+    }
+
+    impl MediaWidget {
+        /// Start following this player, stopping to follow the previous one.
+        fn track_player(&self, player: Option<Player>) {
+            if let Some(tracker) = self.tracker.take() {
+                tracker.abort();
+            }
+            self.player.replace(player.clone());
+            let Some(player) = player else {
+                self.obj().set_visible(false);
+                return;
+            };
+
+            let weak_obj = self.obj().downgrade();
+            let handle = glib::spawn_future_local(async move {
+                let proxy = player.proxy();
+                let mut changes = futures_util::stream::select(
+                    proxy.receive_metadata_changed().await.map(|_| ()),
+                    proxy.receive_playback_status_changed().await.map(|_| ()),
+                );
+
+                loop {
+                    let metadata = player.metadata().await.unwrap_or_default();
+                    let status = player
+                        .playback_status()
+                        .await
+                        .unwrap_or(PlaybackStatus::Stopped);
+
+                    let Some(obj) = weak_obj.upgrade() else {
+                        break;
+                    };
+                    let imp = obj.imp();
+                    // A stopped player has nothing interesting to show.
+                    obj.set_visible(status != PlaybackStatus::Stopped && metadata.title.is_some());
+
+                    let title = metadata.title.unwrap_or_default();
+                    let text = if metadata.artists.is_empty() {
+                        title
+                    } else {
+                        format!("{title} — {}", metadata.artists.join(", "))
+                    };
+                    let label = imp.label.get().unwrap();
+                    label.set_text(&text);
+                    obj.set_tooltip_text(Some(&format!("{}: {text}", player.identity())));
+
+                    let play_pause_button = imp.play_pause_button.get().unwrap();
+                    play_pause_button.set_icon_name(if status == PlaybackStatus::Playing {
+                        "media-playback-pause-symbolic"
+                    } else {
+                        "media-playback-start-symbolic"
+                    });
+                    drop(obj);
+
+                    if changes.next().await.is_none() {
+                        break;
+                    }
+                }
+            });
+            self.tracker.replace(Some(handle));
+        }
+    }
+
+    impl WidgetImpl for MediaWidget {}
+    impl BoxImpl for MediaWidget {}
+}
+
+glib::wrapper! {
+    pub struct MediaWidget(ObjectSubclass<imp::MediaWidget>)
+        @extends gtk::Box, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Orientable;
+}
+
+impl MediaWidget {
+    pub fn new() -> Self {
+        glib::Object::builder()
+            .property("orientation", gtk::Orientation::Horizontal)
+            .property("spacing", 6)
+            .build()
+    }
+}
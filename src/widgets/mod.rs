@@ -1,2 +1,12 @@
+pub mod calendar;
+pub mod controls;
+pub mod focused_window;
+pub mod media;
+pub mod power_menu;
+pub mod privacy;
 pub mod status;
+pub mod taskbar;
 pub mod time;
+pub mod timers;
+pub mod tray;
+pub mod workspaces;
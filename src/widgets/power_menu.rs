@@ -0,0 +1,265 @@
+//! Session power menu.
+//!
+//! Lists the actions logind allows on the session and the system: locking, logging out, suspending,
+//! rebooting, etc. Except for locking, every action must be confirmed first, and runs by itself
+//! once the countdown from the config runs out.
+
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+
+use crate::daemons::logind::{self, Action};
+
+mod imp {
+    use std::cell::{Cell, OnceCell, RefCell};
+
+    use adw::prelude::BinExt;
+    use adw::subclass::bin::BinImpl;
+
+    use super::*;
+
+    #[derive(Default, Debug)]
+    pub struct PowerMenu {
+        pub(super) stack: OnceCell<gtk::Stack>,
+        error_label: OnceCell<gtk::Label>,
+        confirm_title: OnceCell<gtk::Label>,
+        confirm_description: OnceCell<gtk::Label>,
+        confirm_button: OnceCell<gtk::Button>,
+        // The action waiting for confirmation, if any.
+        pub(super) pending_action: Cell<Option<Action>>,
+        countdown: RefCell<Option<glib::SourceId>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for PowerMenu {
+        const NAME: &'static str = "PowerMenu";
+        type Type = super::PowerMenu;
+        type ParentType = adw::Bin;
+    }
+
+    impl ObjectImpl for PowerMenu {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.add_css_class("power-menu");
+            let stack = gtk::Stack::builder()
+                .transition_type(gtk::StackTransitionType::SlideLeftRight)
+                .build();
+            obj.set_child(Some(&stack));
+
+            let actions_box = gtk::Box::builder()
+                .orientation(gtk::Orientation::Vertical)
+                .spacing(3)
+                .build();
+            for action in Action::ALL {
+                let button = action_button(action);
+                // Only show the action once we know logind allows it.
+                button.set_visible(false);
+                button.connect_clicked(glib::clone!(@weak obj => move |_| {
+                    obj.imp().request(action);
+                }));
+                actions_box.append(&button);
+
+                let weak_button = button.downgrade();
+                glib::spawn_future_local(async move {
+                    let can_run = logind::get().can_run(action).await;
+                    if let Some(button) = weak_button.upgrade() {
+                        button.set_visible(can_run);
+                    }
+                });
+            }
+
+            let error_label = gtk::Label::builder()
+                .css_classes(["error", "caption"])
+                .wrap(true)
+                .xalign(0.0)
+                .visible(false)
+                .build();
+            actions_box.append(&error_label);
+            self.error_label.set(error_label).unwrap();
+            stack.add_named(&actions_box, Some("actions"));
+
+            let confirm_title = gtk::Label::builder()
+                .css_classes(["title-4"])
+                .xalign(0.0)
+                .build();
+            let confirm_description = gtk::Label::builder().wrap(true).xalign(0.0).build();
+            let cancel_button = gtk::Button::with_label("Cancel");
+            cancel_button.connect_clicked(glib::clone!(@weak obj => move |_| obj.cancel()));
+            let confirm_button = gtk::Button::builder()
+                .css_classes(["destructive-action"])
+                .hexpand(true)
+                .build();
+            confirm_button.connect_clicked(glib::clone!(@weak obj => move |_| {
+                obj.imp().confirm();
+            }));
+
+            let buttons_box = gtk::Box::builder()
+                .orientation(gtk::Orientation::Horizontal)
+                .spacing(6)
+                .homogeneous(true)
+                .build();
+            buttons_box.append(&cancel_button);
+            buttons_box.append(&confirm_button);
+
+            let confirm_box = gtk::Box::builder()
+                .orientation(gtk::Orientation::Vertical)
+                .spacing(12)
+                .build();
+            confirm_box.append(&confirm_title);
+            confirm_box.append(&confirm_description);
+            confirm_box.append(&buttons_box);
+            stack.add_named(&confirm_box, Some("confirm"));
+
+            self.confirm_title.set(confirm_title).unwrap();
+            self.confirm_description.set(confirm_description).unwrap();
+            self.confirm_button.set(confirm_button).unwrap();
+            stack.set_visible_child_name("actions");
+            self.stack.set(stack).unwrap();
+        }
+
+        fn dispose(&self) {
+            self.stop_countdown();
+        }
+    }
+
+    impl PowerMenu {
+        /// The user clicked on an action, ask for confirmation if needed.
+        fn request(&self, action: Action) {
+            self.error_label.get().unwrap().set_visible(false);
+            if action == Action::Lock {
+                self.run(action);
+                return;
+            }
+
+            let (title, _) = action_label(action);
+            self.confirm_title.get().unwrap().set_text(title);
+            self.confirm_button.get().unwrap().set_label(title);
+            self.pending_action.set(Some(action));
+
+            let remaining = Cell::new(crate::config::get().power.confirmation_countdown);
+            self.update_description(action, remaining.get());
+            let weak_obj = self.obj().downgrade();
+            let countdown = glib::timeout_add_seconds_local(1, move || {
+                let Some(obj) = weak_obj.upgrade() else {
+                    return glib::ControlFlow::Break;
+                };
+
+                remaining.set(remaining.get().saturating_sub(1));
+                if remaining.get() == 0 {
+                    // Returning break removes the source, we must not remove it a second time.
+                    let _ = obj.imp().countdown.take();
+                    obj.imp().confirm();
+                    return glib::ControlFlow::Break;
+                }
+
+                obj.imp().update_description(action, remaining.get());
+                glib::ControlFlow::Continue
+            });
+            self.stop_countdown();
+            self.countdown.replace(Some(countdown));
+            self.stack.get().unwrap().set_visible_child_name("confirm");
+        }
+
+        fn update_description(&self, action: Action, remaining: u32) {
+            let description = match action {
+                Action::Lock => unreachable!(),
+                Action::LogOut => "You will be logged out",
+                Action::Suspend => "The system will suspend",
+                Action::Hibernate => "The system will hibernate",
+                Action::HybridSleep => "The system will enter hybrid sleep",
+                Action::Reboot => "The system will restart",
+                Action::PowerOff => "The system will power off",
+            };
+            let description = format!("{description} in {remaining} seconds.");
+            self.confirm_description
+                .get()
+                .unwrap()
+                .set_text(&description);
+        }
+
+        fn confirm(&self) {
+            self.stop_countdown();
+            if let Some(action) = self.pending_action.take() {
+                self.run(action);
+            }
+            self.stack.get().unwrap().set_visible_child_name("actions");
+        }
+
+        fn run(&self, action: Action) {
+            // Get the menu out of the way.
+            if let Some(popover) = self.obj().ancestor(gtk::Popover::static_type()) {
+                popover.downcast::<gtk::Popover>().unwrap().popdown();
+            }
+
+            let weak_error_label = self.error_label.get().unwrap().downgrade();
+            glib::spawn_future_local(async move {
+                if let Err(err) = logind::get().run(action).await {
+                    error!(?err, ?action, "Failed to run session action");
+                    if let Some(error_label) = weak_error_label.upgrade() {
+                        let (title, _) = action_label(action);
+                        error_label.set_text(&format!("Failed to {}", title.to_lowercase()));
+                        error_label.set_visible(true);
+                    }
+                }
+            });
+        }
+
+        pub(super) fn stop_countdown(&self) {
+            if let Some(countdown) = self.countdown.take() {
+                countdown.remove();
+            }
+        }
+    }
+
+    fn action_button(action: Action) -> gtk::Button {
+        let (label, icon_name) = action_label(action);
+        let content = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(12)
+            .build();
+        content.append(&gtk::Image::from_icon_name(icon_name));
+        content.append(&gtk::Label::builder().label(label).xalign(0.0).build());
+
+        gtk::Button::builder()
+            .css_classes(["flat"])
+            .child(&content)
+            .build()
+    }
+
+    /// Get the label and icon name of an action.
+    fn action_label(action: Action) -> (&'static str, &'static str) {
+        match action {
+            Action::Lock => ("Lock", "system-lock-screen-symbolic"),
+            Action::LogOut => ("Log out", "system-log-out-symbolic"),
+            Action::Suspend => ("Suspend", "system-suspend-symbolic"),
+            Action::Hibernate => ("Hibernate", "system-hibernate-symbolic"),
+            Action::HybridSleep => ("Hybrid sleep", "system-suspend-hibernate-symbolic"),
+            Action::Reboot => ("Restart", "system-reboot-symbolic"),
+            Action::PowerOff => ("Power off", "system-shutdown-symbolic"),
+        }
+    }
+
+    impl WidgetImpl for PowerMenu {}
+    impl BinImpl for PowerMenu {}
+}
+
+glib::wrapper! {
+    pub struct PowerMenu(ObjectSubclass<imp::PowerMenu>)
+        @extends adw::Bin, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget;
+}
+
+impl PowerMenu {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+
+    /// Cancel the action waiting for confirmation, if any.
+    pub fn cancel(&self) {
+        let imp = self.imp();
+        imp.stop_countdown();
+        imp.pending_action.set(None);
+        imp.stack.get().unwrap().set_visible_child_name("actions");
+    }
+}
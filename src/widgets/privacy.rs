@@ -0,0 +1,300 @@
+//! Privacy indicators.
+//!
+//! Shows in the panel when an application is recording from a microphone or using a camera,
+//! with a popover listing who is doing so. The microphone can be muted from there.
+
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+
+use crate::daemons::audio::{self, NodeKind};
+use crate::daemons::camera;
+
+mod imp {
+    use std::cell::OnceCell;
+
+    use adw::prelude::BinExt;
+    use adw::subclass::bin::BinImpl;
+
+    use super::*;
+
+    #[derive(Default, Debug)]
+    pub struct PrivacyIndicator {
+        microphone_icon: OnceCell<gtk::Image>,
+        camera_icon: OnceCell<gtk::Image>,
+        microphone_box: OnceCell<gtk::Box>,
+        microphone_apps_box: OnceCell<gtk::Box>,
+        mute_button: OnceCell<gtk::Button>,
+        camera_box: OnceCell<gtk::Box>,
+        camera_apps_box: OnceCell<gtk::Box>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for PrivacyIndicator {
+        const NAME: &'static str = "PrivacyIndicator";
+        type Type = super::PrivacyIndicator;
+        type ParentType = adw::Bin;
+    }
+
+    impl ObjectImpl for PrivacyIndicator {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.add_css_class("privacy-indicator");
+            obj.set_visible(false);
+
+            let icons_box = gtk::Box::builder()
+                .orientation(gtk::Orientation::Horizontal)
+                .spacing(3)
+                .build();
+            let microphone_icon = gtk::Image::builder()
+                .icon_name("audio-input-microphone-symbolic")
+                .css_classes(["microphone"])
+                .visible(false)
+                .build();
+            let camera_icon = gtk::Image::builder()
+                .icon_name("camera-web-symbolic")
+                .css_classes(["camera"])
+                .visible(false)
+                .build();
+            icons_box.append(&microphone_icon);
+            icons_box.append(&camera_icon);
+
+            let popover_box = gtk::Box::builder()
+                .orientation(gtk::Orientation::Vertical)
+                .spacing(12)
+                .width_request(240)
+                .build();
+
+            let microphone_box = section_box("Using the microphone");
+            let microphone_apps_box = gtk::Box::builder()
+                .orientation(gtk::Orientation::Vertical)
+                .spacing(3)
+                .build();
+            let mute_button = gtk::Button::builder().label("Mute microphone").build();
+            mute_button.connect_clicked(|_| {
+                // Mute the microphones actually being recorded, not only the default one.
+                let state = audio::get().state();
+                let sources = used_sources(&state);
+                let muted = sources.iter().all(|source| source.muted);
+                for source in sources {
+                    if let Err(err) = audio::get().set_muted(source.id, !muted) {
+                        error!(?err, "Failed to mute microphone");
+                    }
+                }
+            });
+            microphone_box.append(&microphone_apps_box);
+            microphone_box.append(&mute_button);
+            popover_box.append(&microphone_box);
+
+            let camera_box = section_box("Using the camera");
+            let camera_apps_box = gtk::Box::builder()
+                .orientation(gtk::Orientation::Vertical)
+                .spacing(3)
+                .build();
+            camera_box.append(&camera_apps_box);
+            popover_box.append(&camera_box);
+
+            let popover = gtk::Popover::builder()
+                .position(gtk::PositionType::Top)
+                .child(&popover_box)
+                .build();
+            obj.set_child(Some(
+                &gtk::MenuButton::builder()
+                    .css_classes(["flat"])
+                    .child(&icons_box)
+                    .popover(&popover)
+                    .build(),
+            ));
+
+            self.microphone_icon.set(microphone_icon).unwrap();
+            self.camera_icon.set(camera_icon).unwrap();
+            self.microphone_box.set(microphone_box).unwrap();
+            self.microphone_apps_box.set(microphone_apps_box).unwrap();
+            self.mute_button.set(mute_button).unwrap();
+            self.camera_box.set(camera_box).unwrap();
+            self.camera_apps_box.set(camera_apps_box).unwrap();
+
+            let mut audio_state = audio::get().subscribe();
+            let weak_obj = obj.downgrade();
+            glib::spawn_future_local(async move {
+                loop {
+                    let current = audio_state.borrow_and_update().clone();
+                    let Some(obj) = weak_obj.upgrade() else {
+                        break;
+                    };
+                    obj.imp().sync_microphone(&current);
+                    drop(obj);
+
+                    if audio_state.changed().await.is_err() {
+                        break;
+                    }
+                }
+            });
+
+            let mut camera_users = camera::get().subscribe();
+            let weak_obj = obj.downgrade();
+            glib::spawn_future_local(async move {
+                loop {
+                    let current = camera_users.borrow_and_update().clone();
+                    let Some(obj) = weak_obj.upgrade() else {
+                        break;
+                    };
+                    obj.imp().sync_camera(&current);
+                    drop(obj);
+
+                    if camera_users.changed().await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    }
+
+    impl PrivacyIndicator {
+        fn sync_microphone(&self, state: &audio::State) {
+            let mut apps = state
+                .nodes(NodeKind::RecordingStream)
+                .filter(|stream| used_source(state, stream).is_some())
+                .map(|stream| {
+                    let name = stream
+                        .app_name
+                        .clone()
+                        .unwrap_or_else(|| stream.name.clone());
+                    (name, stream.app_icon_name.clone())
+                })
+                .collect::<Vec<_>>();
+            apps.sort();
+            apps.dedup();
+
+            let apps_box = self.microphone_apps_box.get().unwrap();
+            clear_box(apps_box);
+            for (name, icon_name) in &apps {
+                apps_box.append(&app_row(name, icon_name.as_deref()));
+            }
+
+            let sources = used_sources(state);
+            let muted = !sources.is_empty() && sources.iter().all(|source| source.muted);
+            let mute_button = self.mute_button.get().unwrap();
+            mute_button.set_label(if muted {
+                "Unmute microphone"
+            } else {
+                "Mute microphone"
+            });
+
+            let microphone_icon = self.microphone_icon.get().unwrap();
+            microphone_icon.set_icon_name(Some(if muted {
+                "microphone-disabled-symbolic"
+            } else {
+                "audio-input-microphone-symbolic"
+            }));
+            microphone_icon.set_visible(!apps.is_empty());
+            self.microphone_box
+                .get()
+                .unwrap()
+                .set_visible(!apps.is_empty());
+            self.update_visibility();
+        }
+
+        fn sync_camera(&self, users: &[camera::CameraUser]) {
+            let apps_box = self.camera_apps_box.get().unwrap();
+            clear_box(apps_box);
+            for user in users {
+                apps_box.append(&app_row(&user.name, None));
+            }
+
+            self.camera_icon
+                .get()
+                .unwrap()
+                .set_visible(!users.is_empty());
+            self.camera_box
+                .get()
+                .unwrap()
+                .set_visible(!users.is_empty());
+            self.update_visibility();
+        }
+
+        fn update_visibility(&self) {
+            let visible = self.microphone_icon.get().unwrap().is_visible()
+                || self.camera_icon.get().unwrap().is_visible();
+            self.obj().set_visible(visible);
+        }
+    }
+
+    /// Get the microphone a recording stream records from.
+    ///
+    /// Streams recording from a sink monitor (visualizers, screen recorders, etc.) don't use the
+    /// microphone.
+    fn used_source<'a>(state: &'a audio::State, stream: &audio::Node) -> Option<&'a audio::Node> {
+        stream
+            .target
+            .and_then(|target| state.nodes.get(&target))
+            .filter(|target| target.kind == NodeKind::Source)
+    }
+
+    /// Get the microphones recording streams record from.
+    fn used_sources(state: &audio::State) -> Vec<&audio::Node> {
+        let mut sources = state
+            .nodes(NodeKind::RecordingStream)
+            .filter_map(|stream| used_source(state, stream))
+            .collect::<Vec<_>>();
+        sources.sort_by_key(|source| source.id);
+        sources.dedup_by_key(|source| source.id);
+        sources
+    }
+
+    fn section_box(title: &str) -> gtk::Box {
+        let section_box = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(6)
+            .visible(false)
+            .build();
+        section_box.append(
+            &gtk::Label::builder()
+                .label(title)
+                .css_classes(["heading"])
+                .xalign(0.0)
+                .build(),
+        );
+        section_box
+    }
+
+    fn app_row(name: &str, icon_name: Option<&str>) -> gtk::Box {
+        let row = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(6)
+            .build();
+        row.append(&gtk::Image::from_icon_name(
+            icon_name.unwrap_or("application-x-executable-symbolic"),
+        ));
+        row.append(
+            &gtk::Label::builder()
+                .label(name)
+                .ellipsize(gtk::pango::EllipsizeMode::End)
+                .xalign(0.0)
+                .build(),
+        );
+        row
+    }
+
+    fn clear_box(container: &gtk::Box) {
+        while let Some(child) = container.first_child() {
+            container.remove(&child);
+        }
+    }
+
+    impl WidgetImpl for PrivacyIndicator {}
+    impl BinImpl for PrivacyIndicator {}
+}
+
+glib::wrapper! {
+    pub struct PrivacyIndicator(ObjectSubclass<imp::PrivacyIndicator>)
+        @extends adw::Bin, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget;
+}
+
+impl PrivacyIndicator {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+}
@@ -2,13 +2,16 @@ use glib::prelude::*;
 use gtk::glib;
 
 mod imp {
-    use std::cell::RefCell;
+    use std::cell::{OnceCell, RefCell};
 
     use adw::prelude::BinExt;
     use adw::subclass::bin::BinImpl;
+    use futures_util::StreamExt;
     use glib::subclass::object::{DerivedObjectProperties, ObjectImpl, ObjectImplExt};
-    use glib::subclass::types::{ObjectSubclass, ObjectSubclassExt};
+    use glib::subclass::types::{ObjectSubclass, ObjectSubclassExt, ObjectSubclassIsExt};
+    use gtk::prelude::WidgetExt;
     use gtk::subclass::widget::WidgetImpl;
+    use tokio::sync::broadcast;
 
     use super::*;
     use crate::daemons::upower;
@@ -16,8 +19,12 @@ mod imp {
     #[derive(glib::Properties, Default, Debug)]
     #[properties(wrapper_type = super::BatteryIcon)]
     pub struct BatteryIcon {
-        #[property(get, set, name = "battery-id", type = String)]
+        /// The UPower device to display. When empty, we use the display device.
+        #[property(get, construct_only, name = "battery-id", type = String)]
         battery_id: RefCell<String>,
+        icon: OnceCell<gtk::Image>,
+        // The future following the tracked device, if we found it.
+        tracker: RefCell<Option<glib::JoinHandle<()>>>,
     }
 
     #[glib::object_subclass]
@@ -34,40 +41,78 @@ mod imp {
 
             let upower_daemon = upower::get();
             let battery_icon = gtk::Image::from_icon_name("battery-missing-symbolic");
-            obj.set_child(Some(&battery_icon));
-
-            if let Some(device) = upower_daemon
-                .devices()
-                .iter()
-                // FIXME: Config
-                .find(|device| &*device.id() == "battery_BAT0")
-            {
-                // We found the needed device, now start listening to it
-                let device_icon_name_changes = device.proxy().receive_icon_name_changed();
-                let battery_icon_weak = ObjectExt::downgrade(&battery_icon);
-                glib::spawn_future_local(async move {
-                    use futures_util::StreamExt;
-                    let mut device_icon_name_changes = device_icon_name_changes.await;
-                    while let Some(changed) = device_icon_name_changes.next().await {
-                        let icon_name = match changed.get().await {
-                            Err(err) => {
-                                error!(?err, "Failed to get new battery icon name");
-                                continue;
-                            }
-                            Ok(icon_name) => icon_name,
-                        };
+            // A small plug on top of the battery when running on AC power.
+            let ac_adapter_icon = gtk::Image::builder()
+                .resource("/fht/desktop/Shell/icons/scalable/actions/ac-adapter-symbolic.svg")
+                .pixel_size(10)
+                .halign(gtk::Align::End)
+                .valign(gtk::Align::End)
+                .css_classes(["ac-adapter"])
+                .visible(false)
+                .build();
+            let overlay = gtk::Overlay::builder().child(&battery_icon).build();
+            overlay.add_overlay(&ac_adapter_icon);
+            obj.set_child(Some(&overlay));
+            self.icon.set(battery_icon).unwrap();
+
+            let weak_ac_adapter_icon = ac_adapter_icon.downgrade();
+            glib::spawn_future_local(async move {
+                let proxy = upower::get().proxy();
+                let mut on_battery_changes = proxy.receive_on_battery_changed().await;
+                loop {
+                    let on_battery = proxy.on_battery().await.unwrap_or(true);
+                    let Some(ac_adapter_icon) = weak_ac_adapter_icon.upgrade() else {
+                        break;
+                    };
+                    ac_adapter_icon.set_visible(!on_battery);
+
+                    if on_battery_changes.next().await.is_none() {
+                        break;
+                    }
+                }
+            });
+
+            let battery_id = self.battery_id.borrow().clone();
+            if battery_id.is_empty() {
+                // The display device always exists, even on desktops where it just reports that
+                // there's no battery present.
+                self.track_device(upower_daemon.display_device().clone());
+                return;
+            }
 
-                        let Some(battery_icon) = battery_icon_weak.upgrade() else {
-                            break; // label does not exist anymore?
-                        };
+            match upower_daemon.device(&battery_id) {
+                Some(device) => self.track_device(device),
+                None => obj.set_visible(false),
+            }
 
-                        // Custom our own resources since I have overriden the icons.
-                        let resource_path =
-                            format!("/fht/desktop/Shell/icons/scalable/actions/{icon_name}.svg");
-                        battery_icon.set_from_resource(Some(&resource_path));
+            // Follow the battery getting plugged in or out.
+            let mut events = upower_daemon.subscribe();
+            let weak_obj = obj.downgrade();
+            glib::spawn_future_local(async move {
+                loop {
+                    let event = match events.recv().await {
+                        Ok(event) => event,
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    };
+
+                    let Some(obj) = weak_obj.upgrade() else {
+                        break;
+                    };
+                    match event {
+                        upower::Event::DeviceAdded(device) if *device.id() == *battery_id => {
+                            obj.imp().track_device(device);
+                        }
+                        upower::Event::DeviceRemoved(id) if *id == *battery_id => {
+                            if let Some(tracker) = obj.imp().tracker.take() {
+                                tracker.abort();
+                            }
+                            obj.set_visible(false);
+                        }
+                        _ => (),
                     }
-                });
-            }
+                }
+            });
         }
 
         fn properties() -> &'static [glib::ParamSpec] {
@@ -83,6 +128,56 @@ mod imp {
         }
     }
 
+    impl BatteryIcon {
+        /// Start following the icon and presence of this device.
+        fn track_device(&self, device: upower::Device) {
+            let weak_obj = self.obj().downgrade();
+            let weak_battery_icon = self.icon.get().unwrap().downgrade();
+
+            let handle = glib::spawn_future_local(async move {
+                let proxy = device.proxy();
+                let mut changes = futures_util::stream::select(
+                    proxy.receive_icon_name_changed().await.map(|_| ()),
+                    proxy.receive_is_present_changed().await.map(|_| ()),
+                );
+
+                loop {
+                    let is_present = proxy.is_present().await.unwrap_or(false);
+                    let icon_name = match proxy.icon_name().await {
+                        Err(err) => {
+                            error!(?err, "Failed to get new battery icon name");
+                            String::new()
+                        }
+                        Ok(icon_name) => icon_name,
+                    };
+
+                    let (Some(obj), Some(battery_icon)) =
+                        (weak_obj.upgrade(), weak_battery_icon.upgrade())
+                    else {
+                        break; // icon does not exist anymore?
+                    };
+
+                    // No need to take space in the panel on machines without a battery.
+                    obj.set_visible(is_present);
+                    if !icon_name.is_empty() {
+                        // Custom our own resources since I have overriden the icons.
+                        let resource_path =
+                            format!("/fht/desktop/Shell/icons/scalable/actions/{icon_name}.svg");
+                        battery_icon.set_from_resource(Some(&resource_path));
+                    }
+
+                    if changes.next().await.is_none() {
+                        break;
+                    }
+                }
+            });
+
+            if let Some(previous) = self.tracker.replace(Some(handle)) {
+                previous.abort();
+            }
+        }
+    }
+
     impl WidgetImpl for BatteryIcon {}
     impl BinImpl for BatteryIcon {}
 }
@@ -94,7 +189,12 @@ glib::wrapper! {
 }
 
 impl BatteryIcon {
-    pub fn new() -> Self {
-        glib::Object::new()
+    /// Create a new battery icon for the given UPower device ID.
+    ///
+    /// When `battery_id` is `None`, the icon follows UPower's display device.
+    pub fn new(battery_id: Option<&str>) -> Self {
+        glib::Object::builder()
+            .property("battery-id", battery_id.unwrap_or_default())
+            .build()
     }
 }
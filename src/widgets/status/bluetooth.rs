@@ -0,0 +1,126 @@
+use glib::prelude::*;
+use gtk::glib;
+
+mod imp {
+    use std::cell::RefCell;
+
+    use adw::prelude::BinExt;
+    use adw::subclass::bin::BinImpl;
+    use futures_util::StreamExt;
+    use glib::subclass::object::{ObjectImpl, ObjectImplExt};
+    use glib::subclass::types::{ObjectSubclass, ObjectSubclassExt};
+    use gtk::prelude::WidgetExt;
+    use gtk::subclass::widget::WidgetImpl;
+    use tokio::sync::broadcast;
+
+    use super::*;
+    use crate::daemons::bluez;
+
+    #[derive(Default, Debug)]
+    pub struct BluetoothIcon {
+        // Tracks the connection state of all the devices.
+        devices_tracker: RefCell<Option<glib::JoinHandle<()>>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for BluetoothIcon {
+        const NAME: &'static str = "BluetoothIcon";
+        type Type = super::BluetoothIcon;
+        type ParentType = adw::Bin;
+    }
+
+    impl ObjectImpl for BluetoothIcon {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.set_child(Some(&gtk::Image::from_icon_name(
+                "bluetooth-active-symbolic",
+            )));
+            // Only shown while a device is connected.
+            obj.set_visible(false);
+            self.track_devices();
+
+            let mut events = bluez::get().subscribe();
+            let weak_obj = obj.downgrade();
+            glib::spawn_future_local(async move {
+                loop {
+                    match events.recv().await {
+                        Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => (),
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    };
+
+                    let Some(obj) = weak_obj.upgrade() else {
+                        break;
+                    };
+                    obj.imp().track_devices();
+                }
+            });
+        }
+
+        fn dispose(&self) {
+            if let Some(tracker) = self.devices_tracker.take() {
+                tracker.abort();
+            }
+        }
+    }
+
+    impl BluetoothIcon {
+        fn track_devices(&self) {
+            if let Some(tracker) = self.devices_tracker.take() {
+                tracker.abort();
+            }
+
+            let devices = bluez::get().devices();
+            let weak_obj = self.obj().downgrade();
+            let tracker = glib::spawn_future_local(async move {
+                let mut changes = futures_util::stream::select_all(
+                    futures_util::future::join_all(devices.iter().map(|device| async {
+                        device
+                            .proxy()
+                            .receive_connected_changed()
+                            .await
+                            .map(|_| ())
+                            .boxed_local()
+                    }))
+                    .await,
+                );
+
+                loop {
+                    let mut connected = vec![];
+                    for device in &devices {
+                        if device.proxy().connected().await.unwrap_or(false) {
+                            connected.push(device.display_name().await);
+                        }
+                    }
+
+                    let Some(obj) = weak_obj.upgrade() else {
+                        break;
+                    };
+                    obj.set_visible(!connected.is_empty());
+                    obj.set_tooltip_text(Some(&format!("Connected to {}", connected.join(", "))));
+                    drop(obj);
+
+                    if changes.next().await.is_none() {
+                        break;
+                    }
+                }
+            });
+            self.devices_tracker.replace(Some(tracker));
+        }
+    }
+
+    impl WidgetImpl for BluetoothIcon {}
+    impl BinImpl for BluetoothIcon {}
+}
+
+glib::wrapper! {
+    pub struct BluetoothIcon(ObjectSubclass<imp::BluetoothIcon>)
+        @extends adw::Bin, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget;
+}
+
+impl BluetoothIcon {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+}
@@ -0,0 +1,72 @@
+use glib::prelude::*;
+use gtk::glib;
+
+mod imp {
+    use adw::prelude::BinExt;
+    use adw::subclass::bin::BinImpl;
+    use glib::subclass::object::{ObjectImpl, ObjectImplExt};
+    use glib::subclass::types::{ObjectSubclass, ObjectSubclassExt};
+    use gtk::prelude::WidgetExt;
+    use gtk::subclass::widget::WidgetImpl;
+
+    use super::*;
+    use crate::daemons::logind;
+
+    #[derive(Default, Debug)]
+    pub struct CaffeineIcon;
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for CaffeineIcon {
+        const NAME: &'static str = "CaffeineIcon";
+        type Type = super::CaffeineIcon;
+        type ParentType = adw::Bin;
+    }
+
+    impl ObjectImpl for CaffeineIcon {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.set_child(Some(&gtk::Image::from_icon_name("caffeine-symbolic")));
+            obj.set_visible(false);
+
+            let mut caffeine = logind::get().subscribe_caffeine();
+            let weak_obj = obj.downgrade();
+            glib::spawn_future_local(async move {
+                loop {
+                    let state = *caffeine.borrow_and_update();
+                    let Some(obj) = weak_obj.upgrade() else {
+                        break;
+                    };
+
+                    obj.set_visible(state.active);
+                    let tooltip = match state.until {
+                        Some(until) => {
+                            format!("Keeping the system awake until {}", until.format("%H:%M"))
+                        }
+                        None => String::from("Keeping the system awake"),
+                    };
+                    obj.set_tooltip_text(Some(&tooltip));
+
+                    if caffeine.changed().await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    }
+
+    impl WidgetImpl for CaffeineIcon {}
+    impl BinImpl for CaffeineIcon {}
+}
+
+glib::wrapper! {
+    pub struct CaffeineIcon(ObjectSubclass<imp::CaffeineIcon>)
+        @extends adw::Bin, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget;
+}
+
+impl CaffeineIcon {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+}
@@ -4,7 +4,10 @@
 //! section. The style is very similar and on click it should open a popup with quick controls.
 
 pub mod battery;
+pub mod bluetooth;
+pub mod caffeine;
 pub mod network;
+pub mod volume;
 
 use gtk::glib;
 use gtk::prelude::*;
@@ -28,8 +31,12 @@ mod imp {
         fn constructed(&self) {
             self.parent_constructed();
             let obj = self.obj();
+            obj.append(&caffeine::CaffeineIcon::new());
             obj.append(&network::NetworkIcons::new());
-            obj.append(&battery::BatteryIcon::new());
+            obj.append(&bluetooth::BluetoothIcon::new());
+            obj.append(&volume::VolumeIcon::new());
+            let config = crate::config::get();
+            obj.append(&battery::BatteryIcon::new(config.battery.device.as_deref()));
         }
     }
 
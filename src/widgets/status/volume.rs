@@ -0,0 +1,89 @@
+use glib::prelude::*;
+use gtk::glib;
+
+mod imp {
+    use adw::prelude::BinExt;
+    use adw::subclass::bin::BinImpl;
+    use glib::subclass::object::{ObjectImpl, ObjectImplExt};
+    use glib::subclass::types::{ObjectSubclass, ObjectSubclassExt};
+    use gtk::prelude::WidgetExt;
+    use gtk::subclass::widget::WidgetImpl;
+
+    use super::*;
+    use crate::daemons::audio;
+
+    #[derive(Default, Debug)]
+    pub struct VolumeIcon;
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for VolumeIcon {
+        const NAME: &'static str = "VolumeIcon";
+        type Type = super::VolumeIcon;
+        type ParentType = adw::Bin;
+    }
+
+    impl ObjectImpl for VolumeIcon {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            let icon = gtk::Image::from_icon_name("audio-volume-muted-symbolic");
+            obj.set_child(Some(&icon));
+            obj.set_visible(false);
+
+            // Scrolling on the icon changes the volume, like most panels do.
+            let scroll_controller =
+                gtk::EventControllerScroll::new(gtk::EventControllerScrollFlags::VERTICAL);
+            scroll_controller.connect_scroll(|_, _, dy| {
+                let step = crate::config::get().audio.step;
+                let step = if dy < 0.0 { step } else { -step };
+                if let Err(err) = audio::get().step_volume(step) {
+                    error!(?err, "Failed to change volume");
+                }
+                glib::Propagation::Stop
+            });
+            obj.add_controller(scroll_controller);
+
+            let mut state = audio::get().subscribe();
+            let weak_obj = obj.downgrade();
+            let weak_icon = icon.downgrade();
+            glib::spawn_future_local(async move {
+                loop {
+                    let sink = state.borrow_and_update().default_sink().cloned();
+                    let (Some(obj), Some(icon)) = (weak_obj.upgrade(), weak_icon.upgrade()) else {
+                        break;
+                    };
+
+                    obj.set_visible(sink.is_some());
+                    if let Some(sink) = sink {
+                        icon.set_icon_name(Some(sink.volume_icon_name()));
+                        let tooltip = if sink.muted {
+                            format!("{}: muted", sink.description)
+                        } else {
+                            format!("{}: {:.0}%", sink.description, sink.volume * 100.0)
+                        };
+                        obj.set_tooltip_text(Some(&tooltip));
+                    }
+
+                    if state.changed().await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    }
+
+    impl WidgetImpl for VolumeIcon {}
+    impl BinImpl for VolumeIcon {}
+}
+
+glib::wrapper! {
+    pub struct VolumeIcon(ObjectSubclass<imp::VolumeIcon>)
+        @extends adw::Bin, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget;
+}
+
+impl VolumeIcon {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+}
@@ -0,0 +1,214 @@
+//! A single button of the taskbar, for an application or a single window.
+//!
+//! Clicking the button focuses its window, or minimizes it when it's already focused and the
+//! compositor supports it. With several windows, clicking again cycles through them. Pinned
+//! applications without windows get launched instead. Middle click opens a new window, and right
+//! click opens a menu to close the windows.
+
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::{gdk, gio, glib};
+
+use crate::daemons::compositor::{self, Window};
+
+/// The prefix of the action group holding the actions of the menu.
+const ACTIONS_PREFIX: &str = "taskbar";
+
+/// What a [`TaskbarButton`] shows.
+#[derive(Debug, Clone, Default)]
+pub struct Entry {
+    /// The application ID of the windows.
+    pub app_id: String,
+    pub app_info: Option<gio::DesktopAppInfo>,
+    pub pinned: bool,
+    /// The windows, in the order they were opened.
+    pub windows: Vec<Window>,
+}
+
+mod imp {
+    use std::cell::{OnceCell, RefCell};
+
+    use super::*;
+
+    #[derive(Default, Debug)]
+    pub struct TaskbarButton {
+        pub(super) entry: RefCell<Entry>,
+        pub(super) icon: gtk::Image,
+        pub(super) popover: OnceCell<gtk::PopoverMenu>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for TaskbarButton {
+        const NAME: &'static str = "TaskbarButton";
+        type Type = super::TaskbarButton;
+        type ParentType = gtk::Button;
+    }
+
+    impl ObjectImpl for TaskbarButton {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.set_css_classes(&["flat", "taskbar-button"]);
+            obj.set_valign(gtk::Align::Center);
+
+            self.icon.set_pixel_size(24);
+            obj.set_child(Some(&self.icon));
+
+            let popover = gtk::PopoverMenu::builder()
+                .position(gtk::PositionType::Top)
+                .has_arrow(false)
+                .build();
+            popover.set_parent(&*obj);
+            self.popover.set(popover).unwrap();
+
+            let actions = gio::SimpleActionGroup::new();
+            let new_window = gio::SimpleAction::new("new-window", None);
+            new_window.connect_activate(glib::clone!(@weak obj => move |_, _| obj.launch()));
+            actions.add_action(&new_window);
+            let close = gio::SimpleAction::new("close", None);
+            close.connect_activate(glib::clone!(@weak obj => move |_, _| obj.close_windows()));
+            actions.add_action(&close);
+            obj.insert_action_group(ACTIONS_PREFIX, Some(&actions));
+
+            let click_gesture = gtk::GestureClick::builder().button(0).build();
+            click_gesture.connect_released(glib::clone!(@weak obj => move |gesture, _, _, _| {
+                match gesture.current_button() {
+                    gdk::BUTTON_MIDDLE => obj.launch(),
+                    gdk::BUTTON_SECONDARY => obj.open_menu(),
+                    _ => return,
+                }
+                gesture.set_state(gtk::EventSequenceState::Claimed);
+            }));
+            obj.add_controller(click_gesture);
+        }
+
+        fn dispose(&self) {
+            if let Some(popover) = self.popover.get() {
+                popover.unparent();
+            }
+        }
+    }
+
+    impl WidgetImpl for TaskbarButton {}
+
+    impl ButtonImpl for TaskbarButton {
+        fn clicked(&self) {
+            let entry = self.entry.borrow();
+            if entry.windows.is_empty() {
+                drop(entry);
+                self.obj().launch();
+                return;
+            }
+
+            let first = &entry.windows[0];
+
+            let focused_idx = entry.windows.iter().position(|window| window.focused);
+            let result = match focused_idx {
+                // Minimize the only window, or cycle through the windows.
+                Some(_) if entry.windows.len() == 1 => {
+                    if !compositor::get().can_minimize() {
+                        return; // already focused, nothing else to do.
+                    }
+                    compositor::get().set_window_minimized(first.id, true)
+                }
+                Some(idx) => {
+                    let next = &entry.windows[(idx + 1) % entry.windows.len()];
+                    compositor::get().focus_window(next.id)
+                }
+                None => compositor::get().focus_window(first.id),
+            };
+            if let Err(err) = result {
+                warn!(?err, app_id = ?entry.app_id, "Failed to activate taskbar button");
+            }
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct TaskbarButton(ObjectSubclass<imp::TaskbarButton>)
+        @extends gtk::Button, gtk::Widget,
+        @implements gtk::Accessible, gtk::Actionable, gtk::Buildable, gtk::ConstraintTarget;
+}
+
+impl TaskbarButton {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+
+    /// Update what the button shows.
+    pub fn set_entry(&self, entry: Entry) {
+        let imp = self.imp();
+        match entry.app_info.as_ref().and_then(|app_info| app_info.icon()) {
+            Some(icon) => imp.icon.set_from_gicon(&icon),
+            None => imp.icon.set_icon_name(Some("application-x-executable")),
+        }
+
+        let tooltip = if entry.windows.is_empty() {
+            entry
+                .app_info
+                .as_ref()
+                .map(|app_info| app_info.display_name().to_string())
+                .unwrap_or_else(|| entry.app_id.clone())
+        } else {
+            let titles = entry
+                .windows
+                .iter()
+                .map(|window| window.title.as_str())
+                .collect::<Vec<_>>();
+            titles.join("\n")
+        };
+        self.set_tooltip_text(Some(&tooltip));
+
+        for (class, enabled) in [
+            ("pinned", entry.pinned),
+            ("running", !entry.windows.is_empty()),
+            ("focused", entry.windows.iter().any(|window| window.focused)),
+            ("urgent", entry.windows.iter().any(|window| window.urgent)),
+        ] {
+            if enabled {
+                self.add_css_class(class);
+            } else {
+                self.remove_css_class(class);
+            }
+        }
+
+        imp.entry.replace(entry);
+    }
+
+    fn launch(&self) {
+        if let Some(app_info) = &self.imp().entry.borrow().app_info {
+            crate::app_info::launch(app_info, &self.display());
+        }
+    }
+
+    fn close_windows(&self) {
+        for window in &self.imp().entry.borrow().windows {
+            if let Err(err) = compositor::get().close_window(window.id) {
+                warn!(?err, id = window.id, "Failed to close window");
+            }
+        }
+    }
+
+    fn open_menu(&self) {
+        let entry = self.imp().entry.borrow();
+        let menu = gio::Menu::new();
+        if entry.app_info.is_some() {
+            menu.append(
+                Some("New Window"),
+                Some(&format!("{ACTIONS_PREFIX}.new-window")),
+            );
+        }
+        match entry.windows.len() {
+            0 => (),
+            1 => menu.append(Some("Close"), Some(&format!("{ACTIONS_PREFIX}.close"))),
+            _ => menu.append(Some("Close All"), Some(&format!("{ACTIONS_PREFIX}.close"))),
+        }
+        if menu.n_items() == 0 {
+            return;
+        }
+
+        let popover = self.imp().popover.get().unwrap();
+        popover.set_menu_model(Some(&menu));
+        popover.popup();
+    }
+}
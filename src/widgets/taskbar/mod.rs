@@ -0,0 +1,297 @@
+//! Panel taskbar.
+//!
+//! Shows a button for each application with open windows, and for the pinned applications from
+//! the configuration. Windows of the same application share a button, unless disabled in the
+//! configuration.
+
+mod button;
+
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::{gio, glib};
+
+use self::button::{Entry, TaskbarButton};
+use crate::daemons::compositor::{self, State, Window};
+
+mod imp {
+    use std::cell::{OnceCell, RefCell};
+
+    use super::*;
+
+    #[derive(Default, Debug)]
+    pub struct Taskbar {
+        /// The pinned applications, resolved once instead of on each compositor update.
+        pinned: RefCell<Vec<(String, Entry)>>,
+        // GIO only sends changes while we hold a reference to the monitor.
+        monitor: OnceCell<gio::AppInfoMonitor>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for Taskbar {
+        const NAME: &'static str = "Taskbar";
+        type Type = super::Taskbar;
+        type ParentType = gtk::Box;
+    }
+
+    impl ObjectImpl for Taskbar {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.add_css_class("taskbar");
+            obj.set_visible(false);
+
+            self.pinned.replace(pinned_entries());
+            // Installing or removing a pinned application changes its entry.
+            let monitor = gio::AppInfoMonitor::get();
+            let weak_obj = obj.downgrade();
+            monitor.connect_changed(move |_| {
+                let Some(obj) = weak_obj.upgrade() else {
+                    return;
+                };
+                obj.imp().pinned.replace(pinned_entries());
+                obj.imp().sync(&compositor::get().state());
+            });
+            self.monitor.set(monitor).unwrap();
+
+            let mut state = compositor::get().subscribe();
+            let weak_obj = obj.downgrade();
+            glib::spawn_future_local(async move {
+                loop {
+                    let current = state.borrow_and_update().clone();
+                    let Some(obj) = weak_obj.upgrade() else {
+                        break;
+                    };
+                    obj.imp().sync(&current);
+                    drop(obj);
+
+                    if state.changed().await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    }
+
+    impl Taskbar {
+        /// Update the buttons, keeping the existing ones so that their menu stays open.
+        fn sync(&self, state: &State) {
+            let obj = self.obj();
+            let entries = entries(
+                &self.pinned.borrow(),
+                &state.windows,
+                crate::config::get().taskbar.group_by_app,
+                crate::app_info::lookup,
+            );
+
+            let mut child = obj.first_child();
+            while let Some(button) = child {
+                child = button.next_sibling();
+                if !entries.iter().any(|(key, _)| button.widget_name() == *key) {
+                    obj.remove(&button);
+                }
+            }
+
+            let mut previous: Option<gtk::Widget> = None;
+            for (key, entry) in entries {
+                let existing =
+                    std::iter::successors(obj.first_child(), |child| child.next_sibling())
+                        .find(|child| child.widget_name() == key)
+                        .and_downcast::<TaskbarButton>();
+                let button = existing.unwrap_or_else(|| {
+                    let button = TaskbarButton::new();
+                    button.set_widget_name(&key);
+                    obj.append(&button);
+                    button
+                });
+                button.set_entry(entry);
+                obj.reorder_child_after(&button, previous.as_ref());
+                previous = Some(button.upcast());
+            }
+
+            obj.set_visible(obj.first_child().is_some());
+        }
+    }
+
+    impl WidgetImpl for Taskbar {}
+    impl BoxImpl for Taskbar {}
+}
+
+glib::wrapper! {
+    pub struct Taskbar(ObjectSubclass<imp::Taskbar>)
+        @extends gtk::Box, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Orientable;
+}
+
+impl Taskbar {
+    pub fn new() -> Self {
+        glib::Object::builder()
+            .property("orientation", gtk::Orientation::Horizontal)
+            .property("spacing", 3)
+            .build()
+    }
+}
+
+/// Resolve the pinned applications from the configuration, keyed like [`entries`] keys them.
+fn pinned_entries() -> Vec<(String, Entry)> {
+    let mut pinned = vec![];
+    for desktop_id in &crate::config::get().taskbar.pinned {
+        let desktop_id = if desktop_id.ends_with(".desktop") {
+            desktop_id.clone()
+        } else {
+            format!("{desktop_id}.desktop")
+        };
+        let Some(app_info) = gio::DesktopAppInfo::new(&desktop_id) else {
+            warn!(?desktop_id, "Pinned application does not exist");
+            continue;
+        };
+        // Key it like the windows are, so that they get merged into the pinned entry.
+        let key = app_info.id().map(String::from).unwrap_or(desktop_id);
+        pinned.push((
+            key.clone(),
+            Entry {
+                app_id: key,
+                app_info: Some(app_info),
+                pinned: true,
+                windows: vec![],
+            },
+        ));
+    }
+    pinned
+}
+
+/// Get the buttons to show, with a unique key for each, pinned applications first.
+///
+/// `lookup` finds the desktop entry of a window application ID.
+fn entries(
+    pinned: &[(String, Entry)],
+    windows: &[Window],
+    group_by_app: bool,
+    lookup: impl Fn(&str) -> Option<gio::DesktopAppInfo>,
+) -> Vec<(String, Entry)> {
+    let mut apps = pinned.to_vec();
+    for window in windows {
+        let app_info = lookup(&window.app_id);
+        // Windows without a desktop entry are grouped by their application ID.
+        let key = app_info
+            .as_ref()
+            .and_then(|app_info| app_info.id())
+            .map(String::from)
+            .unwrap_or_else(|| window.app_id.clone());
+        match apps.iter_mut().find(|(app_key, _)| *app_key == key) {
+            Some((_, entry)) => entry.windows.push(window.clone()),
+            None => apps.push((
+                key,
+                Entry {
+                    app_id: window.app_id.clone(),
+                    app_info,
+                    pinned: false,
+                    windows: vec![window.clone()],
+                },
+            )),
+        }
+    }
+
+    if group_by_app {
+        return apps;
+    }
+    // Split the applications back into their windows, in place.
+    let mut entries = vec![];
+    for (key, entry) in apps {
+        if entry.windows.is_empty() {
+            entries.push((key, entry));
+            continue;
+        }
+
+        for window in &entry.windows {
+            entries.push((
+                format!("{key}-{}", window.id),
+                Entry {
+                    windows: vec![window.clone()],
+                    ..entry.clone()
+                },
+            ));
+        }
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(id: u64, app_id: &str) -> Window {
+        Window {
+            id,
+            title: String::new(),
+            app_id: app_id.to_string(),
+            output: None,
+            workspace_id: None,
+            focused: false,
+            minimized: false,
+            urgent: false,
+        }
+    }
+
+    fn pinned(key: &str) -> (String, Entry) {
+        let entry = Entry {
+            app_id: key.to_string(),
+            app_info: None,
+            pinned: true,
+            windows: vec![],
+        };
+        (key.to_string(), entry)
+    }
+
+    /// The keys of the entries, with the IDs of their windows.
+    fn summary(entries: &[(String, Entry)]) -> Vec<(&str, Vec<u64>)> {
+        entries
+            .iter()
+            .map(|(key, entry)| (key.as_str(), entry.windows.iter().map(|w| w.id).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn groups_windows_by_app() {
+        let windows = [window(1, "foot"), window(2, "firefox"), window(3, "foot")];
+        let entries = entries(&[], &windows, true, |_| None);
+        assert_eq!(
+            summary(&entries),
+            [("foot", vec![1, 3]), ("firefox", vec![2])]
+        );
+        assert!(entries.iter().all(|(_, entry)| !entry.pinned));
+    }
+
+    #[test]
+    fn merges_windows_into_pinned_apps() {
+        let pinned = [pinned("firefox"), pinned("nautilus")];
+        let windows = [window(1, "foot"), window(2, "firefox")];
+        let entries = entries(&pinned, &windows, true, |_| None);
+        assert_eq!(
+            summary(&entries),
+            [
+                ("firefox", vec![2]),
+                ("nautilus", vec![]),
+                ("foot", vec![1])
+            ]
+        );
+        assert!(entries[0].1.pinned && entries[1].1.pinned && !entries[2].1.pinned);
+    }
+
+    #[test]
+    fn splits_windows_when_not_grouping() {
+        let pinned = [pinned("firefox"), pinned("nautilus")];
+        let windows = [window(1, "foot"), window(2, "firefox"), window(3, "foot")];
+        let entries = entries(&pinned, &windows, false, |_| None);
+        assert_eq!(
+            summary(&entries),
+            [
+                ("firefox-2", vec![2]),
+                ("nautilus", vec![]),
+                ("foot-1", vec![1]),
+                ("foot-3", vec![3]),
+            ]
+        );
+        // Split windows keep the pinned state of their application.
+        assert!(entries[0].1.pinned && !entries[2].1.pinned);
+    }
+}
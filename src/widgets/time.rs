@@ -1,7 +1,22 @@
+//! Panel clock.
+//!
+//! Shows the date and time with the format from the configuration, ticking right on the minute,
+//! or on the second when showing the seconds. The tooltip shows the time in the other timezones
+//! from the configuration.
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{FixedOffset, Local, Timelike, Utc};
+use futures_util::StreamExt;
 use gtk::glib;
 use gtk::prelude::*;
 use gtk::subclass::prelude::*;
 
+use crate::config::HourFormat;
+use crate::daemons::timedate;
+
 mod imp {
     use std::cell::OnceCell;
 
@@ -21,10 +36,6 @@ mod imp {
         type ParentType = gtk::Box;
     }
 
-    fn current_time() -> String {
-        format!("{}", chrono::Local::now().format("%a %d, %H:%M"))
-    }
-
     impl ObjectImpl for TimeWidget {
         fn constructed(&self) {
             self.parent_constructed();
@@ -33,6 +44,7 @@ mod imp {
             label.set_halign(gtk::Align::Center);
             label.set_valign(gtk::Align::Center);
             label.set_xalign(-1.0);
+            label.add_css_class("numeric");
 
             let obj = self.obj();
             obj.set_orientation(gtk::Orientation::Vertical);
@@ -45,11 +57,40 @@ mod imp {
             let downgrade = label.downgrade();
             self.label.set(downgrade).unwrap();
 
-            // Now add the ticking.
-            glib::timeout_add_seconds_local(30, move || {
-                let time = current_time();
-                label.set_text(&time);
-                glib::ControlFlow::Continue
+            if !crate::config::get().clock.world_clocks.is_empty() {
+                obj.set_has_tooltip(true);
+                obj.connect_query_tooltip(|_, _, _, _, tooltip| {
+                    tooltip.set_markup(Some(&world_clocks_markup()));
+                    true
+                });
+            }
+
+            // Now add the ticking, right when the shown time changes.
+            let weak_label = label.downgrade();
+            glib::spawn_future_local(async move {
+                loop {
+                    glib::timeout_future(next_tick_delay()).await;
+                    let Some(label) = weak_label.upgrade() else {
+                        break;
+                    };
+                    label.set_text(&current_time());
+                }
+            });
+
+            // Chrono follows the timezone changes by itself, but only when we ask for the time.
+            let weak_label = label.downgrade();
+            glib::spawn_future_local(async move {
+                let mut changes = timedate::get().proxy().receive_timezone_changed().await;
+                while let Some(changed) = changes.next().await {
+                    let Ok(timezone) = changed.get().await else {
+                        continue;
+                    };
+                    info!(?timezone, "Timezone changed");
+                    let Some(label) = weak_label.upgrade() else {
+                        break;
+                    };
+                    label.set_text(&current_time());
+                }
             });
         }
     }
@@ -69,3 +110,117 @@ impl TimeWidget {
         glib::Object::new()
     }
 }
+
+/// Get the format of the clock, from the configuration or the defaults.
+fn clock_format() -> &'static str {
+    static FORMAT: OnceLock<String> = OnceLock::new();
+    FORMAT.get_or_init(|| {
+        let config = &crate::config::get().clock;
+        if let Some(format) = &config.format {
+            // Chrono panics when formatting with an invalid format.
+            if is_valid_format(format) {
+                return format.clone();
+            }
+            warn!(?format, "Invalid clock format, using the default one");
+        }
+        match (uses_24_hours(), config.show_seconds) {
+            (true, false) => "%a %d, %H:%M".to_string(),
+            (true, true) => "%a %d, %H:%M:%S".to_string(),
+            (false, false) => "%a %d, %I:%M %p".to_string(),
+            (false, true) => "%a %d, %I:%M:%S %p".to_string(),
+        }
+    })
+}
+
+/// Whether chrono understands all the specifiers of this format.
+fn is_valid_format(format: &str) -> bool {
+    StrftimeItems::new(format).all(|item| !matches!(item, Item::Error))
+}
+
+/// Get the format of times without the date, IE. for events.
+pub fn time_format() -> &'static str {
+    if uses_24_hours() {
+        "%H:%M"
+    } else {
+        "%I:%M %p"
+    }
+}
+
+/// Whether to show times with 24 hours, from the configuration or the locale.
+fn uses_24_hours() -> bool {
+    static USES_24_HOURS: OnceLock<bool> = OnceLock::new();
+    *USES_24_HOURS.get_or_init(|| match crate::config::get().clock.hour_format {
+        HourFormat::TwelveHour => false,
+        HourFormat::TwentyFourHour => true,
+        // Check how the locale writes 1 PM.
+        HourFormat::Locale => glib::DateTime::from_local(2000, 1, 1, 13, 0, 0.0)
+            .and_then(|date_time| date_time.format("%X"))
+            .map_or(true, |formatted| formatted.contains("13")),
+    })
+}
+
+/// Whether the clock shows the seconds, and should tick every second.
+fn shows_seconds() -> bool {
+    const SECONDS_SPECIFIERS: &[&str] = &["%S", "%T", "%X", "%r", "%s", "%+", "%c"];
+    let format = clock_format();
+    SECONDS_SPECIFIERS
+        .iter()
+        .any(|specifier| format.contains(specifier))
+}
+
+fn current_time() -> String {
+    Local::now().format(clock_format()).to_string()
+}
+
+/// Get how long until the shown time changes.
+fn next_tick_delay() -> Duration {
+    let now = Local::now();
+    let millis = u64::from(now.nanosecond().min(999_999_999) / 1_000_000);
+    let millis = if shows_seconds() {
+        1000 - millis
+    } else {
+        60_000 - (u64::from(now.second()) * 1000 + millis)
+    };
+    // Timers can fire a bit early, make sure we are past the change.
+    Duration::from_millis(millis + 1)
+}
+
+/// Get the time in the other timezones of the configuration, as Pango markup.
+fn world_clocks_markup() -> String {
+    let now = Utc::now();
+    let local_offset = Local::now().offset().local_minus_utc();
+
+    let mut lines = vec![];
+    for world_clock in &crate::config::get().clock.world_clocks {
+        let Some(offset) = glib::TimeZone::from_identifier(Some(&world_clock.timezone))
+            .and_then(|timezone| glib::DateTime::now(&timezone).ok())
+            .and_then(|date_time| {
+                let seconds = date_time.utc_offset().as_seconds();
+                FixedOffset::east_opt(seconds as i32)
+            })
+        else {
+            warn!(timezone = ?world_clock.timezone, "Unknown timezone");
+            continue;
+        };
+
+        let name = world_clock.name.clone().unwrap_or_else(|| {
+            let city = world_clock.timezone.rsplit('/').next().unwrap_or_default();
+            city.replace('_', " ")
+        });
+        let time = now.with_timezone(&offset).format(time_format());
+        let difference = offset.local_minus_utc() - local_offset;
+        let difference = match difference {
+            0 => String::new(),
+            difference => {
+                let hours = f64::from(difference) / 3600.0;
+                format!(" <span alpha=\"60%\">{hours:+}h</span>")
+            }
+        };
+        lines.push(format!(
+            "<b>{}</b>  {time}{difference}",
+            glib::markup_escape_text(&name)
+        ));
+    }
+
+    lines.join("\n")
+}
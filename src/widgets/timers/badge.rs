@@ -0,0 +1,106 @@
+//! Panel badge of the running timer, next to the clock.
+
+use std::pin::pin;
+use std::time::{Duration, SystemTime};
+
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+
+use crate::daemons::timers::{self, format_duration, State};
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, Debug)]
+    pub struct TimerBadge {
+        label: gtk::Label,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for TimerBadge {
+        const NAME: &'static str = "TimerBadge";
+        type Type = super::TimerBadge;
+        type ParentType = gtk::Box;
+    }
+
+    impl ObjectImpl for TimerBadge {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.add_css_class("timer-badge");
+            obj.set_valign(gtk::Align::Center);
+            obj.append(
+                &gtk::Image::builder()
+                    .icon_name("preferences-system-time-symbolic")
+                    .build(),
+            );
+            self.label.add_css_class("numeric");
+            obj.append(&self.label);
+
+            let mut changes = timers::get().subscribe();
+            let weak_obj = obj.downgrade();
+            glib::spawn_future_local(async move {
+                loop {
+                    let running = {
+                        let state = changes.borrow_and_update();
+                        let Some(obj) = weak_obj.upgrade() else {
+                            break;
+                        };
+                        obj.imp().sync(&state)
+                    };
+
+                    if !running {
+                        if changes.changed().await.is_err() {
+                            break;
+                        }
+                        continue;
+                    }
+
+                    let changed = pin!(changes.changed());
+                    let tick = glib::timeout_future(Duration::from_secs(1));
+                    if let futures_util::future::Either::Right((Err(_), _)) =
+                        futures_util::future::select(tick, changed).await
+                    {
+                        break;
+                    }
+                }
+            });
+        }
+    }
+
+    impl TimerBadge {
+        /// Show the timer ending first, returning whether there's one.
+        fn sync(&self, state: &State) -> bool {
+            let obj = self.obj();
+            let Some(timer) = state.next_timer() else {
+                obj.set_visible(false);
+                return false;
+            };
+
+            let remaining = format_duration(timer.remaining(SystemTime::now()));
+            self.label.set_text(&remaining);
+            obj.set_tooltip_text(Some(&timer.title()));
+            obj.set_visible(true);
+            true
+        }
+    }
+
+    impl WidgetImpl for TimerBadge {}
+    impl BoxImpl for TimerBadge {}
+}
+
+glib::wrapper! {
+    pub struct TimerBadge(ObjectSubclass<imp::TimerBadge>)
+        @extends gtk::Box, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Orientable;
+}
+
+impl TimerBadge {
+    pub fn new() -> Self {
+        glib::Object::builder()
+            .property("orientation", gtk::Orientation::Horizontal)
+            .property("spacing", 4)
+            .build()
+    }
+}
@@ -0,0 +1,457 @@
+//! Timers page of the clock popover.
+//!
+//! Lists the countdown timers, the stopwatch and the alarms of the timers daemon, with the
+//! controls to start new ones. The daemon does the actual timing, we only show it.
+
+mod badge;
+
+use std::pin::pin;
+use std::time::{Duration, SystemTime};
+
+use chrono::NaiveTime;
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+
+pub use self::badge::TimerBadge;
+use crate::daemons::timers::{self, format_duration, Alarm, Repeat, State, Timer, TimerState};
+
+/// The repeat options of new alarms, in the order of the drop down.
+const REPEATS: [(Repeat, &str); 3] = [
+    (Repeat::Once, "Once"),
+    (Repeat::Daily, "Daily"),
+    (Repeat::Weekdays, "Weekdays"),
+];
+
+mod imp {
+    use std::cell::RefCell;
+
+    use super::*;
+
+    #[derive(Default, Debug)]
+    pub struct TimersWidget {
+        timers: gtk::Box,
+        stopwatch: gtk::Label,
+        stopwatch_start: gtk::Button,
+        stopwatch_lap: gtk::Button,
+        laps: gtk::Box,
+        alarms: gtk::Box,
+        /// The labels showing the remaining time of each timer, to update them every second.
+        remaining_labels: RefCell<Vec<(u64, gtk::Label)>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for TimersWidget {
+        const NAME: &'static str = "TimersWidget";
+        type Type = super::TimersWidget;
+        type ParentType = gtk::Box;
+    }
+
+    impl ObjectImpl for TimersWidget {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.add_css_class("timers");
+
+            obj.append(&heading("Timers"));
+            self.timers.set_orientation(gtk::Orientation::Vertical);
+            self.timers.set_spacing(6);
+            obj.append(&self.timers);
+            obj.append(&new_timer_controls());
+
+            obj.append(&heading("Stopwatch"));
+            obj.append(&self.stopwatch_controls());
+            self.laps.set_orientation(gtk::Orientation::Vertical);
+            obj.append(&self.laps);
+
+            obj.append(&heading("Alarms"));
+            self.alarms.set_orientation(gtk::Orientation::Vertical);
+            self.alarms.set_spacing(6);
+            obj.append(&self.alarms);
+            obj.append(&new_alarm_controls());
+
+            let mut changes = timers::get().subscribe();
+            let weak_obj = obj.downgrade();
+            glib::spawn_future_local(async move {
+                loop {
+                    let running = {
+                        let state = changes.borrow_and_update();
+                        let Some(obj) = weak_obj.upgrade() else {
+                            break;
+                        };
+                        obj.imp().sync(&state);
+                        state.next_timer().is_some() || state.stopwatch.started_at.is_some()
+                    };
+
+                    if !running {
+                        if changes.changed().await.is_err() {
+                            break;
+                        }
+                        continue;
+                    }
+
+                    // Count down every second until something changes.
+                    let mut changed = pin!(changes.changed());
+                    loop {
+                        let tick = glib::timeout_future(Duration::from_secs(1));
+                        match futures_util::future::select(tick, changed.as_mut()).await {
+                            futures_util::future::Either::Left(_) => {
+                                let Some(obj) = weak_obj.upgrade() else {
+                                    return;
+                                };
+                                if obj.is_mapped() {
+                                    obj.imp().tick(&timers::get().subscribe().borrow());
+                                }
+                            }
+                            futures_util::future::Either::Right((Ok(()), _)) => break,
+                            futures_util::future::Either::Right((Err(_), _)) => return,
+                        }
+                    }
+                }
+            });
+        }
+    }
+
+    impl TimersWidget {
+        fn stopwatch_controls(&self) -> gtk::Box {
+            self.stopwatch.set_css_classes(&["title-2", "numeric"]);
+            self.stopwatch.set_hexpand(true);
+            self.stopwatch.set_xalign(0.0);
+
+            self.stopwatch_start.add_css_class("circular");
+            self.stopwatch_start.connect_clicked(|_| {
+                let daemon = timers::get();
+                if daemon.subscribe().borrow().stopwatch.started_at.is_some() {
+                    daemon.pause_stopwatch();
+                } else {
+                    daemon.start_stopwatch();
+                }
+            });
+            self.stopwatch_lap.set_icon_name("flag-symbolic");
+            self.stopwatch_lap.set_tooltip_text(Some("Lap"));
+            self.stopwatch_lap.add_css_class("circular");
+            self.stopwatch_lap
+                .connect_clicked(|_| timers::get().lap_stopwatch());
+            let reset = icon_button("edit-undo-symbolic", "Reset");
+            reset.add_css_class("circular");
+            reset.connect_clicked(|_| timers::get().reset_stopwatch());
+
+            let controls = gtk::Box::builder()
+                .orientation(gtk::Orientation::Horizontal)
+                .spacing(6)
+                .css_classes(["stopwatch"])
+                .build();
+            controls.append(&self.stopwatch);
+            controls.append(&self.stopwatch_start);
+            controls.append(&self.stopwatch_lap);
+            controls.append(&reset);
+            controls
+        }
+
+        /// Show the timers, alarms and stopwatch again.
+        fn sync(&self, state: &State) {
+            for container in [&self.timers, &self.laps, &self.alarms] {
+                while let Some(child) = container.first_child() {
+                    container.remove(&child);
+                }
+            }
+
+            let mut remaining_labels = vec![];
+            for timer in &state.timers {
+                let (row, remaining_label) = timer_row(timer);
+                self.timers.append(&row);
+                remaining_labels.push((timer.id, remaining_label));
+            }
+            self.remaining_labels.replace(remaining_labels);
+
+            let stopwatch = &state.stopwatch;
+            let (icon_name, tooltip) = match stopwatch.started_at {
+                Some(_) => ("media-playback-pause-symbolic", "Pause"),
+                None => ("media-playback-start-symbolic", "Start"),
+            };
+            self.stopwatch_start.set_icon_name(icon_name);
+            self.stopwatch_start.set_tooltip_text(Some(tooltip));
+            self.stopwatch_lap
+                .set_sensitive(stopwatch.started_at.is_some());
+            // The latest lap comes first.
+            for (idx, lap) in stopwatch.laps.iter().enumerate().rev() {
+                self.laps.append(
+                    &gtk::Label::builder()
+                        .label(format!("Lap {}  {}", idx + 1, format_duration(*lap)))
+                        .xalign(0.0)
+                        .css_classes(["numeric", "dim-label"])
+                        .build(),
+                );
+            }
+
+            for alarm in &state.alarms {
+                self.alarms.append(&alarm_row(alarm));
+            }
+
+            self.tick(state);
+        }
+
+        /// Update the times that change every second.
+        fn tick(&self, state: &State) {
+            let now = SystemTime::now();
+            for (id, label) in self.remaining_labels.borrow().iter() {
+                if let Some(timer) = state.timers.iter().find(|timer| timer.id == *id) {
+                    label.set_text(&remaining_text(timer, now));
+                }
+            }
+            self.stopwatch
+                .set_text(&format_duration(state.stopwatch.elapsed(now)));
+        }
+    }
+
+    impl WidgetImpl for TimersWidget {}
+    impl BoxImpl for TimersWidget {}
+}
+
+glib::wrapper! {
+    pub struct TimersWidget(ObjectSubclass<imp::TimersWidget>)
+        @extends gtk::Box, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Orientable;
+}
+
+impl TimersWidget {
+    pub fn new() -> Self {
+        glib::Object::builder()
+            .property("orientation", gtk::Orientation::Vertical)
+            .property("spacing", 6)
+            .build()
+    }
+}
+
+fn heading(title: &str) -> gtk::Label {
+    gtk::Label::builder()
+        .label(title)
+        .xalign(0.0)
+        .css_classes(["heading"])
+        .build()
+}
+
+fn icon_button(icon_name: &str, tooltip: &str) -> gtk::Button {
+    gtk::Button::builder()
+        .icon_name(icon_name)
+        .tooltip_text(tooltip)
+        .valign(gtk::Align::Center)
+        .css_classes(["flat"])
+        .build()
+}
+
+fn remaining_text(timer: &Timer, now: SystemTime) -> String {
+    match timer.state {
+        TimerState::Finished => "Done".to_string(),
+        _ => format_duration(timer.remaining(now)),
+    }
+}
+
+fn timer_row(timer: &Timer) -> (gtk::Box, gtk::Label) {
+    let row = gtk::Box::builder()
+        .orientation(gtk::Orientation::Horizontal)
+        .spacing(6)
+        .css_classes(["timer"])
+        .build();
+    row.append(
+        &gtk::Label::builder()
+            .label(timer.title())
+            .xalign(0.0)
+            .hexpand(true)
+            .ellipsize(gtk::pango::EllipsizeMode::End)
+            .max_width_chars(20)
+            .build(),
+    );
+    let remaining = gtk::Label::builder()
+        .label(remaining_text(timer, SystemTime::now()))
+        .css_classes(["numeric"])
+        .build();
+    row.append(&remaining);
+
+    let id = timer.id;
+    let toggle = match timer.state {
+        TimerState::Running { .. } => {
+            let button = icon_button("media-playback-pause-symbolic", "Pause");
+            button.connect_clicked(move |_| timers::get().pause_timer(id));
+            button
+        }
+        TimerState::Paused { .. } => {
+            let button = icon_button("media-playback-start-symbolic", "Resume");
+            button.connect_clicked(move |_| timers::get().resume_timer(id));
+            button
+        }
+        TimerState::Finished => {
+            let button = icon_button("view-refresh-symbolic", "Restart");
+            button.connect_clicked(move |_| timers::get().restart_timer(id));
+            button
+        }
+    };
+    row.append(&toggle);
+    let remove = icon_button("window-close-symbolic", "Remove");
+    remove.connect_clicked(move |_| timers::get().remove_timer(id));
+    row.append(&remove);
+
+    if timer.state == TimerState::Finished {
+        row.add_css_class("finished");
+    }
+    (row, remaining)
+}
+
+fn alarm_row(alarm: &Alarm) -> gtk::Box {
+    let row = gtk::Box::builder()
+        .orientation(gtk::Orientation::Horizontal)
+        .spacing(12)
+        .css_classes(["alarm"])
+        .build();
+    row.append(
+        &gtk::Label::builder()
+            .label(alarm.time.format(super::time::time_format()).to_string())
+            .css_classes(["title-4", "numeric"])
+            .build(),
+    );
+
+    let labels = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .valign(gtk::Align::Center)
+        .hexpand(true)
+        .build();
+    labels.append(
+        &gtk::Label::builder()
+            .label(alarm.title())
+            .xalign(0.0)
+            .ellipsize(gtk::pango::EllipsizeMode::End)
+            .max_width_chars(16)
+            .build(),
+    );
+    let repeat = REPEATS
+        .iter()
+        .find(|(repeat, _)| *repeat == alarm.repeat)
+        .map_or("", |(_, name)| name);
+    labels.append(
+        &gtk::Label::builder()
+            .label(repeat)
+            .xalign(0.0)
+            .css_classes(["dim-label", "caption"])
+            .build(),
+    );
+    row.append(&labels);
+
+    let id = alarm.id;
+    let switch = gtk::Switch::builder()
+        .active(alarm.enabled())
+        .valign(gtk::Align::Center)
+        .build();
+    switch.connect_state_set(move |_, enabled| {
+        timers::get().set_alarm_enabled(id, enabled);
+        glib::Propagation::Proceed
+    });
+    row.append(&switch);
+    let remove = icon_button("window-close-symbolic", "Remove");
+    remove.connect_clicked(move |_| timers::get().remove_alarm(id));
+    row.append(&remove);
+    row
+}
+
+fn label_entry() -> gtk::Entry {
+    gtk::Entry::builder()
+        .placeholder_text("Label")
+        .hexpand(true)
+        .width_chars(8)
+        .build()
+}
+
+fn entry_label(entry: &gtk::Entry) -> Option<String> {
+    let label = entry.text().trim().to_string();
+    entry.set_text("");
+    (!label.is_empty()).then_some(label)
+}
+
+fn new_timer_controls() -> gtk::Box {
+    let controls = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(6)
+        .build();
+
+    let presets = gtk::Box::builder()
+        .orientation(gtk::Orientation::Horizontal)
+        .spacing(6)
+        .homogeneous(true)
+        .build();
+    for minutes in crate::config::get().timers.presets.iter().copied() {
+        let button = gtk::Button::builder()
+            .label(format!("{minutes} min"))
+            .css_classes(["pill"])
+            .build();
+        button.connect_clicked(move |_| {
+            let duration = Duration::from_secs(u64::from(minutes) * 60);
+            timers::get().add_timer(None, duration);
+        });
+        presets.append(&button);
+    }
+    controls.append(&presets);
+
+    let custom = gtk::Box::builder()
+        .orientation(gtk::Orientation::Horizontal)
+        .spacing(6)
+        .build();
+    let minutes = gtk::SpinButton::with_range(1.0, 24.0 * 60.0, 1.0);
+    minutes.set_value(15.0);
+    minutes.set_tooltip_text(Some("Minutes"));
+    custom.append(&minutes);
+    let label = label_entry();
+    custom.append(&label);
+    let start = icon_button("media-playback-start-symbolic", "Start Timer");
+    start.connect_clicked(move |_| {
+        let duration = Duration::from_secs(minutes.value_as_int() as u64 * 60);
+        timers::get().add_timer(entry_label(&label), duration);
+    });
+    custom.append(&start);
+    controls.append(&custom);
+    controls
+}
+
+fn new_alarm_controls() -> gtk::Box {
+    let controls = gtk::Box::builder()
+        .orientation(gtk::Orientation::Horizontal)
+        .spacing(6)
+        .build();
+
+    let hour = time_spin_button(23.0, "Hour");
+    hour.set_value(7.0);
+    controls.append(&hour);
+    let minute = time_spin_button(59.0, "Minute");
+    controls.append(&minute);
+    let repeat = gtk::DropDown::from_strings(&REPEATS.map(|(_, name)| name));
+    controls.append(&repeat);
+    let label = label_entry();
+    controls.append(&label);
+
+    let add = icon_button("list-add-symbolic", "Add Alarm");
+    add.connect_clicked(move |_| {
+        let Some(time) =
+            NaiveTime::from_hms_opt(hour.value_as_int() as u32, minute.value_as_int() as u32, 0)
+        else {
+            return;
+        };
+        let repeat = REPEATS
+            .get(repeat.selected() as usize)
+            .map_or(Repeat::Once, |(repeat, _)| *repeat);
+        timers::get().add_alarm(entry_label(&label), time, repeat);
+    });
+    controls.append(&add);
+    controls
+}
+
+/// A spin button for the hours or minutes of an alarm, always with two digits.
+fn time_spin_button(max: f64, tooltip: &str) -> gtk::SpinButton {
+    let spin_button = gtk::SpinButton::with_range(0.0, max, 1.0);
+    spin_button.set_wrap(true);
+    spin_button.set_orientation(gtk::Orientation::Vertical);
+    spin_button.set_tooltip_text(Some(tooltip));
+    spin_button.add_css_class("numeric");
+    spin_button.connect_output(|spin_button| {
+        spin_button.set_text(&format!("{:02}", spin_button.value_as_int()));
+        glib::Propagation::Stop
+    });
+    spin_button
+}
@@ -0,0 +1,376 @@
+//! A single item of the system tray.
+//!
+//! Left click activates the item, middle click sends the secondary activation, and right click
+//! opens its menu. The menu is exported by the application through `com.canonical.dbusmenu`, that
+//! we convert to a [`gio::Menu`] each time it gets opened.
+
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::{gdk, gio, glib};
+
+use crate::daemons::status_notifier::dbusmenu::{DBusMenuProxy, MenuItem, Toggle};
+use crate::daemons::status_notifier::{self, Icon, Item, Status};
+
+/// The prefix of the action group holding the actions of the menu entries.
+const ACTIONS_PREFIX: &str = "dbusmenu";
+
+mod imp {
+    use std::cell::{OnceCell, RefCell};
+
+    use adw::prelude::BinExt;
+    use adw::subclass::bin::BinImpl;
+    use futures_util::StreamExt;
+    use glib::subclass::object::{DerivedObjectProperties, ObjectImpl, ObjectImplExt};
+    use glib::subclass::types::{ObjectSubclass, ObjectSubclassExt, ObjectSubclassIsExt};
+    use gtk::subclass::widget::WidgetImpl;
+
+    use super::*;
+
+    #[derive(glib::Properties, Default, Debug)]
+    #[properties(wrapper_type = super::TrayItem)]
+    pub struct TrayItem {
+        /// The ID of the status notifier item to display.
+        #[property(get, construct_only, name = "item-id", type = String)]
+        item_id: RefCell<String>,
+        pub(super) item: OnceCell<Item>,
+        icon: OnceCell<gtk::Image>,
+        pub(super) popover: OnceCell<gtk::PopoverMenu>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for TrayItem {
+        const NAME: &'static str = "TrayItem";
+        type Type = super::TrayItem;
+        type ParentType = adw::Bin;
+    }
+
+    impl ObjectImpl for TrayItem {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.add_css_class("tray-item");
+
+            let icon = gtk::Image::builder().pixel_size(16).build();
+            obj.set_child(Some(&icon));
+            self.icon.set(icon).unwrap();
+
+            let popover = gtk::PopoverMenu::builder()
+                .position(gtk::PositionType::Top)
+                .has_arrow(false)
+                .build();
+            popover.set_parent(&*obj);
+            self.popover.set(popover).unwrap();
+
+            let Some(item) = status_notifier::get().item(&self.item_id.borrow()) else {
+                warn!(item_id = ?self.item_id.borrow(), "Status notifier item does not exist");
+                obj.set_visible(false);
+                return;
+            };
+            self.item.set(item.clone()).unwrap();
+
+            let click_gesture = gtk::GestureClick::builder().button(0).build();
+            click_gesture.connect_released(glib::clone!(@weak obj => move |gesture, _, _, _| {
+                gesture.set_state(gtk::EventSequenceState::Claimed);
+                match gesture.current_button() {
+                    gdk::BUTTON_PRIMARY => obj.activate_item(),
+                    gdk::BUTTON_MIDDLE => obj.secondary_activate_item(),
+                    gdk::BUTTON_SECONDARY => obj.open_menu(),
+                    _ => (),
+                }
+            }));
+            obj.add_controller(click_gesture);
+
+            let scroll_controller =
+                gtk::EventControllerScroll::new(gtk::EventControllerScrollFlags::BOTH_AXES);
+            let scroll_item = item.clone();
+            scroll_controller.connect_scroll(move |_, dx, dy| {
+                let (delta, orientation) = if dy != 0.0 {
+                    (dy, "vertical")
+                } else {
+                    (dx, "horizontal")
+                };
+                // Like KDE, send 120 per wheel notch, positive going up.
+                let delta = (-delta * 120.0) as i32;
+                let item = scroll_item.clone();
+                glib::spawn_future_local(async move {
+                    if let Err(err) = item.proxy().scroll(delta, orientation).await {
+                        debug!(?err, "Failed to scroll status notifier item");
+                    }
+                });
+                glib::Propagation::Stop
+            });
+            obj.add_controller(scroll_controller);
+
+            let weak_obj = obj.downgrade();
+            glib::spawn_future_local(async move {
+                let proxy = item.proxy();
+                let streams = futures_util::join!(
+                    proxy.receive_new_icon(),
+                    proxy.receive_new_attention_icon(),
+                    proxy.receive_new_status(),
+                    proxy.receive_new_title(),
+                    proxy.receive_new_tool_tip(),
+                );
+                let mut changes = match streams {
+                    (Ok(icon), Ok(attention_icon), Ok(status), Ok(title), Ok(tool_tip)) => {
+                        futures_util::stream::select_all([
+                            icon.map(|_| ()).boxed_local(),
+                            attention_icon.map(|_| ()).boxed_local(),
+                            status.map(|_| ()).boxed_local(),
+                            title.map(|_| ()).boxed_local(),
+                            tool_tip.map(|_| ()).boxed_local(),
+                        ])
+                    }
+                    _ => {
+                        warn!(item_id = ?item.id(), "Failed to follow status notifier item");
+                        futures_util::stream::select_all([])
+                    }
+                };
+
+                loop {
+                    let status = item.status().await;
+                    let item_icon = item.icon().await;
+                    let tooltip = item.tooltip().await;
+
+                    let Some(obj) = weak_obj.upgrade() else {
+                        break;
+                    };
+                    let imp = obj.imp();
+                    obj.set_visible(status != Status::Passive);
+                    if status == Status::NeedsAttention {
+                        obj.add_css_class("needs-attention");
+                    } else {
+                        obj.remove_css_class("needs-attention");
+                    }
+                    set_icon(imp.icon.get().unwrap(), item_icon);
+                    set_tooltip(&obj, tooltip);
+                    drop(obj);
+
+                    if changes.next().await.is_none() {
+                        break;
+                    }
+                }
+            });
+        }
+
+        fn properties() -> &'static [glib::ParamSpec] {
+            Self::derived_properties()
+        }
+
+        fn set_property(&self, id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+            self.derived_set_property(id, value, pspec);
+        }
+
+        fn property(&self, id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            self.derived_property(id, pspec)
+        }
+
+        fn dispose(&self) {
+            if let Some(popover) = self.popover.get() {
+                popover.unparent();
+            }
+        }
+    }
+
+    impl WidgetImpl for TrayItem {}
+
+    impl BinImpl for TrayItem {}
+}
+
+glib::wrapper! {
+    pub struct TrayItem(ObjectSubclass<imp::TrayItem>)
+        @extends adw::Bin, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget;
+}
+
+impl TrayItem {
+    pub fn new(item_id: &str) -> Self {
+        glib::Object::builder().property("item-id", item_id).build()
+    }
+
+    fn item(&self) -> Option<Item> {
+        self.imp().item.get().cloned()
+    }
+
+    fn activate_item(&self) {
+        let Some(item) = self.item() else { return };
+        let weak_self = self.downgrade();
+        glib::spawn_future_local(async move {
+            // Items that are only a menu have nothing to activate.
+            if item.proxy().item_is_menu().await.unwrap_or(false) {
+                if let Some(this) = weak_self.upgrade() {
+                    this.open_menu();
+                }
+                return;
+            }
+
+            // We don't know where we are on screen, Wayland does not tell clients that.
+            if let Err(err) = item.proxy().activate(0, 0).await {
+                debug!(
+                    ?err,
+                    "Failed to activate status notifier item, opening menu"
+                );
+                if let Some(this) = weak_self.upgrade() {
+                    this.open_menu();
+                }
+            }
+        });
+    }
+
+    fn secondary_activate_item(&self) {
+        let Some(item) = self.item() else { return };
+        glib::spawn_future_local(async move {
+            if let Err(err) = item.proxy().secondary_activate(0, 0).await {
+                debug!(?err, "Failed to secondary activate status notifier item");
+            }
+        });
+    }
+
+    fn open_menu(&self) {
+        let Some(item) = self.item() else { return };
+        let weak_self = self.downgrade();
+        glib::spawn_future_local(async move {
+            let Some(menu) = item.menu().await else {
+                // Let the application show its own menu then.
+                if let Err(err) = item.proxy().context_menu(0, 0).await {
+                    debug!(?err, "Failed to open status notifier item context menu");
+                }
+                return;
+            };
+
+            // Applications may update the menu before it gets shown.
+            let _ = menu.about_to_show(0).await;
+            let root = match menu.get_layout(0, -1, &[]).await {
+                Ok((_, layout)) => MenuItem::from_layout(layout),
+                Err(err) => {
+                    warn!(?err, "Failed to get status notifier item menu");
+                    return;
+                }
+            };
+
+            let Some(this) = weak_self.upgrade() else {
+                return;
+            };
+            let actions = gio::SimpleActionGroup::new();
+            let model = build_menu(&root, &menu, &actions);
+            this.insert_action_group(ACTIONS_PREFIX, Some(&actions));
+            let popover = this.imp().popover.get().unwrap();
+            popover.set_menu_model(Some(&model));
+            popover.popup();
+        });
+    }
+}
+
+/// Build a [`gio::Menu`] from the children of a `com.canonical.dbusmenu` entry, adding an action
+/// to `actions` for each entry.
+fn build_menu(
+    parent: &MenuItem,
+    menu: &DBusMenuProxy<'static>,
+    actions: &gio::SimpleActionGroup,
+) -> gio::Menu {
+    // GIO menus have sections instead of separators.
+    let model = gio::Menu::new();
+    let mut section = gio::Menu::new();
+    for child in parent.children.iter().filter(|child| child.visible) {
+        if child.is_separator {
+            if section.n_items() > 0 {
+                model.append_section(None, &section);
+                section = gio::Menu::new();
+            }
+            continue;
+        }
+
+        if !child.children.is_empty() {
+            let submenu = build_menu(child, menu, actions);
+            section.append_submenu(Some(&child.label), &submenu);
+            continue;
+        }
+
+        let action_name = format!("item-{}", child.id);
+        let action = match child.toggle {
+            // GIO has no radio items without a shared action, checkmarks are close enough.
+            Some(Toggle::Checkmark(active) | Toggle::Radio(active)) => {
+                gio::SimpleAction::new_stateful(&action_name, None, &active.to_variant())
+            }
+            None => gio::SimpleAction::new(&action_name, None),
+        };
+        action.set_enabled(child.enabled);
+        let id = child.id;
+        let menu = menu.clone();
+        action.connect_activate(move |_, _| {
+            let menu = menu.clone();
+            glib::spawn_future_local(async move {
+                let data = zbus::zvariant::Value::I32(0);
+                if let Err(err) = menu.event(id, "clicked", &data, 0).await {
+                    warn!(?err, "Failed to activate status notifier menu entry");
+                }
+            });
+        });
+        actions.add_action(&action);
+
+        let menu_item = gio::MenuItem::new(
+            Some(&child.label),
+            Some(&format!("{ACTIONS_PREFIX}.{action_name}")),
+        );
+        section.append_item(&menu_item);
+    }
+
+    if section.n_items() > 0 {
+        model.append_section(None, &section);
+    }
+    model
+}
+
+fn set_icon(image: &gtk::Image, icon: Option<Icon>) {
+    match icon {
+        Some(Icon::Name { name, theme_path }) => {
+            if name.starts_with('/') {
+                image.set_from_file(Some(&name));
+                return;
+            }
+
+            if let Some(theme_path) = theme_path {
+                let icon_theme = gtk::IconTheme::for_display(&image.display());
+                if !icon_theme
+                    .search_path()
+                    .iter()
+                    .any(|path| path.to_str() == Some(&theme_path))
+                {
+                    icon_theme.add_search_path(&theme_path);
+                }
+            }
+            image.set_icon_name(Some(&name));
+        }
+        Some(Icon::Pixmap(pixmap)) => {
+            let texture = gdk::MemoryTexture::new(
+                pixmap.width,
+                pixmap.height,
+                gdk::MemoryFormat::R8g8b8a8,
+                &glib::Bytes::from_owned(pixmap.data),
+                (pixmap.width * 4) as usize,
+            );
+            image.set_paintable(Some(&texture));
+        }
+        None => image.set_icon_name(Some("image-missing-symbolic")),
+    }
+}
+
+fn set_tooltip(obj: &TrayItem, tooltip: Option<(String, String)>) {
+    let Some((title, description)) = tooltip else {
+        obj.set_tooltip_text(None);
+        return;
+    };
+
+    let mut markup = format!("<b>{}</b>", glib::markup_escape_text(&title));
+    if !description.is_empty() {
+        // Items are supposed to send markup, but plenty send plain text with stray `&` or `<`.
+        let description = if gtk::pango::parse_markup(&description, '\0').is_ok() {
+            description
+        } else {
+            glib::markup_escape_text(&description).to_string()
+        };
+        markup.push('\n');
+        markup.push_str(&description);
+    }
+    obj.set_tooltip_markup(Some(&markup));
+}
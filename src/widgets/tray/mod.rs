@@ -0,0 +1,102 @@
+//! Panel system tray.
+//!
+//! Shows the StatusNotifierItems registered by applications, IE. Nextcloud, Element, Steam, etc.
+
+mod item;
+
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+
+use self::item::TrayItem;
+
+mod imp {
+    use tokio::sync::broadcast;
+
+    use super::*;
+    use crate::daemons::status_notifier;
+
+    #[derive(Default, Debug)]
+    pub struct Tray {}
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for Tray {
+        const NAME: &'static str = "Tray";
+        type Type = super::Tray;
+        type ParentType = gtk::Box;
+    }
+
+    impl ObjectImpl for Tray {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.add_css_class("tray");
+            obj.set_visible(false);
+
+            for item in status_notifier::get().items() {
+                self.add_item(&item.id());
+            }
+
+            let mut events = status_notifier::get().subscribe();
+            let weak_obj = obj.downgrade();
+            glib::spawn_future_local(async move {
+                loop {
+                    let event = match events.recv().await {
+                        Ok(event) => event,
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    };
+
+                    let Some(obj) = weak_obj.upgrade() else {
+                        break;
+                    };
+                    match event {
+                        status_notifier::Event::ItemAdded(item) => obj.imp().add_item(&item.id()),
+                        status_notifier::Event::ItemRemoved(id) => obj.imp().remove_item(&id),
+                    }
+                }
+            });
+        }
+    }
+
+    impl Tray {
+        fn add_item(&self, id: &str) {
+            let obj = self.obj();
+            let item = TrayItem::new(id);
+            item.set_widget_name(id);
+            obj.append(&item);
+            obj.set_visible(true);
+        }
+
+        fn remove_item(&self, id: &str) {
+            let obj = self.obj();
+            let mut child = obj.first_child();
+            while let Some(item) = child {
+                child = item.next_sibling();
+                if item.widget_name().as_str() == id {
+                    obj.remove(&item);
+                }
+            }
+
+            obj.set_visible(obj.first_child().is_some());
+        }
+    }
+
+    impl WidgetImpl for Tray {}
+    impl BoxImpl for Tray {}
+}
+
+glib::wrapper! {
+    pub struct Tray(ObjectSubclass<imp::Tray>)
+        @extends gtk::Box, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Orientable;
+}
+
+impl Tray {
+    pub fn new() -> Self {
+        glib::Object::builder()
+            .property("orientation", gtk::Orientation::Horizontal)
+            .property("spacing", 5)
+            .build()
+    }
+}
@@ -0,0 +1,161 @@
+//! Panel workspaces module.
+//!
+//! Shows the workspaces of the output the panel is on, as reported by the compositor daemon.
+//! Clicking a workspace focuses it, and scrolling cycles through them.
+
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+
+use crate::daemons::compositor::{self, State};
+
+mod imp {
+    use std::cell::RefCell;
+
+    use super::*;
+
+    #[derive(Default, Debug)]
+    pub struct WorkspacesWidget {
+        /// The connector name of the output we are on, once we know it.
+        pub(super) output: RefCell<Option<String>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for WorkspacesWidget {
+        const NAME: &'static str = "WorkspacesWidget";
+        type Type = super::WorkspacesWidget;
+        type ParentType = gtk::Box;
+    }
+
+    impl ObjectImpl for WorkspacesWidget {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.add_css_class("workspaces");
+            obj.set_visible(false);
+
+            // Touchpads send lots of small deltas, GTK adds them up into steps for us.
+            let scroll_controller = gtk::EventControllerScroll::new(
+                gtk::EventControllerScrollFlags::VERTICAL
+                    | gtk::EventControllerScrollFlags::DISCRETE,
+            );
+            let weak_obj = obj.downgrade();
+            scroll_controller.connect_scroll(move |_, _, dy| {
+                let Some(obj) = weak_obj.upgrade() else {
+                    return glib::Propagation::Proceed;
+                };
+                // Horizontal scrolls come with no vertical delta.
+                if dy == 0.0 {
+                    return glib::Propagation::Proceed;
+                }
+                let step = if dy < 0.0 { -1 } else { 1 };
+                let output = obj.imp().output.borrow().clone();
+                if let Err(err) = compositor::get().cycle_workspace(output.as_deref(), step) {
+                    warn!(?err, "Failed to cycle workspaces");
+                }
+                glib::Propagation::Stop
+            });
+            obj.add_controller(scroll_controller);
+
+            // We only know on which output we are once the panel surface exists.
+            obj.connect_realize(|obj| {
+                let Some(surface) = obj.native().and_then(|native| native.surface()) else {
+                    return;
+                };
+                let monitor = obj.display().monitor_at_surface(&surface);
+                obj.imp().set_monitor(monitor.as_ref());
+
+                let weak_obj = obj.downgrade();
+                surface.connect_enter_monitor(move |_, monitor| {
+                    if let Some(obj) = weak_obj.upgrade() {
+                        obj.imp().set_monitor(Some(monitor));
+                    }
+                });
+            });
+
+            let mut state = compositor::get().subscribe();
+            let weak_obj = obj.downgrade();
+            glib::spawn_future_local(async move {
+                loop {
+                    let current = state.borrow_and_update().clone();
+                    let Some(obj) = weak_obj.upgrade() else {
+                        break;
+                    };
+                    obj.imp().sync(&current);
+                    drop(obj);
+
+                    if state.changed().await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    }
+
+    impl WorkspacesWidget {
+        fn set_monitor(&self, monitor: Option<&gtk::gdk::Monitor>) {
+            let output = monitor
+                .and_then(|monitor| monitor.connector())
+                .map(String::from);
+            if *self.output.borrow() == output {
+                return;
+            }
+
+            self.output.replace(output);
+            self.sync(&compositor::get().state());
+        }
+
+        fn sync(&self, state: &State) {
+            let obj = self.obj();
+            while let Some(child) = obj.first_child() {
+                obj.remove(&child);
+            }
+
+            let output = self.output.borrow();
+            for workspace in state.workspaces_on(output.as_deref()) {
+                let button = gtk::Button::builder()
+                    .label(&workspace.name)
+                    .css_classes(["flat", "workspace"])
+                    .valign(gtk::Align::Center)
+                    .build();
+                for (class, enabled) in [
+                    ("active", workspace.active),
+                    ("urgent", workspace.urgent),
+                    ("occupied", workspace.occupied),
+                ] {
+                    if enabled {
+                        button.add_css_class(class);
+                    }
+                }
+
+                let id = workspace.id;
+                button.connect_clicked(move |_| {
+                    if let Err(err) = compositor::get().focus_workspace(id) {
+                        warn!(?err, id, "Failed to focus workspace");
+                    }
+                });
+                obj.append(&button);
+            }
+
+            obj.set_visible(obj.first_child().is_some());
+        }
+    }
+
+    impl WidgetImpl for WorkspacesWidget {}
+    impl BoxImpl for WorkspacesWidget {}
+}
+
+glib::wrapper! {
+    pub struct WorkspacesWidget(ObjectSubclass<imp::WorkspacesWidget>)
+        @extends gtk::Box, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Orientable;
+}
+
+impl WorkspacesWidget {
+    pub fn new() -> Self {
+        glib::Object::builder()
+            .property("orientation", gtk::Orientation::Horizontal)
+            .property("spacing", 3)
+            .build()
+    }
+}